@@ -0,0 +1,81 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use sanedit_buffer::PieceTree;
+
+const LARGE: &str = include_str!("large.txt");
+const EDITS: u64 = 10_000;
+
+/// Tree fragmented by inserting and removing text all over the buffer,
+/// leaves pieces that reference contiguous buffer parts
+fn split_pieces() -> PieceTree {
+    let mut pt = PieceTree::from(LARGE);
+    let step = pt.len() / EDITS;
+    for i in 0..EDITS {
+        let pos = i * step;
+        pt.insert(pos, "a");
+        pt.remove(pos..pos + 1);
+    }
+    pt
+}
+
+/// Tree fragmented by small insertions close to each other, like a macro
+/// replayed on every word
+fn small_inserts() -> PieceTree {
+    let mut pt = PieceTree::from(LARGE);
+    for i in 0..EDITS {
+        pt.insert(i * 8, "a");
+    }
+    pt
+}
+
+fn iterate(c: &mut Criterion, name: &str, pt: PieceTree) {
+    c.bench_function(&format!("{name}_chunks"), |bench| {
+        bench.iter(|| {
+            let mut chunks = pt.chunks();
+            let mut n = 0;
+            while chunks.next().is_some() {
+                n += 1;
+            }
+            n
+        });
+    });
+
+    c.bench_function(&format!("{name}_bytes"), |bench| {
+        bench.iter(|| {
+            let mut bytes = pt.bytes();
+            let mut n = 0u64;
+            while let Some(b) = bytes.next() {
+                n += b as u64;
+            }
+            n
+        });
+    });
+}
+
+fn merge(c: &mut Criterion) {
+    iterate(c, "split_pieces", split_pieces());
+
+    let mut pt = split_pieces();
+    pt.compact(false);
+    iterate(c, "split_pieces_compacted", pt);
+}
+
+fn rewrite(c: &mut Criterion) {
+    iterate(c, "small_inserts", small_inserts());
+
+    let mut pt = small_inserts();
+    pt.compact(true);
+    iterate(c, "small_inserts_compacted", pt);
+}
+
+fn compact(c: &mut Criterion) {
+    c.bench_function("compact_split_pieces", |bench| {
+        bench.iter_with_setup(split_pieces, |mut pt| pt.compact(false));
+    });
+
+    c.bench_function("compact_small_inserts", |bench| {
+        bench.iter_with_setup(small_inserts, |mut pt| pt.compact(true));
+    });
+}
+
+criterion_group!(benches, merge, rewrite, compact);
+criterion_main!(benches);
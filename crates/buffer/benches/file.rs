@@ -0,0 +1,95 @@
+use std::{env, fs, io::Write as _, path::PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use sanedit_buffer::PieceTree;
+
+const LARGE: &str = include_str!("large.txt");
+const NEEDLE: &[u8] = b"sanedit";
+
+/// File used to benchmark file backed buffers, set SANEDIT_BENCH_FILE to use
+/// an existing file, for example a multi-GB log. Otherwise a 256MB file is
+/// generated.
+fn bench_file() -> PathBuf {
+    if let Ok(path) = env::var("SANEDIT_BENCH_FILE") {
+        return PathBuf::from(path);
+    }
+
+    let path = env::temp_dir().join("sanedit-bench-file.txt");
+    let size = 256 * 1024 * 1024;
+    let exists = fs::metadata(&path).is_ok_and(|m| m.len() >= size);
+    if !exists {
+        let mut file = fs::File::create(&path).unwrap();
+        let mut written = 0;
+        while written < size {
+            file.write_all(LARGE.as_bytes()).unwrap();
+            written += LARGE.len() as u64;
+        }
+    }
+    path
+}
+
+/// Count the occurences of a needle, like grep would search a buffer
+fn grep(pt: &PieceTree) -> usize {
+    let mut count = 0;
+    let mut carry = Vec::with_capacity(NEEDLE.len() * 2);
+    let mut chunks = pt.chunks();
+    let mut chunk = chunks.get();
+    while let Some((_, chk)) = chunk {
+        let bytes = chk.as_ref();
+        // Matches on chunk boundaries
+        let head = &bytes[..bytes.len().min(NEEDLE.len() - 1)];
+        carry.extend_from_slice(head);
+        count += carry.windows(NEEDLE.len()).filter(|w| *w == NEEDLE).count();
+
+        count += bytes.windows(NEEDLE.len()).filter(|w| *w == NEEDLE).count();
+        carry.clear();
+        let tail = bytes.len().saturating_sub(NEEDLE.len() - 1);
+        carry.extend_from_slice(&bytes[tail..]);
+
+        chunk = chunks.next();
+    }
+    count
+}
+
+fn iterate(c: &mut Criterion, name: &str, pt: PieceTree) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+
+    group.bench_function("bytes", |bench| {
+        bench.iter(|| {
+            let mut bytes = pt.bytes();
+            let mut n = 0u64;
+            while let Some(b) = bytes.next() {
+                n += b as u64;
+            }
+            n
+        });
+    });
+
+    group.bench_function("bytes_prev", |bench| {
+        bench.iter(|| {
+            let mut bytes = pt.bytes_at(pt.len());
+            let mut n = 0u64;
+            while let Some(b) = bytes.prev() {
+                n += b as u64;
+            }
+            n
+        });
+    });
+
+    group.bench_function("grep", |bench| bench.iter(|| grep(&pt)));
+
+    group.finish();
+}
+
+fn file_backed(c: &mut Criterion) {
+    let path = bench_file();
+    iterate(c, "unmapped", PieceTree::from_path_unmapped(&path).unwrap());
+
+    // SAFETY: the benchmark file is not modified while benchmarking
+    let pt = unsafe { PieceTree::from_path_mmap(&path).unwrap() };
+    iterate(c, "mmap", pt);
+}
+
+criterion_group!(benches, file_backed);
+criterion_main!(benches);
@@ -4,7 +4,9 @@ pub use piece_tree::{
     builder::PieceTreeBuilder,
     bytes::Bytes,
     chunks::{Chunk, Chunks},
-    mark::{Mark, MarkResult},
+    compact::Compactor,
+    layout::Layout,
+    mark::{Gravity, Mark, MarkResult, RangeMark, RangeMarkResult},
     slice::PieceTreeSlice,
     PieceTree,
 };
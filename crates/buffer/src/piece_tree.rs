@@ -2,7 +2,11 @@ pub(crate) mod buffers;
 pub(crate) mod builder;
 pub(crate) mod bytes;
 pub(crate) mod chunks;
+pub(crate) mod common;
+pub(crate) mod compact;
 pub(crate) mod inplace;
+pub(crate) mod layout;
+pub(crate) mod line_index;
 pub(crate) mod mark;
 pub(crate) mod slice;
 pub(crate) mod tree;
@@ -10,11 +14,15 @@ pub(crate) mod utf8;
 
 use std::borrow::Cow;
 use std::io::{self, Write};
-use std::ops::RangeBounds;
+use std::ops::{Range, RangeBounds};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use self::buffers::{AddBuffer, AddBufferWriter};
+use self::compact::{relocations, Compactor, RelocationsWriter};
+use self::layout::Layout;
+use self::line_index::{count_eols, EolCounter, Eols};
 use self::mark::Mark;
 use self::tree::Tree;
 use self::utf8::graphemes::Graphemes;
@@ -23,7 +31,7 @@ use crate::piece_tree::chunks::Chunks;
 use crate::piece_tree::tree::piece::Piece;
 use crate::piece_tree::utf8::lines::Lines;
 use buffers::OriginalBuffer;
-use mark::MarkResult;
+use mark::{Gravity, MarkResult, RangeMark, RangeMarkResult};
 
 use self::slice::PieceTreeSlice;
 use self::utf8::chars::Chars;
@@ -45,6 +53,7 @@ pub(crate) const FILE_BACKED_MAX_PIECE_SIZE: usize = 1024 * 256;
 #[derive(Debug)]
 pub struct PieceTree {
     add_writer: AddBufferWriter,
+    relocations: RelocationsWriter,
     slice: PieceTreeSlice,
 }
 
@@ -64,18 +73,43 @@ impl PieceTree {
         Ok(Self::from_original_buffer(orig_buf))
     }
 
-    /// Create a file backed buffer
+    /// Create a file backed buffer.
+    /// A private clone of the file is memory mapped if the file system
+    /// supports copy on write clones, otherwise the file is read when needed.
     #[inline]
     pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<PieceTree> {
         let orig_buf = OriginalBuffer::from_path(path)?;
         Ok(Self::from_original_buffer(orig_buf))
     }
 
+    /// Create a file backed buffer that reads the file when needed, instead of
+    /// memory mapping it
+    #[inline]
+    pub fn from_path_unmapped<P: AsRef<Path>>(path: P) -> io::Result<PieceTree> {
+        let orig_buf = OriginalBuffer::from_path_unmapped(path)?;
+        Ok(Self::from_original_buffer(orig_buf))
+    }
+
+    /// Create a file backed buffer by memory mapping the file directly.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated while the buffer exists, accessing the
+    /// truncated part crashes the process. Use only for files nobody else
+    /// modifies, such as temporary files owned by the caller.
+    #[inline]
+    pub unsafe fn from_path_mmap<P: AsRef<Path>>(path: P) -> io::Result<PieceTree> {
+        let orig_buf = OriginalBuffer::from_path_mmap(path)?;
+        Ok(Self::from_original_buffer(orig_buf))
+    }
+
     #[inline]
     fn from_original_buffer(orig: OriginalBuffer) -> PieceTree {
         let orig = Arc::new(orig);
         let (aread, awrite) = AddBuffer::split();
+        let (rread, rwrite) = relocations();
         let mut pieces = Tree::new();
+        let counter = EolCounter::new(&orig, &aread);
 
         if !orig.is_empty() {
             if orig.is_file_backed() {
@@ -86,24 +120,26 @@ impl PieceTree {
                 while len != 0 {
                     let plen = len.min(FILE_BACKED_MAX_PIECE_SIZE as u64);
                     let piece = Piece::new(BufferKind::Original, pos, plen);
-                    pieces.insert(pos, piece, true);
+                    pieces.insert(pos, piece, true, &counter);
 
                     len -= plen;
                     pos += plen;
                 }
             } else {
                 let piece = Piece::new(BufferKind::Original, 0, orig.len());
-                pieces.insert(0, piece, true);
+                pieces.insert(0, piece, true, &counter);
             }
         }
 
         PieceTree {
             add_writer: awrite,
+            relocations: rwrite,
             slice: PieceTreeSlice {
                 range: 0..orig.len(),
                 tree: pieces,
                 orig,
                 add: aread,
+                relocations: rread,
             },
         }
     }
@@ -125,6 +161,7 @@ impl PieceTree {
         if bytes.is_empty() {
             return;
         }
+        let len = bytes.len() as u64;
 
         let positions: Cow<[u64]> = if is_sorted(positions) {
             positions.into()
@@ -142,19 +179,27 @@ impl PieceTree {
                 AppendResult::Append(n) => (n, true),
             };
 
+            let eols = count_eols(&bytes[..n]);
+            let counter = EolCounter::new(&self.slice.orig, &self.slice.add);
             for (count, pos) in positions.iter().enumerate() {
-                let piece =
+                let mut piece =
                     Piece::new_with_count(BufferKind::Add, bpos as u64, n as u64, count as u32);
+                piece.eol_count = Eols::known(eols);
                 self.slice.range.end += piece.len;
                 let inserted_now = (inserted * (count as u64 + 1)) + (n as u64 * count as u64);
                 self.slice
                     .tree
-                    .insert(*pos + inserted_now, piece, can_append);
+                    .insert(*pos + inserted_now, piece, can_append, &counter);
             }
 
             inserted += n as u64;
             bytes = &bytes[n..];
         }
+
+        for (count, pos) in positions.iter().enumerate() {
+            let start = *pos + len * count as u64;
+            line_index::fix_eol_leads(&mut self.slice, start..start + len);
+        }
     }
 
     /// Insert bytes to a position
@@ -171,6 +216,7 @@ impl PieceTree {
             return;
         }
 
+        let start = pos;
         while !bytes.is_empty() {
             let bpos = self.add_writer.len();
             let (n, can_append) = match self.add_writer.append_slice(bytes) {
@@ -178,13 +224,17 @@ impl PieceTree {
                 AppendResult::Append(n) => (n, true),
             };
 
-            let piece = Piece::new(BufferKind::Add, bpos as u64, n as u64);
+            let mut piece = Piece::new(BufferKind::Add, bpos as u64, n as u64);
+            piece.eol_count = Eols::known(count_eols(&bytes[..n]));
             self.slice.range.end += piece.len;
-            self.slice.tree.insert(pos, piece, can_append);
+            let counter = EolCounter::new(&self.slice.orig, &self.slice.add);
+            self.slice.tree.insert(pos, piece, can_append, &counter);
 
             pos += n as u64;
             bytes = &bytes[n..];
         }
+
+        line_index::fix_eol_leads(&mut self.slice, start..pos);
     }
 
     #[inline]
@@ -215,8 +265,10 @@ impl PieceTree {
             self.slice.range.end
         );
 
-        self.slice.tree.remove(start..end);
+        let counter = EolCounter::new(&self.slice.orig, &self.slice.add);
+        self.slice.tree.remove(start..end, &counter);
         self.slice.range.end -= end - start;
+        line_index::fix_eol_leads(&mut self.slice, start..start);
     }
 
     #[inline]
@@ -279,6 +331,32 @@ impl PieceTree {
         self.slice.pos_at_line(line)
     }
 
+    /// Number of lines in the buffer if the line index is complete
+    #[inline]
+    pub fn line_count(&self) -> Option<u64> {
+        self.slice.line_count()
+    }
+
+    /// Index the original buffer line counts. This reads the whole original
+    /// buffer and can take a long time, so it should be done in the
+    /// background.
+    ///
+    /// Once indexed use `update_line_index` to complete the line index.
+    #[inline]
+    pub fn index_lines(&self, stop: &AtomicBool) -> io::Result<()> {
+        self.slice.index_lines(stop)
+    }
+
+    /// Complete the line index once the original buffer is indexed.
+    /// Returns whether the line index is complete.
+    ///
+    /// Line queries use the index when it is complete, and fall back to
+    /// scanning the buffer otherwise.
+    #[inline]
+    pub fn update_line_index(&mut self) -> bool {
+        line_index::count_uncounted(&mut self.slice)
+    }
+
     #[inline]
     pub fn graphemes(&self) -> Graphemes<'_> {
         self.slice.graphemes()
@@ -304,14 +382,43 @@ impl PieceTree {
         self.slice.mark(pos)
     }
 
+    /// Mark a position with a gravity, see `PieceTreeSlice::mark_with_gravity`
+    #[inline]
+    pub fn mark_with_gravity(&self, pos: u64, gravity: Gravity) -> Mark {
+        self.slice.mark_with_gravity(pos, gravity)
+    }
+
+    /// Mark a range that grows and shrinks with the edits made to it
+    #[inline]
+    pub fn mark_range(&self, range: Range<u64>, start: Gravity, end: Gravity) -> RangeMark {
+        self.slice.mark_range(range, start, end)
+    }
+
     /// Get a buffer position from a mark.
-    /// If the buffer position has been deleted returns the original mark
-    /// position.
+    /// If the buffer position has been deleted returns the position where
+    /// the deletion happened.
     #[inline]
     pub fn mark_to_pos(&self, mark: &Mark) -> MarkResult {
         self.slice.mark_to_pos(mark)
     }
 
+    /// Get buffer positions of many marks using a single walk of the tree
+    #[inline]
+    pub fn marks_to_pos(&self, marks: &[Mark]) -> Vec<MarkResult> {
+        self.slice.marks_to_pos(marks)
+    }
+
+    #[inline]
+    pub fn range_mark_to_range(&self, mark: &RangeMark) -> RangeMarkResult {
+        self.slice.range_mark_to_range(mark)
+    }
+
+    /// Get the ranges of many range marks using a single walk of the tree
+    #[inline]
+    pub fn range_marks_to_ranges(&self, marks: &[RangeMark]) -> Vec<RangeMarkResult> {
+        self.slice.range_marks_to_ranges(marks)
+    }
+
     #[inline]
     pub fn write_to<W: Write>(&self, writer: W) -> io::Result<usize> {
         self.slice.write_to(writer)
@@ -364,9 +471,61 @@ impl PieceTree {
         self.slice.is_empty()
     }
 
+    /// Compact the whole piece tree, see `Compactor` for compacting
+    /// incrementally.
+    ///
+    /// Merges adjacent pieces referencing contiguous parts of the same
+    /// buffer. If `rewrite` is set, runs of small pieces are also copied into
+    /// new add buffer runs.
+    pub fn compact(&mut self, rewrite: bool) {
+        let mut compactor = Compactor::new(rewrite);
+        compactor.step(self, usize::MAX);
+    }
+
+    /// Number of bytes in the add buffer
+    #[inline]
+    pub fn add_buffer_len(&self) -> u64 {
+        self.add_writer.len() as u64
+    }
+
+    /// Copy bytes from the add buffer. Together with a `Layout` these can be
+    /// used to reconstruct slices of the tree later.
+    #[inline]
+    pub fn add_buffer_bytes(&self, range: Range<u64>) -> Vec<u8> {
+        self.slice.add_buffer_bytes(range)
+    }
+
+    /// Copy the original buffer contents. Reads the whole buffer so it should
+    /// not be used for file backed buffers.
+    #[inline]
+    pub fn original_buffer_bytes(&self) -> io::Result<Vec<u8>> {
+        self.slice.original_buffer_bytes()
+    }
+
+    /// Append bytes to the add buffer without changing the buffer contents.
+    /// Used to restore the add buffer of a persisted tree before restoring
+    /// its layouts.
+    pub fn extend_add_buffer(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let n = match self.add_writer.append_slice(bytes) {
+                AppendResult::NewBlock(n) | AppendResult::Append(n) => n,
+            };
+            bytes = &bytes[n..];
+        }
+    }
+
+    /// Create a slice from a layout using the buffers of this tree, the slice
+    /// can then be restored. Returns none if the layout references bytes
+    /// that are not in the buffers.
+    pub fn slice_from_layout(&self, layout: &Layout) -> Option<PieceTreeSlice> {
+        layout::slice_from_layout(self, layout)
+    }
+
     #[inline]
     pub fn restore(&mut self, ro: PieceTreeSlice) {
         self.slice = ro;
+        // Snapshot may have been taken before the line index was complete
+        line_index::count_uncounted(&mut self.slice);
     }
 }
 
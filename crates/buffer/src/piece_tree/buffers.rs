@@ -6,7 +6,7 @@ pub(crate) use original::OriginalBuffer;
 
 use self::original::OriginalBufferSlice;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum BufferKind {
     Add,
     Original,
@@ -1,4 +1,5 @@
 mod cache;
+mod mmap;
 mod slice;
 
 use std::{
@@ -7,10 +8,12 @@ use std::{
     io::{self, Read, Seek, SeekFrom},
     ops::Range,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex, RwLock},
+    sync::{Arc, Mutex, OnceLock, RwLock},
 };
 
-use crate::piece_tree::{buffers::ByteSlice, FILE_BACKED_MAX_PIECE_SIZE};
+use memmap2::Mmap;
+
+use crate::piece_tree::{buffers::ByteSlice, line_index::EolBlock, FILE_BACKED_MAX_PIECE_SIZE};
 
 use self::cache::Cache;
 pub(crate) use slice::OriginalBufferSlice;
@@ -26,44 +29,113 @@ pub(crate) enum OriginalBuffer {
     File {
         file: Mutex<PathFile>,
         cache: Box<RwLock<Cache>>,
+        /// EOL counts of blocks, set once indexed
+        eols: OnceLock<Box<[EolBlock]>>,
     },
     Memory {
         bytes: Vec<u8>,
+        /// EOL counts of blocks, set once indexed
+        eols: OnceLock<Box<[EolBlock]>>,
+    },
+    /// Memory mapped file, slices borrow directly from the mapping
+    Mapped {
+        map: Mmap,
+        /// Path of the file the mapping was created from, None if an unnamed
+        /// private clone of the file was mapped
+        path: Mutex<Option<PathBuf>>,
+        /// EOL counts of blocks, set once indexed
+        eols: OnceLock<Box<[EolBlock]>>,
     },
 }
 
 impl OriginalBuffer {
     #[inline]
     pub fn new() -> OriginalBuffer {
-        OriginalBuffer::Memory { bytes: Vec::new() }
+        Self::from_bytes(Vec::new())
+    }
+
+    #[inline]
+    pub fn from_bytes(bytes: Vec<u8>) -> OriginalBuffer {
+        OriginalBuffer::Memory {
+            bytes,
+            eols: OnceLock::new(),
+        }
     }
 
     #[inline]
     pub fn from_reader<T: io::Read>(mut reader: T) -> io::Result<OriginalBuffer> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes)?;
-        Ok(OriginalBuffer::Memory { bytes })
+        Ok(Self::from_bytes(bytes))
     }
 
-    #[inline]
+    /// Create a file backed buffer. A private clone of the file is memory
+    /// mapped if the file system supports it, otherwise the file is read when
+    /// needed.
     pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<OriginalBuffer> {
+        Self::from_path_with(path.as_ref(), mmap::map_private_clone)
+    }
+
+    fn from_path_with(
+        path: &Path,
+        map: impl FnOnce(&File, &Path) -> io::Result<Mmap>,
+    ) -> io::Result<OriginalBuffer> {
+        let file = File::open(path)?;
+        match map(&file, path) {
+            Ok(map) => Ok(Self::from_mmap(map, None)),
+            Err(e) => {
+                log::debug!("Cannot memory map a clone of {path:?}: {e}");
+                Ok(Self::from_file(file, path))
+            }
+        }
+    }
+
+    /// Create a file backed buffer that reads the file when needed
+    pub fn from_path_unmapped<P: AsRef<Path>>(path: P) -> io::Result<OriginalBuffer> {
         let path = path.as_ref();
         let file = File::open(path)?;
-        Ok(OriginalBuffer::File {
+        Ok(Self::from_file(file, path))
+    }
+
+    fn from_file(file: File, path: &Path) -> OriginalBuffer {
+        OriginalBuffer::File {
             file: Mutex::new(PathFile {
                 file,
                 path: path.into(),
             }),
             cache: RwLock::new(Cache::new()).into(),
-        })
+            eols: OnceLock::new(),
+        }
+    }
+
+    /// Create a file backed buffer by memory mapping the file directly.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated while the buffer exists, for example
+    /// it is a temporary file owned by the caller.
+    pub unsafe fn from_path_mmap<P: AsRef<Path>>(path: P) -> io::Result<OriginalBuffer> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let map = Mmap::map(&file)?;
+        Ok(Self::from_mmap(map, Some(path)))
+    }
+
+    fn from_mmap(map: Mmap, path: Option<&Path>) -> OriginalBuffer {
+        OriginalBuffer::Mapped {
+            map,
+            path: Mutex::new(path.map(PathBuf::from)),
+            eols: OnceLock::new(),
+        }
     }
 
     #[inline(always)]
     pub fn slice(&self, range: Range<u64>) -> io::Result<ByteSlice<'_>> {
         use OriginalBuffer::*;
         match self {
-            Memory { bytes } => Ok(bytes[range.start as usize..range.end as usize].into()),
-            File { cache, file } => {
+            Memory { bytes, .. } => Ok(bytes[range.start as usize..range.end as usize].into()),
+            Mapped { map, .. } => Ok(map[range.start as usize..range.end as usize].into()),
+            File { cache, file, .. } => {
                 let Range { start, end } = range;
                 {
                     let ro_cache = cache
@@ -114,7 +186,8 @@ impl OriginalBuffer {
                     0
                 }
             }
-            Memory { bytes } => bytes.len() as u64,
+            Memory { bytes, .. } => bytes.len() as u64,
+            Mapped { map, .. } => map.len() as u64,
         }
     }
 
@@ -123,9 +196,32 @@ impl OriginalBuffer {
         self.len() == 0
     }
 
+    /// EOL counts of the buffer in blocks of `EOL_BLOCK_SIZE` if indexed
+    #[inline]
+    pub fn eol_blocks(&self) -> Option<&[EolBlock]> {
+        use OriginalBuffer::*;
+        match self {
+            File { eols, .. } | Memory { eols, .. } | Mapped { eols, .. } => {
+                eols.get().map(|blocks| blocks.as_ref())
+            }
+        }
+    }
+
+    pub fn set_eol_blocks(&self, blocks: Box<[EolBlock]>) {
+        use OriginalBuffer::*;
+        match self {
+            File { eols, .. } | Memory { eols, .. } | Mapped { eols, .. } => {
+                let _ = eols.set(blocks);
+            }
+        }
+    }
+
     #[inline]
     pub fn is_file_backed(&self) -> bool {
-        matches!(self, OriginalBuffer::File { .. })
+        matches!(
+            self,
+            OriginalBuffer::File { .. } | OriginalBuffer::Mapped { .. }
+        )
     }
 
     #[inline]
@@ -139,6 +235,7 @@ impl OriginalBuffer {
                     None
                 }
             }
+            Mapped { path, .. } => path.lock().ok().and_then(|path| path.clone()),
             _ => None,
         }
     }
@@ -159,9 +256,114 @@ impl OriginalBuffer {
                     unreachable!("failed to lock backing file");
                 }
             }
+            OriginalBuffer::Mapped { path: current, .. } => {
+                // An unnamed clone has no file to rename, the file it was
+                // created from is not ours
+                let mut current = current.lock().expect("failed to lock backing file path");
+                let Some(from) = current.as_ref() else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "no backing file to rename",
+                    ));
+                };
+
+                // The mapping stays valid even if the file is removed
+                let target = path.as_ref();
+                if std::fs::rename(from, target).is_err() {
+                    std::fs::copy(from, target)?;
+                    let _ = std::fs::remove_file(from);
+                }
+                *current = Some(target.into());
+                Ok(())
+            }
             OriginalBuffer::Memory { .. } => {
                 unreachable!("cannot rename backing file on memory buffer")
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PieceTree;
+
+    #[test]
+    fn file_backed_variants() {
+        let path = std::env::temp_dir().join(format!("sanedit-original-{}", std::process::id()));
+        let content = "hello world\n".repeat(10_000);
+        std::fs::write(&path, &content).unwrap();
+
+        let mapped = unsafe { PieceTree::from_path_mmap(&path).unwrap() };
+        let unmapped = PieceTree::from_path_unmapped(&path).unwrap();
+        let cloned = PieceTree::from_path(&path).unwrap();
+        for mut pt in [mapped, unmapped, cloned] {
+            assert!(pt.is_file_backed());
+            assert_eq!(content, String::from(&pt));
+
+            pt.insert(6, "big ");
+            pt.remove(0..6);
+            assert_eq!(&content[6..], &String::from(&pt)[4..]);
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn mapped_clone_survives_truncation() {
+        let path = std::env::temp_dir().join(format!("sanedit-truncate-{}", std::process::id()));
+        let content = "hello world\n".repeat(10_000);
+        std::fs::write(&path, &content).unwrap();
+
+        // Not mapped on platforms without unnamed files
+        let orig = OriginalBuffer::from_path(&path).unwrap();
+        if matches!(orig, OriginalBuffer::Mapped { .. }) {
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_len(0)
+                .unwrap();
+            let len = orig.len();
+            let bytes = orig.slice(0..len).unwrap();
+            assert_eq!(content.as_bytes(), bytes.as_ref());
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn mapped_clone_has_no_backing_file() {
+        let path = std::env::temp_dir().join(format!("sanedit-rename-{}", std::process::id()));
+        let target = path.with_extension("moved");
+        std::fs::write(&path, "hello").unwrap();
+
+        let orig = OriginalBuffer::from_path(&path).unwrap();
+        if matches!(orig, OriginalBuffer::Mapped { .. }) {
+            assert_eq!(None, orig.file_path());
+            assert!(orig.rename_backing_file(&target).is_err());
+            assert!(path.exists());
+            assert!(!target.exists());
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn unmapped_when_clone_fails() {
+        let path = std::env::temp_dir().join(format!("sanedit-noclone-{}", std::process::id()));
+        let content = "hello world\n".repeat(10_000);
+        std::fs::write(&path, &content).unwrap();
+
+        let orig =
+            OriginalBuffer::from_path_with(&path, |_, _| Err(io::ErrorKind::Unsupported.into()))
+                .unwrap();
+        assert!(matches!(orig, OriginalBuffer::File { .. }));
+        assert_eq!(Some(path.clone()), orig.file_path());
+        let len = orig.len();
+        let bytes = orig.slice(0..len).unwrap();
+        assert_eq!(content.as_bytes(), bytes.as_ref());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
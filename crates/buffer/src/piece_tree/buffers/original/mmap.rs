@@ -0,0 +1,62 @@
+use std::{fs::File, io, path::Path};
+
+use memmap2::Mmap;
+
+/// Memory map a private clone of a file.
+///
+/// Accessing a part of a memory mapped file that was truncated by another
+/// process crashes the editor. To prevent that the file is cloned to an
+/// unnamed file in the same directory using a copy on write clone, which is
+/// instant and uses no extra space. Nobody else can open the unnamed clone,
+/// so the mapping stays valid.
+///
+/// Fails if the file system does not support cloning files.
+pub(crate) fn map_private_clone(file: &File, path: &Path) -> io::Result<Mmap> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let clone = open_unnamed(dir)?;
+    clone_file(file, &clone)?;
+
+    // SAFETY: the clone is only accessible through our file descriptor, so
+    // it cannot be modified
+    unsafe { Mmap::map(&clone) }
+}
+
+/// Open an unnamed file in a directory, it is removed once closed
+#[cfg(target_os = "linux")]
+fn open_unnamed(dir: &Path) -> io::Result<File> {
+    use std::{fs::OpenOptions, os::unix::fs::OpenOptionsExt as _};
+
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .mode(0o600)
+        .custom_flags(libc::O_TMPFILE)
+        .open(dir)
+}
+
+/// Make `to` a copy on write clone of `from`
+#[cfg(target_os = "linux")]
+fn clone_file(from: &File, to: &File) -> io::Result<()> {
+    use std::os::fd::AsRawFd as _;
+
+    // SAFETY: both file descriptors are valid for the duration of the call
+    let ret = unsafe { libc::ioctl(to.as_raw_fd(), libc::FICLONE, from.as_raw_fd()) };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_unnamed(_dir: &Path) -> io::Result<File> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clone_file(_from: &File, _to: &File) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
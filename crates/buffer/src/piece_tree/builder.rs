@@ -15,7 +15,7 @@ impl PieceTreeBuilder {
     }
 
     pub fn build(self) -> PieceTree {
-        let orig_buf = OriginalBuffer::Memory { bytes: self.buf };
+        let orig_buf = OriginalBuffer::from_bytes(self.buf);
         PieceTree::from_original_buffer(orig_buf)
     }
 }
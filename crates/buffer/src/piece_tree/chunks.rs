@@ -62,7 +62,7 @@ impl<'a> Chunks<'a> {
 }
 
 #[inline(always)]
-fn read_piece<'a>(pt: &'a PieceTreeSlice, piece: &Piece) -> Option<Chunk<'a>> {
+pub(crate) fn read_piece<'a>(pt: &'a PieceTreeSlice, piece: &Piece) -> Option<Chunk<'a>> {
     match piece.kind {
         BufferKind::Add => {
             let bytes = pt
@@ -0,0 +1,164 @@
+use std::{cmp::min, ops::Range, sync::Arc};
+
+use super::{
+    buffers::BufferKind,
+    chunks::read_piece,
+    tree::{piece::Piece, pieces::Pieces},
+};
+use crate::PieceTreeSlice;
+
+/// Walks the pieces of a slice forwards or backwards, allowing to consume
+/// only a part of a piece at a time
+struct PieceCursor<'a> {
+    pieces: Pieces<'a>,
+    piece: Option<Piece>,
+    /// Bytes consumed from the current piece
+    used: u64,
+    forward: bool,
+}
+
+impl<'a> PieceCursor<'a> {
+    fn new(slice: &'a PieceTreeSlice, forward: bool) -> PieceCursor<'a> {
+        let (pieces, piece) = if forward {
+            let pieces = Pieces::new(slice, 0);
+            let piece = pieces.get();
+            (pieces, piece)
+        } else {
+            let mut pieces = Pieces::new(slice, slice.len());
+            let piece = pieces.prev();
+            (pieces, piece)
+        };
+
+        PieceCursor {
+            pieces,
+            piece: piece.map(|(_, piece)| piece),
+            used: 0,
+            forward,
+        }
+    }
+
+    /// Current piece and the buffer range of its unconsumed part
+    fn get(&self) -> Option<(&Piece, Range<u64>)> {
+        let piece = self.piece.as_ref()?;
+        let range = if self.forward {
+            piece.pos + self.used..piece.pos + piece.len
+        } else {
+            piece.pos..piece.pos + piece.len - self.used
+        };
+        Some((piece, range))
+    }
+
+    fn advance(&mut self, n: u64) {
+        let Some(piece) = self.piece.as_ref() else {
+            return;
+        };
+
+        self.used += n;
+        if self.used >= piece.len {
+            self.used = 0;
+            let next = if self.forward {
+                self.pieces.next()
+            } else {
+                self.pieces.prev()
+            };
+            self.piece = next.map(|(_, piece)| piece);
+        }
+    }
+}
+
+/// Length of the common prefix or suffix of two slices.
+///
+/// Parts of pieces that reference the same bytes of the same buffers are
+/// skipped without comparing the bytes, so slices that share most of their
+/// pieces, like a buffer and its snapshot, are compared quickly.
+pub(crate) fn common_len(a: &PieceTreeSlice, b: &PieceTreeSlice, forward: bool) -> u64 {
+    let same_orig = Arc::ptr_eq(&a.orig, &b.orig);
+    let same_add = a.add.ptr_eq(&b.add);
+    let mut acur = PieceCursor::new(a, forward);
+    let mut bcur = PieceCursor::new(b, forward);
+    let mut total = 0;
+
+    while let (Some((apiece, arange)), Some((bpiece, brange))) = (acur.get(), bcur.get()) {
+        let n = min(arange.end - arange.start, brange.end - brange.start);
+        let (arange, brange) = if forward {
+            (
+                arange.start..arange.start + n,
+                brange.start..brange.start + n,
+            )
+        } else {
+            (arange.end - n..arange.end, brange.end - n..brange.end)
+        };
+
+        let same_buffer = match apiece.kind {
+            BufferKind::Original => same_orig,
+            BufferKind::Add => same_add,
+        };
+        let shared = apiece.kind == bpiece.kind && arange == brange && same_buffer;
+
+        if !shared {
+            let apart = Piece::new(apiece.kind, arange.start, n);
+            let bpart = Piece::new(bpiece.kind, brange.start, n);
+            let (Some(abytes), Some(bbytes)) = (read_piece(a, &apart), read_piece(b, &bpart))
+            else {
+                break;
+            };
+            let (abytes, bbytes) = (abytes.as_ref(), bbytes.as_ref());
+            let equal = if forward {
+                abytes
+                    .iter()
+                    .zip(bbytes.iter())
+                    .take_while(|(x, y)| x == y)
+                    .count()
+            } else {
+                abytes
+                    .iter()
+                    .rev()
+                    .zip(bbytes.iter().rev())
+                    .take_while(|(x, y)| x == y)
+                    .count()
+            } as u64;
+
+            total += equal;
+            if equal < n {
+                break;
+            }
+        } else {
+            total += n;
+        }
+
+        acur.advance(n);
+        bcur.advance(n);
+    }
+
+    total
+}
+
+#[cfg(test)]
+mod test {
+    use crate::PieceTree;
+
+    #[test]
+    fn shared_pieces() {
+        let mut pt = PieceTree::from("hello world, this is a test");
+        let before = pt.slice(..);
+        pt.insert(5, ",");
+        pt.remove(20..22);
+        let after = pt.slice(..);
+
+        assert_eq!(5, before.common_prefix_len(&after));
+        assert_eq!(6, before.common_suffix_len(&after));
+        assert_eq!(before.len(), before.common_prefix_len(&before));
+    }
+
+    #[test]
+    fn different_buffers() {
+        let a = PieceTree::from("hello world");
+        let mut b = PieceTree::from("hello");
+        b.insert(5, " there world");
+
+        let (a, b) = (a.slice(..), b.slice(..));
+        assert_eq!(6, a.common_prefix_len(&b));
+        assert_eq!(6, a.common_suffix_len(&b));
+        assert_eq!(0, a.slice(..0).common_prefix_len(&b));
+    }
+}
@@ -0,0 +1,579 @@
+use std::{
+    collections::BTreeSet,
+    ops::Range,
+    sync::{Arc, RwLock},
+};
+
+use sanedit_utils::appendlist::{Appendlist, Reader, Writer};
+
+use super::{
+    buffers::{AppendResult, BufferKind},
+    line_index::{self, count_eols, EolCounter, Eols},
+    mark::Mark,
+    tree::{piece::Piece, pieces::PieceIter},
+    PieceTree, FILE_BACKED_MAX_PIECE_SIZE,
+};
+use crate::{MarkResult, PieceTreeSlice};
+
+/// Pieces shorter than this are considered fragments
+const FRAGMENT_LEN: u64 = 64;
+
+/// Minimum number of consecutive fragments to rewrite
+const MIN_FRAGMENTS: usize = 8;
+
+/// Maximum number of bytes rewritten into a single add buffer run
+const MAX_REWRITE_LEN: u64 = 4096;
+
+/// Maximum number of relocations followed when resolving a mark
+const MAX_RELOCATED_MARKS: usize = 16;
+
+/// Relocations indexed by the rewritten piece's buffer kind, count and
+/// position, and the index of the relocation
+type RelocationIndex = BTreeSet<(BufferKind, u32, u64, usize)>;
+
+/// Creates the relocations of a piece tree, shared with its snapshots
+pub(crate) fn relocations() -> (RelocationsReader, RelocationsWriter) {
+    let (list_read, list_write) = Appendlist::split();
+    let index = Arc::new(RwLock::new(RelocationIndex::new()));
+    let reader = RelocationsReader {
+        list: list_read,
+        index: index.clone(),
+    };
+    let writer = RelocationsWriter {
+        list: list_write,
+        index,
+    };
+    (reader, writer)
+}
+
+#[derive(Debug)]
+pub(crate) struct RelocationsWriter {
+    list: Writer<Relocation>,
+    index: Arc<RwLock<RelocationIndex>>,
+}
+
+impl RelocationsWriter {
+    fn append(&self, relocation: Relocation) {
+        let i = self.list.len();
+        self.list.append(relocation);
+        if let Ok(mut index) = self.index.write() {
+            index.insert((relocation.kind, relocation.count, relocation.from, i));
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RelocationsReader {
+    list: Reader<Relocation>,
+    index: Arc<RwLock<RelocationIndex>>,
+}
+
+impl RelocationsReader {
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Relocations that moved `mark` forward into the add buffer.
+    ///
+    /// A relocation is never longer than a single rewrite, so only the
+    /// relocations starting at most that far before the mark are looked at.
+    fn forward(&self, mark: &Mark) -> Vec<Mark> {
+        let Ok(index) = self.index.read() else {
+            return vec![];
+        };
+        let start = mark.pos.saturating_sub(MAX_REWRITE_LEN);
+        let range =
+            (mark.kind, mark.count, start, 0)..=(mark.kind, mark.count, mark.pos, usize::MAX);
+        index
+            .range(range)
+            .filter_map(|(_, _, _, i)| self.list.get(*i)?.forward(mark))
+            .collect()
+    }
+
+    /// Relocation that moved `mark` back from the add buffer.
+    ///
+    /// Rewrites always append to the add buffer, so relocations are sorted
+    /// by their add buffer positions and do not overlap.
+    fn backward(&self, mark: &Mark) -> Option<Mark> {
+        if mark.kind != BufferKind::Add || mark.count != 0 {
+            return None;
+        }
+
+        // Index of the first relocation starting after the mark
+        let (mut low, mut high) = (0, self.list.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.list.get(mid)?.to <= mark.pos {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        self.list.get(low.checked_sub(1)?)?.backward(mark)
+    }
+}
+
+/// Records where the bytes of a rewritten piece were copied to in the add
+/// buffer.
+///
+/// Marks reference buffer positions of the pieces, so marks created before a
+/// rewrite are translated using these to find them in the rewritten tree, and
+/// the other way around to find marks created after the rewrite in older
+/// snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Relocation {
+    kind: BufferKind,
+    count: u32,
+    /// Position in the rewritten piece's buffer
+    from: u64,
+    len: u64,
+    /// Position in the add buffer
+    to: u64,
+}
+
+impl Relocation {
+    /// Translate a mark in the rewritten piece to the add buffer
+    fn forward(&self, mark: &Mark) -> Option<Mark> {
+        if mark.kind != self.kind
+            || mark.count != self.count
+            || !(self.from..self.from + self.len).contains(&mark.pos)
+        {
+            return None;
+        }
+
+        Some(Mark {
+            kind: BufferKind::Add,
+            pos: self.to + (mark.pos - self.from),
+            count: 0,
+            ..*mark
+        })
+    }
+
+    /// Translate a mark in the add buffer back to the rewritten piece
+    fn backward(&self, mark: &Mark) -> Option<Mark> {
+        if mark.kind != BufferKind::Add
+            || mark.count != 0
+            || !(self.to..self.to + self.len).contains(&mark.pos)
+        {
+            return None;
+        }
+
+        Some(Mark {
+            kind: self.kind,
+            pos: self.from + (mark.pos - self.to),
+            count: self.count,
+            ..*mark
+        })
+    }
+}
+
+/// Find a mark that may have been moved by rewrites.
+/// Tries marks at the relocated positions until one is found.
+pub(crate) fn relocated_mark_to_pos(slice: &PieceTreeSlice, mark: &Mark) -> Option<u64> {
+    let relocations = &slice.relocations;
+    if relocations.is_empty() {
+        return None;
+    }
+
+    let mut marks = vec![*mark];
+    let mut i = 0;
+    while i < marks.len() && marks.len() < MAX_RELOCATED_MARKS {
+        let current = marks[i];
+        i += 1;
+
+        let mut moved = relocations.forward(&current);
+        moved.extend(relocations.backward(&current));
+
+        for moved in moved {
+            if marks.contains(&moved) {
+                continue;
+            }
+
+            if let MarkResult::Found(pos) = slice.mark_to_pos_direct(&moved) {
+                return Some(pos);
+            }
+
+            marks.push(moved);
+        }
+    }
+
+    None
+}
+
+/// Incrementally compacts a piece tree.
+///
+/// Long editing sessions leave the tree with many small pieces, which makes
+/// every traversal slower. Compaction merges adjacent pieces that reference
+/// contiguous parts of the same buffer, and optionally copies runs of small
+/// pieces into a fresh add buffer run.
+///
+/// The buffer contents stay the same, existing snapshots are not modified and
+/// marks can still be resolved.
+#[derive(Debug, Clone, Default)]
+pub struct Compactor {
+    /// Position to continue from
+    pos: u64,
+    rewrite: bool,
+}
+
+impl Compactor {
+    /// Create a new compactor, if `rewrite` is set fragmented regions are
+    /// copied into new add buffer runs.
+    pub fn new(rewrite: bool) -> Compactor {
+        Compactor { pos: 0, rewrite }
+    }
+
+    /// Compact at most `budget` pieces continuing from where the previous
+    /// step ended. Returns true when the end of the tree was reached, the
+    /// next step starts from the beginning again.
+    pub fn step(&mut self, pt: &mut PieceTree, budget: usize) -> bool {
+        // The last piece is visited again on the next step, so it can be
+        // merged with the following ones
+        let budget = budget.max(2);
+        let pieces = collect_pieces(&pt.slice, self.pos.min(pt.len()), budget);
+        let done = pieces.len() < budget;
+        self.pos = if done {
+            0
+        } else {
+            pieces.last().map(|(pos, _)| *pos).unwrap_or(0)
+        };
+
+        let groups = merge_groups(&pt.slice, pieces, self.rewrite);
+        for group in groups {
+            match group {
+                Group::Merge(pos, pieces) => merge(pt, pos, &pieces),
+                Group::Rewrite(pos, pieces) => rewrite(pt, pos, &pieces),
+            }
+        }
+
+        done
+    }
+}
+
+/// Compaction operation on consecutive pieces starting at a position
+#[derive(Debug)]
+enum Group {
+    Merge(u64, Vec<Piece>),
+    Rewrite(u64, Vec<Piece>),
+}
+
+impl Group {
+    fn len(pieces: &[Piece]) -> u64 {
+        pieces.iter().map(|p| p.len).sum()
+    }
+}
+
+fn collect_pieces(slice: &PieceTreeSlice, at: u64, max: usize) -> Vec<(u64, Piece)> {
+    let mut result = Vec::with_capacity(max.min(1024));
+    let mut pieces = PieceIter::new(slice, at);
+    let mut piece = pieces.get();
+
+    while let Some(pos_piece) = piece {
+        if result.len() >= max {
+            break;
+        }
+        result.push(pos_piece);
+        piece = pieces.next();
+    }
+
+    result
+}
+
+/// Whether `right` can be appended to `left` to form a single piece
+fn can_join(slice: &PieceTreeSlice, left: &Piece, right: &Piece) -> bool {
+    if left.kind != right.kind || left.count != right.count || left.pos + left.len != right.pos {
+        return false;
+    }
+
+    let range = left.pos..right.pos + right.len;
+    match left.kind {
+        BufferKind::Add => slice
+            .add
+            .is_contiguous(range.start as usize..range.end as usize),
+        BufferKind::Original => {
+            // File backed buffers are read in blocks, pieces cannot span
+            // over them
+            let block = FILE_BACKED_MAX_PIECE_SIZE as u64;
+            !slice.orig.is_file_backed() || range.start / block == (range.end - 1) / block
+        }
+    }
+}
+
+/// Group joinable pieces together, and runs of fragments to rewrite
+fn merge_groups(slice: &PieceTreeSlice, pieces: Vec<(u64, Piece)>, rewrite: bool) -> Vec<Group> {
+    // Joinable runs of pieces
+    let mut runs: Vec<(u64, Vec<Piece>)> = vec![];
+    for (pos, piece) in pieces {
+        match runs.last_mut() {
+            Some((_, run)) if can_join(slice, run.last().unwrap(), &piece) => run.push(piece),
+            _ => runs.push((pos, vec![piece])),
+        }
+    }
+
+    let mut groups = vec![];
+    let mut fragments: Vec<(u64, Vec<Piece>)> = vec![];
+
+    let flush = |fragments: &mut Vec<(u64, Vec<Piece>)>, groups: &mut Vec<Group>| {
+        if fragments.len() >= MIN_FRAGMENTS {
+            let pos = fragments[0].0;
+            let pieces = fragments.drain(..).flat_map(|(_, run)| run).collect();
+            groups.push(Group::Rewrite(pos, pieces));
+        } else {
+            for (pos, run) in fragments.drain(..) {
+                if run.len() > 1 {
+                    groups.push(Group::Merge(pos, run));
+                }
+            }
+        }
+    };
+
+    for (pos, run) in runs {
+        let len = Group::len(&run);
+        let is_fragment = rewrite && len < FRAGMENT_LEN;
+        let fits = fragments
+            .iter()
+            .map(|(_, run)| Group::len(run))
+            .sum::<u64>()
+            + len
+            <= MAX_REWRITE_LEN;
+
+        if !is_fragment || !fits {
+            flush(&mut fragments, &mut groups);
+        }
+
+        if is_fragment {
+            fragments.push((pos, run));
+        } else if run.len() > 1 {
+            groups.push(Group::Merge(pos, run));
+        }
+    }
+
+    flush(&mut fragments, &mut groups);
+    groups
+}
+
+/// Replace pieces starting at `pos` with new ones of the same total length
+fn replace(pt: &mut PieceTree, pos: u64, old: &[Piece], new: Vec<Piece>) {
+    let end = pos + Group::len(old);
+    let slice = &mut pt.slice;
+    let counter = EolCounter::new(&slice.orig, &slice.add);
+    slice.tree.remove(pos..end, &counter);
+
+    let mut at = pos;
+    for piece in new {
+        let len = piece.len;
+        slice.tree.insert(at, piece, false, &counter);
+        at += len;
+    }
+
+    line_index::fix_eol_leads(slice, pos..end);
+}
+
+fn merge(pt: &mut PieceTree, pos: u64, pieces: &[Piece]) {
+    let counter = EolCounter::new(&pt.slice.orig, &pt.slice.add);
+    let mut merged = pieces[0].clone();
+    for piece in &pieces[1..] {
+        counter.append(&mut merged, piece);
+    }
+
+    replace(pt, pos, pieces, vec![merged]);
+}
+
+fn rewrite(pt: &mut PieceTree, pos: u64, pieces: &[Piece]) {
+    let len = Group::len(pieces);
+    let bytes = Vec::from(&pt.slice.slice(pos..pos + len));
+    let mut new = vec![];
+    let mut written: Vec<Range<u64>> = vec![];
+    let mut remaining = bytes.as_slice();
+
+    while !remaining.is_empty() {
+        let bpos = pt.add_writer.len() as u64;
+        let n = match pt.add_writer.append_slice(remaining) {
+            AppendResult::NewBlock(n) | AppendResult::Append(n) => n,
+        };
+
+        let mut piece = Piece::new(BufferKind::Add, bpos, n as u64);
+        piece.eol_count = Eols::known(count_eols(&remaining[..n]));
+        new.push(piece);
+        written.push(bpos..bpos + n as u64);
+        remaining = &remaining[n..];
+    }
+
+    // Record where each part of the old pieces ended up
+    let mut offset = 0;
+    for piece in pieces {
+        let prange = offset..offset + piece.len;
+        let mut woffset = 0;
+        for range in &written {
+            let wrange = woffset..woffset + (range.end - range.start);
+            let start = prange.start.max(wrange.start);
+            let end = prange.end.min(wrange.end);
+            if start < end {
+                pt.relocations.append(Relocation {
+                    kind: piece.kind,
+                    count: piece.count,
+                    from: piece.pos + (start - prange.start),
+                    len: end - start,
+                    to: range.start + (start - wrange.start),
+                });
+            }
+            woffset = wrange.end;
+        }
+        offset = prange.end;
+    }
+
+    replace(pt, pos, pieces, new);
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+    use crate::piece_tree::tree::test::is_valid_tree;
+
+    fn indexed(content: &str) -> PieceTree {
+        let mut pt = PieceTree::from(content);
+        pt.index_lines(&AtomicBool::new(false)).unwrap();
+        pt.update_line_index();
+        pt
+    }
+
+    /// Create pieces that reference contiguous add buffer parts by
+    /// splitting them and removing the inserted part
+    fn split_pieces(pt: &mut PieceTree, n: u64) {
+        pt.insert(0, "a".repeat(n as usize * 2));
+        for i in 0..n {
+            let pos = i * 2 + 1;
+            pt.insert(pos, "b");
+            pt.remove(pos..pos + 1);
+        }
+    }
+
+    #[test]
+    fn merge_adjacent() {
+        let mut pt = PieceTree::new();
+        split_pieces(&mut pt, 20);
+        let content = String::from(&pt);
+        assert!(pt.piece_count() > 1);
+
+        pt.compact(false);
+        assert_eq!(1, pt.piece_count());
+        assert_eq!(content, String::from(&pt));
+        assert_eq!(Ok(()), is_valid_tree(pt.tree()));
+    }
+
+    #[test]
+    fn merge_original() {
+        let mut pt = indexed("hello\r\nworld\n");
+        pt.insert(6, "x");
+        pt.remove(6..7);
+        assert_eq!(2, pt.piece_count());
+
+        pt.compact(false);
+        assert_eq!(1, pt.piece_count());
+        assert_eq!("hello\r\nworld\n", String::from(&pt));
+        assert_eq!(Some(3), pt.line_count());
+        assert_eq!(Ok(()), is_valid_tree(pt.tree()));
+    }
+
+    #[test]
+    fn keep_different_counts() {
+        let mut pt = PieceTree::from("abc");
+        pt.insert_multi(&[1, 2], "x");
+        let count = pt.piece_count();
+        pt.compact(false);
+        assert_eq!(count, pt.piece_count());
+    }
+
+    #[test]
+    fn rewrite_fragments() {
+        let mut pt = indexed(&"0123\r\n".repeat(10));
+        for i in 0..20 {
+            pt.insert(i * 2, "\n");
+        }
+        let content = String::from(&pt);
+        let lines = pt.line_count();
+        let count = pt.piece_count();
+
+        pt.compact(true);
+        assert!(pt.piece_count() < count);
+        assert_eq!(content, String::from(&pt));
+        assert_eq!(lines, pt.line_count());
+        assert_eq!(Ok(()), is_valid_tree(pt.tree()));
+    }
+
+    #[test]
+    fn step() {
+        let mut pt = PieceTree::new();
+        split_pieces(&mut pt, 20);
+        let content = String::from(&pt);
+
+        let mut compactor = Compactor::new(false);
+        let mut steps = 1;
+        while !compactor.step(&mut pt, 4) {
+            steps += 1;
+        }
+        assert!(steps > 1);
+        assert_eq!(1, pt.piece_count());
+        assert_eq!(content, String::from(&pt));
+    }
+
+    #[test]
+    fn marks_and_snapshots() {
+        let mut pt = PieceTree::from("0123456789".repeat(3));
+        for i in 0..10 {
+            pt.insert(i * 3, "x");
+        }
+        let before = pt.slice(..);
+        let marks: Vec<(u64, Mark)> = (0..pt.len()).map(|pos| (pos, pt.mark(pos))).collect();
+        let eob = pt.mark(pt.len());
+
+        pt.compact(true);
+        let after = pt.slice(..);
+        assert_eq!(String::from(&before), String::from(&after));
+
+        for (pos, mark) in &marks {
+            let result = pt.mark_to_pos(mark);
+            assert!(result.is_found());
+            assert_eq!(*pos, result.pos());
+        }
+        assert_eq!(pt.len(), pt.mark_to_pos(&eob).pos());
+
+        // Marks created after compaction in an older snapshot
+        for pos in 0..pt.len() {
+            let mark = pt.mark(pos);
+            let result = before.mark_to_pos(&mark);
+            assert!(result.is_found());
+            assert_eq!(pos, result.pos());
+        }
+
+        // Restoring an older snapshot keeps working
+        pt.restore(before.clone());
+        for (pos, mark) in &marks {
+            assert_eq!(*pos, pt.mark_to_pos(mark).pos());
+        }
+    }
+
+    #[test]
+    fn marks_after_repeated_rewrites() {
+        let mut pt = PieceTree::from("0123456789".repeat(3));
+        for i in 0..10 {
+            pt.insert(i * 3, "x");
+        }
+        let before = pt.slice(..);
+        let marks: Vec<(u64, Mark)> = (0..pt.len()).map(|pos| (pos, pt.mark(pos))).collect();
+
+        // Rewrite the same pieces twice, into different add buffer runs
+        pt.compact(true);
+        pt.restore(before);
+        pt.compact(true);
+
+        for (pos, mark) in &marks {
+            let result = pt.mark_to_pos(mark);
+            assert!(result.is_found());
+            assert_eq!(*pos, result.pos());
+        }
+    }
+}
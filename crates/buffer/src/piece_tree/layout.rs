@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use super::{
+    buffers::BufferKind,
+    line_index::{self, EolCounter},
+    tree::{piece::Piece, pieces::Pieces, Tree},
+    PieceTree,
+};
+use crate::PieceTreeSlice;
+
+/// Bytes used to serialize a single piece
+const PIECE_SIZE: usize = 1 + 8 + 8 + 4;
+
+/// Layout of the pieces of a slice.
+///
+/// Pieces only reference the original and add buffers, so a layout can be
+/// persisted and turned back into a slice of a piece tree that has the same
+/// original buffer and add buffer contents. See `PieceTree::add_buffer_bytes`
+/// and `PieceTree::extend_add_buffer`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    pieces: Vec<LayoutPiece>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LayoutPiece {
+    kind: BufferKind,
+    pos: u64,
+    len: u64,
+    count: u32,
+}
+
+impl Layout {
+    pub(crate) fn of(slice: &PieceTreeSlice) -> Layout {
+        let mut result = vec![];
+        let mut pieces = Pieces::new(slice, 0);
+        let mut piece = pieces.get();
+
+        while let Some((_, p)) = piece {
+            result.push(LayoutPiece {
+                kind: p.kind,
+                pos: p.pos,
+                len: p.len,
+                count: p.count,
+            });
+            piece = pieces.next();
+        }
+
+        Layout { pieces: result }
+    }
+
+    /// Length of the content this layout describes
+    pub fn len(&self) -> u64 {
+        self.pieces.iter().map(|p| p.len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn piece_count(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Serialize the layout
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pieces.len() * PIECE_SIZE);
+        for piece in &self.pieces {
+            let kind = match piece.kind {
+                BufferKind::Original => 0u8,
+                BufferKind::Add => 1u8,
+            };
+            bytes.push(kind);
+            bytes.extend_from_slice(&piece.pos.to_le_bytes());
+            bytes.extend_from_slice(&piece.len.to_le_bytes());
+            bytes.extend_from_slice(&piece.count.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserialize a layout serialized using `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Option<Layout> {
+        if !bytes.len().is_multiple_of(PIECE_SIZE) {
+            return None;
+        }
+
+        let mut pieces = Vec::with_capacity(bytes.len() / PIECE_SIZE);
+        for chunk in bytes.chunks_exact(PIECE_SIZE) {
+            let kind = match chunk[0] {
+                0 => BufferKind::Original,
+                1 => BufferKind::Add,
+                _ => return None,
+            };
+            let pos = u64::from_le_bytes(chunk[1..9].try_into().ok()?);
+            let len = u64::from_le_bytes(chunk[9..17].try_into().ok()?);
+            let count = u32::from_le_bytes(chunk[17..21].try_into().ok()?);
+            pieces.push(LayoutPiece {
+                kind,
+                pos,
+                len,
+                count,
+            });
+        }
+
+        Some(Layout { pieces })
+    }
+}
+
+/// Build a slice with the pieces of a layout using the buffers of the piece
+/// tree. Returns none if the layout references bytes the buffers do not
+/// have.
+pub(crate) fn slice_from_layout(pt: &PieceTree, layout: &Layout) -> Option<PieceTreeSlice> {
+    let orig = &pt.slice.orig;
+    let add = &pt.slice.add;
+    let counter = EolCounter::new(orig, add);
+    let mut tree = Tree::new();
+    let mut pos = 0;
+
+    for lpiece in &layout.pieces {
+        let end = lpiece.pos.checked_add(lpiece.len)?;
+        let valid = match lpiece.kind {
+            BufferKind::Original => end <= orig.len(),
+            BufferKind::Add => {
+                end <= add.len() as u64 && add.is_contiguous(lpiece.pos as usize..end as usize)
+            }
+        };
+        if !valid || lpiece.len == 0 {
+            return None;
+        }
+
+        let mut piece = Piece::new_with_count(lpiece.kind, lpiece.pos, lpiece.len, lpiece.count);
+        // Original buffer pieces are counted once it is indexed
+        if piece.kind == BufferKind::Add {
+            counter.count_piece(&mut piece);
+        }
+        tree.insert(pos, piece, false, &counter);
+        pos += lpiece.len;
+    }
+
+    let mut slice = PieceTreeSlice {
+        range: 0..pos,
+        orig: Arc::clone(orig),
+        add: add.clone(),
+        tree,
+        relocations: pt.slice.relocations.clone(),
+    };
+    line_index::fix_eol_leads(&mut slice, 0..pos);
+    line_index::count_uncounted(&mut slice);
+    Some(slice)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+    use crate::piece_tree::tree::test::is_valid_tree;
+
+    fn edited() -> PieceTree {
+        let mut pt = PieceTree::from("hello\r\nworld\n");
+        pt.index_lines(&AtomicBool::new(false)).unwrap();
+        pt.update_line_index();
+        pt.insert(6, "\n, ");
+        pt.insert_multi(&[0, 5], "x");
+        pt.remove(2..4);
+        pt
+    }
+
+    #[test]
+    fn serialize() {
+        let pt = edited();
+        let layout = pt.slice(..).layout();
+        assert_eq!(pt.len(), layout.len());
+        assert_eq!(pt.piece_count(), layout.piece_count());
+        assert_eq!(Some(layout.clone()), Layout::from_bytes(&layout.to_bytes()));
+        assert_eq!(None, Layout::from_bytes(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn restore_to_new_tree() {
+        let pt = edited();
+        let layout = pt.slice(..).layout();
+        let add = pt.add_buffer_bytes(0..pt.add_buffer_len());
+        assert_eq!(
+            b"hello\r\nworld\n",
+            pt.original_buffer_bytes().unwrap().as_slice()
+        );
+
+        let mut restored = PieceTree::from("hello\r\nworld\n");
+        restored.index_lines(&AtomicBool::new(false)).unwrap();
+        restored.extend_add_buffer(&add);
+        let slice = restored.slice_from_layout(&layout).unwrap();
+        restored.restore(slice);
+
+        assert_eq!(String::from(&pt), String::from(&restored));
+        assert_eq!(pt.line_count(), restored.line_count());
+        assert_eq!(Ok(()), is_valid_tree(restored.tree()));
+    }
+
+    #[test]
+    fn compare_to_original_buffer() {
+        let mut pt = PieceTree::from("hello\r\nworld\n");
+        assert!(pt.slice(..).is_original_buffer().unwrap());
+
+        pt.insert(0, "x");
+        assert!(!pt.slice(..).is_original_buffer().unwrap());
+
+        pt.remove(0..1);
+        assert!(pt.slice(..).is_original_buffer().unwrap());
+
+        pt.insert(5, "!");
+        pt.remove(6..7);
+        assert!(!pt.slice(..).is_original_buffer().unwrap());
+    }
+
+    #[test]
+    fn partial_slice() {
+        let pt = edited();
+        let slice = pt.slice(3..8);
+        let layout = slice.layout();
+        let restored = pt.slice_from_layout(&layout).unwrap();
+        assert_eq!(String::from(&slice), String::from(&restored));
+    }
+
+    #[test]
+    fn invalid_layout() {
+        let pt = edited();
+        let layout = pt.slice(..).layout();
+        let fresh = PieceTree::from("hello\r\nworld\n");
+        assert!(fresh.slice_from_layout(&layout).is_none());
+    }
+}
@@ -0,0 +1,807 @@
+use std::{
+    cmp::min,
+    io,
+    ops::{Add, AddAssign, Range, Sub, SubAssign},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use super::{
+    buffers::{AddBufferReader, BufferKind, OriginalBuffer},
+    tree::{piece::Piece, pieces::PieceIter},
+    FILE_BACKED_MAX_PIECE_SIZE,
+};
+use crate::{Bytes, PieceTreeSlice};
+
+/// Size of the blocks the original buffer is indexed in.
+pub(crate) const EOL_BLOCK_SIZE: u64 = FILE_BACKED_MAX_PIECE_SIZE as u64;
+
+/// Count of end of lines that may not be known yet.
+///
+/// Arithmetic with an unknown count results in an unknown count, so sums of
+/// counts are known only if all of the parts are known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Eols(Option<u64>);
+
+impl Eols {
+    pub const ZERO: Eols = Eols(Some(0));
+    pub const UNKNOWN: Eols = Eols(None);
+
+    #[inline]
+    pub fn known(n: u64) -> Eols {
+        Eols(Some(n))
+    }
+
+    #[inline]
+    pub fn get(&self) -> Option<u64> {
+        self.0
+    }
+
+    #[inline]
+    pub fn is_known(&self) -> bool {
+        self.0.is_some()
+    }
+
+    #[inline]
+    pub fn offset(self, off: i64) -> Eols {
+        Eols(self.0.map(|n| n.wrapping_add_signed(off)))
+    }
+}
+
+impl Add for Eols {
+    type Output = Eols;
+
+    #[inline]
+    fn add(self, rhs: Eols) -> Eols {
+        match (self.0, rhs.0) {
+            (Some(a), Some(b)) => Eols(Some(a + b)),
+            _ => Eols::UNKNOWN,
+        }
+    }
+}
+
+impl Sub for Eols {
+    type Output = Eols;
+
+    #[inline]
+    fn sub(self, rhs: Eols) -> Eols {
+        match (self.0, rhs.0) {
+            (Some(a), Some(b)) => Eols(Some(a - b)),
+            _ => Eols::UNKNOWN,
+        }
+    }
+}
+
+impl AddAssign for Eols {
+    #[inline]
+    fn add_assign(&mut self, rhs: Eols) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Eols {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Eols) {
+        *self = *self - rhs;
+    }
+}
+
+/// Whether the byte at `i` ends an EOL. Only the bytes in the slice are
+/// considered, so the function is context free.
+///
+/// This matches the EOLs found by `next_eol`, an EOL is counted at its last
+/// byte.
+#[inline(always)]
+fn ends_eol(bytes: &[u8], i: usize) -> bool {
+    match bytes[i] {
+        // LF VT FF
+        0x0a..=0x0c => true,
+        // CR, but not the CR in CRLF
+        0x0d => bytes.get(i + 1) != Some(&0x0a),
+        // NEL
+        0x85 => i >= 1 && bytes[i - 1] == 0xc2,
+        // LS PS
+        0xa8 | 0xa9 => i >= 2 && bytes[i - 2] == 0xe2 && bytes[i - 1] == 0x80,
+        _ => false,
+    }
+}
+
+/// Count EOLs in bytes without considering the bytes around them.
+pub(crate) fn count_eols(bytes: &[u8]) -> u64 {
+    let mut count = 0;
+    for i in 0..bytes.len() {
+        if ends_eol(bytes, i) {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Difference in EOL counts when `before` and `after` are joined together.
+/// An EOL can span at most 3 bytes so only 2 bytes from both sides are
+/// needed.
+///
+/// When a piece is preceded by other content this is the amount its context
+/// free count should be adjusted with.
+fn join_difference(before: &[u8], after: &[u8]) -> i64 {
+    let before = &before[before.len().saturating_sub(2)..];
+    let after = &after[..min(2, after.len())];
+    let mut joined = [0u8; 4];
+    joined[..before.len()].copy_from_slice(before);
+    joined[before.len()..before.len() + after.len()].copy_from_slice(after);
+    let joined = &joined[..before.len() + after.len()];
+
+    count_eols(joined) as i64 - count_eols(before) as i64 - count_eols(after) as i64
+}
+
+/// EOL count of an original buffer block
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct EolBlock {
+    /// Context free EOL count of the block
+    pub(crate) eols: u64,
+    /// Count difference when the block is preceded by the previous block
+    pub(crate) lead: i8,
+}
+
+/// Index original buffer EOL counts in blocks, so that pieces can be counted
+/// without reading them.
+pub(crate) fn index_original(orig: &OriginalBuffer, kill: &AtomicBool) -> io::Result<()> {
+    if orig.eol_blocks().is_some() {
+        return Ok(());
+    }
+
+    let len = orig.len();
+    let mut blocks = Vec::with_capacity(len.div_ceil(EOL_BLOCK_SIZE) as usize);
+    let mut tail: &[u8] = &[];
+    let mut tail_buf = [0u8; 2];
+    let mut pos = 0;
+    while pos < len {
+        if kill.load(Ordering::Acquire) {
+            return Err(io::Error::from(io::ErrorKind::Interrupted));
+        }
+
+        let end = min(len, pos + EOL_BLOCK_SIZE);
+        let bytes = orig.slice(pos..end)?;
+        let bytes = bytes.as_ref();
+        blocks.push(EolBlock {
+            eols: count_eols(bytes),
+            lead: join_difference(tail, bytes) as i8,
+        });
+
+        let tlen = min(2, bytes.len());
+        tail_buf[..tlen].copy_from_slice(&bytes[bytes.len() - tlen..]);
+        tail = &tail_buf[..tlen];
+        pos = end;
+    }
+
+    orig.set_eol_blocks(blocks.into());
+    Ok(())
+}
+
+/// Counts EOLs in the pieces by reading the buffers they reference.
+pub(crate) struct EolCounter<'a> {
+    orig: &'a OriginalBuffer,
+    add: &'a AddBufferReader,
+}
+
+impl<'a> EolCounter<'a> {
+    pub fn new(orig: &'a OriginalBuffer, add: &'a AddBufferReader) -> EolCounter<'a> {
+        EolCounter { orig, add }
+    }
+
+    /// Read at most 2 bytes before and after position `at` in a buffer,
+    /// without going outside of `bounds`.
+    fn read_around(
+        &self,
+        kind: BufferKind,
+        at: u64,
+        bounds: Range<u64>,
+    ) -> Option<([u8; 2], usize, [u8; 2], usize)> {
+        let start = at.saturating_sub(2).max(bounds.start);
+        let end = min(at + 2, bounds.end);
+        let mut before = [0u8; 2];
+        let mut after = [0u8; 2];
+        let blen = (at - start) as usize;
+        let alen = (end - at) as usize;
+
+        match kind {
+            BufferKind::Add => {
+                before[..blen].copy_from_slice(self.add.slice(start as usize..at as usize));
+                after[..alen].copy_from_slice(self.add.slice(at as usize..end as usize));
+            }
+            BufferKind::Original => {
+                // Read sides separately as a file backed buffer cannot be read
+                // over block boundaries
+                if blen != 0 {
+                    let bslice = self.orig.slice(start..at).ok()?;
+                    before[..blen].copy_from_slice(bslice.as_ref());
+                }
+
+                if alen != 0 {
+                    let aslice = self.orig.slice(at..end).ok()?;
+                    after[..alen].copy_from_slice(aslice.as_ref());
+                }
+            }
+        }
+
+        Some((before, blen, after, alen))
+    }
+
+    /// EOL count difference when the buffer range is split at `at`
+    fn join_difference_at(&self, kind: BufferKind, at: u64, bounds: Range<u64>) -> Option<i64> {
+        let (before, blen, after, alen) = self.read_around(kind, at, bounds)?;
+        Some(join_difference(&before[..blen], &after[..alen]))
+    }
+
+    /// Context free EOL count of a buffer range
+    fn count(&self, kind: BufferKind, range: Range<u64>) -> Eols {
+        match kind {
+            BufferKind::Add => {
+                let bytes = self.add.slice(range.start as usize..range.end as usize);
+                Eols::known(count_eols(bytes))
+            }
+            BufferKind::Original => self.count_original(range),
+        }
+    }
+
+    fn count_original(&self, range: Range<u64>) -> Eols {
+        let Some(blocks) = self.orig.eol_blocks() else {
+            return Eols::UNKNOWN;
+        };
+
+        let mut count = 0i64;
+        let mut pos = range.start;
+        while pos < range.end {
+            let block = pos / EOL_BLOCK_SIZE;
+            let block_start = block * EOL_BLOCK_SIZE;
+            let end = min(range.end, block_start + EOL_BLOCK_SIZE);
+
+            if pos == block_start && end - pos == EOL_BLOCK_SIZE {
+                count += blocks[block as usize].eols as i64;
+            } else {
+                let Ok(bytes) = self.orig.slice(pos..end) else {
+                    return Eols::UNKNOWN;
+                };
+                count += count_eols(bytes.as_ref()) as i64;
+            }
+
+            if pos != range.start {
+                let Some(diff) = self.join_difference_at(BufferKind::Original, pos, range.clone())
+                else {
+                    return Eols::UNKNOWN;
+                };
+                count += diff;
+            }
+
+            pos = end;
+        }
+
+        Eols::known(count as u64)
+    }
+
+    /// Count a piece that has not been counted yet
+    pub fn count_piece(&self, piece: &mut Piece) {
+        piece.eol_count = self.count(piece.kind, piece.range());
+    }
+
+    /// Split the piece at offset from the piece start, keeping the EOL
+    /// counts of both halves up to date.
+    /// Modifies the current piece to be the left half and returns the right
+    /// half.
+    ///
+    /// The right half is adjusted to be preceded by the left half, so the
+    /// total count of the halves stays the same.
+    pub fn split_left(&self, piece: &mut Piece, offset: u64) -> Piece {
+        let whole = piece.clone();
+        let mut right = piece.split_left(offset);
+        piece.eol_lead = whole.eol_lead;
+
+        if whole.eol_count.is_known() {
+            let at = whole.pos + offset;
+            let Some(diff) = self.join_difference_at(whole.kind, at, whole.range()) else {
+                return right;
+            };
+
+            // Count the smaller half only
+            if piece.len <= right.len {
+                self.count_piece(piece);
+                right.eol_count = (whole.eol_count - piece.eol_count).offset(-diff);
+            } else {
+                self.count_piece(&mut right);
+                piece.eol_count = (whole.eol_count - right.eol_count).offset(-diff);
+            }
+
+            // If either count failed both are unknown
+            if !piece.eol_count.is_known() || !right.eol_count.is_known() {
+                piece.eol_count = Eols::UNKNOWN;
+                right.eol_count = Eols::UNKNOWN;
+            }
+
+            right.eol_lead = diff as i8;
+        }
+
+        right
+    }
+
+    /// Split the piece at offset from the piece start, keeping the EOL
+    /// counts of both halves up to date.
+    /// Modifies the current piece to be the right half and returns the left
+    /// half.
+    pub fn split_right(&self, piece: &mut Piece, offset: u64) -> Piece {
+        let right = self.split_left(piece, offset);
+        std::mem::replace(piece, right)
+    }
+
+    /// Append `right` to `left`, they must be contiguous in the same buffer.
+    /// Returns the amount the count of `left` increased.
+    pub fn append(&self, left: &mut Piece, right: &Piece) -> Eols {
+        debug_assert!(left.kind == right.kind && left.pos + left.len == right.pos);
+
+        let before = left.eols();
+        let bounds = left.pos..right.pos + right.len;
+        let diff = self.join_difference_at(left.kind, right.pos, bounds);
+        left.len += right.len;
+        left.eol_count = match diff {
+            Some(diff) => (left.eol_count + right.eol_count).offset(diff),
+            None => Eols::UNKNOWN,
+        };
+
+        left.eols() - before
+    }
+}
+
+/// Calculate the EOL count adjustment for a piece starting at `pos` in the
+/// slice. The adjustment depends on the bytes before the piece.
+pub(crate) fn eol_lead_at(slice: &PieceTreeSlice, pos: u64) -> i8 {
+    let mut before = [0u8; 2];
+    let mut blen = 0;
+    let mut bytes = Bytes::new(slice, pos);
+    while blen < 2 {
+        match bytes.prev() {
+            Some(b) => {
+                before[1 - blen] = b;
+                blen += 1;
+            }
+            None => break,
+        }
+    }
+    let before = &before[2 - blen..];
+
+    let mut after = [0u8; 2];
+    let mut alen = 0;
+    let mut bytes = Bytes::new(slice, pos);
+    while alen < 2 {
+        match bytes.next() {
+            Some(b) => {
+                after[alen] = b;
+                alen += 1;
+            }
+            None => break,
+        }
+    }
+
+    join_difference(before, &after[..alen]) as i8
+}
+
+/// Whether the index can be used to answer line queries for the slice
+#[inline]
+fn is_indexed(slice: &PieceTreeSlice) -> bool {
+    slice.range.start == 0 && slice.tree.eols().is_known() && slice.range.end == slice.tree.len()
+}
+
+/// Scan the EOLs ending in range using the full buffer context. Calls `f` with
+/// the position after each EOL until it returns false.
+fn scan_eols(slice: &PieceTreeSlice, range: Range<u64>, mut f: impl FnMut(u64) -> bool) {
+    if range.is_empty() {
+        return;
+    }
+
+    let mut prev = [0u8; 2];
+    let mut bytes = Bytes::new(slice, range.start);
+    for i in 0..2 {
+        if let Some(b) = bytes.prev() {
+            prev[1 - i] = b;
+        }
+    }
+
+    let mut bytes = Bytes::new(slice, range.start);
+    let mut pos = range.start;
+    while pos < range.end {
+        let Some(byte) = bytes.next() else {
+            break;
+        };
+        pos += 1;
+
+        let ends = match byte {
+            0x0a..=0x0c => true,
+            0x0d => bytes.get() != Some(0x0a),
+            0x85 => prev[1] == 0xc2,
+            0xa8 | 0xa9 => prev == [0xe2, 0x80],
+            _ => false,
+        };
+
+        if ends && !f(pos) {
+            return;
+        }
+
+        prev = [prev[1], byte];
+    }
+}
+
+/// EOL count before piece start, without a CRLF that is split between the
+/// piece and the previous one. The CR of such an EOL is counted in the
+/// previous piece, but the EOL actually ends in this one.
+fn eols_before(slice: &PieceTreeSlice, piece_start: u64, before: Eols) -> Option<u64> {
+    let mut before = before.get()?;
+    if 0 < piece_start {
+        let mut bytes = Bytes::new(slice, piece_start);
+        let cur = bytes.get();
+        let prev = bytes.prev();
+        if prev == Some(0x0d) && cur == Some(0x0a) {
+            before -= 1;
+        }
+    }
+
+    Some(before)
+}
+
+/// Line number at position using the line index.
+/// Returns none if the slice is not indexed.
+pub(crate) fn line_number_at(slice: &PieceTreeSlice, pos: u64) -> Option<u64> {
+    if !is_indexed(slice) {
+        return None;
+    }
+
+    let (piece_start, before) = slice.tree.eols_before_piece_at(pos);
+    let mut line = eols_before(slice, piece_start, before)?;
+    scan_eols(slice, piece_start..pos, |_| {
+        line += 1;
+        true
+    });
+    Some(line)
+}
+
+/// Position at the start of a line using the line index.
+/// Returns none if the slice is not indexed, and some none if the line does not
+/// exist.
+pub(crate) fn pos_at_line_number(slice: &PieceTreeSlice, line: u64) -> Option<Option<u64>> {
+    if !is_indexed(slice) {
+        return None;
+    }
+
+    if line == 0 {
+        return Some(Some(0));
+    }
+
+    let total = slice.tree.eols().get()?;
+    if total < line {
+        return Some(None);
+    }
+
+    // Find the piece the EOL ending the previous line is counted in and scan
+    // from its start
+    let (piece_start, before) = slice.tree.piece_with_eol(line);
+    let mut n = eols_before(slice, piece_start, before)?;
+    let mut result = None;
+    scan_eols(slice, piece_start..slice.len(), |pos| {
+        n += 1;
+        if n == line {
+            result = Some(pos);
+        }
+        result.is_none()
+    });
+
+    Some(result)
+}
+
+/// Count the pieces with unknown EOL counts using the original buffer index.
+/// Returns whether the line index is now complete.
+pub(crate) fn count_uncounted(slice: &mut PieceTreeSlice) -> bool {
+    if slice.tree.eols().is_known() {
+        return true;
+    }
+
+    let Some(blocks) = slice.orig.eol_blocks() else {
+        return false;
+    };
+
+    let pieces = slice.tree.uncounted_pieces();
+    let leads: Vec<(u64, i8)> = pieces
+        .iter()
+        .map(|(pos, piece)| (*pos, uncounted_eol_lead(slice, blocks, *pos, piece)))
+        .collect();
+
+    let counter = EolCounter::new(&slice.orig, &slice.add);
+    slice.tree.count_eols(&counter, &leads);
+    slice.tree.eols().is_known()
+}
+
+/// EOL count adjustment for a piece that has not been counted yet.
+///
+/// Uncounted pieces are usually original buffer blocks in their original
+/// order, use the block index for them to avoid reading the whole buffer.
+fn uncounted_eol_lead(slice: &PieceTreeSlice, blocks: &[EolBlock], pos: u64, piece: &Piece) -> i8 {
+    if pos == 0 {
+        return 0;
+    }
+
+    let block_aligned = piece.kind == BufferKind::Original
+        && piece.pos.is_multiple_of(EOL_BLOCK_SIZE)
+        && piece.len >= 2;
+    if block_aligned {
+        let (stack, _) = slice.tree.find_node(pos - 1);
+        let follows_block = stack.last().is_some_and(|n| {
+            let prev = &n.piece;
+            prev.kind == BufferKind::Original && prev.pos + prev.len == piece.pos && prev.len >= 2
+        });
+
+        if follows_block {
+            return blocks[(piece.pos / EOL_BLOCK_SIZE) as usize].lead;
+        }
+    }
+
+    eol_lead_at(slice, pos)
+}
+
+/// Recalculate EOL count adjustments of the pieces around an edited range.
+///
+/// The adjustment depends on the bytes before and at the start of the piece,
+/// so pieces starting near the edited range are affected.
+pub(crate) fn fix_eol_leads(slice: &mut PieceTreeSlice, range: Range<u64>) {
+    let start = range.start.saturating_sub(1);
+    let end = min(range.end + 2, slice.len());
+    let mut leads = vec![];
+    {
+        let mut pieces = PieceIter::new(slice, start);
+        let mut cur = pieces.get();
+        while let Some((pos, piece)) = cur {
+            if end < pos {
+                break;
+            }
+
+            if start <= pos {
+                let lead = eol_lead_at(slice, pos);
+                if piece.eol_lead != lead {
+                    leads.push((pos, lead));
+                }
+            }
+
+            cur = pieces.next();
+        }
+    }
+
+    for (pos, lead) in leads {
+        slice.tree.set_eol_lead(pos, lead);
+    }
+}
+
+/// Number of lines in the slice using the line index.
+/// Returns none if the slice is not indexed.
+pub(crate) fn line_count(slice: &PieceTreeSlice) -> Option<u64> {
+    if !is_indexed(slice) {
+        return None;
+    }
+
+    let eols = slice.tree.eols().get()?;
+    Some(eols + 1)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+    use crate::piece_tree::tree::test::is_valid_tree;
+    use crate::PieceTree;
+
+    const EOLS: [&str; 8] = [
+        "\n", "\r", "\r\n", "\u{000B}", "\u{000C}", "\u{0085}", "\u{2028}", "\u{2029}",
+    ];
+
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    fn random_text(rng: &mut Rng, len: usize) -> Vec<u8> {
+        let mut text = vec![];
+        while text.len() < len {
+            match rng.below(4) {
+                0 => text.extend_from_slice(EOLS[rng.below(EOLS.len() as u64) as usize].as_bytes()),
+                _ => text.push(b'a' + rng.below(3) as u8),
+            }
+        }
+        text
+    }
+
+    fn slow_line_starts(pt: &PieceTree) -> Vec<u64> {
+        let slice = pt.slice(..);
+        let mut lines = slice.lines();
+        let mut starts = vec![];
+        while let Some(line) = lines.next() {
+            starts.push(line.start());
+        }
+        starts
+    }
+
+    fn assert_index_matches(pt: &PieceTree) {
+        assert_eq!(Ok(()), is_valid_tree(pt.tree()));
+        let starts = slow_line_starts(pt);
+        assert_eq!(Some(starts.len() as u64), pt.line_count());
+
+        for (n, start) in starts.iter().enumerate() {
+            assert_eq!(Some(*start), pt.pos_at_line(n as u64), "line {n}");
+        }
+        assert_eq!(None, pt.pos_at_line(starts.len() as u64));
+
+        let slice = pt.slice(..);
+        let mut lines = slice.lines();
+        let mut n = 0;
+        let mut cur = lines.next();
+        while let Some(line) = cur {
+            for pos in line.start()..line.end() {
+                let (ln, lslice) = pt.line_at(pos);
+                assert_eq!((n, line.range()), (ln, lslice.range()), "pos {pos}");
+            }
+
+            if line.end() == pt.len() {
+                let (ln, lslice) = pt.line_at(pt.len());
+                assert_eq!((n, line.range()), (ln, lslice.range()), "end");
+                break;
+            }
+
+            n += 1;
+            cur = lines.next();
+        }
+    }
+
+    #[test]
+    fn count_eols_context_free() {
+        assert_eq!(0, count_eols(b""));
+        assert_eq!(1, count_eols(b"\r"));
+        assert_eq!(1, count_eols(b"\r\n"));
+        assert_eq!(2, count_eols(b"\r\r\n"));
+        assert_eq!(0, count_eols(b"\x80\xa8"));
+        assert_eq!(1, count_eols("a\u{2028}b".as_bytes()));
+    }
+
+    #[test]
+    fn join_difference_split_eols() {
+        assert_eq!(-1, join_difference(b"\r", b"\n"));
+        assert_eq!(1, join_difference(b"\xe2", b"\x80\xa8"));
+        assert_eq!(1, join_difference(b"\xe2\x80", b"\xa8"));
+        assert_eq!(1, join_difference(b"\xc2", b"\x85"));
+        assert_eq!(0, join_difference(b"\n", b"\n"));
+    }
+
+    #[test]
+    fn index_after_inserts() {
+        let mut pt = PieceTree::new();
+        pt.insert(0, "foo\r");
+        pt.insert(4, "\nbar");
+        assert_eq!(Some(2), pt.line_count());
+        assert_eq!(Some(5), pt.pos_at_line(1));
+        assert_index_matches(&pt);
+
+        pt.insert(2, "\u{2028}");
+        pt.insert(3, "\r");
+        assert_index_matches(&pt);
+    }
+
+    #[test]
+    fn index_split_multibyte_eols() {
+        let mut pt = PieceTree::new();
+        pt.insert(0, "a\u{2028}b\r\nc\u{0085}d");
+
+        // Split every EOL between pieces using removes and inserts
+        let bytes: Vec<u8> = (&pt).into();
+        for i in 1..bytes.len() as u64 {
+            let b = bytes[i as usize];
+            pt.remove(i..i + 1);
+            assert_index_matches(&pt);
+            pt.insert(i, [b]);
+            assert_index_matches(&pt);
+        }
+    }
+
+    #[test]
+    fn index_random_edits() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..20 {
+            let mut pt = PieceTree::from(random_text(&mut rng, 64));
+            pt.index_lines(&AtomicBool::new(false)).unwrap();
+            assert!(pt.update_line_index());
+            assert_index_matches(&pt);
+
+            for _ in 0..30 {
+                let len = pt.len();
+                if rng.below(3) == 0 && len != 0 {
+                    let start = rng.below(len);
+                    let end = min(len, start + 1 + rng.below(6));
+                    pt.remove(start..end);
+                } else {
+                    let pos = rng.below(len + 1);
+                    let tlen = 1 + rng.below(5) as usize;
+                    let text = random_text(&mut rng, tlen);
+                    if rng.below(2) == 0 {
+                        pt.insert(pos, &text);
+                    } else {
+                        pt.insert_multi(&[pos, pos.saturating_sub(3), pos], &text);
+                    }
+                }
+
+                assert_index_matches(&pt);
+            }
+        }
+    }
+
+    #[test]
+    fn index_restore_snapshot() {
+        let mut pt = PieceTree::from("foo\nbar\r\nbaz");
+        let unindexed = pt.slice(..);
+        assert_eq!(None, pt.line_count());
+
+        pt.index_lines(&AtomicBool::new(false)).unwrap();
+        assert!(pt.update_line_index());
+        pt.insert(4, "\r");
+        let indexed = pt.slice(..);
+        assert_index_matches(&pt);
+
+        pt.restore(unindexed);
+        assert_eq!(Some(3), pt.line_count());
+        assert_index_matches(&pt);
+
+        pt.restore(indexed);
+        assert_eq!(Some(4), pt.line_count());
+        assert_index_matches(&pt);
+    }
+
+    #[test]
+    fn index_file_backed() {
+        // Make CRLFs and LSs span over block boundaries
+        let mut content = vec![];
+        let mut i = 0;
+        while content.len() < EOL_BLOCK_SIZE as usize * 3 + 10 {
+            content.extend_from_slice(b"ab");
+            content.extend_from_slice(EOLS[i % EOLS.len()].as_bytes());
+            i += 1;
+        }
+        let block = EOL_BLOCK_SIZE as usize;
+        content[block - 1] = b'\r';
+        content[block] = b'\n';
+        content[block * 2 - 1..block * 2 + 2].copy_from_slice("\u{2028}".as_bytes());
+
+        let path = std::env::temp_dir().join(format!("sanedit-line-index-{}", std::process::id()));
+        std::fs::write(&path, &content).unwrap();
+
+        let mut pt = PieceTree::from_path(&path).unwrap();
+        let expected = count_eols(&content) + 1;
+        assert_eq!(None, pt.line_count());
+        assert!(!pt.update_line_index());
+
+        pt.index_lines(&AtomicBool::new(false)).unwrap();
+        assert!(pt.update_line_index());
+        assert_eq!(Some(expected), pt.line_count());
+
+        let starts = slow_line_starts(&pt);
+        for n in [0, 1, starts.len() / 3, starts.len() / 2, starts.len() - 2] {
+            assert_eq!(Some(starts[n]), pt.pos_at_line(n as u64));
+            assert_eq!(n as u64, pt.line_at(starts[n]).0);
+        }
+
+        pt.insert(block as u64, "\n");
+        pt.remove(block as u64 * 2 - 3..block as u64 * 2 + 1);
+        assert_eq!(Some(slow_line_starts(&pt).len() as u64), pt.line_count());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
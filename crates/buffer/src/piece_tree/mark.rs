@@ -1,4 +1,11 @@
-use super::buffers::BufferKind;
+use std::{cmp::min, ops::Range};
+
+use super::{
+    buffers::BufferKind,
+    compact,
+    tree::{piece::Piece, pieces::Pieces},
+};
+use crate::PieceTreeSlice;
 
 /// A mark that tracks a position in text.
 /// It can be retrieved if the position has not been deleted
@@ -8,13 +15,20 @@ pub struct Mark {
     pub(crate) kind: BufferKind,
     pub(crate) pos: u64,
     pub(crate) count: u32,
-    pub(crate) end_of_buffer: bool,
+    /// Mark is positioned after the referenced byte, used to mark the end
+    /// of buffer and positions with left gravity
+    pub(crate) after: bool,
 }
 
 impl Mark {
     pub fn original_position(&self) -> u64 {
         self.orig
     }
+
+    /// Whether this is a mark of an empty buffer
+    pub(crate) fn is_empty_buffer(&self) -> bool {
+        self.orig == 0 && self.after
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -35,3 +49,291 @@ impl MarkResult {
         matches!(self, MarkResult::Found(..))
     }
 }
+
+/// Where a marked position moves when text is inserted exactly at it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Gravity {
+    /// Stay before the inserted text
+    Left,
+    /// Move after the inserted text
+    #[default]
+    Right,
+}
+
+/// A mark that tracks a range of text.
+/// The range grows and shrinks with the edits made to it, the start and end
+/// gravities determine whether text inserted at the ends is included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeMark {
+    pub(crate) start: Mark,
+    pub(crate) end: Mark,
+}
+
+impl RangeMark {
+    pub fn original_range(&self) -> Range<u64> {
+        self.start.orig..self.end.orig
+    }
+
+    pub fn start(&self) -> &Mark {
+        &self.start
+    }
+
+    pub fn end(&self) -> &Mark {
+        &self.end
+    }
+
+    pub(crate) fn result(&self, start: MarkResult, end: MarkResult) -> RangeMarkResult {
+        let (start, end) = (start.pos(), end.pos());
+        let was_empty = self.start.orig == self.end.orig;
+        if end < start || (start == end && !was_empty) {
+            return RangeMarkResult::Deleted(min(start, end));
+        }
+
+        RangeMarkResult::Found(start..end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeMarkResult {
+    /// Range after the edits made to it
+    Found(Range<u64>),
+    /// All of the text in the range was deleted, contains the position the
+    /// range collapsed to
+    Deleted(u64),
+}
+
+impl RangeMarkResult {
+    /// Resolved range, an empty range if the contents were deleted
+    pub fn range(&self) -> Range<u64> {
+        match self {
+            RangeMarkResult::Found(range) => range.clone(),
+            RangeMarkResult::Deleted(n) => *n..*n,
+        }
+    }
+
+    pub fn is_found(&self) -> bool {
+        matches!(self, RangeMarkResult::Found(..))
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        matches!(self, RangeMarkResult::Deleted(..))
+    }
+}
+
+/// Search for a marked byte from the pieces of a slice.
+///
+/// If the marked byte has been deleted, the closest pieces of the same
+/// buffer before and after it are used to find where the deletion happened.
+#[derive(Debug, Default)]
+pub(crate) struct MarkSearch {
+    found: Option<u64>,
+    /// Buffer and slice position of the end of the closest piece before the
+    /// marked byte
+    prev: Option<(u64, u64)>,
+    /// Buffer and slice position of the start of the closest piece after the
+    /// marked byte
+    next: Option<(u64, u64)>,
+}
+
+impl MarkSearch {
+    /// Visit a piece at a slice position, returns whether the mark was found
+    pub(crate) fn visit(&mut self, mark: &Mark, p_pos: u64, piece: &Piece) -> bool {
+        if piece.kind != mark.kind || piece.count != mark.count {
+            return false;
+        }
+
+        let end = piece.pos + piece.len;
+        if piece.pos <= mark.pos && mark.pos < end {
+            let mut off = mark.pos - piece.pos;
+            if mark.after {
+                off += 1;
+            }
+            self.found = Some(p_pos + off);
+            return true;
+        }
+
+        if end <= mark.pos && self.prev.is_none_or(|(prev, _)| prev < end) {
+            self.prev = Some((end, p_pos + piece.len));
+        }
+
+        if mark.pos < piece.pos && self.next.is_none_or(|(next, _)| piece.pos < next) {
+            self.next = Some((piece.pos, p_pos));
+        }
+
+        false
+    }
+
+    pub(crate) fn result(&self, mark: &Mark, len: u64) -> MarkResult {
+        if let Some(pos) = self.found {
+            return MarkResult::Found(pos);
+        }
+
+        let prev = self.prev.map(|(_, pos)| pos);
+        let next = self.next.map(|(_, pos)| pos);
+        let closest = if mark.after {
+            prev.or(next)
+        } else {
+            next.or(prev)
+        };
+        MarkResult::Deleted(closest.unwrap_or(min(mark.orig, len)))
+    }
+}
+
+/// Resolves many marks using a single walk of the tree.
+///
+/// The pieces are collected and sorted by their buffer positions, so each
+/// mark is resolved using a binary search instead of walking the pieces.
+#[derive(Debug)]
+pub(crate) struct MarkResolver<'a> {
+    slice: &'a PieceTreeSlice,
+    /// Pieces and their slice positions sorted by buffer position
+    pieces: Vec<(u64, Piece)>,
+}
+
+impl<'a> MarkResolver<'a> {
+    pub fn new(slice: &'a PieceTreeSlice) -> MarkResolver<'a> {
+        let mut result = vec![];
+        let mut pieces = Pieces::new(slice, 0);
+        let mut piece = pieces.get();
+        while let Some(p) = piece {
+            result.push(p);
+            piece = pieces.next();
+        }
+        result.sort_by_key(|(_, p)| (p.kind, p.count, p.pos));
+
+        MarkResolver {
+            slice,
+            pieces: result,
+        }
+    }
+
+    pub fn resolve(&self, mark: &Mark) -> MarkResult {
+        if mark.is_empty_buffer() {
+            return MarkResult::Found(0);
+        }
+
+        let key = (mark.kind, mark.count, mark.pos);
+        let idx = self
+            .pieces
+            .partition_point(|(_, p)| (p.kind, p.count, p.pos) <= key);
+        let mut search = MarkSearch::default();
+        for (p_pos, piece) in self.pieces[idx.saturating_sub(1)..].iter().take(2) {
+            if search.visit(mark, *p_pos, piece) {
+                break;
+            }
+        }
+
+        let result = search.result(mark, self.slice.len());
+        if result.is_found() {
+            return result;
+        }
+
+        // The marked piece may have been rewritten by compaction
+        match compact::relocated_mark_to_pos(self.slice, mark) {
+            Some(pos) => MarkResult::Found(pos),
+            None => result,
+        }
+    }
+
+    pub fn resolve_range(&self, mark: &RangeMark) -> RangeMarkResult {
+        let start = self.resolve(&mark.start);
+        let end = self.resolve(&mark.end);
+        mark.result(start, end)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::PieceTree;
+
+    use super::*;
+
+    fn range(pt: &PieceTree, mark: &RangeMark) -> RangeMarkResult {
+        pt.range_mark_to_range(mark)
+    }
+
+    #[test]
+    fn gravity() {
+        let mut pt = PieceTree::from("hello world.");
+        let left = pt.mark_range(6..11, Gravity::Left, Gravity::Right);
+        let right = pt.mark_range(6..11, Gravity::Right, Gravity::Left);
+
+        pt.insert(6, "big ");
+        pt.insert(15, "!");
+        assert_eq!(RangeMarkResult::Found(6..16), range(&pt, &left));
+        assert_eq!(RangeMarkResult::Found(10..15), range(&pt, &right));
+    }
+
+    #[test]
+    fn empty_range() {
+        let mut pt = PieceTree::from("ab");
+        let mark = pt.mark_range(1..1, Gravity::Left, Gravity::Right);
+        pt.insert(1, "xyz");
+        assert_eq!(RangeMarkResult::Found(1..4), range(&pt, &mark));
+
+        let mut pt = PieceTree::new();
+        let mark = pt.mark_range(0..0, Gravity::Left, Gravity::Right);
+        assert_eq!(RangeMarkResult::Found(0..0), range(&pt, &mark));
+        pt.insert(0, "a");
+        assert_eq!(0, range(&pt, &mark).range().start);
+    }
+
+    #[test]
+    fn shrink_and_delete() {
+        let mut pt = PieceTree::from("hello world");
+        let mark = pt.mark_range(2..9, Gravity::Right, Gravity::Left);
+
+        pt.remove(0..4);
+        assert_eq!(RangeMarkResult::Found(0..5), range(&pt, &mark));
+
+        pt.remove(3..7);
+        assert_eq!(RangeMarkResult::Found(0..3), range(&pt, &mark));
+
+        pt.remove(0..3);
+        assert_eq!(RangeMarkResult::Deleted(0), range(&pt, &mark));
+    }
+
+    #[test]
+    fn deleted_mark_position() {
+        let mut pt = PieceTree::from("hello world");
+        let mark = pt.mark(6);
+        pt.remove(4..8);
+        assert!(matches!(pt.mark_to_pos(&mark), MarkResult::Deleted(4)));
+
+        let mut pt = PieceTree::from("a");
+        let mark = pt.mark(1);
+        assert!(matches!(pt.mark_to_pos(&mark), MarkResult::Found(1)));
+        pt.insert(0, "b");
+        assert!(matches!(pt.mark_to_pos(&mark), MarkResult::Found(2)));
+    }
+
+    #[test]
+    fn bulk_matches_single() {
+        let mut pt = PieceTree::from("the quick brown fox");
+        let marks: Vec<RangeMark> = [(0..3, 0), (4..9, 1), (10..15, 2), (16..19, 3), (9..9, 4)]
+            .into_iter()
+            .map(|(r, i)| {
+                let g = if i % 2 == 0 {
+                    (Gravity::Left, Gravity::Right)
+                } else {
+                    (Gravity::Right, Gravity::Left)
+                };
+                pt.mark_range(r, g.0, g.1)
+            })
+            .collect();
+
+        pt.insert(4, "very ");
+        pt.insert_multi(&[0, 14], "_");
+        pt.remove(16..22);
+        pt.insert(pt.len(), "es");
+
+        let bulk = pt.range_marks_to_ranges(&marks);
+        let single: Vec<RangeMarkResult> = marks.iter().map(|m| range(&pt, m)).collect();
+        assert_eq!(single, bulk);
+
+        let starts: Vec<Mark> = marks.iter().map(|m| *m.start()).collect();
+        let bulk: Vec<u64> = pt.marks_to_pos(&starts).iter().map(|m| m.pos()).collect();
+        let single: Vec<u64> = starts.iter().map(|m| pt.mark_to_pos(m).pos()).collect();
+        assert_eq!(single, bulk);
+    }
+}
@@ -1,20 +1,25 @@
 use std::{
     io,
     ops::{Bound, Range, RangeBounds},
-    sync::Arc,
+    sync::{atomic::AtomicBool, Arc},
 };
 
 use super::{
     buffers::{AddBufferReader, OriginalBuffer},
     chunks::Chunks,
+    common,
+    compact::{self, RelocationsReader},
     inplace::write_in_place,
+    layout::Layout,
+    line_index,
+    mark::{MarkResolver, MarkSearch},
     tree::Tree,
     utf8::{self, chars::Chars, graphemes::Graphemes, lines::Lines},
-    Bytes,
+    Bytes, FILE_BACKED_MAX_PIECE_SIZE,
 };
 use crate::{
     piece_tree::{buffers::BufferKind, tree::pieces::Pieces},
-    Mark, MarkResult,
+    Gravity, Mark, MarkResult, RangeMark, RangeMarkResult,
 };
 
 /// A read only slice of the piecetree
@@ -24,6 +29,8 @@ pub struct PieceTreeSlice {
     pub(crate) orig: Arc<OriginalBuffer>,
     pub(crate) add: AddBufferReader,
     pub(crate) tree: Tree,
+    /// Where compaction has moved pieces, shared by all slices of a tree
+    pub(crate) relocations: RelocationsReader,
 }
 
 impl PieceTreeSlice {
@@ -127,6 +134,7 @@ impl PieceTreeSlice {
             orig: self.orig.clone(),
             add: self.add.clone(),
             tree: self.tree.clone(),
+            relocations: self.relocations.clone(),
         }
     }
 
@@ -152,6 +160,18 @@ impl PieceTreeSlice {
         utf8::lines::pos_at_line(self, line)
     }
 
+    /// Number of lines in the slice if the line index is complete
+    #[inline]
+    pub fn line_count(&self) -> Option<u64> {
+        line_index::line_count(self)
+    }
+
+    /// Index the original buffer line counts, see `PieceTree::index_lines`
+    #[inline]
+    pub fn index_lines(&self, stop: &AtomicBool) -> io::Result<()> {
+        line_index::index_original(&self.orig, stop)
+    }
+
     #[inline]
     pub fn graphemes(&self) -> Graphemes<'_> {
         self.graphemes_at(0)
@@ -167,6 +187,87 @@ impl PieceTreeSlice {
         self.orig.is_file_backed()
     }
 
+    /// Length of the common prefix of two slices. Pieces shared by the
+    /// slices are skipped without comparing their bytes.
+    pub fn common_prefix_len(&self, other: &PieceTreeSlice) -> u64 {
+        common::common_len(self, other, true)
+    }
+
+    /// Length of the common suffix of two slices. Pieces shared by the
+    /// slices are skipped without comparing their bytes.
+    pub fn common_suffix_len(&self, other: &PieceTreeSlice) -> u64 {
+        common::common_len(self, other, false)
+    }
+
+    /// Layout of the pieces in this slice, can be used to persist the slice
+    pub fn layout(&self) -> Layout {
+        Layout::of(self)
+    }
+
+    /// Copy bytes from the add buffer of the tree. Together with a `Layout`
+    /// these can be used to reconstruct slices of the tree later.
+    pub fn add_buffer_bytes(&self, range: Range<u64>) -> Vec<u8> {
+        let add = &self.add;
+        let end = (range.end as usize).min(add.len());
+        let mut pos = (range.start as usize).min(end);
+        let mut bytes = Vec::with_capacity(end - pos);
+
+        while pos < end {
+            let block_end = add.block_end(pos).min(end);
+            bytes.extend_from_slice(add.slice(pos..block_end));
+            pos = block_end;
+        }
+
+        bytes
+    }
+
+    /// Copy the original buffer contents of the tree. Reads the whole buffer
+    /// so it should not be used for file backed buffers.
+    pub fn original_buffer_bytes(&self) -> io::Result<Vec<u8>> {
+        let orig = &self.orig;
+        let len = orig.len();
+        let mut bytes = Vec::with_capacity(len as usize);
+        let mut pos = 0;
+
+        while pos < len {
+            let block = FILE_BACKED_MAX_PIECE_SIZE as u64;
+            let end = (pos - pos % block + block).min(len);
+            let slice = orig.slice(pos..end)?;
+            bytes.extend_from_slice(slice.as_ref());
+            pos = end;
+        }
+
+        Ok(bytes)
+    }
+
+    /// Whether the contents of this slice are the same as the original
+    /// buffer contents of the tree, compared without copying either
+    pub fn is_original_buffer(&self) -> io::Result<bool> {
+        if self.len() != self.orig.len() {
+            return Ok(false);
+        }
+
+        let block = FILE_BACKED_MAX_PIECE_SIZE as u64;
+        let mut chunks = self.chunks();
+        let mut chunk = chunks.get();
+        while let Some((pos, chk)) = chunk {
+            let mut bytes = chk.as_ref();
+            let mut at = pos;
+            while !bytes.is_empty() {
+                let end = (at - at % block + block).min(at + bytes.len() as u64);
+                let n = (end - at) as usize;
+                if self.orig.slice(at..end)?.as_ref() != &bytes[..n] {
+                    return Ok(false);
+                }
+                bytes = &bytes[n..];
+                at = end;
+            }
+            chunk = chunks.next();
+        }
+
+        Ok(true)
+    }
+
     /// Mark a position in the buffer
     // Internally works using offsets into the read only and append only buffers.
     // These can be safely indexed into because they never change after written.
@@ -180,6 +281,7 @@ impl PieceTreeSlice {
             pos,
             self.len()
         );
+        let orig = self.start() + pos;
         let end_of_buffer = pos == self.len();
         if end_of_buffer {
             // If marking an empty buffer use original 0 and after flag
@@ -189,7 +291,7 @@ impl PieceTreeSlice {
                     kind: BufferKind::Original,
                     pos: 0,
                     count: 0,
-                    end_of_buffer,
+                    after: true,
                 };
             }
 
@@ -202,52 +304,98 @@ impl PieceTreeSlice {
             .unwrap_or_else(|| panic!("Cannot find a piece for position {}", pos));
         let off = pos - p_pos;
         Mark {
-            orig: self.start() + pos,
+            orig,
             kind: piece.kind,
             pos: piece.pos + off,
             count: piece.count,
-            end_of_buffer,
+            after: end_of_buffer,
+        }
+    }
+
+    /// Mark a position in the buffer with a gravity, which determines where
+    /// the mark moves if text is inserted at the position.
+    ///
+    /// The start of the buffer always has right gravity and the end of the
+    /// buffer left gravity.
+    pub fn mark_with_gravity(&self, pos: u64, gravity: Gravity) -> Mark {
+        match gravity {
+            Gravity::Left if pos != 0 => {
+                // Mark the byte before the position instead
+                let mut mark = self.mark(pos - 1);
+                mark.orig = self.start() + pos;
+                mark.after = true;
+                mark
+            }
+            _ => self.mark(pos),
+        }
+    }
+
+    /// Mark a range in the buffer
+    pub fn mark_range(&self, range: Range<u64>, start: Gravity, end: Gravity) -> RangeMark {
+        RangeMark {
+            start: self.mark_with_gravity(range.start, start),
+            end: self.mark_with_gravity(range.end, end),
         }
     }
 
     /// Get a buffer position from a mark.
-    /// If the buffer position has been deleted returns the original mark
-    /// position.
+    /// If the buffer position has been deleted returns the position where
+    /// the deletion happened.
     #[inline]
     pub fn mark_to_pos(&self, mark: &Mark) -> MarkResult {
-        // Marked an empty buffer
-        if mark.orig == 0 && mark.end_of_buffer {
+        let result = self.mark_to_pos_direct(mark);
+        if result.is_found() {
+            return result;
+        }
+
+        // The marked piece may have been rewritten by compaction
+        match compact::relocated_mark_to_pos(self, mark) {
+            Some(pos) => MarkResult::Found(pos),
+            None => result,
+        }
+    }
+
+    /// Find a mark without following compaction relocations
+    pub(crate) fn mark_to_pos_direct(&self, mark: &Mark) -> MarkResult {
+        if mark.is_empty_buffer() {
             return MarkResult::Found(0);
         }
 
-        let mut min_dist = self.len();
-        let mut deleted_closest = std::cmp::min(mark.orig, self.len());
+        let mut search = MarkSearch::default();
         let mut pieces = Pieces::new(self, 0);
         let mut piece = pieces.get();
 
         while let Some((p_pos, p)) = piece {
-            if p.kind == mark.kind && mark.count == p.count {
-                // If mark in piece we found it
-                if p.pos <= mark.pos && mark.pos < p.pos + p.len {
-                    let mut off = mark.pos - p.pos;
-                    if mark.end_of_buffer {
-                        off += 1;
-                    }
-                    return MarkResult::Found(p_pos + off);
-                }
-
-                // Try to find closest match if mark position is deleted
-                let dist = p.min_abs_distance(mark.pos);
-                if dist < min_dist {
-                    min_dist = dist;
-                    deleted_closest = mark.pos + if mark.end_of_buffer { 1 } else { 0 };
-                }
+            if search.visit(mark, p_pos, &p) {
+                break;
             }
 
             piece = pieces.next();
         }
 
-        MarkResult::Deleted(deleted_closest)
+        search.result(mark, self.len())
+    }
+
+    /// Get buffer positions of many marks using a single walk of the tree
+    pub fn marks_to_pos(&self, marks: &[Mark]) -> Vec<MarkResult> {
+        let resolver = MarkResolver::new(self);
+        marks.iter().map(|mark| resolver.resolve(mark)).collect()
+    }
+
+    /// Get the range of a range mark
+    pub fn range_mark_to_range(&self, mark: &RangeMark) -> RangeMarkResult {
+        let start = self.mark_to_pos(&mark.start);
+        let end = self.mark_to_pos(&mark.end);
+        mark.result(start, end)
+    }
+
+    /// Get the ranges of many range marks using a single walk of the tree
+    pub fn range_marks_to_ranges(&self, marks: &[RangeMark]) -> Vec<RangeMarkResult> {
+        let resolver = MarkResolver::new(self);
+        marks
+            .iter()
+            .map(|mark| resolver.resolve_range(mark))
+            .collect()
     }
 
     ///
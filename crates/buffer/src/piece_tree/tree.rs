@@ -12,6 +12,7 @@ use self::node::Node;
 use self::piece::Piece;
 
 use super::buffers::BufferKind;
+use super::line_index::{EolCounter, Eols};
 
 #[derive(Clone, Debug)]
 pub(crate) struct Tree {
@@ -42,17 +43,17 @@ impl Tree {
 
     /// Insert piece `piece` to tree at index `index`.
     #[inline]
-    pub fn insert(&mut self, pos: u64, piece: Piece, allow_append: bool) {
-        let inserted = insert_rec(&mut self.root, pos, piece, true, allow_append);
+    pub fn insert(&mut self, pos: u64, piece: Piece, allow_append: bool, eols: &EolCounter) {
+        let inserted = insert_rec(&mut self.root, pos, piece, true, allow_append, eols);
         self.node_count += inserted.nodes;
     }
 
-    pub fn remove(&mut self, range: Range<u64>) {
+    pub fn remove(&mut self, range: Range<u64>, eols: &EolCounter) {
         let mut removed_bytes = 0;
         let len = range.end - range.start;
 
         while removed_bytes < len {
-            let removed = remove_rec(&mut self.root, range.start, len - removed_bytes, true);
+            let removed = remove_rec(&mut self.root, range.start, len - removed_bytes, true, eols);
 
             if removed.node {
                 self.node_count -= 1;
@@ -63,7 +64,7 @@ impl Tree {
             if let Some(p) = removed.reinsert {
                 removed_bytes -= p.len;
 
-                let inserted = insert_rec(&mut self.root, range.start, p, true, true);
+                let inserted = insert_rec(&mut self.root, range.start, p, true, true, eols);
                 self.node_count += inserted.nodes;
             }
         }
@@ -104,11 +105,170 @@ impl Tree {
             }
         }
     }
+
+    /// Total length of the pieces in the tree
+    pub fn len(&self) -> u64 {
+        let mut len = 0;
+        let mut node = self.root.as_ref();
+        while let Node::Internal(n) = node {
+            len += n.left_subtree_len + n.piece.len;
+            node = &n.right;
+        }
+        len
+    }
+
+    /// Total EOL count of the pieces in the tree
+    pub fn eols(&self) -> Eols {
+        subtree_eols(&self.root)
+    }
+
+    /// Find the piece containing position `target`, returns the piece start
+    /// position and the EOL count before it.
+    pub fn eols_before_piece_at(&self, mut target: u64) -> (u64, Eols) {
+        let mut pos = 0;
+        let mut eols = Eols::ZERO;
+        let mut node = self.root.as_ref();
+
+        while let Node::Internal(n) = node {
+            if n.left_subtree_len > target {
+                node = &n.left;
+            } else if n.left_subtree_len + n.piece.len > target || n.right.is_leaf() {
+                return (pos + n.left_subtree_len, eols + n.left_subtree_eols);
+            } else {
+                target -= n.left_subtree_len + n.piece.len;
+                pos += n.left_subtree_len + n.piece.len;
+                eols += n.left_subtree_eols + n.piece.eols();
+                node = &n.right;
+            }
+        }
+
+        (pos, eols)
+    }
+
+    /// Find the piece where the EOL number `line` is counted in, returns the
+    /// piece start position and the EOL count before it.
+    ///
+    /// The EOL counts of the tree need to be known.
+    pub fn piece_with_eol(&self, line: u64) -> (u64, Eols) {
+        let mut pos = 0;
+        let mut eols = 0;
+        let mut node = self.root.as_ref();
+
+        while let Node::Internal(n) = node {
+            let left = n.left_subtree_eols.get().unwrap_or(0);
+            let piece = n.piece.eols().get().unwrap_or(0);
+
+            if line <= eols + left && !n.left.is_leaf() {
+                node = &n.left;
+            } else if line <= eols + left + piece || n.right.is_leaf() {
+                return (pos + n.left_subtree_len, Eols::known(eols + left));
+            } else {
+                pos += n.left_subtree_len + n.piece.len;
+                eols += left + piece;
+                node = &n.right;
+            }
+        }
+
+        (pos, Eols::known(eols))
+    }
+
+    /// Set the EOL count adjustment of a piece starting at position `target`
+    pub fn set_eol_lead(&mut self, target: u64, lead: i8) {
+        let (stack, pos) = self.find_node(target);
+        let Some(n) = stack.last() else {
+            return;
+        };
+        if pos != target || n.piece.eol_lead == lead {
+            return;
+        }
+
+        let diff = lead as i64 - n.piece.eol_lead as i64;
+        let mut target = target;
+        let mut node = &mut self.root;
+
+        while !node.is_leaf() {
+            let n = Arc::make_mut(node).internal();
+            if n.left_subtree_len > target {
+                n.left_subtree_eols = n.left_subtree_eols.offset(diff);
+                node = &mut n.left;
+            } else if n.left_subtree_len == target {
+                n.piece.eol_lead = lead;
+                return;
+            } else {
+                target -= n.left_subtree_len + n.piece.len;
+                node = &mut n.right;
+            }
+        }
+    }
+
+    /// Pieces with unknown EOL counts and their positions
+    pub fn uncounted_pieces(&self) -> Vec<(u64, Piece)> {
+        fn rec(node: &Node, pos: u64, pieces: &mut Vec<(u64, Piece)>) {
+            if subtree_eols(node).is_known() {
+                return;
+            }
+
+            if let Node::Internal(n) = node {
+                let ppos = pos + n.left_subtree_len;
+                rec(&n.left, pos, pieces);
+                if !n.piece.eol_count.is_known() {
+                    pieces.push((ppos, n.piece.clone()));
+                }
+                rec(&n.right, ppos + n.piece.len, pieces);
+            }
+        }
+
+        let mut pieces = vec![];
+        rec(&self.root, 0, &mut pieces);
+        pieces
+    }
+
+    /// Count pieces with unknown EOL counts.
+    /// The EOL count adjustments of the counted pieces are looked up from
+    /// `leads` using the piece position.
+    pub fn count_eols(&mut self, counter: &EolCounter, leads: &[(u64, i8)]) {
+        fn rec(node: &mut Arc<Node>, pos: u64, counter: &EolCounter, leads: &[(u64, i8)]) -> Eols {
+            let eols = subtree_eols(node);
+            if eols.is_known() {
+                return eols;
+            }
+
+            let n = Arc::make_mut(node).internal();
+            let ppos = pos + n.left_subtree_len;
+            if !n.left_subtree_eols.is_known() {
+                n.left_subtree_eols = rec(&mut n.left, pos, counter, leads);
+            }
+
+            if !n.piece.eol_count.is_known() {
+                counter.count_piece(&mut n.piece);
+                if let Ok(i) = leads.binary_search_by_key(&ppos, |(pos, _)| *pos) {
+                    n.piece.eol_lead = leads[i].1;
+                }
+            }
+
+            let right = rec(&mut n.right, ppos + n.piece.len, counter, leads);
+            n.left_subtree_eols + n.piece.eols() + right
+        }
+
+        rec(&mut self.root, 0, counter, leads);
+    }
+}
+
+/// Total EOL count of a subtree
+fn subtree_eols(mut node: &Node) -> Eols {
+    let mut eols = Eols::ZERO;
+    while let Node::Internal(n) = node {
+        eols += n.left_subtree_eols + n.piece.eols();
+        node = &n.right;
+    }
+    eols
 }
 
 struct Inserted {
     nodes: usize,
     bytes: u64,
+    /// Increase in subtree EOL count
+    eols: Eols,
 }
 
 fn insert_rec(
@@ -117,15 +277,18 @@ fn insert_rec(
     piece: Piece,   // Piece to insert
     at_root: bool,
     allow_append: bool,
+    eols: &EolCounter,
 ) -> Inserted {
     if node.is_leaf() {
         let ins_bytes = piece.len;
+        let ins_eols = piece.eols();
         let node_color = if at_root { Color::Black } else { Color::Red };
         *node = Arc::new(Node::new(node_color, piece));
 
         return Inserted {
             nodes: 1,
             bytes: ins_bytes,
+            eols: ins_eols,
         };
     }
 
@@ -134,18 +297,22 @@ fn insert_rec(
     let node_piece = &node.piece;
 
     let inserted = if node_left_len > index {
-        let ret = insert_rec(&mut node.left, index, piece, false, allow_append);
+        let ret = insert_rec(&mut node.left, index, piece, false, allow_append, eols);
 
         node.left_subtree_len += ret.bytes;
+        node.left_subtree_eols += ret.eols;
         ret
     } else if node_left_len == index {
         let ins_bytes = piece.len;
+        let ins_eols = piece.eols();
         node.insert_left(piece);
 
         node.left_subtree_len += ins_bytes;
+        node.left_subtree_eols += ins_eols;
         Inserted {
             nodes: 1,
             bytes: ins_bytes,
+            eols: ins_eols,
         }
     } else if node_left_len + node_piece.len == index {
         // Append?
@@ -153,39 +320,46 @@ fn insert_rec(
             && node_piece.kind == BufferKind::Add
             && node_piece.pos + node_piece.len == piece.pos
         {
-            node.piece.len += piece.len;
+            let ins_eols = eols.append(&mut node.piece, &piece);
             Inserted {
                 nodes: 0,
                 bytes: piece.len,
+                eols: ins_eols,
             }
         } else {
             // Otherwise insert to the right side
             let ins_bytes = piece.len;
+            let ins_eols = piece.eols();
             node.insert_right(piece);
             Inserted {
                 nodes: 1,
                 bytes: ins_bytes,
+                eols: ins_eols,
             }
         }
     } else if node_left_len + node_piece.len > index {
         // Index is in the middle of the piece split the current piece.
-        let right_piece = node.piece.split_left(index - node_left_len);
+        // Splitting keeps the EOL count of the halves the same as the whole
+        // piece.
+        let right_piece = eols.split_left(&mut node.piece, index - node_left_len);
 
         // Remove right piece length from left subtree lengths because we are
         // temporarily removing it.
         let ins_bytes = piece.len;
+        let ins_eols = piece.eols();
         node.insert_right(right_piece);
         node.insert_right(piece);
 
         Inserted {
             nodes: 2,
             bytes: ins_bytes,
+            eols: ins_eols,
         }
     } else {
         // node_left_len + node_piece_len < index
         // Go right
         index -= node_left_len + node_piece.len;
-        insert_rec(&mut node.right, index, piece, false, allow_append)
+        insert_rec(&mut node.right, index, piece, false, allow_append, eols)
     };
 
     if inserted.nodes > 0 {
@@ -215,6 +389,7 @@ fn remove_rec(
     mut index: u64, // Remove buffer position
     len: u64,       // Remove length
     at_root: bool,
+    eols: &EolCounter,
 ) -> Removed {
     if node.is_leaf() {
         unreachable!("Remove rec found leaf node");
@@ -227,8 +402,9 @@ fn remove_rec(
     let n_piece_len = n.piece.len;
 
     let (removed, remove_cur_node) = if n_left_len > index {
-        let removed = remove_rec(&mut n.left, index, len, false);
+        let removed = remove_rec(&mut n.left, index, len, false, eols);
         n.left_subtree_len -= removed.piece.len;
+        n.left_subtree_eols -= removed.piece.eols();
         (removed, false)
     } else if n_left_len == index {
         if len >= n_piece_len {
@@ -240,7 +416,7 @@ fn remove_rec(
             };
             (remove, true)
         } else {
-            let rem_p = n.piece.split_right(len);
+            let rem_p = eols.split_right(&mut n.piece, len);
             let remove = Removed {
                 piece: rem_p,
                 node: false,
@@ -250,7 +426,7 @@ fn remove_rec(
         }
     } else if n_left_len + n_piece_len > index {
         // Removing from middle
-        let mut right_p = n.piece.split_left(index - n_left_len);
+        let mut right_p = eols.split_left(&mut n.piece, index - n_left_len);
 
         let rem_p = right_p.clone();
         let ins_p = if len >= right_p.len {
@@ -259,7 +435,7 @@ fn remove_rec(
         } else {
             // A part of right piece is removed.
             // We need to reinsert the remaining part
-            right_p.split_right(len);
+            eols.split_right(&mut right_p, len);
             Some(right_p)
         };
 
@@ -271,7 +447,7 @@ fn remove_rec(
         (remove, false)
     } else {
         index -= n_left_len + n_piece_len;
-        let remove = remove_rec(&mut n.right, index, len, false);
+        let remove = remove_rec(&mut n.right, index, len, false, eols);
         (remove, false)
     };
 
@@ -759,6 +935,28 @@ pub(crate) mod test {
         subtree_len(node).is_ok()
     }
 
+    fn left_subtree_eols_match(node: &Arc<Node>) -> bool {
+        fn subtree_eols(node: &Arc<Node>) -> Result<Eols, ()> {
+            match node.as_ref() {
+                Node::Leaf => Ok(Eols::ZERO),
+                Node::BBLeaf => Ok(Eols::ZERO),
+                Node::Internal(node) => {
+                    let left = subtree_eols(&node.left)?;
+                    let right = subtree_eols(&node.right)?;
+                    // Unknown counts are allowed but known ones need to be
+                    // correct
+                    if left == node.left_subtree_eols || !node.left_subtree_eols.is_known() {
+                        Ok(left + right + node.piece.eols())
+                    } else {
+                        Err(())
+                    }
+                }
+            }
+        }
+
+        subtree_eols(node).is_ok()
+    }
+
     fn red_nodes_have_black_children(node: &Arc<Node>) -> bool {
         let self_ok = if node.color() == Color::Red {
             let node = if let Node::Internal(n) = node.as_ref() {
@@ -800,6 +998,10 @@ pub(crate) mod test {
             return Err("Left subtree counts are invalid.");
         }
 
+        if !left_subtree_eols_match(root) {
+            return Err("Left subtree EOL counts are invalid.");
+        }
+
         Ok(())
     }
 }
@@ -96,6 +96,7 @@ impl Node {
                     let left = Arc::make_mut(&mut n.left);
                     let piece = left.remove_max();
                     n.left_subtree_len -= piece.len;
+                    n.left_subtree_eols -= piece.eols();
                     n.piece = piece;
                     n.bubble();
                 }
@@ -1,6 +1,7 @@
 use std::mem;
 use std::sync::Arc;
 
+use crate::piece_tree::line_index::Eols;
 use crate::piece_tree::tree::color::Color;
 use crate::piece_tree::tree::piece::Piece;
 
@@ -17,6 +18,8 @@ pub(crate) struct InternalNode {
     pub(crate) piece: Piece,
     /// Left subtree length in bytes
     pub(crate) left_subtree_len: u64,
+    /// Left subtree EOL count
+    pub(crate) left_subtree_eols: Eols,
 }
 
 impl InternalNode {
@@ -27,6 +30,7 @@ impl InternalNode {
             color,
             piece,
             left_subtree_len: 0,
+            left_subtree_eols: Eols::ZERO,
         }
     }
 
@@ -97,6 +101,7 @@ impl InternalNode {
     pub fn insert_right(&mut self, piece: Piece) {
         fn ins_left(node: &mut InternalNode, piece: Piece) {
             node.left_subtree_len += piece.len;
+            node.left_subtree_eols += piece.eols();
 
             let left = Arc::make_mut(&mut node.left);
             match left {
@@ -177,9 +182,11 @@ impl InternalNode {
                 x.color = B;
 
                 self.left_subtree_len -= y.piece.len + y.left_subtree_len;
+                self.left_subtree_eols -= y.piece.eols() + y.left_subtree_eols;
 
                 mem::swap(&mut self.piece, &mut y.piece);
                 mem::swap(&mut self.left_subtree_len, &mut y.left_subtree_len);
+                mem::swap(&mut self.left_subtree_eols, &mut y.left_subtree_eols);
                 mem::swap(&mut y.left, &mut y.right);
                 mem::swap(&mut self.right, &mut y.right);
 
@@ -205,11 +212,15 @@ impl InternalNode {
 
                 self.left_subtree_len -=
                     x.piece.len + x.left_subtree_len + y.left_subtree_len + y.piece.len;
+                self.left_subtree_eols -=
+                    x.piece.eols() + x.left_subtree_eols + y.left_subtree_eols + y.piece.eols();
 
                 y.left_subtree_len += x.piece.len + x.left_subtree_len;
+                y.left_subtree_eols += x.piece.eols() + x.left_subtree_eols;
 
                 mem::swap(&mut self.piece, &mut y.piece);
                 mem::swap(&mut self.left_subtree_len, &mut y.left_subtree_len);
+                mem::swap(&mut self.left_subtree_eols, &mut y.left_subtree_eols);
                 mem::swap(&mut y.left, &mut y.right);
                 mem::swap(&mut x.right, &mut y.right);
                 mem::swap(&mut self.right, &mut y.right);
@@ -235,10 +246,13 @@ impl InternalNode {
                 y.color = B;
 
                 z.left_subtree_len -= y.left_subtree_len + y.piece.len;
+                z.left_subtree_eols -= y.left_subtree_eols + y.piece.eols();
                 y.left_subtree_len += self.left_subtree_len + self.piece.len;
+                y.left_subtree_eols += self.left_subtree_eols + self.piece.eols();
 
                 mem::swap(&mut self.piece, &mut y.piece);
                 mem::swap(&mut self.left_subtree_len, &mut y.left_subtree_len);
+                mem::swap(&mut self.left_subtree_eols, &mut y.left_subtree_eols);
                 mem::swap(&mut z.left, &mut y.right);
                 mem::swap(&mut y.left, &mut y.right);
                 mem::swap(&mut self.left, &mut y.left);
@@ -264,9 +278,11 @@ impl InternalNode {
                 z.color = B;
 
                 y.left_subtree_len += self.left_subtree_len + self.piece.len;
+                y.left_subtree_eols += self.left_subtree_eols + self.piece.eols();
 
                 mem::swap(&mut self.piece, &mut y.piece);
                 mem::swap(&mut self.left_subtree_len, &mut y.left_subtree_len);
+                mem::swap(&mut self.left_subtree_eols, &mut y.left_subtree_eols);
                 mem::swap(&mut y.left, &mut y.right);
                 mem::swap(&mut self.left, &mut y.left);
 
@@ -296,10 +312,14 @@ impl InternalNode {
                 w.color = R;
 
                 z.left_subtree_len -= y.piece.len + y.left_subtree_len;
+                z.left_subtree_eols -= y.piece.eols() + y.left_subtree_eols;
                 y.left_subtree_len = self.left_subtree_len + y.left_subtree_len + self.piece.len;
+                y.left_subtree_eols =
+                    self.left_subtree_eols + y.left_subtree_eols + self.piece.eols();
 
                 mem::swap(&mut self.piece, &mut y.piece);
                 mem::swap(&mut self.left_subtree_len, &mut y.left_subtree_len);
+                mem::swap(&mut self.left_subtree_eols, &mut y.left_subtree_eols);
                 mem::swap(&mut y.left, &mut y.right);
                 mem::swap(&mut y.left, &mut self.left);
 
@@ -334,11 +354,15 @@ impl InternalNode {
 
                 self.left_subtree_len -=
                     x.piece.len + x.left_subtree_len + y.piece.len + y.left_subtree_len;
+                self.left_subtree_eols -=
+                    x.piece.eols() + x.left_subtree_eols + y.piece.eols() + y.left_subtree_eols;
 
                 y.left_subtree_len += x.piece.len + x.left_subtree_len;
+                y.left_subtree_eols += x.piece.eols() + x.left_subtree_eols;
 
                 mem::swap(&mut self.piece, &mut y.piece);
                 mem::swap(&mut self.left_subtree_len, &mut y.left_subtree_len);
+                mem::swap(&mut self.left_subtree_eols, &mut y.left_subtree_eols);
                 mem::swap(&mut y.left, &mut y.right);
                 mem::swap(&mut self.right, &mut y.right);
                 mem::swap(&mut self.right, &mut x.right);
@@ -1,9 +1,9 @@
 use std::{mem, ops::Range};
 
-use crate::piece_tree::buffers::BufferKind;
+use crate::piece_tree::{buffers::BufferKind, line_index::Eols};
 
 /// Piece describes an index and byte length in a buffer.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub(crate) struct Piece {
     /// are we indexing add buffer or read only buffer
     pub(crate) kind: BufferKind,
@@ -17,8 +17,27 @@ pub(crate) struct Piece {
     /// are the same. count can be used to identify a piece from other same
     /// pieces allowing piece to represent the same region but with its own id.
     pub(crate) count: u32,
+
+    /// Number of EOLs in the referenced bytes, not considering the bytes
+    /// around the piece. Unknown until counted.
+    pub(crate) eol_count: Eols,
+    /// Adjustment to the EOL count caused by the bytes preceding this piece
+    /// in the tree, for example a CRLF split between two pieces.
+    pub(crate) eol_lead: i8,
+}
+
+impl PartialEq for Piece {
+    fn eq(&self, other: &Self) -> bool {
+        // EOL counts are cached data and do not identify the piece
+        self.kind == other.kind
+            && self.pos == other.pos
+            && self.len == other.len
+            && self.count == other.count
+    }
 }
 
+impl Eq for Piece {}
+
 impl Piece {
     pub fn new(kind: BufferKind, pos: u64, len: u64) -> Self {
         Piece {
@@ -26,6 +45,8 @@ impl Piece {
             pos,
             len,
             count: 0,
+            eol_count: Eols::UNKNOWN,
+            eol_lead: 0,
         }
     }
 
@@ -35,18 +56,24 @@ impl Piece {
             pos,
             len,
             count,
+            eol_count: Eols::UNKNOWN,
+            eol_lead: 0,
         }
     }
 
     /// Split the piece at offset from the piece start.
     /// Modifies the current piece to be the left half
     /// and returns the right half.
+    ///
+    /// The EOL counts of both halves are unknown after the split, use
+    /// `EolCounter` to keep them.
     pub fn split_left(&mut self, offset: u64) -> Piece {
         debug_assert!(offset <= self.len);
         let right_start = self.pos + offset;
         let right_len = self.len - offset;
 
         self.len = offset;
+        self.eol_count = Eols::UNKNOWN;
 
         Piece::new_with_count(self.kind, right_start, right_len, self.count)
     }
@@ -59,24 +86,14 @@ impl Piece {
         mem::replace(self, right)
     }
 
+    /// EOL count of the piece in the tree it is in
+    #[inline]
+    pub fn eols(&self) -> Eols {
+        self.eol_count.offset(self.eol_lead as i64)
+    }
+
     /// Returns the range this piece references
     pub fn range(&self) -> Range<u64> {
         self.pos..self.pos + self.len
     }
-
-    /// Minimum absolute distance to buffer position
-    pub fn min_abs_distance(&self, bpos: u64) -> u64 {
-        fn abs_diff(x: u64, y: u64) -> u64 {
-            y.abs_diff(x)
-        }
-
-        if self.pos <= bpos && bpos < self.pos + self.len {
-            return 0;
-        }
-
-        let start = abs_diff(self.pos, bpos);
-        let end = abs_diff(self.pos + self.len, bpos);
-
-        std::cmp::min(start, end)
-    }
 }
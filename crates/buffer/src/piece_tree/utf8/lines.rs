@@ -2,7 +2,7 @@ mod eol;
 
 use std::ops::Range;
 
-use crate::{Bytes, PieceTreeSlice};
+use crate::{piece_tree::line_index, Bytes, PieceTreeSlice};
 
 pub use self::eol::EndOfLine;
 
@@ -204,6 +204,10 @@ pub fn prev_eol(bytes: &mut Bytes) -> Option<EOLMatch> {
 
 /// return position at line start of line
 pub(crate) fn pos_at_line(slice: &PieceTreeSlice, line: u64) -> Option<u64> {
+    if let Some(pos) = line_index::pos_at_line_number(slice, line) {
+        return pos;
+    }
+
     let mut n = 0;
     let mut lines = slice.lines();
 
@@ -220,6 +224,10 @@ pub(crate) fn pos_at_line(slice: &PieceTreeSlice, line: u64) -> Option<u64> {
 
 /// return the line and its number at pos
 pub(crate) fn line_at(slice: &PieceTreeSlice, pos: u64) -> (u64, PieceTreeSlice) {
+    if let Some(line) = indexed_line_at(slice, pos) {
+        return line;
+    }
+
     let mut lines = slice.lines();
     let mut cur = lines.next();
     let mut n = 0;
@@ -239,6 +247,29 @@ pub(crate) fn line_at(slice: &PieceTreeSlice, pos: u64) -> (u64, PieceTreeSlice)
     )
 }
 
+/// return the line and its number at pos using the line index
+fn indexed_line_at(slice: &PieceTreeSlice, pos: u64) -> Option<(u64, PieceTreeSlice)> {
+    if pos > slice.len() {
+        return None;
+    }
+
+    let mut n = line_index::line_number_at(slice, pos)?;
+    let mut start = line_index::pos_at_line_number(slice, n)??;
+
+    // At the end of slice ending in an EOL, the line before the empty last
+    // line is returned
+    if n != 0 && pos == slice.len() && start == pos {
+        n -= 1;
+        start = line_index::pos_at_line_number(slice, n)??;
+    }
+
+    let mut bytes = Bytes::new(slice, start);
+    let end = next_eol(&mut bytes)
+        .map(|mat| mat.range.end)
+        .unwrap_or(slice.len());
+    Some((n, slice.slice(start..end)))
+}
+
 #[derive(Debug, Clone)]
 pub struct Lines<'a> {
     bytes: Bytes<'a>,
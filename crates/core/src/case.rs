@@ -0,0 +1,289 @@
+/// Identifier case styles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// snake_case
+    Snake,
+    /// camelCase
+    Camel,
+    /// PascalCase
+    Pascal,
+    /// kebab-case
+    Kebab,
+    /// SCREAMING_SNAKE_CASE
+    Constant,
+    /// Title Case
+    Title,
+}
+
+impl Case {
+    /// Order in which cases are cycled through
+    const CYCLE: [Case; 6] = [
+        Case::Snake,
+        Case::Camel,
+        Case::Pascal,
+        Case::Kebab,
+        Case::Constant,
+        Case::Title,
+    ];
+
+    /// Next case when cycling through cases
+    pub fn next(&self) -> Case {
+        let pos = Self::CYCLE.iter().position(|c| c == self).unwrap_or(0);
+        Self::CYCLE[(pos + 1) % Self::CYCLE.len()]
+    }
+
+    /// Detect the case of an identifier, returns None if text has no letters
+    pub fn detect(text: &str) -> Option<Case> {
+        let text = text.trim_matches(is_separator);
+        if !text.chars().any(char::is_alphabetic) {
+            return None;
+        }
+
+        let has_lower = text.chars().any(char::is_lowercase);
+        let has_upper = text.chars().any(char::is_uppercase);
+        let case = if text.contains(char::is_whitespace) {
+            Case::Title
+        } else if text.contains('-') {
+            Case::Kebab
+        } else if !has_lower {
+            Case::Constant
+        } else if text.contains('_') {
+            Case::Snake
+        } else if text.starts_with(char::is_uppercase) {
+            Case::Pascal
+        } else if has_upper {
+            Case::Camel
+        } else {
+            Case::Snake
+        };
+        Some(case)
+    }
+
+    /// Convert the identifiers in text to this case. Each identifier is
+    /// converted separately, and the text between them such as whitespace and
+    /// punctuation is kept as is. A line containing only a Title Case phrase
+    /// is converted as a single identifier.
+    pub fn convert(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        for line in text.split_inclusive('\n') {
+            let content = line.trim();
+            if !is_title_phrase(content) {
+                self.convert_identifiers(line, &mut result);
+                continue;
+            }
+
+            let lead = &line[..line.len() - line.trim_start().len()];
+            let trail = &line[lead.len() + content.len()..];
+            result.push_str(lead);
+            result.push_str(&self.join(split_words(content)));
+            result.push_str(trail);
+        }
+        result
+    }
+
+    fn convert_identifiers(&self, text: &str, result: &mut String) {
+        let mut rest = text;
+        while let Some(start) = rest.find(is_identifier_char) {
+            result.push_str(&rest[..start]);
+            let text = &rest[start..];
+            let len = identifier_len(text);
+            self.convert_identifier(&text[..len], result);
+            rest = &text[len..];
+        }
+        result.push_str(rest);
+    }
+
+    /// Convert a single identifier, underscores around it are kept
+    fn convert_identifier(&self, ident: &str, result: &mut String) {
+        let content = ident.trim_matches('_');
+        let lead = &ident[..ident.len() - ident.trim_start_matches('_').len()];
+        let trail = &ident[lead.len() + content.len()..];
+
+        result.push_str(lead);
+        result.push_str(&self.join(split_words(content)));
+        result.push_str(trail);
+    }
+
+    fn join(&self, words: Vec<&str>) -> String {
+        let lower = words.iter().map(|w| w.to_lowercase());
+        match self {
+            Case::Snake => lower.collect::<Vec<_>>().join("_"),
+            Case::Kebab => lower.collect::<Vec<_>>().join("-"),
+            Case::Constant => words
+                .iter()
+                .map(|w| w.to_uppercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            Case::Title => words
+                .iter()
+                .map(|w| capitalize(w))
+                .collect::<Vec<_>>()
+                .join(" "),
+            Case::Pascal => words.iter().map(|w| capitalize(w)).collect(),
+            Case::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(i, w)| {
+                    if i == 0 {
+                        w.to_lowercase()
+                    } else {
+                        capitalize(w)
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Characters separating words in identifiers
+fn is_separator(ch: char) -> bool {
+    matches!(ch, '_' | '-') || ch.is_whitespace()
+}
+
+fn is_identifier_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// Length of the identifier at the start of text. Dashes are part of the
+/// identifier only between alphanumeric characters, so that `a-b` is a
+/// kebab-case identifier but `a - b` is not.
+fn identifier_len(text: &str) -> usize {
+    let mut prev = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, ch)) = chars.next() {
+        let is_dash = ch == '-'
+            && prev.is_some_and(char::is_alphanumeric)
+            && chars.peek().is_some_and(|(_, n)| n.is_alphanumeric());
+        if !is_identifier_char(ch) && !is_dash {
+            return i;
+        }
+        prev = Some(ch);
+    }
+
+    text.len()
+}
+
+/// Whether text is words starting with an uppercase letter separated by
+/// single spaces, such as `Foo Bar`
+fn is_title_phrase(text: &str) -> bool {
+    text.contains(' ')
+        && text.split(' ').all(|word| {
+            word.starts_with(char::is_uppercase) && word.chars().all(char::is_alphanumeric)
+        })
+}
+
+/// Uppercase the first letter and lowercase the rest
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.as_str().to_lowercase().chars())
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// Split an identifier into words at separators and case humps.
+/// Uppercase runs are kept together as acronyms so that `HTTPServer` splits
+/// into `HTTP` and `Server`.
+pub fn split_words(text: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let mut start: Option<usize> = None;
+    let mut prev: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, ch)) = chars.next() {
+        if is_separator(ch) {
+            if let Some(s) = start.take() {
+                words.push(&text[s..i]);
+            }
+            prev = None;
+            continue;
+        }
+
+        if let (Some(s), Some(p)) = (start, prev) {
+            let next_lower = chars.peek().is_some_and(|(_, n)| n.is_lowercase());
+            let hump = ch.is_uppercase()
+                && (p.is_lowercase() || p.is_numeric() || (p.is_uppercase() && next_lower));
+            if hump {
+                words.push(&text[s..i]);
+                start = Some(i);
+            }
+        }
+
+        start.get_or_insert(i);
+        prev = Some(ch);
+    }
+
+    if let Some(s) = start {
+        words.push(&text[s..]);
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split() {
+        assert_eq!(vec!["foo", "bar"], split_words("foo_bar"));
+        assert_eq!(vec!["foo", "Bar", "Baz"], split_words("fooBarBaz"));
+        assert_eq!(vec!["HTTP", "Server"], split_words("HTTPServer"));
+        assert_eq!(vec!["version2", "Name"], split_words("version2Name"));
+        assert_eq!(vec!["a", "b", "c"], split_words("a-b c"));
+        assert_eq!(vec!["Über", "Größe"], split_words("ÜberGröße"));
+    }
+
+    #[test]
+    fn convert() {
+        let text = "parseHTTPResponse";
+        assert_eq!("parse_http_response", Case::Snake.convert(text));
+        assert_eq!("parseHttpResponse", Case::Camel.convert(text));
+        assert_eq!("ParseHttpResponse", Case::Pascal.convert(text));
+        assert_eq!("parse-http-response", Case::Kebab.convert(text));
+        assert_eq!("PARSE_HTTP_RESPONSE", Case::Constant.convert(text));
+        assert_eq!("Parse Http Response", Case::Title.convert(text));
+
+        assert_eq!("__init_value__", Case::Snake.convert("__InitValue__"));
+        assert_eq!("ÉtéChaud", Case::Pascal.convert("été_chaud"));
+        assert_eq!(
+            "  foo_bar\n  baz_qux",
+            Case::Snake.convert("  fooBar\n  bazQux")
+        );
+        assert_eq!("foo_bar", Case::Snake.convert("Foo Bar"));
+    }
+
+    #[test]
+    fn convert_identifiers() {
+        assert_eq!(
+            "let foo_bar = baz_qux(x, y - 1);",
+            Case::Snake.convert("let fooBar = bazQux(x, y - 1);")
+        );
+        assert_eq!(
+            "fooBar(someValue, __private__)",
+            Case::Camel.convert("foo_bar(some-value, __private__)")
+        );
+        assert_eq!("FooBar::BazQux", Case::Pascal.convert("foo_bar::baz_qux"));
+    }
+
+    #[test]
+    fn cycle() {
+        assert_eq!(Some(Case::Snake), Case::detect("foo_bar"));
+        assert_eq!(Some(Case::Snake), Case::detect("foo"));
+        assert_eq!(Some(Case::Camel), Case::detect("fooBar"));
+        assert_eq!(Some(Case::Pascal), Case::detect("FooBar"));
+        assert_eq!(Some(Case::Kebab), Case::detect("foo-bar"));
+        assert_eq!(Some(Case::Constant), Case::detect("FOO_BAR"));
+        assert_eq!(Some(Case::Title), Case::detect("Foo Bar"));
+        assert_eq!(None, Case::detect("_12_"));
+
+        let mut case = Case::Snake;
+        for _ in 0..6 {
+            case = case.next();
+        }
+        assert_eq!(Case::Snake, case);
+    }
+}
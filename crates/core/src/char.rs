@@ -40,6 +40,16 @@ impl Chars {
         Chars::Single { ch }
     }
 
+    /// Two digit hexadecimal representation of a single byte
+    pub fn hex(byte: u8) -> Chars {
+        Chars::wide(format!("{byte:02x}"), 1)
+    }
+
+    /// Virtual text standing in for `len` bytes of the buffer
+    pub fn placeholder(text: &str, len: u64) -> Chars {
+        Chars::from_str(text, len)
+    }
+
     fn from_str(string: &str, len: u64) -> Chars {
         let mut chars = vec![];
         for ch in string.chars() {
@@ -139,6 +149,21 @@ impl Char {
         }
     }
 
+    /// Virtual character standing in for `len` bytes of the buffer that are
+    /// not shown, the cursor cannot be placed on it
+    pub fn new_hidden(ch: char, len: u64, eol: bool) -> Char {
+        Char {
+            character: ch,
+            extra: None,
+            flags: if eol {
+                flags::VIRTUAL | flags::EOL
+            } else {
+                flags::VIRTUAL
+            },
+            len_in_buffer: len,
+        }
+    }
+
     pub fn width(&self) -> usize {
         let width = if let Some(extra) = self.extra.as_ref() {
             extra.wide.width()
@@ -192,24 +217,6 @@ pub enum GraphemeCategory {
     Unknown,
 }
 
-impl GraphemeCategory {
-    #[inline(always)]
-    pub fn is_word(&self) -> bool {
-        use GraphemeCategory::*;
-        matches!(self, Word | Punctuation | Eol)
-    }
-}
-
-#[inline(always)]
-pub fn is_word_break(prev: &GraphemeCategory, next: &GraphemeCategory) -> bool {
-    prev != next && next.is_word()
-}
-
-#[inline(always)]
-pub fn is_word_break_end(prev: &GraphemeCategory, next: &GraphemeCategory) -> bool {
-    prev != next && prev.is_word()
-}
-
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
 #[repr(usize)]
 pub enum Replacement {
@@ -220,6 +227,9 @@ pub enum Replacement {
     TrailingWhitespace,
     NonBreakingSpace,
     Wrap,
+    Fold,
+    Precedes,
+    Extends,
 }
 
 /// Options on how to display chars
@@ -241,7 +251,7 @@ impl DisplayOptions {
     pub fn new(width: usize, height: usize) -> DisplayOptions {
         use Replacement::*;
 
-        const DEFAULT: [(Replacement, char); 7] = [
+        const DEFAULT: [(Replacement, char); 10] = [
             (Tab, '›'),
             (TabFill, ' '),
             (EOL, ' '),
@@ -249,6 +259,9 @@ impl DisplayOptions {
             (TrailingWhitespace, '•'),
             (NonBreakingSpace, '•'),
             (Wrap, '↳'),
+            (Fold, '⋯'),
+            (Precedes, '«'),
+            (Extends, '»'),
         ];
 
         let mut replacements = FxHashMap::default();
@@ -1,8 +1,10 @@
 use std::{cmp, mem, ops::RangeBounds};
 
+use serde::{Deserialize, Serialize};
+
 use crate::{BufferRange, Range};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub struct Cursor {
     /// Position in buffer
     pos: u64,
@@ -26,6 +26,10 @@ impl Diagnostic {
         &self.range
     }
 
+    pub fn set_range(&mut self, range: BufferRange) {
+        self.range = range;
+    }
+
     pub fn description(&self) -> &str {
         &self.description
     }
@@ -0,0 +1,368 @@
+use std::ops::Range;
+
+use rustc_hash::FxHashMap;
+use sanedit_buffer::PieceTreeSlice;
+
+use crate::{Change, Changes};
+
+/// Maximum edit distance searched before giving up and replacing the whole
+/// differing region at once
+const MAX_EDIT_DISTANCE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffGranularity {
+    /// Differing lines are replaced as a whole
+    #[default]
+    Line,
+    /// Differing lines are further diffed by words
+    Word,
+}
+
+/// Calculate changes that turn `a` into `b`, or none if they are equal.
+///
+/// The common prefix and suffix are skipped first, using the shared pieces
+/// of the slices where possible, and only the differing region in between
+/// is diffed line by line.
+pub fn diff(
+    a: &PieceTreeSlice,
+    b: &PieceTreeSlice,
+    granularity: DiffGranularity,
+) -> Option<Changes> {
+    let prefix = line_start_before(a, a.common_prefix_len(b));
+    let (arest, brest) = (a.slice(prefix..), b.slice(prefix..));
+    let suffix = arest.common_suffix_len(&brest);
+    let suffix_start = line_start_after(&arest, arest.len() - suffix, arest.len());
+    let aend = prefix + suffix_start;
+    let bend = b.len() - (arest.len() - suffix_start);
+
+    if aend == prefix && bend == prefix {
+        return None;
+    }
+
+    let abytes = Vec::from(&a.slice(prefix..aend));
+    let bbytes = Vec::from(&b.slice(prefix..bend));
+    let alines = split_lines(&abytes);
+    let blines = split_lines(&bbytes);
+    let mut interner = Interner::default();
+    let aids = interner.intern_all(&abytes, &alines);
+    let bids = interner.intern_all(&bbytes, &blines);
+
+    let Some(hunks) = myers(&aids, &bids) else {
+        return Some(Changes::from(Change::replace(prefix..aend, &bbytes)));
+    };
+
+    let mut changes = vec![];
+    for (ahunk, bhunk) in hunks {
+        let arange = token_range(&alines, &ahunk);
+        let brange = token_range(&blines, &bhunk);
+        let aslice = &abytes[arange.clone()];
+        let bslice = &bbytes[brange];
+        let start = prefix + arange.start as u64;
+
+        if granularity == DiffGranularity::Word && !aslice.is_empty() && !bslice.is_empty() {
+            if let Some(words) = diff_words(aslice, bslice, start) {
+                changes.extend(words);
+                continue;
+            }
+        }
+
+        changes.push(Change::replace(start..prefix + arange.end as u64, bslice));
+    }
+
+    Some(Changes::from(changes))
+}
+
+/// Diff two byte slices by words, positions are offset by `offset`
+fn diff_words(a: &[u8], b: &[u8], offset: u64) -> Option<Vec<Change>> {
+    let awords = split_words(a);
+    let bwords = split_words(b);
+    let mut interner = Interner::default();
+    let aids = interner.intern_all(a, &awords);
+    let bids = interner.intern_all(b, &bwords);
+    let hunks = myers(&aids, &bids)?;
+
+    let changes = hunks
+        .into_iter()
+        .map(|(ahunk, bhunk)| {
+            let arange = token_range(&awords, &ahunk);
+            let brange = token_range(&bwords, &bhunk);
+            let range = offset + arange.start as u64..offset + arange.end as u64;
+            Change::replace(range, &b[brange])
+        })
+        .collect();
+    Some(changes)
+}
+
+/// Move position back to the start of the line it is on
+fn line_start_before(slice: &PieceTreeSlice, pos: u64) -> u64 {
+    let mut bytes = slice.bytes_at(pos);
+    while let Some(byte) = bytes.prev() {
+        if byte == b'\n' {
+            return bytes.pos() + 1;
+        }
+    }
+    0
+}
+
+/// Move position forward to the start of the next line, unless it is
+/// already at a line start
+fn line_start_after(slice: &PieceTreeSlice, pos: u64, end: u64) -> u64 {
+    if pos == 0 || pos == end {
+        return pos;
+    }
+
+    let mut bytes = slice.bytes_at(pos - 1);
+    while let Some(byte) = bytes.next() {
+        if byte == b'\n' {
+            return bytes.pos();
+        }
+    }
+    end
+}
+
+/// Ranges of the lines in bytes, including the line endings
+fn split_lines(bytes: &[u8]) -> Vec<Range<usize>> {
+    let mut lines = vec![];
+    let mut start = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if *byte == b'\n' {
+            lines.push(start..i + 1);
+            start = i + 1;
+        }
+    }
+    if start != bytes.len() {
+        lines.push(start..bytes.len());
+    }
+    lines
+}
+
+/// Ranges of the words in bytes. Words are runs of word characters or
+/// whitespace, each line ending and other character is a word of its own.
+fn split_words(bytes: &[u8]) -> Vec<Range<usize>> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+
+    fn class(byte: u8) -> Class {
+        if byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80 {
+            Class::Word
+        } else if byte != b'\n' && byte.is_ascii_whitespace() {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    }
+
+    let mut words = vec![];
+    let mut start = 0;
+    while start < bytes.len() {
+        let cls = class(bytes[start]);
+        let mut end = start + 1;
+        if cls != Class::Other {
+            while end < bytes.len() && class(bytes[end]) == cls {
+                end += 1;
+            }
+        }
+        words.push(start..end);
+        start = end;
+    }
+    words
+}
+
+/// Byte range of a range of tokens
+fn token_range(tokens: &[Range<usize>], range: &Range<usize>) -> Range<usize> {
+    let pos = |i: usize| {
+        tokens
+            .get(i)
+            .map(|t| t.start)
+            .unwrap_or_else(|| tokens.last().map(|t| t.end).unwrap_or(0))
+    };
+    pos(range.start)..pos(range.end)
+}
+
+/// Maps equal tokens to the same identifier so they can be compared cheaply
+#[derive(Debug, Default)]
+struct Interner<'a> {
+    ids: FxHashMap<&'a [u8], u32>,
+}
+
+impl<'a> Interner<'a> {
+    fn intern_all(&mut self, bytes: &'a [u8], tokens: &[Range<usize>]) -> Vec<u32> {
+        tokens
+            .iter()
+            .map(|range| {
+                let next = self.ids.len() as u32;
+                *self.ids.entry(&bytes[range.clone()]).or_insert(next)
+            })
+            .collect()
+    }
+}
+
+/// Myers' O(ND) difference algorithm.
+///
+/// Returns the differing token ranges of `a` and `b` in order, or none if
+/// the edit distance is larger than [`MAX_EDIT_DISTANCE`].
+fn myers(a: &[u32], b: &[u32]) -> Option<Vec<(Range<usize>, Range<usize>)>> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    // Furthest reaching x for each diagonal k, stored for each d
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    for d in 0..=MAX_EDIT_DISTANCE as isize {
+        let mut v = vec![0; 2 * d as usize + 1];
+        for k in (-d..=d).step_by(2) {
+            let prev = |k: isize| trace[(d - 1) as usize][(k + d - 1) as usize];
+            let mut x = if d == 0 {
+                0
+            } else if k == -d || (k != d && prev(k - 1) < prev(k + 1)) {
+                prev(k + 1)
+            } else {
+                prev(k - 1) + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(k + d) as usize] = x;
+
+            if x >= n && y >= m {
+                trace.push(v);
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+        trace.push(v);
+    }
+
+    None
+}
+
+/// Walk the trace backwards from the end and collect the edits into hunks
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut hunks: Vec<(Range<usize>, Range<usize>)> = vec![];
+    let (mut x, mut y) = (n, m);
+
+    for d in (1..trace.len() as isize).rev() {
+        let prev = |k: isize| trace[(d - 1) as usize][(k + d - 1) as usize];
+        let k = x - y;
+        let insert = k == -d || (k != d && prev(k - 1) < prev(k + 1));
+        let pk = if insert { k + 1 } else { k - 1 };
+        let px = prev(pk);
+        let py = px - pk;
+
+        let (arange, brange) = if insert {
+            (px..px, py..py + 1)
+        } else {
+            (px..px + 1, py..py)
+        };
+        let (arange, brange) = (
+            arange.start as usize..arange.end as usize,
+            brange.start as usize..brange.end as usize,
+        );
+
+        match hunks.last_mut() {
+            Some((ahunk, bhunk)) if ahunk.start == arange.end && bhunk.start == brange.end => {
+                ahunk.start = arange.start;
+                bhunk.start = brange.start;
+            }
+            _ => hunks.push((arange, brange)),
+        }
+
+        x = px;
+        y = py;
+    }
+
+    hunks.reverse();
+    hunks
+}
+
+#[cfg(test)]
+mod test {
+    use sanedit_buffer::PieceTree;
+
+    use super::*;
+    use crate::BufferRange;
+
+    fn check(a: &str, b: &str, granularity: DiffGranularity) -> Changes {
+        let mut pt = PieceTree::from(a);
+        let other = PieceTree::from(b);
+        let changes = diff(&pt.slice(..), &other.slice(..), granularity).unwrap();
+        changes.apply(&mut pt);
+        assert_eq!(b, String::from(&pt));
+        changes
+    }
+
+    #[test]
+    fn identical() {
+        let a = PieceTree::from("hello\nworld\n");
+        let b = PieceTree::from("hello\nworld\n");
+        assert!(diff(&a.slice(..), &b.slice(..), DiffGranularity::Line).is_none());
+        assert!(diff(&a.slice(..0), &b.slice(..0), DiffGranularity::Word).is_none());
+    }
+
+    #[test]
+    fn lines() {
+        let changes = check(
+            "one\ntwo\nthree\nfour\nfive\n",
+            "one\n2\nthree\nfive\nsix\n",
+            DiffGranularity::Line,
+        );
+        let changes: Vec<(BufferRange, &[u8])> =
+            changes.iter().map(|c| (c.range(), c.text())).collect();
+        assert_eq!(
+            vec![
+                (BufferRange::from(4..8), b"2\n".as_slice()),
+                (BufferRange::from(14..19), b"".as_slice()),
+                (BufferRange::from(24..24), b"six\n".as_slice()),
+            ],
+            changes
+        );
+
+        check("a\nb\nc", "a\nb\nc\nd", DiffGranularity::Line);
+        check("", "a\nb\n", DiffGranularity::Line);
+        check("a\nb\n", "", DiffGranularity::Line);
+        check("x\na\nb\na\n", "a\nb\nb\nx\n", DiffGranularity::Line);
+    }
+
+    #[test]
+    fn words() {
+        let changes = check(
+            "let foo = bar(1, 2);\nfoo\n",
+            "let foo = baz(1, 3);\nfoo\n",
+            DiffGranularity::Word,
+        );
+        let changes: Vec<(BufferRange, &[u8])> =
+            changes.iter().map(|c| (c.range(), c.text())).collect();
+        assert_eq!(
+            vec![
+                (BufferRange::from(10..13), b"baz".as_slice()),
+                (BufferRange::from(17..18), b"3".as_slice()),
+            ],
+            changes
+        );
+
+        check("a b c\nd e f", "a c\nd x e f\ng", DiffGranularity::Word);
+    }
+
+    #[test]
+    fn shared_pieces() {
+        let mut pt = PieceTree::from("first\nsecond\nthird\n");
+        let saved = pt.slice(..);
+        pt.insert(6, "inserted\n");
+        pt.remove(0..1);
+
+        let changes = diff(&pt.slice(..), &saved, DiffGranularity::Line).unwrap();
+        changes.apply(&mut pt);
+        assert_eq!(Vec::from(&saved), Vec::from(&pt.slice(..)));
+    }
+
+    #[test]
+    fn too_many_edits() {
+        let a: String = (0..3000).map(|i| format!("{i}\n")).collect();
+        let b: String = (0..3000).map(|i| format!("{}\n", i * 7)).collect();
+        check(&a, &b, DiffGranularity::Word);
+    }
+}
@@ -12,6 +12,8 @@ use rustc_hash::FxHashSet;
 const TMP_DIR: &str = "tmp";
 pub const SANE_DIR: &str = "sanedit";
 pub const SESSION_DIR: &str = "session";
+pub const UNDO_DIR: &str = "undo";
+pub const RECOVERY_DIR: &str = "recovery";
 pub const TMP_FILE_DIR: &str = "tmp-file";
 pub const LANG_DIR: &str = "language";
 pub const THEME_DIR: &str = "themes";
@@ -161,6 +163,18 @@ pub fn session_dir() -> Option<PathBuf> {
     Some(data.join(SESSION_DIR))
 }
 
+/// Directory to persist undo history to
+pub fn undo_dir() -> Option<PathBuf> {
+    let data = data_dir()?;
+    Some(data.join(UNDO_DIR))
+}
+
+/// Directory to write recovery journals of unsaved changes to
+pub fn recovery_dir() -> Option<PathBuf> {
+    let data = data_dir()?;
+    Some(data.join(RECOVERY_DIR))
+}
+
 pub fn tmp_dir() -> Option<PathBuf> {
     let data = data_dir()?;
     let tmp = data.join(TMP_DIR);
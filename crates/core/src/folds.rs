@@ -0,0 +1,151 @@
+use sanedit_buffer::PieceTreeSlice;
+
+use crate::{
+    movement::{end_of_line, next_line_start, prev_line_start, start_of_line},
+    BufferRange, Range,
+};
+
+/// Width of the indentation of the line starting at `start`, None if the
+/// line is empty or whitespace only
+fn line_indent(slice: &PieceTreeSlice, start: u64) -> Option<u64> {
+    let mut bytes = slice.bytes_at(start);
+    let mut n = 0;
+    while let Some(b) = bytes.next() {
+        match b {
+            b' ' | b'\t' => n += 1,
+            b'\n' | b'\r' => return None,
+            _ => return Some(n),
+        }
+    }
+
+    None
+}
+
+/// Start of the line after the one starting at `start`, None if this is the
+/// last line
+fn next_line(slice: &PieceTreeSlice, start: u64) -> Option<u64> {
+    let next = next_line_start(slice, start);
+    if next <= start || next >= slice.len() {
+        return None;
+    }
+    Some(next)
+}
+
+/// Start of the line before the one starting at `start`, None if this is the
+/// first line
+fn prev_line(slice: &PieceTreeSlice, start: u64) -> Option<u64> {
+    if start == 0 {
+        return None;
+    }
+    Some(prev_line_start(slice, start))
+}
+
+/// Fold over the lines indented more than the line starting at `header`
+fn indent_fold_from(slice: &PieceTreeSlice, header: u64, indent: u64) -> Option<BufferRange> {
+    let mut last = None;
+    let mut line = next_line(slice, header);
+    while let Some(start) = line {
+        match line_indent(slice, start) {
+            Some(n) if n <= indent => break,
+            Some(_) => last = Some(start),
+            None => {}
+        }
+        line = next_line(slice, start);
+    }
+
+    let last = last?;
+    Some(Range::from(header..end_of_line(slice, last)))
+}
+
+/// Fold based on indentation containing the line at `pos`.
+///
+/// The fold starts at a header line and covers the following lines that
+/// are more indented than the header. If the next line is more indented the
+/// line at `pos` is the header, otherwise the nearest less indented line
+/// above it. The fold ends at the end of its last non empty line.
+pub fn indent_fold_at(slice: &PieceTreeSlice, pos: u64) -> Option<BufferRange> {
+    let mut start = start_of_line(slice, pos);
+
+    // Use the first non empty line above as reference for empty lines
+    let mut indent = line_indent(slice, start);
+    while indent.is_none() {
+        start = prev_line(slice, start)?;
+        indent = line_indent(slice, start);
+    }
+    let indent = indent?;
+
+    if let Some(fold) = indent_fold_from(slice, start, indent) {
+        if fold.end >= pos {
+            return Some(fold);
+        }
+    }
+
+    let mut header = start;
+    loop {
+        header = prev_line(slice, header)?;
+        if let Some(n) = line_indent(slice, header) {
+            if n < indent {
+                return indent_fold_from(slice, header, n);
+            }
+        }
+    }
+}
+
+/// Convert a range to a fold over the lines it touches, None if the range
+/// is within a single line
+pub fn fold_from_range(slice: &PieceTreeSlice, range: BufferRange) -> Option<BufferRange> {
+    let start = start_of_line(slice, range.start);
+    let last = range.end.saturating_sub(1).max(range.start);
+    let end = end_of_line(slice, last);
+    if end_of_line(slice, start) >= end {
+        return None;
+    }
+
+    Some(Range::from(start..end))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sanedit_buffer::PieceTree;
+
+    const TEXT: &str =
+        "fn a() {\n    let x = 1;\n\n    if x {\n        b();\n    }\n}\nfn c() {}\n";
+
+    #[test]
+    fn indent_fold_header() {
+        let pt = PieceTree::from(TEXT);
+        let slice = pt.slice(..);
+        // Header line, fold ends at the end of the last more indented line
+        assert_eq!(Some(Range::from(0..54)), indent_fold_at(&slice, 3));
+        assert_eq!(Some(Range::from(25..48)), indent_fold_at(&slice, 26));
+    }
+
+    #[test]
+    fn indent_fold_body() {
+        let pt = PieceTree::from(TEXT);
+        let slice = pt.slice(..);
+        assert_eq!(Some(Range::from(0..54)), indent_fold_at(&slice, 12));
+        // Empty line uses the line above
+        assert_eq!(Some(Range::from(0..54)), indent_fold_at(&slice, 24));
+        assert_eq!(Some(Range::from(25..48)), indent_fold_at(&slice, 40));
+    }
+
+    #[test]
+    fn indent_fold_none() {
+        let pt = PieceTree::from(TEXT);
+        let slice = pt.slice(..);
+        assert_eq!(None, indent_fold_at(&slice, 58));
+    }
+
+    #[test]
+    fn fold_from_multiline_range() {
+        let pt = PieceTree::from(TEXT);
+        let slice = pt.slice(..);
+        assert_eq!(
+            Some(Range::from(0..56)),
+            fold_from_range(&slice, Range::from(0..56))
+        );
+        assert_eq!(None, fold_from_range(&slice, Range::from(2..6)));
+    }
+}
@@ -0,0 +1,485 @@
+use std::ops::Range as StdRange;
+
+use sanedit_buffer::PieceTreeSlice;
+
+use crate::{
+    movement::{end_of_line, start_of_line},
+    BufferRange, Range,
+};
+
+/// Increment the number, date, time or boolean under position or after it on
+/// the same line by `amount`. Booleans are toggled if amount is odd.
+///
+/// Returns the range to replace and the replacement.
+pub fn increment(slice: &PieceTreeSlice, pos: u64, amount: i64) -> Option<(BufferRange, String)> {
+    let start = start_of_line(slice, pos);
+    let end = end_of_line(slice, pos);
+    let line = String::from(&slice.slice(start..end));
+    let col = (pos - start) as usize;
+    let (range, text) = increment_in_line(&line, col, amount)?;
+    let range = Range::from(start + range.start as u64..start + range.end as u64);
+    Some((range, text))
+}
+
+fn increment_in_line(line: &str, col: usize, amount: i64) -> Option<(StdRange<usize>, String)> {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let item = DateTime::parse(bytes, i)
+            .map(Item::DateTime)
+            .or_else(|| Bool::parse(bytes, i).map(Item::Bool))
+            .or_else(|| Number::parse(bytes, i).map(Item::Number));
+
+        match item {
+            Some(item) if item.range().end > col => return item.increment(line, col, amount),
+            Some(item) => i = item.range().end,
+            None => i += 1,
+        }
+    }
+
+    None
+}
+
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+#[derive(Debug)]
+enum Item {
+    Number(Number),
+    Bool(Bool),
+    DateTime(DateTime),
+}
+
+impl Item {
+    fn range(&self) -> StdRange<usize> {
+        match self {
+            Item::Number(num) => num.range.clone(),
+            Item::Bool(b) => b.range.clone(),
+            Item::DateTime(dt) => dt.range.clone(),
+        }
+    }
+
+    fn increment(&self, line: &str, col: usize, amount: i64) -> Option<(StdRange<usize>, String)> {
+        match self {
+            Item::Number(num) => num.increment(line, amount),
+            Item::Bool(b) => b.toggle(line, amount),
+            Item::DateTime(dt) => dt.increment(col, amount),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Number {
+    /// Range of the sign and digits, excluding a radix prefix
+    range: StdRange<usize>,
+    radix: u32,
+}
+
+impl Number {
+    fn parse(bytes: &[u8], i: usize) -> Option<Number> {
+        let digits_from = |start: usize, radix: u32| {
+            let len = bytes[start..]
+                .iter()
+                .take_while(|b| (**b as char).is_digit(radix))
+                .count();
+            start + len
+        };
+        let boundary = i == 0 || !is_word_byte(bytes[i - 1]);
+
+        // 0x, 0b and 0o prefixed
+        if boundary && bytes[i] == b'0' && i + 1 < bytes.len() {
+            let radix = match bytes[i + 1] {
+                b'x' | b'X' => 16,
+                b'b' | b'B' => 2,
+                b'o' | b'O' => 8,
+                _ => 0,
+            };
+            if radix != 0 {
+                let end = digits_from(i + 2, radix);
+                if end > i + 2 {
+                    return Some(Number {
+                        range: i + 2..end,
+                        radix,
+                    });
+                }
+            }
+        }
+
+        let negative =
+            bytes[i] == b'-' && boundary && bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit());
+        let start = if negative { i + 1 } else { i };
+        let end = digits_from(start, 10);
+        if end == start {
+            return None;
+        }
+
+        Some(Number {
+            range: i..end,
+            radix: 10,
+        })
+    }
+
+    fn increment(&self, line: &str, amount: i64) -> Option<(StdRange<usize>, String)> {
+        let text = &line[self.range.clone()];
+        let digits = text.trim_start_matches('-');
+        let width = if self.radix != 10 || (digits.len() > 1 && digits.starts_with('0')) {
+            digits.len()
+        } else {
+            0
+        };
+
+        let result = if self.radix == 10 {
+            let value: i128 = text.parse().ok()?;
+            let value = value.checked_add(amount as i128)?;
+            let sign = if value < 0 { "-" } else { "" };
+            format!("{sign}{:0width$}", value.unsigned_abs())
+        } else {
+            let value = u64::from_str_radix(digits, self.radix).ok()?;
+            let mut value = value as i128 + amount as i128;
+            if value < 0 || value > u64::MAX as i128 {
+                // Wrap around within the digits the number was written with
+                let modulus = (self.radix as i128)
+                    .checked_pow(width as u32)
+                    .map_or(1 << 64, |m| m.min(1 << 64));
+                value = value.rem_euclid(modulus);
+            }
+            let value = value as u64;
+            match self.radix {
+                16 if digits.bytes().any(|b| b.is_ascii_uppercase()) => {
+                    format!("{value:0width$X}")
+                }
+                16 => format!("{value:0width$x}"),
+                8 => format!("{value:0width$o}"),
+                _ => format!("{value:0width$b}"),
+            }
+        };
+
+        Some((self.range.clone(), result))
+    }
+}
+
+#[derive(Debug)]
+struct Bool {
+    range: StdRange<usize>,
+}
+
+impl Bool {
+    const VALUES: [(&'static str, &'static str); 3] =
+        [("true", "false"), ("True", "False"), ("TRUE", "FALSE")];
+
+    fn parse(bytes: &[u8], i: usize) -> Option<Bool> {
+        if i != 0 && is_word_byte(bytes[i - 1]) {
+            return None;
+        }
+
+        for (t, f) in Self::VALUES {
+            for word in [t, f] {
+                let end = i + word.len();
+                if bytes[i..].starts_with(word.as_bytes())
+                    && bytes.get(end).is_none_or(|b| !is_word_byte(*b))
+                {
+                    return Some(Bool { range: i..end });
+                }
+            }
+        }
+
+        None
+    }
+
+    fn toggle(&self, line: &str, amount: i64) -> Option<(StdRange<usize>, String)> {
+        if amount % 2 == 0 {
+            return None;
+        }
+
+        let text = &line[self.range.clone()];
+        let toggled = Self::VALUES.iter().find_map(|(t, f)| {
+            if text == *t {
+                Some(*f)
+            } else if text == *f {
+                Some(*t)
+            } else {
+                None
+            }
+        })?;
+        Some((self.range.clone(), toggled.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+/// ISO 8601 date, time or date and time
+#[derive(Debug)]
+struct DateTime {
+    range: StdRange<usize>,
+    date: Option<(i64, u32, u32)>,
+    /// Separator between date and time
+    separator: char,
+    time: Option<(u32, u32, Option<u32>)>,
+    /// Fields and their ranges
+    fields: Vec<(StdRange<usize>, Field)>,
+}
+
+impl DateTime {
+    fn parse(bytes: &[u8], i: usize) -> Option<DateTime> {
+        if i != 0 && bytes[i - 1].is_ascii_digit() {
+            return None;
+        }
+
+        let num = |start: usize, len: usize| -> Option<u32> {
+            let digits = bytes.get(start..start + len)?;
+            if !digits.iter().all(u8::is_ascii_digit) {
+                return None;
+            }
+            std::str::from_utf8(digits).ok()?.parse().ok()
+        };
+        let sep = |pos: usize, ch: u8| bytes.get(pos) == Some(&ch);
+
+        let mut fields = vec![];
+        let mut end = i;
+        let mut date = None;
+        if sep(i + 4, b'-') && sep(i + 7, b'-') {
+            let year = num(i, 4)?;
+            let month = num(i + 5, 2)?;
+            let day = num(i + 8, 2)?;
+            let year = year as i64;
+            if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+                return None;
+            }
+            date = Some((year, month, day));
+            fields.push((i..i + 4, Field::Year));
+            fields.push((i + 5..i + 7, Field::Month));
+            fields.push((i + 8..i + 10, Field::Day));
+            end = i + 10;
+        }
+
+        let mut separator = 'T';
+        let mut tstart = end;
+        if date.is_some() {
+            match bytes.get(end) {
+                Some(b'T') => {}
+                Some(b' ') => separator = ' ',
+                _ => tstart = usize::MAX,
+            }
+            tstart = tstart.saturating_add(1);
+        }
+
+        let mut time = None;
+        if tstart < bytes.len() && sep(tstart + 2, b':') {
+            let hour = num(tstart, 2);
+            let min = num(tstart + 3, 2);
+            if let (Some(hour), Some(min)) = (hour, min) {
+                let sec = if sep(tstart + 5, b':') {
+                    num(tstart + 6, 2)
+                } else {
+                    None
+                };
+                if hour < 24 && min < 60 && sec.is_none_or(|s| s < 60) {
+                    time = Some((hour, min, sec));
+                    fields.push((tstart..tstart + 2, Field::Hour));
+                    fields.push((tstart + 3..tstart + 5, Field::Minute));
+                    end = tstart + 5;
+                    if sec.is_some() {
+                        fields.push((tstart + 6..tstart + 8, Field::Second));
+                        end = tstart + 8;
+                    }
+                }
+            }
+        }
+
+        if fields.is_empty() || bytes.get(end).is_some_and(u8::is_ascii_digit) {
+            return None;
+        }
+
+        Some(DateTime {
+            range: i..end,
+            date,
+            separator,
+            time,
+            fields,
+        })
+    }
+
+    fn increment(&self, col: usize, amount: i64) -> Option<(StdRange<usize>, String)> {
+        // Field under cursor, or the smallest unit if cursor is before
+        let field = if col < self.range.start {
+            self.fields.last()?.1
+        } else {
+            self.fields.iter().find(|(range, _)| range.end > col)?.1
+        };
+
+        let mut date = self.date;
+        let mut time = self.time;
+        match field {
+            Field::Year | Field::Month => {
+                let (year, month, day) = date?;
+                let months = match field {
+                    Field::Year => amount.checked_mul(12)?,
+                    _ => amount,
+                };
+                let total = (year * 12 + month as i64 - 1).checked_add(months)?;
+                let year = total.div_euclid(12);
+                let month = total.rem_euclid(12) as u32 + 1;
+                let day = day.min(days_in_month(year, month));
+                date = Some((year, month, day));
+            }
+            Field::Day => {
+                let (year, month, day) = date?;
+                let days = days_from_civil(year, month, day).checked_add(amount)?;
+                date = Some(civil_from_days(days));
+            }
+            Field::Hour | Field::Minute | Field::Second => {
+                let (hour, min, sec) = time?;
+                let unit = match field {
+                    Field::Hour => 3600,
+                    Field::Minute => 60,
+                    _ => 1,
+                };
+                let secs = (hour * 3600 + min * 60 + sec.unwrap_or(0)) as i64;
+                let secs = amount.checked_mul(unit)?.checked_add(secs)?;
+                let carry = secs.div_euclid(86400);
+                let secs = secs.rem_euclid(86400) as u32;
+                time = Some((secs / 3600, secs / 60 % 60, sec.map(|_| secs % 60)));
+
+                if let Some((year, month, day)) = date {
+                    let days = days_from_civil(year, month, day).checked_add(carry)?;
+                    date = Some(civil_from_days(days));
+                }
+            }
+        }
+
+        let mut result = String::new();
+        if let Some((year, month, day)) = date {
+            if !(0..=9999).contains(&year) {
+                return None;
+            }
+            result.push_str(&format!("{year:04}-{month:02}-{day:02}"));
+        }
+
+        if let Some((hour, min, sec)) = time {
+            if date.is_some() {
+                result.push(self.separator);
+            }
+            result.push_str(&format!("{hour:02}:{min:02}"));
+            if let Some(sec) = sec {
+                result.push_str(&format!(":{sec:02}"));
+            }
+        }
+
+        Some((self.range.clone(), result))
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Date from days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400;
+    let year = if month <= 2 { year + 1 } else { year };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn inc(line: &str, col: usize, amount: i64) -> Option<String> {
+        let (range, text) = increment_in_line(line, col, amount)?;
+        let mut result = line.to_string();
+        result.replace_range(range, &text);
+        Some(result)
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(Some("x = 42;".into()), inc("x = 41;", 0, 1));
+        assert_eq!(Some("x = 1;".into()), inc("x = -1;", 5, 2));
+        assert_eq!(Some("x = -1;".into()), inc("x = 1;", 4, -2));
+        assert_eq!(Some("v2 + 10".into()), inc("v1 + 10", 0, 1));
+        assert_eq!(Some("v1 + 20".into()), inc("v1 + 10", 2, 10));
+        assert_eq!(Some("a-2".into()), inc("a-1", 0, 1));
+        assert_eq!(Some("007 008".into()), inc("007 008", 4, 0));
+        assert_eq!(Some("010".into()), inc("009", 0, 1));
+        assert_eq!(Some("-010".into()), inc("-009", 0, -1));
+        assert_eq!(Some("0x0100".into()), inc("0x00ff", 0, 1));
+        assert_eq!(Some("0xFF".into()), inc("0x0A", 0, 245));
+        assert_eq!(Some("0b0110".into()), inc("0b0101", 0, 1));
+        assert_eq!(Some("0o10".into()), inc("0o07", 0, 1));
+        assert_eq!(Some("0xff".into()), inc("0x00", 0, -1));
+        assert_eq!(Some("0xfffe".into()), inc("0x0000", 0, -0x10002));
+        assert_eq!(Some("0b11".into()), inc("0b00", 0, -1));
+        assert_eq!(
+            Some("0x0000000000000000".into()),
+            inc("0xffffffffffffffff", 0, 1)
+        );
+        assert_eq!(None, inc("no numbers", 0, 1));
+        assert_eq!(None, inc("1 before", 3, 1));
+    }
+
+    #[test]
+    fn booleans() {
+        assert_eq!(Some("let a = false;".into()), inc("let a = true;", 0, 1));
+        assert_eq!(Some("x = True".into()), inc("x = False", 6, -1));
+        assert_eq!(Some("FALSE".into()), inc("TRUE", 0, 3));
+        assert_eq!(None, inc("untrue", 0, 1));
+        assert_eq!(None, inc("true", 0, 2));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(Some("2024-03-01".into()), inc("2024-02-29", 8, 1));
+        assert_eq!(Some("2025-02-28".into()), inc("2024-02-29", 2, 1));
+        assert_eq!(Some("2024-01-31".into()), inc("2023-12-31", 6, 1));
+        assert_eq!(Some("2023-12-31".into()), inc("2024-01-01", 9, -1));
+        assert_eq!(Some("23:59".into()), inc("00:00", 4, -1));
+        assert_eq!(Some("10:00:05".into()), inc("09:00:05", 1, 1));
+        assert_eq!(
+            Some("2024-01-01T00:00:10".into()),
+            inc("2023-12-31T23:59:50", 18, 20)
+        );
+        assert_eq!(
+            Some("at 2024-01-02 10:00".into()),
+            inc("at 2024-01-01 10:00", 0, 24 * 60)
+        );
+        // Not a valid date, increments the number
+        assert_eq!(Some("2024-14-01".into()), inc("2024-13-01", 6, 1));
+    }
+}
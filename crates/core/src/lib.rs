@@ -1,29 +1,47 @@
+pub(crate) mod case;
 pub(crate) mod change;
 pub(crate) mod char;
 pub(crate) mod cursor;
 pub(crate) mod diagnostic;
+pub(crate) mod diff;
 pub(crate) mod dirs;
+pub(crate) mod folds;
+pub(crate) mod increment;
 pub(crate) mod indent;
 pub(crate) mod language;
+pub(crate) mod lines;
 pub(crate) mod locations;
 pub(crate) mod range;
+pub(crate) mod reflow;
 pub(crate) mod search;
+pub(crate) mod sentence;
 pub(crate) mod severity;
+pub(crate) mod surround;
 pub(crate) mod text;
 pub(crate) mod text_object;
+pub(crate) mod word;
 
 pub mod movement;
 
+pub use case::*;
 pub use change::*;
 pub use char::*;
 pub use cursor::*;
 pub use diagnostic::*;
+pub use diff::*;
 pub use dirs::*;
+pub use folds::*;
+pub use increment::*;
 pub use indent::*;
 pub use language::*;
+pub use lines::*;
 pub use locations::*;
 pub use range::*;
+pub use reflow::*;
 pub use search::*;
+pub use sentence::*;
 pub use severity::*;
+pub use surround::*;
 pub use text::*;
 pub use text_object::*;
+pub use word::*;
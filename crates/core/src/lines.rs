@@ -0,0 +1,233 @@
+use std::{cmp::Ordering, str::Chars};
+
+use rustc_hash::FxHashSet;
+
+/// How lines are compared when sorting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineOrder {
+    /// Compare lines byte by byte
+    #[default]
+    Lexical,
+    /// Compare the number at the start of the lines, lines without a number
+    /// are sorted first
+    Numeric,
+    /// Compare digit runs as numbers and the rest lexically
+    Natural,
+    /// Compare lowercased lines
+    CaseInsensitive,
+}
+
+/// Part of a line to sort by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// Whole line
+    #[default]
+    Line,
+    /// Nth whitespace separated field starting from 1
+    Field(usize),
+    /// Text starting from the nth character starting from 1
+    Column(usize),
+}
+
+impl SortKey {
+    fn of<'a>(&self, line: &'a str) -> &'a str {
+        match *self {
+            SortKey::Line => line,
+            SortKey::Field(n) => line
+                .split_whitespace()
+                .nth(n.saturating_sub(1))
+                .unwrap_or(""),
+            SortKey::Column(n) => match line.char_indices().nth(n.saturating_sub(1)) {
+                Some((i, _)) => &line[i..],
+                None => "",
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortOptions {
+    pub order: LineOrder,
+    pub key: SortKey,
+    pub reverse: bool,
+}
+
+/// Sort lines, the sort is stable so lines with equal keys keep their order
+pub fn sort_lines(lines: &mut [String], opts: &SortOptions) {
+    lines.sort_by(|a, b| {
+        let (ka, kb) = (opts.key.of(a), opts.key.of(b));
+        let ord = match opts.order {
+            LineOrder::Lexical => ka.cmp(kb),
+            LineOrder::Numeric => numeric_cmp(ka, kb),
+            LineOrder::Natural => natural_cmp(ka, kb),
+            LineOrder::CaseInsensitive => ka.to_lowercase().cmp(&kb.to_lowercase()),
+        };
+
+        if opts.reverse {
+            ord.reverse()
+        } else {
+            ord
+        }
+    });
+}
+
+/// Remove duplicate lines keeping the first occurence
+pub fn unique_lines(lines: Vec<String>) -> Vec<String> {
+    let mut seen = FxHashSet::default();
+    lines
+        .into_iter()
+        .filter(|line| seen.insert(line.clone()))
+        .collect()
+}
+
+/// Shuffle lines using the seed as a source of randomness
+pub fn shuffle_lines(lines: &mut [String], seed: u64) {
+    // Xorshift, state must be non zero
+    let mut state = seed | 1;
+    for i in (1..lines.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let j = (state % (i as u64 + 1)) as usize;
+        lines.swap(i, j);
+    }
+}
+
+/// Number at the start of text
+fn leading_number(text: &str) -> Option<f64> {
+    let text = text.trim_start();
+    let mut end = 0;
+    let mut seen_dot = false;
+    for (i, ch) in text.char_indices() {
+        match ch {
+            '-' | '+' if i == 0 => {}
+            '.' if !seen_dot => seen_dot = true,
+            '0'..='9' => {}
+            _ => break,
+        }
+        end = i + ch.len_utf8();
+    }
+
+    text[..end].parse().ok()
+}
+
+fn numeric_cmp(a: &str, b: &str) -> Ordering {
+    match (leading_number(a), leading_number(b)) {
+        (Some(na), Some(nb)) => na.total_cmp(&nb).then_with(|| a.cmp(b)),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => a.cmp(b),
+    }
+}
+
+/// Take a run of ascii digits starting with `first`
+fn digit_run(first: char, chars: &mut std::iter::Peekable<Chars>) -> String {
+    let mut run = String::from(first);
+    while let Some(ch) = chars.next_if(char::is_ascii_digit) {
+        run.push(ch);
+    }
+    run
+}
+
+/// Compare strings so that digit runs are compared by their numeric value,
+/// `file2` sorts before `file10`
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut ac = a.chars().peekable();
+    let mut bc = b.chars().peekable();
+
+    loop {
+        let (ca, cb) = match (ac.next(), bc.next()) {
+            (Some(ca), Some(cb)) => (ca, cb),
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+        };
+
+        let ord = if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let na = digit_run(ca, &mut ac);
+            let nb = digit_run(cb, &mut bc);
+            let na = na.trim_start_matches('0');
+            let nb = nb.trim_start_matches('0');
+            na.len().cmp(&nb.len()).then_with(|| na.cmp(nb))
+        } else {
+            ca.cmp(&cb)
+        };
+
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sorted(lines: &[&str], opts: SortOptions) -> Vec<String> {
+        let mut lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        sort_lines(&mut lines, &opts);
+        lines
+    }
+
+    #[test]
+    fn sort() {
+        let lines = ["b10", "B2", "a", "b2"];
+        let opts = |order| SortOptions {
+            order,
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["B2", "a", "b10", "b2"],
+            sorted(&lines, opts(LineOrder::Lexical))
+        );
+        assert_eq!(
+            vec!["B2", "a", "b2", "b10"],
+            sorted(&lines, opts(LineOrder::Natural))
+        );
+        assert_eq!(
+            vec!["a", "b10", "B2", "b2"],
+            sorted(&lines, opts(LineOrder::CaseInsensitive))
+        );
+        assert_eq!(
+            vec!["x", "-1.5", "2", "10 y"],
+            sorted(&["10 y", "2", "x", "-1.5"], opts(LineOrder::Numeric))
+        );
+    }
+
+    #[test]
+    fn sort_by_key() {
+        let lines = ["a 3 z", "b 1 y", "c 2 x"];
+        let field = SortOptions {
+            key: SortKey::Field(2),
+            reverse: true,
+            ..Default::default()
+        };
+        assert_eq!(vec!["a 3 z", "c 2 x", "b 1 y"], sorted(&lines, field));
+
+        let column = SortOptions {
+            key: SortKey::Column(5),
+            ..Default::default()
+        };
+        assert_eq!(vec!["c 2 x", "b 1 y", "a 3 z"], sorted(&lines, column));
+    }
+
+    #[test]
+    fn unique_and_shuffle() {
+        let lines: Vec<String> = ["a", "b", "a", "c", "b"].map(String::from).into();
+        assert_eq!(vec!["a", "b", "c"], unique_lines(lines));
+
+        let mut lines: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        shuffle_lines(&mut lines, 42);
+        let mut sorted = lines.clone();
+        sort_lines(
+            &mut sorted,
+            &SortOptions {
+                order: LineOrder::Natural,
+                ..Default::default()
+            },
+        );
+        let expected: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        assert_eq!(expected, sorted);
+        assert_ne!(expected, lines);
+    }
+}
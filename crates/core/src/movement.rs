@@ -5,9 +5,11 @@ use sanedit_buffer::Bytes;
 use sanedit_buffer::PieceTreeSlice;
 
 use crate::Cursor;
+use crate::Sentences;
+use crate::Words;
 
 use super::text::{pos_at_width, width_at_pos};
-use crate::{is_word_break, is_word_break_end, DisplayOptions, GraphemeCategory};
+use crate::{DisplayOptions, GraphemeCategory};
 
 #[inline]
 pub fn next_grapheme_boundary(slice: &PieceTreeSlice, pos: u64) -> u64 {
@@ -89,21 +91,16 @@ pub fn prev_line_end(slice: &PieceTreeSlice, pos: u64) -> u64 {
 
 /// Find next word start, this will move even if we currently are on a word
 /// start.
-pub fn next_word_start(slice: &PieceTreeSlice, mut pos: u64) -> u64 {
-    let mut prev: Option<GraphemeCategory> = None;
-    let mut graphemes = slice.graphemes_at(pos);
+pub fn next_word_start(slice: &PieceTreeSlice, pos: u64, subword: bool) -> u64 {
+    let mut words = Words::new(slice, pos, subword);
+    let mut prev = words.next().map(|word| word.kind);
 
-    while let Some(g) = graphemes.next() {
-        let cat = grapheme_category(&g);
-
-        if let Some(ref prev) = prev {
-            if is_word_break(prev, &cat) {
-                return pos;
-            }
+    while let Some(word) = words.next() {
+        if word.is_word_start(prev) {
+            return word.range.start;
         }
 
-        pos += g.len();
-        prev = Some(cat);
+        prev = Some(word.kind);
     }
 
     slice.len()
@@ -111,82 +108,56 @@ pub fn next_word_start(slice: &PieceTreeSlice, mut pos: u64) -> u64 {
 
 /// Find previous word start, this will move even if we currently are on a word
 /// start.
-pub fn prev_word_start(slice: &PieceTreeSlice, mut pos: u64) -> u64 {
-    let mut cat: Option<GraphemeCategory> = None;
-    let mut graphemes = slice.graphemes_at(pos);
-
-    while let Some(g) = graphemes.prev() {
-        let prev = grapheme_category(&g);
-
-        if let Some(cat) = cat {
-            if is_word_break(&prev, &cat) {
-                return pos;
-            }
+pub fn prev_word_start(slice: &PieceTreeSlice, pos: u64, subword: bool) -> u64 {
+    let mut words = Words::new(slice, pos, subword);
+    let mut word = match words.prev() {
+        Some(word) => word,
+        None => return 0,
+    };
+
+    loop {
+        let prev = words.prev();
+        if word.is_word_start(prev.map(|word| word.kind)) {
+            return word.range.start;
         }
 
-        pos -= g.len();
-        cat = Some(prev);
-    }
-
-    0
-}
-
-pub fn next_word_end(slice: &PieceTreeSlice, mut pos: u64) -> u64 {
-    let mut prev: Option<(GraphemeCategory, u64)> = None;
-    let mut graphemes = slice.graphemes_at(pos);
-
-    while let Some(g) = graphemes.next() {
-        let cat = grapheme_category(&g);
-
-        if let Some((ref prev, _len)) = prev {
-            if is_word_break_end(prev, &cat) {
-                return pos;
-            }
+        match prev {
+            Some(prev) => word = prev,
+            None => return 0,
         }
-
-        pos += g.len();
-        prev = Some((cat, g.len()));
     }
-
-    slice.len()
 }
 
-/// Find next word end and move to the next character
-pub fn next_word_end_next(slice: &PieceTreeSlice, mut pos: u64) -> u64 {
-    let mut prev: Option<(GraphemeCategory, u64)> = None;
-    let mut graphemes = slice.graphemes_at(pos);
-
-    while let Some(g) = graphemes.next() {
-        let cat = grapheme_category(&g);
-
-        if let Some((ref prev, _len)) = prev {
-            if is_word_break_end(prev, &cat) {
-                return pos;
-            }
+pub fn next_word_end(slice: &PieceTreeSlice, pos: u64, subword: bool) -> u64 {
+    let mut words = Words::new(slice, pos, subword);
+    let mut word = match words.next() {
+        Some(word) => word,
+        None => return slice.len(),
+    };
+
+    loop {
+        let next = words.next();
+        if word.is_word_end(next.map(|word| word.kind)) {
+            return word.range.end;
         }
 
-        pos += g.len();
-        prev = Some((cat, g.len()));
+        match next {
+            Some(next) => word = next,
+            None => return slice.len(),
+        }
     }
-
-    slice.len()
 }
 
-pub fn prev_word_end(slice: &PieceTreeSlice, mut pos: u64) -> u64 {
-    let mut cat: Option<GraphemeCategory> = None;
-    let mut graphemes = slice.graphemes_at(pos);
-
-    while let Some(g) = graphemes.prev() {
-        let prev = grapheme_category(&g);
-        pos -= g.len();
+pub fn prev_word_end(slice: &PieceTreeSlice, pos: u64, subword: bool) -> u64 {
+    let mut words = Words::new(slice, pos, subword);
+    let mut next = None;
 
-        if let Some(cat) = cat {
-            if is_word_break_end(&prev, &cat) {
-                return pos + g.len();
-            }
+    while let Some(word) = words.prev() {
+        if word.range.end < pos && word.is_word_end(next) {
+            return word.range.end;
         }
 
-        cat = Some(prev);
+        next = Some(word.kind);
     }
 
     0
@@ -246,6 +217,26 @@ pub fn prev_paragraph(slice: &PieceTreeSlice, pos: u64) -> u64 {
     0
 }
 
+/// Find next sentence start, this will move even if we currently are on a
+/// sentence start.
+pub fn next_sentence(slice: &PieceTreeSlice, pos: u64) -> u64 {
+    let mut sentences = Sentences::new(slice, pos);
+    match sentences.next() {
+        Some(range) => range.end,
+        None => slice.len(),
+    }
+}
+
+/// Find previous sentence start, this will move even if we currently are on
+/// a sentence start.
+pub fn prev_sentence(slice: &PieceTreeSlice, pos: u64) -> u64 {
+    let mut sentences = Sentences::new(slice, pos);
+    match sentences.prev() {
+        Some(range) => range.start,
+        None => 0,
+    }
+}
+
 pub fn next_blank_line(slice: &PieceTreeSlice, pos: u64) -> u64 {
     let mut bytes = slice.bytes_at(pos);
     utf8::next_eol(&mut bytes);
@@ -0,0 +1,323 @@
+use std::ops::Range as StdRange;
+
+use sanedit_buffer::PieceTreeSlice;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{BufferRange, Range};
+
+/// Options used to wrap text
+#[derive(Debug, Clone)]
+pub struct ReflowOptions<'a> {
+    /// Maximum display width of a line
+    pub width: usize,
+    pub tabstop: u8,
+    /// Line comment string, lines starting with it keep it as a prefix
+    pub comment: &'a str,
+    /// Line ending used for the new lines
+    pub eol: &'a str,
+}
+
+/// Line split into its prefix and content
+#[derive(Debug)]
+struct Line<'a> {
+    /// Indentation, comment and whitespace after it
+    lead: &'a str,
+    commented: bool,
+    /// List bullet and whitespace after it
+    bullet: &'a str,
+    content: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn parse(line: &'a str, comment: &str) -> Line<'a> {
+        let comment = comment.trim();
+        let mut lead = line.len() - line.trim_start().len();
+        let mut commented = false;
+        if !comment.is_empty() && line[lead..].starts_with(comment) {
+            commented = true;
+            lead += comment.len();
+            lead += line[lead..].len() - line[lead..].trim_start().len();
+        }
+
+        let rest = &line[lead..];
+        let bullet = bullet_len(rest);
+        Line {
+            lead: &line[..lead],
+            commented,
+            bullet: &rest[..bullet],
+            content: &rest[bullet..],
+        }
+    }
+
+    fn is_blank(&self) -> bool {
+        self.bullet.is_empty() && self.content.trim().is_empty()
+    }
+
+    /// Prefix of the lines continuing this line
+    fn continuation(&self, tabstop: u8) -> String {
+        let mut prefix = self.lead.to_string();
+        let bullet = str_width(self.bullet, str_width(self.lead, 0, tabstop), tabstop);
+        prefix.extend(std::iter::repeat_n(' ', bullet));
+        prefix
+    }
+}
+
+/// Length of a markdown style list bullet with the whitespace after it
+fn bullet_len(text: &str) -> usize {
+    let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let marker = match text[digits..].chars().next() {
+        Some('-' | '*' | '+') if digits == 0 => 1,
+        Some('.' | ')') if digits != 0 => digits + 1,
+        _ => return 0,
+    };
+
+    let rest = &text[marker..];
+    let space = rest.len() - rest.trim_start().len();
+    if space == 0 {
+        return 0;
+    }
+    marker + space
+}
+
+/// Display width of text starting at column `col`
+fn str_width(text: &str, col: usize, tabstop: u8) -> usize {
+    let tabstop = (tabstop as usize).max(1);
+    let mut width = col;
+    for ch in text.chars() {
+        if ch == '\t' {
+            width += tabstop - (width % tabstop);
+        } else {
+            width += ch.width().unwrap_or(0);
+        }
+    }
+    width - col
+}
+
+/// Paragraph being filled
+#[derive(Debug)]
+struct Paragraph<'a> {
+    first: String,
+    rest: String,
+    commented: bool,
+    words: Vec<&'a str>,
+}
+
+impl Paragraph<'_> {
+    fn fill(&self, opts: &ReflowOptions, lines: &mut Vec<String>) {
+        let mut line = self.first.clone();
+        let mut width = str_width(&line, 0, opts.tabstop);
+        let mut empty = true;
+
+        for word in &self.words {
+            let wlen = word.width();
+            if !empty && width + 1 + wlen > opts.width {
+                lines.push(line);
+                line = self.rest.clone();
+                width = str_width(&line, 0, opts.tabstop);
+                empty = true;
+            }
+
+            if !empty {
+                line.push(' ');
+                width += 1;
+            }
+            line.push_str(word);
+            width += wlen;
+            empty = false;
+        }
+
+        lines.push(line);
+    }
+}
+
+/// Rewrap text so that lines fit in the given width. Indentation, comment
+/// prefixes and list bullets are kept. Blank lines separate paragraphs and
+/// are kept as is. Words longer than the width are placed on their own line.
+pub fn reflow(text: &str, opts: &ReflowOptions) -> String {
+    let mut lines = vec![];
+    let mut para: Option<Paragraph> = None;
+
+    for line in text.lines() {
+        let parsed = Line::parse(line, opts.comment);
+        if parsed.is_blank() {
+            if let Some(para) = para.take() {
+                para.fill(opts, &mut lines);
+            }
+            lines.push(line.trim_end().to_string());
+            continue;
+        }
+
+        let continues = parsed.bullet.is_empty()
+            && para
+                .as_ref()
+                .is_some_and(|para| para.commented == parsed.commented);
+        if !continues {
+            if let Some(para) = para.take() {
+                para.fill(opts, &mut lines);
+            }
+
+            para = Some(Paragraph {
+                first: format!("{}{}", parsed.lead, parsed.bullet),
+                rest: parsed.continuation(opts.tabstop),
+                commented: parsed.commented,
+                words: vec![],
+            });
+        }
+
+        if let Some(para) = para.as_mut() {
+            para.words.extend(parsed.content.split_whitespace());
+        }
+    }
+
+    if let Some(para) = para.take() {
+        para.fill(opts, &mut lines);
+    }
+
+    let mut result = lines.join(opts.eol);
+    if text.ends_with('\n') || text.ends_with('\r') {
+        result.push_str(opts.eol);
+    }
+    result
+}
+
+/// Find where to break a line that is being typed. `line` is the text from
+/// the start of the line to the cursor.
+///
+/// Returns the byte range of the whitespace to replace and the replacement
+/// which is a line ending and the prefix of the continuation line.
+pub fn wrap_line(line: &str, opts: &ReflowOptions) -> Option<(StdRange<usize>, String)> {
+    if str_width(line, 0, opts.tabstop) <= opts.width {
+        return None;
+    }
+
+    let parsed = Line::parse(line, opts.comment);
+    let content_start = parsed.lead.len() + parsed.bullet.len();
+    let mut best = None;
+    let mut run: Option<usize> = None;
+
+    for (i, ch) in line[content_start..].char_indices() {
+        let i = content_start + i;
+        if ch.is_whitespace() {
+            run.get_or_insert(i);
+            continue;
+        }
+
+        if let Some(start) = run.take() {
+            if start == content_start {
+                continue;
+            }
+
+            let fits = str_width(&line[..start], 0, opts.tabstop) <= opts.width;
+            if fits || best.is_none() {
+                best = Some(start..i);
+            }
+            if !fits {
+                break;
+            }
+        }
+    }
+
+    let range = best?;
+    let replacement = format!("{}{}", opts.eol, parsed.continuation(opts.tabstop));
+    Some((range, replacement))
+}
+
+/// Range of the lines around pos to reflow. Lines are included until a
+/// blank line or a change between commented and uncommented lines.
+pub fn reflow_range_at(slice: &PieceTreeSlice, pos: u64, comment: &str) -> Option<BufferRange> {
+    let kind = |line: &PieceTreeSlice| {
+        let text = String::from(line);
+        let parsed = Line::parse(&text, comment);
+        (!parsed.is_blank()).then_some(parsed.commented)
+    };
+
+    let mut lines = slice.lines_at(pos);
+    let current = lines.next()?;
+    let commented = kind(&current)?;
+    let mut start = current.start();
+    let mut end = current.end();
+
+    while let Some(line) = lines.next() {
+        if kind(&line) != Some(commented) {
+            break;
+        }
+        end = line.end();
+    }
+
+    let mut lines = slice.lines_at(start);
+    while let Some(line) = lines.prev() {
+        if kind(&line) != Some(commented) {
+            break;
+        }
+        start = line.start();
+    }
+
+    Some(Range::from(start - slice.start()..end - slice.start()))
+}
+
+#[cfg(test)]
+mod test {
+    use sanedit_buffer::PieceTree;
+
+    use super::*;
+
+    fn opts(width: usize) -> ReflowOptions<'static> {
+        ReflowOptions {
+            width,
+            tabstop: 4,
+            comment: "// ",
+            eol: "\n",
+        }
+    }
+
+    #[test]
+    fn reflow_text() {
+        assert_eq!(
+            "one two three\nfour five six\nseven\n",
+            reflow("one two\nthree four five six seven\n", &opts(14))
+        );
+        assert_eq!(
+            "    // one two\n    // three four\n\n    //   five",
+            reflow(
+                "    // one two three\n    // four\n\n    //   five",
+                &opts(17)
+            )
+        );
+        assert_eq!(
+            "- one two\n  three\n10. four\n    five",
+            reflow("- one\ntwo three\n10. four five", &opts(10))
+        );
+        assert_eq!("a\nverylongword\nb", reflow("a verylongword b", &opts(5)));
+        // Wide characters take two columns
+        assert_eq!("漢字 漢字\n漢字", reflow("漢字 漢字 漢字", &opts(10)));
+    }
+
+    #[test]
+    fn wrap() {
+        assert_eq!(None, wrap_line("one two", &opts(10)));
+        assert_eq!(
+            Some((7..8, "\n".to_string())),
+            wrap_line("one two three", &opts(10))
+        );
+        assert_eq!(
+            Some((8..9, "\n  // ".to_string())),
+            wrap_line("  // one two", &opts(10))
+        );
+        assert_eq!(
+            Some((5..6, "\n  ".to_string())),
+            wrap_line("- abc defghij", &opts(8))
+        );
+        assert_eq!(None, wrap_line("abcdefghijkl", &opts(8)));
+    }
+
+    #[test]
+    fn range_at() {
+        let pt = PieceTree::from("fn a() {}\n// one\n// two\nfn b() {}\n");
+        let slice = pt.slice(..);
+        assert_eq!(
+            Some(Range::from(10..24)),
+            reflow_range_at(&slice, 12, "// ")
+        );
+        assert_eq!(Some(Range::from(0..10)), reflow_range_at(&slice, 2, "// "));
+    }
+}
@@ -0,0 +1,479 @@
+use sanedit_buffer::{utf8::Chars, PieceTreeSlice};
+use sanedit_ucd::{sentence_break, SentenceBreak};
+
+use crate::{
+    movement::{next_line_start, prev_grapheme_boundary, prev_line_start, start_of_line},
+    BufferRange, Range,
+};
+
+/// How far back to look for a paragraph to start segmenting from
+const MAX_LOOKBACK: u64 = 1024 * 64;
+
+/// Iterates sentences forwards or backwards from a position.
+///
+/// Sentences are segmented according to UAX #29, tailored for hard wrapped
+/// prose: a single line break is treated as a space, and only the line
+/// break ending a run of blank lines separates paragraphs.
+/// Sentences include their trailing whitespace.
+#[derive(Debug, Clone)]
+pub struct Sentences<'a> {
+    slice: &'a PieceTreeSlice,
+    pos: u64,
+    /// Segmenter positioned at `pos`, used to continue forward iteration
+    forward: Option<Segmenter<'a>>,
+}
+
+impl<'a> Sentences<'a> {
+    pub fn new(slice: &'a PieceTreeSlice, pos: u64) -> Sentences<'a> {
+        Sentences {
+            slice,
+            pos,
+            forward: None,
+        }
+    }
+
+    /// Returns the range from the current position to the next sentence
+    /// boundary
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<BufferRange> {
+        if self.pos >= self.slice.len() {
+            return None;
+        }
+
+        let mut segmenter = match self.forward.take() {
+            Some(seg) => seg,
+            None => Segmenter::new(self.slice, segment_start(self.slice, self.pos)),
+        };
+        let mut end = self.slice.len();
+        while let Some(boundary) = segmenter.next_boundary() {
+            if boundary > self.pos {
+                end = boundary;
+                break;
+            }
+        }
+
+        let range = Range::from(self.pos..end);
+        self.pos = end;
+        self.forward = Some(segmenter);
+        Some(range)
+    }
+
+    /// Returns the range from the previous sentence boundary to the current
+    /// position
+    pub fn prev(&mut self) -> Option<BufferRange> {
+        if self.pos == 0 {
+            return None;
+        }
+
+        self.forward = None;
+        let before = prev_grapheme_boundary(self.slice, self.pos);
+        let start = segment_start(self.slice, before);
+        let mut segmenter = Segmenter::new(self.slice, start);
+        let mut prev = start;
+        while let Some(boundary) = segmenter.next_boundary() {
+            if boundary >= self.pos {
+                break;
+            }
+            prev = boundary;
+        }
+
+        let range = Range::from(prev..self.pos);
+        self.pos = prev;
+        Some(range)
+    }
+}
+
+/// Find a position at or before `pos` where a sentence is known to start.
+/// These are the start of the slice and the lines following a run of blank
+/// lines.
+fn segment_start(slice: &PieceTreeSlice, pos: u64) -> u64 {
+    let is_blank = |start: u64| {
+        let end = next_line_start(slice, start);
+        let mut chars = slice.chars_at(start);
+        while let Some((cstart, _, ch)) = chars.next() {
+            if cstart >= end {
+                break;
+            }
+
+            if !matches!(
+                sentence_break(ch),
+                SentenceBreak::Sp | SentenceBreak::CR | SentenceBreak::LF
+            ) {
+                return false;
+            }
+        }
+        true
+    };
+
+    let min = pos.saturating_sub(MAX_LOOKBACK);
+    let mut start = start_of_line(slice, pos);
+    let mut blank = is_blank(start);
+    while start != 0 && start > min {
+        let prev = prev_line_start(slice, start);
+        let prev_blank = is_blank(prev);
+        if prev_blank && !blank {
+            return start;
+        }
+
+        start = prev;
+        blank = prev_blank;
+    }
+
+    start
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    start: u64,
+    end: u64,
+    sb: SentenceBreak,
+}
+
+/// Characters grouped into tokens, line breaks are classified as spaces or
+/// paragraph separators (`Sep`).
+#[derive(Debug, Clone)]
+struct Tokens<'a> {
+    chars: Chars<'a>,
+    /// Character already read from chars
+    peeked: Option<(u64, u64, char)>,
+    /// Whether only spaces are on the current line so far
+    blank_line: bool,
+}
+
+impl<'a> Tokens<'a> {
+    /// Create tokens starting at a line start
+    fn new(slice: &'a PieceTreeSlice, pos: u64) -> Tokens<'a> {
+        Tokens {
+            chars: slice.chars_at(pos),
+            peeked: None,
+            blank_line: true,
+        }
+    }
+
+    fn next_char(&mut self) -> Option<(u64, u64, char)> {
+        self.peeked.take().or_else(|| self.chars.next())
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        use SentenceBreak::*;
+
+        let (start, mut end, ch) = self.next_char()?;
+        let mut sb = sentence_break(ch);
+        match sb {
+            CR => {
+                // SB3
+                match self.chars.next() {
+                    Some((_, lf_end, '\n')) => end = lf_end,
+                    next => self.peeked = next,
+                }
+                sb = self.line_break();
+            }
+            LF => sb = self.line_break(),
+            Sep => self.blank_line = true,
+            Sp => {}
+            _ => self.blank_line = false,
+        }
+
+        // SB5
+        if sb != Sep {
+            while let Some(next) = self.next_char() {
+                if !matches!(sentence_break(next.2), Extend | Format) {
+                    self.peeked = Some(next);
+                    break;
+                }
+                end = next.1;
+            }
+        }
+
+        Some(Token { start, end, sb })
+    }
+
+    /// Classify a line break that was just read
+    fn line_break(&mut self) -> SentenceBreak {
+        let blank = self.blank_line;
+        self.blank_line = true;
+
+        if blank && !self.next_line_blank() {
+            SentenceBreak::Sep
+        } else {
+            SentenceBreak::Sp
+        }
+    }
+
+    fn next_line_blank(&self) -> bool {
+        let mut chars = self.chars.clone();
+        let mut next = self.peeked.or_else(|| chars.next());
+        while let Some((_, _, ch)) = next {
+            match sentence_break(ch) {
+                SentenceBreak::Sp => {}
+                SentenceBreak::CR | SentenceBreak::LF => return true,
+                _ => return false,
+            }
+            next = chars.next();
+        }
+
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// Inside a sentence
+    Sentence,
+    /// After a sentence terminator
+    Terminated {
+        aterm: bool,
+        /// Token before the terminator
+        before: Option<SentenceBreak>,
+        /// Whether the terminator was the previous token
+        immediate: bool,
+        seen_sp: bool,
+    },
+}
+
+/// Finds sentence boundaries starting from a sentence start
+#[derive(Debug, Clone)]
+struct Segmenter<'a> {
+    tokens: Tokens<'a>,
+    /// Token that starts the next sentence
+    pending: Option<Token>,
+}
+
+impl<'a> Segmenter<'a> {
+    fn new(slice: &'a PieceTreeSlice, pos: u64) -> Segmenter<'a> {
+        Segmenter {
+            tokens: Tokens::new(slice, pos),
+            pending: None,
+        }
+    }
+
+    /// Returns the next sentence boundary
+    fn next_boundary(&mut self) -> Option<u64> {
+        use SentenceBreak::*;
+
+        let mut state = State::Sentence;
+        let mut last: Option<SentenceBreak> = None;
+        let mut end = None;
+
+        loop {
+            // SB2
+            let Some(tok) = self.pending.take().or_else(|| self.tokens.next()) else {
+                return end;
+            };
+
+            match state {
+                State::Sentence => match tok.sb {
+                    // SB4
+                    Sep => return Some(tok.end),
+                    ATerm | STerm => {
+                        state = State::Terminated {
+                            aterm: tok.sb == ATerm,
+                            before: last,
+                            immediate: true,
+                            seen_sp: false,
+                        }
+                    }
+                    _ => {}
+                },
+                State::Terminated {
+                    aterm,
+                    before,
+                    immediate,
+                    seen_sp,
+                } => {
+                    let continues = match tok.sb {
+                        // SB6
+                        Numeric if aterm && immediate => true,
+                        // SB7
+                        Upper if aterm && immediate && matches!(before, Some(Upper | Lower)) => {
+                            true
+                        }
+                        // SB8a
+                        SContinue => true,
+                        // SB8
+                        _ => aterm && self.followed_by_lower(tok),
+                    };
+
+                    state = match tok.sb {
+                        _ if continues => State::Sentence,
+                        // SB8a
+                        ATerm | STerm => State::Terminated {
+                            aterm: tok.sb == ATerm,
+                            before: last,
+                            immediate: true,
+                            seen_sp: false,
+                        },
+                        // SB9
+                        Close if !seen_sp => State::Terminated {
+                            aterm,
+                            before,
+                            immediate: false,
+                            seen_sp,
+                        },
+                        // SB9, SB10
+                        Sp => State::Terminated {
+                            aterm,
+                            before,
+                            immediate: false,
+                            seen_sp: true,
+                        },
+                        // SB9, SB10, SB11
+                        Sep => return Some(tok.end),
+                        // SB11
+                        _ => {
+                            self.pending = Some(tok);
+                            return Some(tok.start);
+                        }
+                    };
+                }
+            }
+
+            last = Some(tok.sb);
+            end = Some(tok.end);
+        }
+    }
+
+    /// SB8: ATerm Close* Sp* × ( ¬(OLetter | Upper | Lower | ParaSep | SATerm) )* Lower
+    fn followed_by_lower(&self, tok: Token) -> bool {
+        use SentenceBreak::*;
+
+        let mut tokens = self.tokens.clone();
+        let mut next = Some(tok);
+        while let Some(tok) = next {
+            match tok.sb {
+                Lower => return true,
+                OLetter | Upper | Sep | ATerm | STerm => return false,
+                _ => {}
+            }
+            next = tokens.next();
+        }
+
+        false
+    }
+}
+
+/// Sentence at position, if `include` is false the trailing whitespace is
+/// excluded
+pub fn sentence_at_pos(slice: &PieceTreeSlice, pos: u64, include: bool) -> Option<BufferRange> {
+    let mut sentences = Sentences::new(slice, pos);
+    let end = sentences.next()?.end;
+    let mut sentences = Sentences::new(slice, end);
+    let start = sentences.prev()?.start;
+
+    if include {
+        return Some(Range::from(start..end));
+    }
+
+    let mut content_end = start;
+    let mut chars = slice.chars_at(start);
+    while let Some((_, cend, ch)) = chars.next() {
+        if cend > end {
+            break;
+        }
+
+        if !ch.is_whitespace() {
+            content_end = cend;
+        }
+    }
+
+    if content_end == start {
+        return None;
+    }
+
+    Some(Range::from(start..content_end))
+}
+
+#[cfg(test)]
+mod test {
+    use sanedit_buffer::PieceTree;
+
+    use super::*;
+    use crate::movement;
+
+    fn sentences(text: &str) -> Vec<String> {
+        let pt = PieceTree::from(text);
+        let slice = pt.slice(..);
+        let mut sentences = Sentences::new(&slice, 0);
+        let mut result = vec![];
+        while let Some(range) = sentences.next() {
+            result.push(String::from(&slice.slice(range)));
+        }
+
+        let mut prev = vec![];
+        let mut sentences = Sentences::new(&slice, slice.len());
+        while let Some(range) = sentences.prev() {
+            prev.push(String::from(&slice.slice(range)));
+        }
+        prev.reverse();
+        assert_eq!(result, prev);
+
+        result
+    }
+
+    #[test]
+    fn segment() {
+        assert_eq!(
+            vec!["Hello world. ", "How are you? ", "Fine!"],
+            sentences("Hello world. How are you? Fine!")
+        );
+        assert_eq!(
+            vec!["Pi is 3.14 or so. ", "(Roughly.) ", "Next"],
+            sentences("Pi is 3.14 or so. (Roughly.) Next")
+        );
+        assert_eq!(
+            vec!["See e.g. the docs, U.S.A. works. ", "Yes"],
+            sentences("See e.g. the docs, U.S.A. works. Yes")
+        );
+        assert_eq!(
+            vec!["She said \"stop.\" ", "Then left."],
+            sentences("She said \"stop.\" Then left.")
+        );
+    }
+
+    #[test]
+    fn segment_lines() {
+        assert_eq!(
+            vec![
+                "A wrapped\nsentence. ",
+                "Another\none\n\n",
+                "# Heading\n\n\n",
+                "Text"
+            ],
+            sentences("A wrapped\nsentence. Another\none\n\n# Heading\n\n\nText")
+        );
+        assert_eq!(
+            vec!["\r\n", "Windows\r\nline.\r\n", "Next"],
+            sentences("\r\nWindows\r\nline.\r\nNext")
+        );
+    }
+
+    #[test]
+    fn movement() {
+        let pt = PieceTree::from("First one. Second one.\n\nThird.");
+        let slice = pt.slice(..);
+        assert_eq!(11, movement::next_sentence(&slice, 0));
+        assert_eq!(11, movement::next_sentence(&slice, 3));
+        assert_eq!(24, movement::next_sentence(&slice, 11));
+        assert_eq!(30, movement::next_sentence(&slice, 24));
+        assert_eq!(11, movement::prev_sentence(&slice, 24));
+        assert_eq!(11, movement::prev_sentence(&slice, 15));
+        assert_eq!(0, movement::prev_sentence(&slice, 11));
+    }
+
+    #[test]
+    fn sentence_at() {
+        let pt = PieceTree::from("First one.  Second one.\n\nThird.");
+        let slice = pt.slice(..);
+        assert_eq!(Some(Range::from(0..12)), sentence_at_pos(&slice, 4, true));
+        assert_eq!(Some(Range::from(0..10)), sentence_at_pos(&slice, 4, false));
+        assert_eq!(
+            Some(Range::from(12..23)),
+            sentence_at_pos(&slice, 12, false)
+        );
+        assert_eq!(
+            Some(Range::from(12..23)),
+            sentence_at_pos(&slice, 23, false)
+        );
+        assert_eq!(Some(Range::from(25..31)), sentence_at_pos(&slice, 29, true));
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use sanedit_buffer::PieceTreeSlice;
+
+use crate::{find_range, BufferRange, FindRangeOptions, Range};
+
+/// Maximum length of a tag to parse
+const MAX_TAG_LEN: u64 = 1024;
+
+/// How far from the position tags are searched for
+const TAG_HORIZON: u64 = 1024 * 64;
+
+const PAIRS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
+
+/// Delimiters to surround text with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Surround {
+    /// Opening and closing strings
+    Pair(String, String),
+    /// HTML/XML element, contains the tag name and possible attributes
+    Tag(String),
+}
+
+impl Surround {
+    /// Pair for a character, either side of a bracket pair results in the
+    /// pair and other characters are used on both sides
+    pub fn from_char(ch: char) -> Surround {
+        for (open, close) in PAIRS {
+            if ch == open || ch == close {
+                return Surround::Pair(open.into(), close.into());
+            }
+        }
+
+        Surround::Pair(ch.into(), ch.into())
+    }
+
+    pub fn open(&self) -> String {
+        match self {
+            Surround::Pair(open, _) => open.clone(),
+            Surround::Tag(tag) => format!("<{}>", tag.trim()),
+        }
+    }
+
+    pub fn close(&self) -> String {
+        match self {
+            Surround::Pair(_, close) => close.clone(),
+            Surround::Tag(tag) => {
+                let name = tag.split_whitespace().next().unwrap_or("");
+                format!("</{name}>")
+            }
+        }
+    }
+}
+
+/// Find the nearest delimiter pair surrounding pos. The character `ch`
+/// selects the pair as in [`Surround::from_char`], `t` finds any enclosing
+/// HTML/XML element.
+///
+/// Returns ranges of the opening and closing delimiters.
+pub fn find_surrounding(
+    slice: &PieceTreeSlice,
+    pos: u64,
+    ch: char,
+) -> Option<(BufferRange, BufferRange)> {
+    if ch == 't' {
+        return find_tag_pair(slice, pos);
+    }
+
+    let Surround::Pair(open, close) = Surround::from_char(ch) else {
+        return None;
+    };
+    let mut opts = FindRangeOptions::default().include();
+    if open != close {
+        opts = opts.multiline();
+    }
+    let range = find_range(slice, pos, &open, &close, opts)?;
+    let olen = open.len() as u64;
+    let clen = close.len() as u64;
+    Some((
+        Range::from(range.start..range.start + olen),
+        Range::from(range.end - clen..range.end),
+    ))
+}
+
+#[derive(Debug)]
+struct Tag {
+    name: String,
+    closing: bool,
+    self_closing: bool,
+    range: BufferRange,
+}
+
+/// Parse a tag starting at pos
+fn parse_tag(slice: &PieceTreeSlice, pos: u64) -> Option<Tag> {
+    let mut chars = slice.chars_at(pos);
+    let (_, _, ch) = chars.next()?;
+    if ch != '<' {
+        return None;
+    }
+
+    let mut content = String::new();
+    let end = loop {
+        let (_, end, ch) = chars.next()?;
+        if end - pos > MAX_TAG_LEN || ch == '<' {
+            return None;
+        }
+
+        if ch == '>' {
+            break end;
+        }
+        content.push(ch);
+    };
+
+    let (closing, content) = match content.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, content.as_str()),
+    };
+    let is_name = |ch: char| ch.is_alphanumeric() || matches!(ch, '-' | '_' | ':' | '.');
+    let name_len = content.find(|ch| !is_name(ch)).unwrap_or(content.len());
+    let (name, rest) = content.split_at(name_len);
+    let valid_rest = rest.is_empty() || rest == "/" || rest.starts_with(char::is_whitespace);
+    if !name.starts_with(char::is_alphabetic) || !valid_rest {
+        return None;
+    }
+
+    Some(Tag {
+        name: name.into(),
+        closing,
+        self_closing: content.ends_with('/'),
+        range: Range::from(pos..end),
+    })
+}
+
+/// Tags starting within range, in order
+fn tags_in(slice: &PieceTreeSlice, range: BufferRange) -> Vec<Tag> {
+    let mut tags = vec![];
+    let mut chars = slice.chars_at(range.start);
+    while let Some((start, _, ch)) = chars.next() {
+        if start >= range.end {
+            break;
+        }
+        if ch != '<' {
+            continue;
+        }
+
+        if let Some(tag) = parse_tag(slice, start) {
+            tags.push(tag);
+        }
+    }
+
+    tags
+}
+
+/// Index of the closing tag of each opening tag. Only tags with the same
+/// name are considered when matching, an opening tag without a closing tag
+/// such as <br> has none.
+fn match_tags(tags: &[Tag]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; tags.len()];
+    let mut open: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, tag) in tags.iter().enumerate() {
+        if tag.self_closing {
+            continue;
+        }
+
+        let stack = open.entry(tag.name.as_str()).or_default();
+        if !tag.closing {
+            stack.push(i);
+        } else if let Some(opening) = stack.pop() {
+            matches[opening] = Some(i);
+        }
+    }
+
+    matches
+}
+
+/// Find the nearest HTML/XML element surrounding pos, positions on the
+/// tags themselves are considered to be inside the element. Only tags
+/// within `TAG_HORIZON` of pos are considered.
+///
+/// Returns ranges of the opening and closing tags.
+pub fn find_tag_pair(slice: &PieceTreeSlice, pos: u64) -> Option<(BufferRange, BufferRange)> {
+    let start = pos.saturating_sub(TAG_HORIZON);
+    let end = (pos + TAG_HORIZON).min(slice.len());
+    let tags = tags_in(slice, Range::from(start..end));
+    let matches = match_tags(&tags);
+    let mut closed: Vec<&str> = vec![];
+
+    for (i, tag) in tags.iter().enumerate().rev() {
+        if tag.range.start > pos || tag.self_closing {
+            continue;
+        }
+
+        if tag.closing {
+            // Elements closed before pos do not contain it
+            if tag.range.end <= pos {
+                closed.push(&tag.name);
+            }
+            continue;
+        }
+
+        if closed.last() == Some(&tag.name.as_str()) {
+            closed.pop();
+            continue;
+        }
+
+        // Possibly an element without a closing tag such as <br>
+        if let Some(close) = matches[i].map(|j| &tags[j]) {
+            if close.range.end > pos {
+                return Some((tag.range, close.range));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use sanedit_buffer::PieceTree;
+
+    use super::*;
+
+    fn surrounding(text: &str, pos: u64, ch: char) -> Option<(String, String)> {
+        let pt = PieceTree::from(text);
+        let slice = pt.slice(..);
+        let (open, close) = find_surrounding(&slice, pos, ch)?;
+        Some((
+            String::from(&slice.slice(open)),
+            String::from(&slice.slice(close)),
+        ))
+    }
+
+    fn pair(open: &str, close: &str) -> Option<(String, String)> {
+        Some((open.into(), close.into()))
+    }
+
+    #[test]
+    fn delimiters() {
+        assert_eq!(
+            Surround::Pair("(".into(), ")".into()),
+            Surround::from_char(')')
+        );
+        assert_eq!(
+            Surround::Pair("*".into(), "*".into()),
+            Surround::from_char('*')
+        );
+
+        let tag = Surround::Tag("div class=\"a\"".into());
+        assert_eq!("<div class=\"a\">", tag.open());
+        assert_eq!("</div>", tag.close());
+    }
+
+    #[test]
+    fn pairs() {
+        assert_eq!(pair("(", ")"), surrounding("a(b[c]d)e", 3, '('));
+        assert_eq!(pair("[", "]"), surrounding("a(b[c]d)e", 4, ']'));
+        assert_eq!(pair("\"", "\""), surrounding("x = \"abc\";", 6, '"'));
+        assert_eq!(pair("{", "}"), surrounding("{\n  a\n}", 4, '{'));
+        assert_eq!(None, surrounding("abc", 1, '('));
+    }
+
+    #[test]
+    fn tags() {
+        let text = "<div><p class=\"a\">text<br>more</p><img/></div>";
+        assert_eq!(pair("<p class=\"a\">", "</p>"), surrounding(text, 20, 't'));
+        assert_eq!(pair("<p class=\"a\">", "</p>"), surrounding(text, 28, 't'));
+        // On a tag
+        assert_eq!(pair("<p class=\"a\">", "</p>"), surrounding(text, 6, 't'));
+        assert_eq!(pair("<div>", "</div>"), surrounding(text, 36, 't'));
+        assert_eq!(pair("<div>", "</div>"), surrounding(text, 1, 't'));
+        assert_eq!(None, surrounding("<a></a> b", 8, 't'));
+        assert_eq!(
+            pair("<a>", "</a>"),
+            surrounding("<a><a>x</a>y</a>", 11, 't')
+        );
+
+        // Many elements without closing tags
+        let text = format!("<a>{}</a>", "<br>".repeat(10_000));
+        assert_eq!(pair("<a>", "</a>"), surrounding(&text, 20_000, 't'));
+    }
+}
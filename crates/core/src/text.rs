@@ -1,16 +1,16 @@
 use std::io;
 
 use crate::{
-    grapheme_category, is_word_break, is_word_break_end,
+    grapheme_category,
     movement::{end_of_line, first_char_of_line, is_empty_or_whitespace},
     BufferRange, Chars, DisplayOptions, GraphemeCategory, Range,
 };
 use sanedit_buffer::{
-    utf8::{self, EndOfLine, Grapheme},
+    utf8::{self, EndOfLine},
     PieceTree, PieceTreeSlice,
 };
 
-use super::movement::{next_word_end, prev_word_start, start_of_line};
+use super::movement::start_of_line;
 
 pub fn width_at_pos(slice: &PieceTreeSlice, pos: u64, opts: &DisplayOptions) -> usize {
     let target = pos;
@@ -86,69 +86,6 @@ pub fn pos_at_width(slice: &PieceTreeSlice, pos: u64, width: usize, opts: &Displ
     pos
 }
 
-pub fn on_word_end(
-    prev: (u64, GraphemeCategory),
-    next: Option<(u64, GraphemeCategory)>,
-    pos: u64,
-    slice_len: u64,
-) -> bool {
-    match (prev, next) {
-        ((_, p), Some((_, n))) => is_word_break_end(&p, &n),
-        ((len, _), _) => pos + len == slice_len,
-    }
-}
-
-pub fn on_word_start(
-    prev: Option<(u64, GraphemeCategory)>,
-    next: (u64, GraphemeCategory),
-    pos: u64,
-) -> bool {
-    match (prev, next) {
-        (Some((_, p)), (_, n)) => is_word_break(&p, &n),
-        _ => pos == 0,
-    }
-}
-
-/// Returns the range of the word that includes position pos
-pub fn word_at_pos(slice: &PieceTreeSlice, pos: u64) -> Option<BufferRange> {
-    let make_pair = |grapheme: Grapheme| {
-        let len = grapheme.len();
-        let cat = grapheme_category(&grapheme);
-        (len, cat)
-    };
-    let mut graphemes = slice.graphemes_at(pos);
-
-    let before = if graphemes.prev().is_none() {
-        None
-    } else {
-        graphemes.next().map(make_pair)
-    };
-    let current = graphemes.next().map(make_pair)?;
-    let after = graphemes.next().map(make_pair);
-
-    if !current.1.is_word() {
-        return None;
-    }
-
-    let start = {
-        let mut start = pos;
-        if !on_word_start(before, current, pos) {
-            start = prev_word_start(slice, pos);
-        }
-        start
-    };
-
-    let end = {
-        let mut end = pos + 1;
-        if !on_word_end(current, after, pos, slice.len()) {
-            end = next_word_end(slice, pos);
-        }
-        end
-    };
-
-    Some(Range::from(start..end))
-}
-
 pub fn prev_non_word(slice: &PieceTreeSlice, pos: u64) -> (u64, Option<GraphemeCategory>) {
     let mut start = pos;
     let mut graphemes = slice.graphemes_at(pos);
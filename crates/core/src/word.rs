@@ -0,0 +1,649 @@
+use std::collections::VecDeque;
+
+use sanedit_buffer::{utf8::Chars, PieceTreeSlice};
+use sanedit_ucd::{word_break, Property, WordBreak};
+
+use crate::{
+    movement::{prev_grapheme_boundary, start_of_line},
+    BufferRange, Range,
+};
+
+/// How far back to look for a position to start segmenting from
+const MAX_LOOKBACK: u64 = 1024 * 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WordKind {
+    /// Letters, numbers, ideographs and emoji
+    Word,
+    Punctuation,
+    Whitespace,
+    Eol,
+}
+
+/// A word boundary delimited segment of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordSegment {
+    pub range: BufferRange,
+    pub kind: WordKind,
+}
+
+impl WordSegment {
+    /// Whether a word starts at this segment if `prev` is the segment kind
+    /// before it. Runs of punctuation and line breaks are treated as one word.
+    pub fn is_word_start(&self, prev: Option<WordKind>) -> bool {
+        self.kind != WordKind::Whitespace
+            && (self.kind == WordKind::Word || prev != Some(self.kind))
+    }
+
+    /// Whether a word ends at this segment if `next` is the segment kind after
+    /// it. Runs of punctuation and line breaks are treated as one word.
+    pub fn is_word_end(&self, next: Option<WordKind>) -> bool {
+        self.kind != WordKind::Whitespace
+            && (self.kind == WordKind::Word || next != Some(self.kind))
+    }
+}
+
+/// Iterates word segments forwards or backwards from a position.
+///
+/// Words are segmented according to UAX #29, tailored for source code so
+/// that full stops and colons do not join letters. In subword mode words are
+/// further split at camelCase humps and underscores.
+#[derive(Debug, Clone)]
+pub struct Words<'a> {
+    slice: &'a PieceTreeSlice,
+    pos: u64,
+    subword: bool,
+    /// Segmenter positioned at `pos`, used to continue forward iteration
+    forward: Option<Segmenter<'a>>,
+    /// Subwords of the current segment not yet returned
+    pending: VecDeque<WordSegment>,
+}
+
+impl<'a> Words<'a> {
+    pub fn new(slice: &'a PieceTreeSlice, pos: u64, subword: bool) -> Words<'a> {
+        Words {
+            slice,
+            pos,
+            subword,
+            forward: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Returns the next segment ending after the current position, the first
+    /// segment may start before the position.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<WordSegment> {
+        if self.pos >= self.slice.len() {
+            return None;
+        }
+
+        if let Some(word) = self.pending.pop_front() {
+            self.pos = word.range.end;
+            return Some(word);
+        }
+
+        let mut segmenter = match self.forward.take() {
+            Some(seg) => seg,
+            None => Segmenter::new(self.slice, segment_start(self.slice, self.pos)),
+        };
+
+        let word = loop {
+            let range = segmenter.next_segment()?;
+            if range.end > self.pos {
+                break self.segment(range);
+            }
+        };
+
+        let pos = self.pos;
+        let mut words = self.split(word).into_iter().filter(|w| w.range.end > pos);
+        let word = words.next()?;
+        self.pending.extend(words);
+        self.pos = word.range.end;
+        self.forward = Some(segmenter);
+        Some(word)
+    }
+
+    /// Returns the previous segment starting before the current position, the
+    /// first segment may end after the position.
+    pub fn prev(&mut self) -> Option<WordSegment> {
+        if self.pos == 0 {
+            return None;
+        }
+
+        self.forward = None;
+        self.pending.clear();
+
+        let before = prev_grapheme_boundary(self.slice, self.pos);
+        let mut segmenter = Segmenter::new(self.slice, segment_start(self.slice, before));
+        let mut prev = None;
+        while let Some(range) = segmenter.next_segment() {
+            if range.start >= self.pos {
+                break;
+            }
+
+            prev = Some(range);
+        }
+
+        let word = self.segment(prev?);
+        let word = self
+            .split(word)
+            .into_iter()
+            .rev()
+            .find(|w| w.range.start < self.pos)?;
+        self.pos = word.range.start;
+        Some(word)
+    }
+
+    fn segment(&self, range: BufferRange) -> WordSegment {
+        let ch = self
+            .slice
+            .chars_at(range.start)
+            .next()
+            .map(|(_, _, ch)| ch)
+            .unwrap_or('\u{fffd}');
+        let kind = match word_break(ch) {
+            WordBreak::CR | WordBreak::LF | WordBreak::Newline => WordKind::Eol,
+            _ if ch.is_whitespace() => WordKind::Whitespace,
+            WordBreak::ALetter
+            | WordBreak::HebrewLetter
+            | WordBreak::Katakana
+            | WordBreak::Numeric
+            | WordBreak::ExtendNumLet
+            | WordBreak::RegionalIndicator => WordKind::Word,
+            _ if ch.is_alphanumeric() || Property::ExtendedPictographic.check(ch) => WordKind::Word,
+            _ => WordKind::Punctuation,
+        };
+
+        WordSegment { range, kind }
+    }
+
+    /// Split a word into subwords if subword mode is enabled
+    fn split(&self, word: WordSegment) -> Vec<WordSegment> {
+        if !self.subword || word.kind != WordKind::Word {
+            return vec![word];
+        }
+
+        subwords(self.slice, word)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hump {
+    Upper,
+    Lower,
+    Underscore,
+    Other,
+}
+
+impl Hump {
+    fn of(ch: char) -> Hump {
+        if ch == '_' {
+            Hump::Underscore
+        } else if ch.is_uppercase() {
+            Hump::Upper
+        } else if ch.is_lowercase() {
+            Hump::Lower
+        } else {
+            Hump::Other
+        }
+    }
+}
+
+/// Split a word at camelCase humps and underscores. Underscores separating
+/// subwords are returned as punctuation.
+fn subwords(slice: &PieceTreeSlice, word: WordSegment) -> Vec<WordSegment> {
+    let mut chars = slice.chars_at(word.range.start);
+    let mut humps = vec![];
+    while let Some((start, _, ch)) = chars.next() {
+        if start >= word.range.end {
+            break;
+        }
+        humps.push((start, Hump::of(ch)));
+    }
+
+    if humps.iter().all(|(_, hump)| *hump == Hump::Underscore) {
+        return vec![word];
+    }
+
+    let kind = |hump: Hump| {
+        if hump == Hump::Underscore {
+            WordKind::Punctuation
+        } else {
+            WordKind::Word
+        }
+    };
+
+    let mut result = vec![];
+    let mut start = word.range.start;
+    for i in 1..humps.len() {
+        let (pos, cur) = humps[i];
+        let prev = humps[i - 1].1;
+        let next = humps.get(i + 1).map(|(_, hump)| *hump);
+        let is_break = match (prev, cur) {
+            (Hump::Underscore, _) | (_, Hump::Underscore) => prev != cur,
+            // fooBar, utf8Decode
+            (Hump::Lower | Hump::Other, Hump::Upper) => true,
+            // HTTPServer
+            (Hump::Upper, Hump::Upper) => next == Some(Hump::Lower),
+            _ => false,
+        };
+
+        if is_break {
+            result.push(WordSegment {
+                range: Range::from(start..pos),
+                kind: kind(prev),
+            });
+            start = pos;
+        }
+    }
+
+    let last = humps.last().map(|(_, hump)| *hump).unwrap_or(Hump::Other);
+    result.push(WordSegment {
+        range: Range::from(start..word.range.end),
+        kind: kind(last),
+    });
+    result
+}
+
+/// Find a position at or before `pos` where a word segment is known to
+/// start. This is the start of the line, or on very long lines a position
+/// after whitespace.
+fn segment_start(slice: &PieceTreeSlice, pos: u64) -> u64 {
+    let start = start_of_line(slice, pos);
+    let min = pos.saturating_sub(MAX_LOOKBACK);
+    if start >= min {
+        return start;
+    }
+
+    let is_space = |ch: char| ch.is_whitespace();
+    let is_joining = |ch: char| {
+        matches!(
+            word_break(ch),
+            WordBreak::Extend | WordBreak::Format | WordBreak::ZWJ
+        )
+    };
+    let mut chars = slice.chars_at(min);
+    let mut after_space = false;
+    while let Some((cstart, _, ch)) = chars.next() {
+        if cstart >= pos {
+            break;
+        }
+
+        if after_space && !is_space(ch) && !is_joining(ch) {
+            return cstart;
+        }
+        after_space = is_space(ch);
+    }
+
+    pos
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    start: u64,
+    end: u64,
+    wb: WordBreak,
+    ch: char,
+    /// Whether the token ends with a zero width joiner
+    zwj: bool,
+}
+
+impl Token {
+    fn is_ahletter(&self) -> bool {
+        matches!(self.wb, WordBreak::ALetter | WordBreak::HebrewLetter)
+    }
+
+    fn is_numeric(&self) -> bool {
+        self.wb == WordBreak::Numeric
+    }
+
+    fn is_mid_letter(&self) -> bool {
+        // Tailored so that member access and paths are separate words
+        matches!(
+            self.wb,
+            WordBreak::MidLetter | WordBreak::MidNumLet | WordBreak::SingleQuote
+        ) && !matches!(self.ch, '.' | ':')
+    }
+
+    fn is_mid_num(&self) -> bool {
+        matches!(
+            self.wb,
+            WordBreak::MidNum | WordBreak::MidNumLet | WordBreak::SingleQuote
+        )
+    }
+}
+
+/// Characters grouped into tokens, extending characters are attached to
+/// the preceding character (WB4).
+#[derive(Debug, Clone)]
+struct Tokens<'a> {
+    chars: Chars<'a>,
+    /// Character already read from chars
+    peeked: Option<(u64, u64, char)>,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(slice: &'a PieceTreeSlice, pos: u64) -> Tokens<'a> {
+        Tokens {
+            chars: slice.chars_at(pos),
+            peeked: None,
+        }
+    }
+
+    fn next_char(&mut self) -> Option<(u64, u64, char)> {
+        self.peeked.take().or_else(|| self.chars.next())
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        use WordBreak::*;
+
+        let (start, mut end, ch) = self.next_char()?;
+        let wb = word_break(ch);
+        let mut zwj = wb == ZWJ;
+        match wb {
+            CR => {
+                // WB3
+                match self.next_char() {
+                    Some((_, lf_end, '\n')) => end = lf_end,
+                    next => self.peeked = next,
+                }
+            }
+            LF | Newline => {}
+            _ => {
+                // WB4
+                while let Some(next) = self.next_char() {
+                    let next_wb = word_break(next.2);
+                    if !matches!(next_wb, Extend | Format | ZWJ) {
+                        self.peeked = Some(next);
+                        break;
+                    }
+                    end = next.1;
+                    zwj = next_wb == ZWJ;
+                }
+            }
+        }
+
+        Some(Token {
+            start,
+            end,
+            wb,
+            ch,
+            zwj,
+        })
+    }
+}
+
+/// Finds word segments starting from a segment start
+#[derive(Debug, Clone)]
+struct Segmenter<'a> {
+    tokens: Tokens<'a>,
+    /// Token before the current one
+    prev: Option<Token>,
+    /// Tokens read ahead
+    ahead: VecDeque<Token>,
+    /// Number of consecutive regional indicators ending at the current token
+    ri_count: usize,
+}
+
+impl<'a> Segmenter<'a> {
+    fn new(slice: &'a PieceTreeSlice, pos: u64) -> Segmenter<'a> {
+        Segmenter {
+            tokens: Tokens::new(slice, pos),
+            prev: None,
+            ahead: VecDeque::new(),
+            ri_count: 0,
+        }
+    }
+
+    fn peek(&mut self, n: usize) -> Option<Token> {
+        while self.ahead.len() <= n {
+            let tok = self.tokens.next()?;
+            self.ahead.push_back(tok);
+        }
+
+        Some(self.ahead[n])
+    }
+
+    /// Returns the next word segment
+    fn next_segment(&mut self) -> Option<BufferRange> {
+        let mut cur = self.ahead.pop_front().or_else(|| self.tokens.next())?;
+        let start = cur.start;
+
+        loop {
+            if cur.wb == WordBreak::RegionalIndicator {
+                self.ri_count += 1;
+            } else {
+                self.ri_count = 0;
+            }
+
+            // WB2
+            let Some(next) = self.peek(0) else {
+                self.prev = Some(cur);
+                return Some(Range::from(start..cur.end));
+            };
+
+            let is_break = self.is_break(cur, next);
+            self.prev = Some(cur);
+            if is_break {
+                return Some(Range::from(start..cur.end));
+            }
+
+            cur = self.ahead.pop_front()?;
+        }
+    }
+
+    /// Whether there is a word boundary between `cur` and `next`
+    fn is_break(&mut self, cur: Token, next: Token) -> bool {
+        use WordBreak::*;
+
+        let prev = self.prev;
+        let after = |this: &mut Self, f: fn(&Token) -> bool| this.peek(1).is_some_and(|t| f(&t));
+        let before = |f: fn(&Token) -> bool| prev.is_some_and(|t| f(&t));
+
+        match (cur.wb, next.wb) {
+            // WB3a, WB3b
+            (CR | LF | Newline, _) | (_, CR | LF | Newline) => true,
+            // WB3c
+            _ if cur.zwj && Property::ExtendedPictographic.check(next.ch) => false,
+            // WB3d
+            (WSegSpace, WSegSpace) => false,
+            // WB6
+            _ if cur.is_ahletter() && next.is_mid_letter() && after(self, Token::is_ahletter) => {
+                false
+            }
+            // WB7
+            _ if before(Token::is_ahletter) && cur.is_mid_letter() && next.is_ahletter() => false,
+            // WB7a
+            (HebrewLetter, SingleQuote) => false,
+            // WB7b
+            (HebrewLetter, DoubleQuote) if after(self, |t| t.wb == HebrewLetter) => false,
+            // WB7c
+            (DoubleQuote, HebrewLetter) if before(|t| t.wb == HebrewLetter) => false,
+            // WB5, WB8, WB9, WB10
+            _ if (cur.is_numeric() || cur.is_ahletter())
+                && (next.is_numeric() || next.is_ahletter()) =>
+            {
+                false
+            }
+            // WB11
+            _ if before(Token::is_numeric) && cur.is_mid_num() && next.is_numeric() => false,
+            // WB12
+            _ if cur.is_numeric() && next.is_mid_num() && after(self, Token::is_numeric) => false,
+            // WB13
+            (Katakana, Katakana) => false,
+            // WB13a
+            (ALetter | HebrewLetter | Numeric | Katakana | ExtendNumLet, ExtendNumLet) => false,
+            // WB13b
+            (ExtendNumLet, ALetter | HebrewLetter | Numeric | Katakana) => false,
+            // WB15, WB16
+            (RegionalIndicator, RegionalIndicator) => self.ri_count.is_multiple_of(2),
+            // WB999
+            _ => true,
+        }
+    }
+}
+
+/// Word at position, runs of punctuation and line breaks are treated as one
+/// word. Returns None if position is on whitespace.
+pub fn word_at_pos(slice: &PieceTreeSlice, pos: u64, subword: bool) -> Option<BufferRange> {
+    let word = Words::new(slice, pos, subword).next()?;
+    match word.kind {
+        WordKind::Whitespace => return None,
+        WordKind::Word => return Some(word.range),
+        _ => {}
+    }
+
+    let mut range = word.range;
+    let mut words = Words::new(slice, range.end, subword);
+    while let Some(next) = words.next() {
+        if next.kind != word.kind {
+            break;
+        }
+        range.end = next.range.end;
+    }
+
+    let mut words = Words::new(slice, range.start, subword);
+    while let Some(prev) = words.prev() {
+        if prev.kind != word.kind {
+            break;
+        }
+        range.start = prev.range.start;
+    }
+
+    Some(range)
+}
+
+#[cfg(test)]
+mod test {
+    use sanedit_buffer::PieceTree;
+
+    use super::*;
+    use crate::movement;
+
+    fn words(text: &str, subword: bool) -> Vec<String> {
+        let pt = PieceTree::from(text);
+        let slice = pt.slice(..);
+        let mut words = Words::new(&slice, 0, subword);
+        let mut result = vec![];
+        while let Some(word) = words.next() {
+            result.push(String::from(&slice.slice(word.range)));
+        }
+
+        let mut prev = vec![];
+        let mut words = Words::new(&slice, slice.len(), subword);
+        while let Some(word) = words.prev() {
+            prev.push(String::from(&slice.slice(word.range)));
+        }
+        prev.reverse();
+        assert_eq!(result, prev);
+
+        result
+    }
+
+    #[test]
+    fn segment() {
+        assert_eq!(
+            vec!["can't", " ", "stop", ",", " ", "3.14", " ", "foo", "-", "bar"],
+            words("can't stop, 3.14 foo-bar", false)
+        );
+        assert_eq!(
+            vec![
+                "snake_case",
+                " ",
+                "obj",
+                ".",
+                "get",
+                "(",
+                "a",
+                ":",
+                "b",
+                ")",
+                "\r\n",
+                "x"
+            ],
+            words("snake_case obj.get(a:b)\r\nx", false)
+        );
+        // Ideographs are separate words, katakana is kept together
+        assert_eq!(
+            vec!["漢", "字", "カタカナ", "。"],
+            words("漢字カタカナ。", false)
+        );
+        // Emoji ZWJ sequences and flags
+        assert_eq!(
+            vec!["👩\u{200d}💻", " ", "🇫🇮", "🇸🇪", "🇫"],
+            words("👩\u{200d}💻 🇫🇮🇸🇪🇫", false)
+        );
+        // Combining marks stay with the base character
+        assert_eq!(
+            vec!["cafe\u{301}", "  ", "x"],
+            words("cafe\u{301}  x", false)
+        );
+    }
+
+    #[test]
+    fn segment_subword() {
+        assert_eq!(
+            vec!["camel", "Case", " ", "HTTP", "Server", " ", "utf8", "Decode"],
+            words("camelCase HTTPServer utf8Decode", true)
+        );
+        assert_eq!(
+            vec!["snake", "_", "case", " ", "__", "init", "__", " ", "_"],
+            words("snake_case __init__ _", true)
+        );
+    }
+
+    #[test]
+    fn movement() {
+        let pt = PieceTree::from("foo::bar  baz\n\nqux");
+        let slice = pt.slice(..);
+        assert_eq!(3, movement::next_word_start(&slice, 0, false));
+        assert_eq!(5, movement::next_word_start(&slice, 3, false));
+        assert_eq!(10, movement::next_word_start(&slice, 5, false));
+        assert_eq!(13, movement::next_word_start(&slice, 10, false));
+        assert_eq!(15, movement::next_word_start(&slice, 13, false));
+        assert_eq!(18, movement::next_word_start(&slice, 15, false));
+
+        assert_eq!(10, movement::prev_word_start(&slice, 13, false));
+        assert_eq!(5, movement::prev_word_start(&slice, 10, false));
+        assert_eq!(5, movement::prev_word_start(&slice, 7, false));
+        assert_eq!(3, movement::prev_word_start(&slice, 5, false));
+        assert_eq!(0, movement::prev_word_start(&slice, 3, false));
+
+        assert_eq!(3, movement::next_word_end(&slice, 0, false));
+        assert_eq!(5, movement::next_word_end(&slice, 3, false));
+        assert_eq!(8, movement::next_word_end(&slice, 5, false));
+        assert_eq!(13, movement::next_word_end(&slice, 8, false));
+
+        assert_eq!(8, movement::prev_word_end(&slice, 11, false));
+        assert_eq!(5, movement::prev_word_end(&slice, 8, false));
+        assert_eq!(3, movement::prev_word_end(&slice, 5, false));
+        assert_eq!(0, movement::prev_word_end(&slice, 3, false));
+    }
+
+    #[test]
+    fn movement_subword() {
+        let pt = PieceTree::from("parseHTTPRequest next_item");
+        let slice = pt.slice(..);
+        assert_eq!(5, movement::next_word_start(&slice, 0, true));
+        assert_eq!(9, movement::next_word_start(&slice, 5, true));
+        assert_eq!(17, movement::next_word_start(&slice, 9, true));
+        assert_eq!(21, movement::next_word_start(&slice, 17, true));
+        assert_eq!(17, movement::next_word_start(&slice, 9, false));
+        assert_eq!(9, movement::prev_word_start(&slice, 12, true));
+        assert_eq!(5, movement::next_word_end(&slice, 0, true));
+        assert_eq!(16, movement::next_word_end(&slice, 0, false));
+    }
+
+    #[test]
+    fn word_at() {
+        let pt = PieceTree::from("let fooBar = a->b;  ");
+        let slice = pt.slice(..);
+        assert_eq!(Some(Range::from(4..10)), word_at_pos(&slice, 6, false));
+        assert_eq!(Some(Range::from(7..10)), word_at_pos(&slice, 7, true));
+        assert_eq!(Some(Range::from(4..7)), word_at_pos(&slice, 4, true));
+        assert_eq!(Some(Range::from(14..16)), word_at_pos(&slice, 15, false));
+        assert_eq!(None, word_at_pos(&slice, 3, false));
+        assert_eq!(None, word_at_pos(&slice, slice.len(), false));
+    }
+}
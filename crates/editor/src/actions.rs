@@ -13,6 +13,7 @@ pub(crate) mod movement;
 pub(crate) mod popup;
 pub(crate) mod prompt;
 pub(crate) mod search;
+pub(crate) mod signs;
 pub(crate) mod snapshots;
 pub(crate) mod snippets;
 pub(crate) mod syntax;
@@ -187,6 +188,8 @@ pub(crate) const COMMANDS: &[Action] = &[
     editor::copy_to_eol,
     editor::paste,
     editor::cut,
+    editor::paste_kill_ring,
+    editor::select_register,
     editor::open_config,
     editor::open_new_scratch_buffer,
     editor::nop,
@@ -211,12 +214,35 @@ pub(crate) const COMMANDS: &[Action] = &[
     text::uncomment_lines,
     text::toggle_comment_lines,
     text::join_lines,
+    text::reflow,
+    text::increment,
+    text::decrement,
+    text::surround_selections,
+    text::change_surrounding,
+    text::delete_surrounding,
     text::insert_literal,
     text::remove_line,
     text::remove_to_eol,
     text::reload_file_from_disk,
     text::uppercase,
     text::lowercase,
+    text::snake_case,
+    text::camel_case,
+    text::pascal_case,
+    text::kebab_case,
+    text::constant_case,
+    text::title_case,
+    text::cycle_case,
+    text::sort_lines,
+    text::sort_lines_reverse,
+    text::sort_lines_numeric,
+    text::sort_lines_natural,
+    text::sort_lines_case_insensitive,
+    text::sort_lines_by_field,
+    text::sort_lines_by_column,
+    text::unique_lines,
+    text::reverse_lines,
+    text::shuffle_lines,
     text::rotate_selections,
     text::rotate_selections_backwards,
     text::set_language,
@@ -225,6 +251,8 @@ pub(crate) const COMMANDS: &[Action] = &[
     text::set_indentation,
     text::redindent,
     text::set_eol,
+    text::set_encoding,
+    text::reopen_with_encoding,
     text::fix_eols,
 
     prompt::open_file,
@@ -232,10 +260,12 @@ pub(crate) const COMMANDS: &[Action] = &[
     prompt::select_theme,
     prompt::goto_line,
     prompt::goto_percentage,
+    prompt::goto_offset,
     prompt::change_working_dir,
     prompt::grep,
     prompt::command_palette,
     prompt::show_keymaps,
+    prompt::paste_from_register,
 
     search::search_forward,
     search::search_backward,
@@ -251,6 +281,8 @@ pub(crate) const COMMANDS: &[Action] = &[
     movement::prev_word_start,
     movement::next_paragraph,
     movement::prev_paragraph,
+    movement::next_sentence,
+    movement::prev_sentence,
     movement::next_word_end,
     movement::prev_word_end,
     movement::goto_matching_pair,
@@ -271,6 +303,7 @@ pub(crate) const COMMANDS: &[Action] = &[
     movement::next_grapheme_on_line,
 
     cursors::start_selection,
+    cursors::start_block_selection,
     cursors::stop_selection,
     cursors::new_cursor_to_next_line,
     cursors::new_cursor_to_prev_line,
@@ -300,6 +333,10 @@ pub(crate) const COMMANDS: &[Action] = &[
 
     view::scroll_up,
     view::scroll_down,
+    view::close_fold,
+    view::open_fold,
+    view::toggle_fold,
+    view::open_all_folds,
 
     indent::indent_line,
     indent::dedent_line,
@@ -317,6 +354,8 @@ pub(crate) const COMMANDS: &[Action] = &[
     text_objects::select_angle,
     text_objects::select_word,
     text_objects::select_paragraph,
+    text_objects::select_sentence,
+    text_objects::select_sentence_incl,
     text_objects::select_double,
     text_objects::select_double_incl,
     text_objects::select_single,
@@ -324,6 +363,16 @@ pub(crate) const COMMANDS: &[Action] = &[
     text_objects::select_backtick,
     text_objects::select_backtick_incl,
     text_objects::select_pattern,
+    text_objects::select_function,
+    text_objects::select_function_incl,
+    text_objects::select_class,
+    text_objects::select_class_incl,
+    text_objects::select_argument,
+    text_objects::select_argument_incl,
+    text_objects::select_comment,
+    text_objects::select_comment_incl,
+    text_objects::select_string,
+    text_objects::select_string_incl,
 
     window::reload_window,
     window::goto_prev_buffer,
@@ -331,6 +380,17 @@ pub(crate) const COMMANDS: &[Action] = &[
     window::cancel,
     window::new_window_horizontal,
     window::new_window_vertical,
+    window::split_vertical,
+    window::split_horizontal,
+    window::close_split,
+    window::focus_split_left,
+    window::focus_split_right,
+    window::focus_split_up,
+    window::focus_split_down,
+    window::grow_split_width,
+    window::shrink_split_width,
+    window::grow_split_height,
+    window::shrink_split_height,
     window::status,
     window::view_to_cursor,
     window::view_to_cursor_top,
@@ -345,6 +405,16 @@ pub(crate) const COMMANDS: &[Action] = &[
     window::insert_mode_end_of_line,
     window::insert_mode_first_char_of_line,
     window::snake,
+    window::toggle_hex_mode,
+    window::toggle_hex_insert,
+    window::cycle_line_numbers,
+    window::toggle_wrap,
+
+    signs::toggle_bookmark,
+    signs::next_bookmark,
+    signs::prev_bookmark,
+    signs::clear_bookmarks,
+    signs::reload_vcs_signs,
 
     completion::complete,
 
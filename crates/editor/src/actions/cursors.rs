@@ -64,6 +64,33 @@ fn start_selection(editor: &mut Editor, id: ClientId) -> ActionResult {
     ActionResult::Ok
 }
 
+#[action("Cursors: Start or stop block selection")]
+fn start_block_selection(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    // Already in block selection, keep rows as separate selections
+    if win.cursors.is_block() {
+        win.cursors.stop_block();
+        return ActionResult::Ok;
+    }
+
+    let selecting = win.cursors.primary().is_selecting();
+    mode_select(editor, id);
+
+    let (win, buf) = win_buf!(editor, id);
+    win.start_block_selection(buf);
+    if !selecting {
+        next_grapheme.execute(editor, id);
+    }
+    ActionResult::Ok
+}
+
+#[action("Cursors: Stop block selection")]
+fn stop_block_selection(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    win.cursors.stop_block();
+    ActionResult::Ok
+}
+
 #[action("Cursors: Cancel selection")]
 fn stop_selection(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, _buf) = win_buf!(editor, id);
@@ -122,6 +149,7 @@ fn make_prev_cursor_primary(editor: &mut Editor, id: ClientId) -> ActionResult {
 
 #[action("Cursors: Remove selections")]
 fn remove_cursor_selections(editor: &mut Editor, id: ClientId) -> ActionResult {
+    editor.push_kill(id);
     if editor.config.editor.copy_on_delete {
         editor.copy_to_clipboard(id);
     }
@@ -1,14 +1,18 @@
+use std::sync::Arc;
+
 use crate::{
     common::is_yes,
     editor::{
         config::Config,
         hooks::Hook,
         ignore::Ignore,
-        windows::{Focus, Prompt},
+        registers::is_register,
+        windows::{Focus, NextKeyFunction, Prompt},
         Editor,
     },
 };
 use sanedit_core::Language;
+use sanedit_messages::key::Key;
 use sanedit_server::ClientId;
 
 use super::{
@@ -70,8 +74,35 @@ fn paste(editor: &mut Editor, id: ClientId) -> ActionResult {
     ActionResult::Ok
 }
 
+#[action("Editor: Paste from kill ring, repeat to paste older deletions")]
+fn paste_kill_ring(editor: &mut Editor, id: ClientId) -> ActionResult {
+    editor.paste_from_kill_ring(id);
+    ActionResult::Ok
+}
+
+#[action("Editor: Select register for the next copy or paste")]
+fn select_register(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    win.next_key_handler = Some(NextKeyFunction(Arc::new(|editor, id, event| {
+        let (win, _buf) = win_buf!(editor, id);
+        let Key::Char(ch) = *event.key() else {
+            return ActionResult::Failed;
+        };
+
+        if !is_register(ch) {
+            win.warn_msg(&format!("No such register {ch}"));
+            return ActionResult::Failed;
+        }
+
+        win.select_register(ch);
+        ActionResult::Ok
+    })));
+    ActionResult::Ok
+}
+
 #[action("Editor: Cut to clipboard")]
 fn cut(editor: &mut Editor, id: ClientId) -> ActionResult {
+    editor.push_kill(id);
     editor.copy_to_clipboard(id);
 
     run(editor, id, Hook::RemovePre);
@@ -1,25 +1,37 @@
 // Contains different jobs to run in tokio runtime
+mod compact;
 mod conntest;
+mod decompress;
 mod directory_option_provider;
 mod file_option_provider;
 mod games;
 mod grep;
+mod history;
+mod journal;
+mod line_index;
 mod lsp;
 mod matcher;
 mod search;
 mod shell;
 mod syntax;
+mod vcs;
 // mod text;
 
 pub(crate) const CHANNEL_SIZE: usize = 64;
 
 pub(crate) use self::grep::*;
+pub(crate) use compact::*;
 pub(crate) use conntest::*;
+pub(crate) use decompress::*;
 pub(crate) use directory_option_provider::*;
 pub(crate) use file_option_provider::*;
 pub(crate) use games::*;
+pub(crate) use history::*;
+pub(crate) use journal::*;
+pub(crate) use line_index::*;
 pub(crate) use lsp::*;
 pub(crate) use matcher::*;
 pub(crate) use search::*;
 pub(crate) use syntax::*;
+pub(crate) use vcs::*;
 // pub(crate) use text::*;
@@ -0,0 +1,78 @@
+use std::{
+    any::Any,
+    time::{Duration, Instant},
+};
+
+use crate::editor::{buffers::BufferId, job_broker::KeepInTouch, Editor};
+use sanedit_server::{ClientId, Job, JobContext, JobResult};
+
+/// Number of pieces compacted at once
+const STEP_PIECES: usize = 1024;
+
+/// Time spent compacting before letting the editor handle other messages
+const STEP_TIME: Duration = Duration::from_millis(5);
+
+/// Compacts the piece tree of a buffer after a delay. The compaction itself
+/// runs on the editor in small steps, as the tree is owned by it.
+#[derive(Debug, Clone)]
+pub(crate) struct Compact {
+    client_id: ClientId,
+    bid: BufferId,
+    delay: Duration,
+}
+
+impl Compact {
+    pub fn new(id: ClientId, bid: BufferId, delay: Duration) -> Compact {
+        Compact {
+            client_id: id,
+            bid,
+            delay,
+        }
+    }
+
+    /// Slot of the compaction job of a buffer
+    pub fn slot(bid: BufferId) -> String {
+        format!("compact-{bid:?}")
+    }
+}
+
+struct Step;
+
+impl Job for Compact {
+    fn run(&self, ctx: JobContext) -> JobResult {
+        let delay = self.delay;
+        let fut = async move {
+            tokio::time::sleep(delay).await;
+            ctx.send(Step);
+            Ok(())
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl KeepInTouch for Compact {
+    fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    fn on_message(&self, editor: &mut Editor, msg: Box<dyn Any>) {
+        if msg.downcast::<Step>().is_err() {
+            return;
+        }
+
+        let Some(buf) = editor.buffers_mut().get_mut(self.bid) else {
+            return;
+        };
+        let start = Instant::now();
+        while !buf.compact(STEP_PIECES) {
+            if start.elapsed() > STEP_TIME {
+                let job = Compact::new(self.client_id, self.bid, Duration::ZERO);
+                editor
+                    .job_broker
+                    .request_slot(self.client_id, &Compact::slot(self.bid), job);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::{any::Any, fs, path::PathBuf};
+
+use crate::{
+    actions::hooks::run,
+    editor::{
+        buffers::{decompress_to_tmp, BufferId, Codec},
+        hooks::Hook,
+        job_broker::KeepInTouch,
+        Editor,
+    },
+};
+use sanedit_server::{CPUJob, ClientId, JobContext};
+
+/// Decompresses a big compressed file to a temporary file in the background,
+/// which is then used as the backing file of its buffer
+#[derive(Clone)]
+pub(crate) struct Decompress {
+    client_id: ClientId,
+    bid: BufferId,
+    path: PathBuf,
+    codec: &'static dyn Codec,
+}
+
+impl Decompress {
+    pub fn new(id: ClientId, bid: BufferId, path: PathBuf, codec: &'static dyn Codec) -> Self {
+        Decompress {
+            client_id: id,
+            bid,
+            path,
+            codec,
+        }
+    }
+}
+
+impl CPUJob for Decompress {
+    fn run(&self, ctx: JobContext) -> anyhow::Result<()> {
+        let result = decompress_to_tmp(&self.path, self.codec).map_err(|e| e.to_string());
+        ctx.send(result);
+        Ok(())
+    }
+}
+
+impl KeepInTouch for Decompress {
+    fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    fn on_message(&self, editor: &mut Editor, msg: Box<dyn Any>) {
+        let Ok(result) = msg.downcast::<Result<PathBuf, String>>() else {
+            return;
+        };
+        let tmp = match *result {
+            Ok(tmp) => tmp,
+            Err(e) => {
+                log::error!("Failed to decompress {:?}: {e}", self.path);
+                if let Some(win) = editor.windows.get_mut(self.client_id) {
+                    win.error_msg(&format!("Failed to decompress file: {e}"));
+                }
+                return;
+            }
+        };
+        if editor.buffers().get(self.bid).is_none() {
+            let _ = fs::remove_file(&tmp);
+            return;
+        }
+
+        let Some(buf) = editor.buffers_mut().get_mut(self.bid) else {
+            return;
+        };
+        if let Err(e) = buf.finish_decompress(&tmp) {
+            log::error!("Failed to open decompressed {:?}: {e}", self.path);
+            let _ = fs::remove_file(&tmp);
+            return;
+        }
+
+        // Hooks ran on the empty buffer when it was created, rerun the ones
+        // depending on the contents
+        run(editor, self.client_id, Hook::BufReloaded(self.bid));
+
+        let clients = editor.windows().find_clients_with_buf(self.bid);
+        for client in clients {
+            let (win, buf) = win_buf!(editor, client);
+            win.full_reload(buf);
+        }
+
+        if let Some(buf) = editor.buffers.get(self.bid) {
+            for win in editor.windows.inactive_with_buf_mut(self.bid) {
+                win.full_reload(buf);
+            }
+        }
+    }
+}
@@ -23,6 +23,7 @@ use crate::actions::locations;
 use crate::common::Choice;
 
 use crate::editor::ignore::Ignore;
+use crate::editor::windows::{PaneId, Window};
 use crate::editor::Map;
 use crate::editor::{job_broker::KeepInTouch, Editor};
 use sanedit_server::{ClientId, Job, JobContext, JobId, JobResult, KillSwitch};
@@ -32,6 +33,8 @@ use super::FileOptionProvider;
 #[derive(Clone)]
 pub(crate) struct Grep {
     client_id: ClientId,
+    /// Pane to show the results in
+    pane: PaneId,
     pattern: String,
     file_opt_provider: FileOptionProvider,
     buffers: Arc<Map<PathBuf, PieceTreeSlice>>,
@@ -44,12 +47,14 @@ impl Grep {
         ignore: Ignore,
         buffers: Map<PathBuf, PieceTreeSlice>,
         id: ClientId,
+        pane: PaneId,
         git_ignore: bool,
     ) -> Grep {
         let fprovider = FileOptionProvider::new(path, ignore, git_ignore);
 
         Grep {
             client_id: id,
+            pane,
             pattern: pattern.into(),
             file_opt_provider: fprovider,
             buffers: Arc::new(buffers),
@@ -349,6 +354,12 @@ impl Job for Grep {
     }
 }
 
+impl Grep {
+    fn window<'a>(&self, editor: &'a mut Editor) -> Option<&'a mut Window> {
+        editor.windows.pane_mut(self.client_id, self.pane)
+    }
+}
+
 impl KeepInTouch for Grep {
     fn client_id(&self) -> ClientId {
         self.client_id
@@ -356,12 +367,20 @@ impl KeepInTouch for Grep {
 
     fn on_message(&self, editor: &mut Editor, mut msg: Box<dyn Any>) {
         if let Some(Start(id)) = msg.downcast_mut::<Start>() {
-            let (win, _buf) = win_buf!(editor, self.client_id);
+            let Some(win) = self.window(editor) else {
+                return;
+            };
             win.locations.extra.is_loading = true;
             win.locations.extra.job = Some(*id);
             win.locations.extra.title = format!("Grep {:?}", self.pattern);
-            locations::clear_locations.execute(editor, self.client_id);
-            locations::show_locations.execute(editor, self.client_id);
+            win.locations.clear();
+
+            // Focus moves to the locations only if the pane is still focused
+            if editor.windows.focused_pane(self.client_id) == Some(self.pane) {
+                locations::show_locations.execute(editor, self.client_id);
+            } else if let Some(win) = self.window(editor) {
+                win.locations.extra.show = true;
+            }
             return;
         }
 
@@ -369,7 +388,9 @@ impl KeepInTouch for Grep {
             let draw = editor.draw_state(self.client_id);
             draw.no_redraw_window();
 
-            let (win, _buf) = win_buf!(editor, self.client_id);
+            let Some(win) = self.window(editor) else {
+                return;
+            };
             for res in results.into_iter() {
                 let items: Vec<Item> = res.matches.into_iter().map(Item::from).collect();
                 let mut group = Group::new(&res.path);
@@ -380,20 +401,26 @@ impl KeepInTouch for Grep {
     }
 
     fn on_success(&self, editor: &mut Editor) {
-        let (win, _buf) = win_buf!(editor, self.client_id);
+        let Some(win) = self.window(editor) else {
+            return;
+        };
         win.locations.extra.is_loading = false;
         win.locations.extra.job = None;
     }
 
     fn on_stop(&self, editor: &mut Editor) {
-        let (win, _buf) = win_buf!(editor, self.client_id);
+        let Some(win) = self.window(editor) else {
+            return;
+        };
         win.locations.extra.is_loading = false;
         win.locations.extra.job = None;
     }
 
     fn on_failure(&self, editor: &mut Editor, reason: &str) {
         log::error!("Grep error: {reason}");
-        let (win, _buf) = win_buf!(editor, self.client_id);
+        let Some(win) = self.window(editor) else {
+            return;
+        };
         win.locations.clear();
         win.locations.extra.is_loading = false;
         win.locations.extra.job = None;
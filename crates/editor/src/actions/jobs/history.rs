@@ -0,0 +1,34 @@
+use crate::editor::{buffers::PendingHistory, job_broker::KeepInTouch, Editor};
+use sanedit_server::{CPUJob, ClientId, JobContext};
+
+/// Writes the undo history of a buffer to disk in the background
+#[derive(Debug, Clone)]
+pub(crate) struct PersistHistory {
+    client_id: ClientId,
+    history: PendingHistory,
+}
+
+impl PersistHistory {
+    pub fn new(id: ClientId, history: PendingHistory) -> PersistHistory {
+        PersistHistory {
+            client_id: id,
+            history,
+        }
+    }
+}
+
+impl CPUJob for PersistHistory {
+    fn run(&self, _ctx: JobContext) -> anyhow::Result<()> {
+        self.history.write()
+    }
+}
+
+impl KeepInTouch for PersistHistory {
+    fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    fn on_failure(&self, _editor: &mut Editor, reason: &str) {
+        log::warn!("Failed to persist undo history: {reason}");
+    }
+}
@@ -0,0 +1,54 @@
+use std::{any::Any, time::Duration};
+
+use crate::editor::{buffers::BufferId, job_broker::KeepInTouch, Editor};
+use sanedit_server::{ClientId, Job, JobContext, JobResult};
+
+/// Flushes the changes not yet written to the recovery journal of a buffer
+/// once the journal write interval has passed
+#[derive(Debug, Clone)]
+pub(crate) struct FlushJournal {
+    client_id: ClientId,
+    bid: BufferId,
+    delay: Duration,
+}
+
+impl FlushJournal {
+    pub fn new(id: ClientId, bid: BufferId, delay: Duration) -> FlushJournal {
+        FlushJournal {
+            client_id: id,
+            bid,
+            delay,
+        }
+    }
+}
+
+struct Flush;
+
+impl Job for FlushJournal {
+    fn run(&self, ctx: JobContext) -> JobResult {
+        let delay = self.delay;
+        let fut = async move {
+            tokio::time::sleep(delay).await;
+            ctx.send(Flush);
+            Ok(())
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl KeepInTouch for FlushJournal {
+    fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    fn on_message(&self, editor: &mut Editor, msg: Box<dyn Any>) {
+        if msg.downcast::<Flush>().is_err() {
+            return;
+        }
+
+        if let Some(buf) = editor.buffers_mut().get_mut(self.bid) {
+            buf.flush_journal();
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    any::Any,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use sanedit_buffer::PieceTreeSlice;
+
+use crate::editor::{buffers::BufferId, job_broker::KeepInTouch, Editor};
+use sanedit_server::{CPUJob, ClientId, JobContext};
+
+/// Indexes buffer line counts in the background, so that line numbers can be
+/// queried without scanning the buffer.
+#[derive(Clone)]
+pub(crate) struct LineIndexer {
+    client_id: ClientId,
+    bid: BufferId,
+    pt: PieceTreeSlice,
+}
+
+impl LineIndexer {
+    pub fn new(id: ClientId, bid: BufferId, pt: PieceTreeSlice) -> Self {
+        LineIndexer {
+            client_id: id,
+            bid,
+            pt,
+        }
+    }
+}
+
+impl CPUJob for LineIndexer {
+    fn run(&self, ctx: JobContext) -> anyhow::Result<()> {
+        let kill: Arc<AtomicBool> = ctx.kill.clone().into();
+        self.pt.index_lines(&kill)?;
+        ctx.send(());
+        Ok(())
+    }
+}
+
+impl KeepInTouch for LineIndexer {
+    fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    fn on_message(&self, editor: &mut Editor, _msg: Box<dyn Any>) {
+        if let Some(buf) = editor.buffers_mut().get_mut(self.bid) {
+            buf.update_line_index();
+        }
+    }
+}
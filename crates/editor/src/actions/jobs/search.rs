@@ -10,7 +10,10 @@ use sanedit_syntax::PieceTreeSliceSource;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
 use crate::editor::{
-    buffers::BufferId, job_broker::KeepInTouch, windows::SearchHighlights, Editor,
+    buffers::BufferId,
+    job_broker::KeepInTouch,
+    windows::{PaneId, SearchHighlights},
+    Editor,
 };
 use sanedit_server::{ClientId, Job, JobContext, JobResult};
 
@@ -24,6 +27,8 @@ enum SearchMessage {
 #[derive(Clone)]
 pub(crate) struct Search {
     client_id: ClientId,
+    /// Pane to highlight the matches in
+    pane: PaneId,
     searcher: Arc<Searcher>,
     slice: PieceTreeSlice,
     bid: BufferId,
@@ -33,6 +38,7 @@ pub(crate) struct Search {
 impl Search {
     pub fn new(
         id: ClientId,
+        pane: PaneId,
         searcher: Searcher,
         bid: BufferId,
         slice: PieceTreeSlice,
@@ -40,6 +46,7 @@ impl Search {
     ) -> Search {
         Search {
             client_id: id,
+            pane,
             searcher: Arc::new(searcher),
             bid,
             slice,
@@ -98,8 +105,16 @@ impl Job for Search {
 impl KeepInTouch for Search {
     fn on_message(&self, editor: &mut Editor, msg: Box<dyn Any>) {
         if let Ok(output) = msg.downcast::<SearchMessage>() {
-            let (win, buf) = win_buf!(editor, self.client_id);
-            if buf.id != self.bid || buf.total_changes_made() != self.changes_made {
+            let Some(buf) = editor.buffers.get(self.bid) else {
+                return;
+            };
+            if buf.total_changes_made() != self.changes_made {
+                return;
+            }
+            let Some(win) = editor.windows.pane_mut(self.client_id, self.pane) else {
+                return;
+            };
+            if win.buffer_id() != self.bid {
                 return;
             }
 
@@ -7,7 +7,7 @@ use crate::editor::{
     buffers::BufferId,
     job_broker::KeepInTouch,
     syntax::{Syntax, SyntaxResult},
-    windows::ViewSyntax,
+    windows::{PaneId, ViewSyntax},
     Editor,
 };
 use sanedit_server::{CPUJob, ClientId, JobContext};
@@ -15,6 +15,8 @@ use sanedit_server::{CPUJob, ClientId, JobContext};
 #[derive(Clone)]
 pub(crate) struct SyntaxParser {
     client_id: ClientId,
+    /// Pane to parse the syntax for
+    pane: PaneId,
     syntax: Syntax,
     bid: BufferId,
     total_changes_made: u32,
@@ -25,6 +27,7 @@ pub(crate) struct SyntaxParser {
 impl SyntaxParser {
     pub fn new(
         id: ClientId,
+        pane: PaneId,
         bid: BufferId,
         total_changes_made: u32,
         syntax: Syntax,
@@ -33,6 +36,7 @@ impl SyntaxParser {
     ) -> Self {
         SyntaxParser {
             client_id: id,
+            pane,
             bid,
             total_changes_made,
             syntax,
@@ -56,11 +60,17 @@ impl KeepInTouch for SyntaxParser {
     }
 
     fn on_message(&self, editor: &mut Editor, msg: Box<dyn Any>) {
-        if let Ok(output) = msg.downcast::<SyntaxResult>() {
-            let (win, buf) = win_buf!(editor, self.client_id);
-            if buf.id == self.bid && self.total_changes_made == buf.total_changes_made() {
-                *win.view_syntax() = ViewSyntax::new(self.bid, *output, self.total_changes_made);
-            }
+        let Ok(output) = msg.downcast::<SyntaxResult>() else {
+            return;
+        };
+        let Some(buf) = editor.buffers.get(self.bid) else {
+            return;
+        };
+        let Some(win) = editor.windows.pane_mut(self.client_id, self.pane) else {
+            return;
+        };
+        if win.buffer_id() == self.bid && self.total_changes_made == buf.total_changes_made() {
+            *win.view_syntax() = ViewSyntax::new(self.bid, *output, self.total_changes_made);
         }
     }
 }
@@ -0,0 +1,225 @@
+use std::{
+    any::Any,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use sanedit_buffer::{PieceTree, PieceTreeSlice};
+use sanedit_core::{diff, DiffGranularity};
+
+use crate::{
+    actions::signs::request_vcs_diff,
+    editor::{buffers::BufferId, job_broker::KeepInTouch, signs::SignKind, Editor},
+};
+use sanedit_server::{CPUJob, ClientId, Job, JobContext, JobResult};
+
+/// Diffs a buffer after a delay, so that the buffer is not diffed on every
+/// edit while typing
+#[derive(Debug, Clone)]
+pub(crate) struct ScheduleVcsDiff {
+    client_id: ClientId,
+    bid: BufferId,
+    delay: Duration,
+}
+
+impl ScheduleVcsDiff {
+    pub fn new(id: ClientId, bid: BufferId, delay: Duration) -> ScheduleVcsDiff {
+        ScheduleVcsDiff {
+            client_id: id,
+            bid,
+            delay,
+        }
+    }
+}
+
+struct Diff;
+
+impl Job for ScheduleVcsDiff {
+    fn run(&self, ctx: JobContext) -> JobResult {
+        let delay = self.delay;
+        let fut = async move {
+            tokio::time::sleep(delay).await;
+            ctx.send(Diff);
+            Ok(())
+        };
+
+        Box::pin(fut)
+    }
+}
+
+impl KeepInTouch for ScheduleVcsDiff {
+    fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    fn on_message(&self, editor: &mut Editor, msg: Box<dyn Any>) {
+        if msg.downcast::<Diff>().is_err() {
+            return;
+        }
+
+        request_vcs_diff(editor, self.client_id, self.bid);
+    }
+}
+
+/// Diffs a buffer against the file contents in the last commit, to find the
+/// lines changed since
+#[derive(Clone)]
+pub(crate) struct VcsDiff {
+    client_id: ClientId,
+    bid: BufferId,
+    total_changes_made: u32,
+    path: PathBuf,
+    /// File contents in the last commit, fetched if None
+    base: Option<PieceTreeSlice>,
+    pt: PieceTreeSlice,
+}
+
+impl VcsDiff {
+    pub fn new(
+        id: ClientId,
+        bid: BufferId,
+        total_changes_made: u32,
+        path: PathBuf,
+        base: Option<PieceTreeSlice>,
+        pt: PieceTreeSlice,
+    ) -> Self {
+        VcsDiff {
+            client_id: id,
+            bid,
+            total_changes_made,
+            path,
+            base,
+            pt,
+        }
+    }
+}
+
+struct VcsDiffResult {
+    base: Option<PieceTreeSlice>,
+    lines: Vec<(u64, SignKind)>,
+}
+
+impl CPUJob for VcsDiff {
+    fn run(&self, ctx: JobContext) -> anyhow::Result<()> {
+        let base = self.base.clone().or_else(|| fetch_base(&self.path));
+        let lines = base
+            .as_ref()
+            .map(|base| changed_lines(base, &self.pt))
+            .unwrap_or_default();
+        ctx.send(VcsDiffResult { base, lines });
+        Ok(())
+    }
+}
+
+impl KeepInTouch for VcsDiff {
+    fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    fn on_message(&self, editor: &mut Editor, msg: Box<dyn Any>) {
+        let Ok(result) = msg.downcast::<VcsDiffResult>() else {
+            return;
+        };
+        let Some(buf) = editor.buffers_mut().get_mut(self.bid) else {
+            return;
+        };
+
+        buf.vcs.base = result.base;
+        buf.vcs.fetched = true;
+        if buf.total_changes_made() == self.total_changes_made {
+            let lines = result
+                .lines
+                .into_iter()
+                .map(|(pos, kind)| (buf.mark(pos), kind))
+                .collect();
+            buf.vcs.set_lines(lines);
+        }
+    }
+}
+
+/// Contents of a file in the last git commit, None if the file is not
+/// tracked
+fn fetch_base(path: &Path) -> Option<PieceTreeSlice> {
+    let dir = path.parent()?;
+    let mut spec = OsString::from("HEAD:./");
+    spec.push(path.file_name()?);
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(spec)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let pt = PieceTree::from_reader(io::Cursor::new(output.stdout)).ok()?;
+    Some(pt.slice(..))
+}
+
+/// Start of each line in `current` that differs from `base`. Deleted lines
+/// are marked on the line following them.
+fn changed_lines(base: &PieceTreeSlice, current: &PieceTreeSlice) -> Vec<(u64, SignKind)> {
+    let Some(changes) = diff(base, current, DiffGranularity::Line) else {
+        return vec![];
+    };
+
+    let mut lines = vec![];
+    // Changes are positioned in base, move them to where they are in current
+    let mut delta = 0i64;
+    for change in changes.iter() {
+        let start = (change.start() as i64 + delta) as u64;
+        let text = change.text();
+        delta += text.len() as i64 - (change.end() - change.start()) as i64;
+
+        if text.is_empty() {
+            lines.push((start, SignKind::Deleted));
+            continue;
+        }
+
+        let kind = if change.is_insert() {
+            SignKind::Added
+        } else {
+            SignKind::Modified
+        };
+        lines.push((start, kind));
+        for (i, byte) in text.iter().enumerate() {
+            if *byte == b'\n' && i + 1 < text.len() {
+                lines.push((start + i as u64 + 1, kind));
+            }
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn changed_lines_of_diff() {
+        let base = PieceTree::from("a\nb\nc\nd\n");
+        let current = PieceTree::from("a\nB\nc\nnew\nnew\n");
+        assert_eq!(
+            vec![
+                (2, SignKind::Modified),
+                (6, SignKind::Modified),
+                (10, SignKind::Modified),
+            ],
+            changed_lines(&base.slice(..), &current.slice(..))
+        );
+
+        let current = PieceTree::from("x\na\nc\nd\n");
+        assert_eq!(
+            vec![(0, SignKind::Added), (4, SignKind::Deleted)],
+            changed_lines(&base.slice(..), &current.slice(..))
+        );
+    }
+}
@@ -393,7 +393,7 @@ fn rename(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, buf) = win_buf!(editor, id);
     let cursor = win.cursors.primary().pos();
     let slice = buf.slice(..);
-    let word = getf!(word_at_pos(&slice, cursor));
+    let word = getf!(word_at_pos(&slice, cursor, false));
     let word = String::from(&slice.slice(word));
 
     win.prompt = Prompt::builder()
@@ -445,6 +445,19 @@ pub(crate) fn open_document(editor: &mut Editor, id: ClientId) -> ActionResult {
     .into()
 }
 
+#[action("Send LSP document contents")]
+pub(crate) fn reload_document(editor: &mut Editor, id: ClientId) -> ActionResult {
+    lsp_notify(editor, id, |buf, path, slice, _lsp| {
+        let version = buf.total_changes_made() as i32;
+        Some(Notification::DidChange {
+            path,
+            changes: Either::Right(String::from(&slice)),
+            version,
+        })
+    })
+    .into()
+}
+
 #[action("Send LSP open document notification")]
 pub(crate) fn close_document(editor: &mut Editor, id: ClientId) -> ActionResult {
     lsp_notify(editor, id, |_buf, path, _slice, _lsp| {
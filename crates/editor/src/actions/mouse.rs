@@ -37,6 +37,23 @@ pub(crate) fn on_button_down_left_click(
     ActionResult::Ok
 }
 
+/// Jump to the sign shown on a view line
+pub(crate) fn goto_sign(editor: &mut Editor, id: ClientId, line: usize) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    let signs = win.view_signs(buf);
+    let sign = getf!(signs.get(line).copied().flatten());
+    {
+        let mut cursors = win.cursors.cursors_mut();
+        cursors.remove_except_primary();
+        cursors.primary().stop_selection();
+    }
+    win.jump_to_offset(sign.pos, buf);
+    hooks::run(editor, id, Hook::CursorMoved);
+    mode_normal(editor, id);
+
+    ActionResult::Ok
+}
+
 pub(crate) fn on_drag(editor: &mut Editor, id: ClientId, point: Point) -> ActionResult {
     let (win, _buf) = win_buf!(editor, id);
 
@@ -80,7 +97,7 @@ fn drag_word(editor: &mut Editor, id: ClientId, point: Point) -> ActionResult {
     win.cursors.cursors_mut().remove_except_primary();
     let slice = buf.slice(..);
     let pos = getf!(pos_at_point(win, point));
-    let word = getf!(word_at_pos(&slice, pos));
+    let word = getf!(word_at_pos(&slice, pos, false));
     drag_impl(editor, id, word)
 }
 
@@ -152,7 +169,7 @@ fn select_word(editor: &mut Editor, id: ClientId, point: Point) -> ActionResult
     let (win, buf) = win_buf!(editor, id);
     let slice = buf.slice(..);
     let pos = getf!(pos_at_point(win, point));
-    let word = getf!(word_at_pos(&slice, pos));
+    let word = getf!(word_at_pos(&slice, pos, false));
 
     {
         let mut cursors = win.cursors.cursors_mut();
@@ -15,7 +15,7 @@ use sanedit_messages::redraw::Point;
 
 use crate::editor::{
     hooks::Hook,
-    windows::{Jump, JumpGroup, NextKeyFunction, View},
+    windows::{HexLayout, Jump, JumpGroup, NextKeyFunction, View},
     Editor,
 };
 
@@ -32,6 +32,8 @@ fn do_move_line<F: Fn(&PieceTreeSlice, &Cursor, &DisplayOptions) -> (u64, usize)
 ) -> ActionResult {
     let (win, buf) = win_buf!(editor, id);
     let opts = win.display_options().clone();
+    // Block selection is moved using its moving corner
+    win.cursors.collapse_block();
     let primary = win.cursors.primary_index();
     let mut jump = None;
     let mut changed = false;
@@ -48,6 +50,7 @@ fn do_move_line<F: Fn(&PieceTreeSlice, &Cursor, &DisplayOptions) -> (u64, usize)
             jump = Some(Jump::new(mark, None));
         }
     }
+    win.update_block_selection(buf);
 
     if let Some(jump) = jump {
         win.cursor_jumps.push(JumpGroup::new(buf.id, vec![jump]));
@@ -73,6 +76,8 @@ fn do_move<F: Fn(&PieceTreeSlice, u64) -> u64>(
 ) -> ActionResult {
     let (win, buf) = win_buf!(editor, id);
     let mut changed = false;
+    // Block selection is moved using its moving corner
+    win.cursors.collapse_block();
     let primary = win.cursors.primary_index();
     let mut jump = None;
 
@@ -91,6 +96,7 @@ fn do_move<F: Fn(&PieceTreeSlice, u64) -> u64>(
             jump = Some(Jump::new(mark, None));
         }
     }
+    win.update_block_selection(buf);
 
     if let Some(jump) = jump {
         win.cursor_jumps.push(JumpGroup::new(buf.id, vec![jump]));
@@ -117,13 +123,34 @@ fn do_move_static(
     do_move(editor, id, |_, _| pos, col, save_jump)
 }
 
+/// Layout of the window if it is in hex mode, where cursors move on bytes
+/// and rows instead of characters and lines
+fn hex_layout(editor: &mut Editor, id: ClientId) -> Option<HexLayout> {
+    let (win, buf) = win_buf_ref!(editor, id);
+    win.view().hex_layout(buf)
+}
+
+fn next_byte(slice: &PieceTreeSlice, pos: u64) -> u64 {
+    min(pos + 1, slice.len())
+}
+
+fn prev_byte(_slice: &PieceTreeSlice, pos: u64) -> u64 {
+    pos.saturating_sub(1)
+}
+
 #[action("Cursors: Goto to next character")]
 fn next_grapheme(editor: &mut Editor, id: ClientId) -> ActionResult {
+    if hex_layout(editor, id).is_some() {
+        return do_move(editor, id, next_byte, None, false);
+    }
     do_move(editor, id, next_grapheme_boundary, None, false)
 }
 
 #[action("Cursors: Goto to previous character")]
 fn prev_grapheme(editor: &mut Editor, id: ClientId) -> ActionResult {
+    if hex_layout(editor, id).is_some() {
+        return do_move(editor, id, prev_byte, None, false);
+    }
     do_move(editor, id, prev_grapheme_boundary, None, false)
 }
 
@@ -134,11 +161,24 @@ fn first_char_of_line(editor: &mut Editor, id: ClientId) -> ActionResult {
 
 #[action("Cursors: Goto to line start")]
 fn start_of_line(editor: &mut Editor, id: ClientId) -> ActionResult {
+    if let Some(layout) = hex_layout(editor, id) {
+        return do_move(editor, id, |_, pos| layout.row_start(pos), None, false);
+    }
     do_move(editor, id, movement::start_of_line, Some(0), false)
 }
 
 #[action("Cursors: Goto to line end")]
 fn end_of_line(editor: &mut Editor, id: ClientId) -> ActionResult {
+    if let Some(layout) = hex_layout(editor, id) {
+        let row = layout.bytes_per_row;
+        return do_move(
+            editor,
+            id,
+            |slice, pos| min(layout.row_start(pos) + row - 1, slice.len()),
+            None,
+            false,
+        );
+    }
     do_move(editor, id, movement::end_of_line, Some(usize::MAX), false)
 }
 
@@ -156,24 +196,58 @@ fn end_of_buffer(editor: &mut Editor, id: ClientId) -> ActionResult {
     do_move_static(editor, id, blen, None, true)
 }
 
+/// Whether word movements should stop at subwords
+fn subword(editor: &Editor, id: ClientId) -> bool {
+    let (win, _buf) = win_buf_ref!(editor, id);
+    win.config.subword
+}
+
 #[action("Cursors: Goto to next word start")]
 fn next_word_start(editor: &mut Editor, id: ClientId) -> ActionResult {
-    do_move(editor, id, movement::next_word_start, None, false)
+    let subword = subword(editor, id);
+    do_move(
+        editor,
+        id,
+        |slice, pos| movement::next_word_start(slice, pos, subword),
+        None,
+        false,
+    )
 }
 
 #[action("Cursors: Goto to previous word start")]
 fn prev_word_start(editor: &mut Editor, id: ClientId) -> ActionResult {
-    do_move(editor, id, movement::prev_word_start, None, false)
+    let subword = subword(editor, id);
+    do_move(
+        editor,
+        id,
+        |slice, pos| movement::prev_word_start(slice, pos, subword),
+        None,
+        false,
+    )
 }
 
 #[action("Cursors: Goto to next word end")]
 fn next_word_end(editor: &mut Editor, id: ClientId) -> ActionResult {
-    do_move(editor, id, movement::next_word_end, None, false)
+    let subword = subword(editor, id);
+    do_move(
+        editor,
+        id,
+        |slice, pos| movement::next_word_end(slice, pos, subword),
+        None,
+        false,
+    )
 }
 
 #[action("Cursors: Goto to previous word end")]
 fn prev_word_end(editor: &mut Editor, id: ClientId) -> ActionResult {
-    do_move(editor, id, movement::prev_word_end, None, false)
+    let subword = subword(editor, id);
+    do_move(
+        editor,
+        id,
+        |slice, pos| movement::prev_word_end(slice, pos, subword),
+        None,
+        false,
+    )
 }
 
 #[action("Cursors: Goto to next paragraph")]
@@ -186,13 +260,43 @@ fn prev_paragraph(editor: &mut Editor, id: ClientId) -> ActionResult {
     do_move(editor, id, movement::prev_paragraph, None, true)
 }
 
+#[action("Cursors: Goto to next sentence")]
+fn next_sentence(editor: &mut Editor, id: ClientId) -> ActionResult {
+    do_move(editor, id, movement::next_sentence, None, false)
+}
+
+#[action("Cursors: Goto to previous sentence")]
+fn prev_sentence(editor: &mut Editor, id: ClientId) -> ActionResult {
+    do_move(editor, id, movement::prev_sentence, None, false)
+}
+
 #[action("Cursors: Goto to next line")]
 fn next_line(editor: &mut Editor, id: ClientId) -> ActionResult {
+    if let Some(layout) = hex_layout(editor, id) {
+        let row = layout.bytes_per_row;
+        return do_move(
+            editor,
+            id,
+            |slice, pos| min(pos + row, slice.len()),
+            None,
+            false,
+        );
+    }
     do_move_line(editor, id, movement::next_line, false)
 }
 
 #[action("Cursors: Goto to previous line")]
 fn prev_line(editor: &mut Editor, id: ClientId) -> ActionResult {
+    if let Some(layout) = hex_layout(editor, id) {
+        let row = layout.bytes_per_row;
+        return do_move(
+            editor,
+            id,
+            |_, pos| pos.checked_sub(row).unwrap_or(pos),
+            None,
+            false,
+        );
+    }
     do_move_line(editor, id, movement::prev_line, false)
 }
 
@@ -316,11 +420,42 @@ fn prev_searched_char(editor: &mut Editor, id: ClientId) -> ActionResult {
 
 #[action("Cursors: Goto to previous character on the same line")]
 fn prev_grapheme_on_line(editor: &mut Editor, id: ClientId) -> ActionResult {
+    if let Some(layout) = hex_layout(editor, id) {
+        return do_move(
+            editor,
+            id,
+            |_, pos| {
+                if pos == layout.row_start(pos) {
+                    pos
+                } else {
+                    pos - 1
+                }
+            },
+            None,
+            false,
+        );
+    }
     do_move(editor, id, movement::prev_grapheme_on_line, None, false)
 }
 
 #[action("Cursors: Goto to next character on the same line")]
 fn next_grapheme_on_line(editor: &mut Editor, id: ClientId) -> ActionResult {
+    if let Some(layout) = hex_layout(editor, id) {
+        return do_move(
+            editor,
+            id,
+            |slice, pos| {
+                let next = next_byte(slice, pos);
+                if next == layout.row_start(next) {
+                    pos
+                } else {
+                    next
+                }
+            },
+            None,
+            false,
+        );
+    }
     do_move(editor, id, movement::next_grapheme_on_line, None, false)
 }
 
@@ -328,9 +463,9 @@ fn next_grapheme_on_line(editor: &mut Editor, id: ClientId) -> ActionResult {
 pub(crate) fn prev_visual_line(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, buf) = win_buf!(editor, id);
 
-    // If multicursor use lines
+    // If multicursor or lines are not wrapped use lines
     let multi_cursor = win.cursors.len() > 1;
-    if multi_cursor {
+    if multi_cursor || !win.view().is_wrapped() {
         prev_line.execute(editor, id);
         return ActionResult::Ok;
     }
@@ -395,9 +530,9 @@ fn prev_visual_line_impl(view: &View, cursor: &Cursor) -> Option<(u64, usize)> {
 fn next_visual_line(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, buf) = win_buf!(editor, id);
 
-    // If multicursor use lines
+    // If multicursor or lines are not wrapped use lines
     let multi_cursor = win.cursors.len() > 1;
-    if multi_cursor {
+    if multi_cursor || !win.view().is_wrapped() {
         next_line.execute(editor, id);
         return ActionResult::Ok;
     }
@@ -21,6 +21,7 @@ use crate::{
         buffers::BufferId,
         hooks::Hook,
         keymap::KeymapResult,
+        registers::SPECIAL_REGISTERS,
         windows::{Focus, HistoryKind, Prompt},
         Editor, Map,
     },
@@ -342,6 +343,38 @@ fn goto_percentage(editor: &mut Editor, id: ClientId) -> ActionResult {
     ActionResult::Ok
 }
 
+#[action("Cursors: Goto offset")]
+fn goto_offset(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    // Hex mode uses hex addresses by default
+    let hex = win.view().is_hex();
+
+    win.prompt = Prompt::builder()
+        .prompt(if hex { "Offset (hex)" } else { "Offset" })
+        .simple()
+        .on_confirm(move |editor, id, out| {
+            let text = getf!(out.text());
+            let offset = getf!(parse_offset(text, hex));
+            let (win, buf) = win_buf!(editor, id);
+            win.jump_to_offset(offset, buf);
+            hooks::run(editor, id, Hook::CursorMoved);
+            ActionResult::Ok
+        })
+        .build();
+    focus(editor, id, Focus::Prompt);
+    ActionResult::Ok
+}
+
+/// Parse a byte offset, hex offsets are prefixed with 0x
+fn parse_offset(text: &str, hex: bool) -> Option<u64> {
+    let text = text.trim().replace('_', "");
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(digits) => u64::from_str_radix(digits, 16).ok(),
+        None if hex => u64::from_str_radix(&text, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
 #[action("Editor: Change working directory")]
 fn change_working_dir(editor: &mut Editor, id: ClientId) -> ActionResult {
     let wd = editor.working_dir().to_path_buf();
@@ -446,7 +479,9 @@ fn grep(editor: &mut Editor, id: ClientId) -> ActionResult {
 
                 map
             };
-            let job = Grep::new(patt, wd, ignore, buffers, id, e.config.editor.git_ignore);
+            let pane = getf!(e.windows.focused_pane(id));
+            let git_ignore = e.config.editor.git_ignore;
+            let job = Grep::new(patt, wd, ignore, buffers, id, pane, git_ignore);
             let job_name = format!("Grep '{patt}'");
             e.job_broker.request_slot(id, &job_name, job);
             ActionResult::Ok
@@ -583,6 +618,73 @@ fn kill_jobs(editor: &mut Editor, id: ClientId) -> ActionResult {
     ActionResult::Ok
 }
 
+#[action("Editor: Paste from register")]
+fn paste_from_register(editor: &mut Editor, id: ClientId) -> ActionResult {
+    const KILL_PREFIX: &str = "kill ";
+
+    let mut choices: Vec<Arc<Choice>> = vec![];
+    for (name, yank) in editor.registers.named() {
+        choices.push(Choice::from_text_with_description(
+            name.to_string(),
+            yank.preview(),
+        ));
+    }
+    for (i, yank) in editor.registers.kills().enumerate() {
+        choices.push(Choice::from_text_with_description(
+            format!("{KILL_PREFIX}{}", i + 1),
+            yank.preview(),
+        ));
+    }
+    for name in SPECIAL_REGISTERS {
+        if let Some(yank) = editor.register_yank(id, name) {
+            choices.push(Choice::from_text_with_description(
+                name.to_string(),
+                yank.preview(),
+            ));
+        }
+    }
+
+    let (win, _buf) = win_buf!(editor, id);
+    if choices.is_empty() {
+        win.warn_msg("Registers are empty");
+        return ActionResult::Skipped;
+    }
+
+    let job = MatcherJob::builder(id)
+        .options(Arc::new(choices))
+        .handler(Prompt::matcher_result_handler)
+        .build();
+
+    win.prompt = Prompt::builder()
+        .prompt("Paste from register")
+        .loads_options()
+        .on_confirm(move |editor, id, out| {
+            let text = getf!(out.text());
+            let yank = match text.strip_prefix(KILL_PREFIX) {
+                Some(n) => {
+                    let n: usize = getf!(n.parse().ok());
+                    getf!(editor.registers.kill(n.saturating_sub(1)).cloned())
+                }
+                None => {
+                    let mut chars = text.chars();
+                    let name = getf!(chars.next());
+                    if chars.next().is_some() {
+                        return ActionResult::Failed;
+                    }
+                    getf!(editor.register_yank(id, name))
+                }
+            };
+
+            editor.paste_yank(id, yank);
+            ActionResult::Ok
+        })
+        .build();
+
+    editor.job_broker.request(job);
+    focus(editor, id, Focus::Prompt);
+    ActionResult::Ok
+}
+
 #[action("Editor: Show key mappings")]
 fn show_keymaps(editor: &mut Editor, id: ClientId) -> ActionResult {
     const PROMPT_MESSAGE: &str = "Maps";
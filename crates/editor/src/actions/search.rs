@@ -113,12 +113,16 @@ fn highlight_current_search_matches(editor: &mut Editor, id: ClientId) {
 /// Highlights search matches on view using
 fn highlight_view_matches(editor: &mut Editor, id: ClientId, searcher: Searcher) {
     const JOB_NAME: &str = "search-highlight";
+    let Some(pane) = editor.windows.focused_pane(id) else {
+        return;
+    };
     let (win, buf) = win_buf!(editor, id);
     let mut view = win.view().range();
     view.start = view.start.saturating_sub(HORIZON_TOP);
     view.end = min(buf.len(), view.end + HORIZON_BOTTOM);
     let slice = buf.slice(view);
-    let job = jobs::Search::new(id, searcher, buf.id, slice, buf.total_changes_made());
+    let changes_made = buf.total_changes_made();
+    let job = jobs::Search::new(id, pane, searcher, buf.id, slice, changes_made);
     editor.job_broker.request_slot(id, JOB_NAME, job);
 }
 
@@ -204,7 +208,7 @@ fn search_next_word_under_cursor(editor: &mut Editor, id: ClientId) -> ActionRes
     let (win, buf) = win_buf!(editor, id);
     let pos = win.cursors.primary().pos();
     let slice = buf.slice(..);
-    let range = getf!(word_at_pos(&slice, pos));
+    let range = getf!(word_at_pos(&slice, pos, false));
     let word = String::from(&slice.slice(range));
 
     new_search(editor, id, &word, false);
@@ -216,7 +220,7 @@ fn search_prev_word_under_cursor(editor: &mut Editor, id: ClientId) -> ActionRes
     let (win, buf) = win_buf!(editor, id);
     let pos = win.cursors.primary().pos();
     let slice = buf.slice(..);
-    let range = getf!(word_at_pos(&slice, pos));
+    let range = getf!(word_at_pos(&slice, pos, false));
     let word = String::from(&slice.slice(range));
 
     new_search(editor, id, &word, true);
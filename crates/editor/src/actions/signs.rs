@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use sanedit_core::movement::{end_of_line, start_of_line};
+use sanedit_server::ClientId;
+
+use crate::editor::{buffers::BufferId, hooks::Hook, Editor};
+
+use super::{
+    jobs::{ScheduleVcsDiff, VcsDiff},
+    ActionResult,
+};
+
+const VCS_DIFF_JOB: &str = "vcs-diff";
+
+/// Time to wait for edits to settle before diffing the buffer again
+const VCS_DIFF_DELAY: Duration = Duration::from_millis(300);
+
+/// Buffer the running hook is for, or the buffer of the client window
+fn hook_buffer(editor: &Editor, id: ClientId) -> Option<BufferId> {
+    editor
+        .hooks
+        .running_hook()
+        .and_then(Hook::buffer_id)
+        .or_else(|| editor.windows().bid(id))
+}
+
+#[action("Buffer: Update version control signs")]
+fn update_vcs_signs(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let bid = getf!(hook_buffer(editor, id));
+    request_vcs_diff(editor, id, bid)
+}
+
+/// Update version control signs once edits have settled
+#[action("Buffer: Schedule version control signs update")]
+fn schedule_vcs_signs(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let bid = getf!(hook_buffer(editor, id));
+    if !needs_vcs_diff(editor, bid) {
+        return ActionResult::Skipped;
+    }
+
+    let job = ScheduleVcsDiff::new(id, bid, VCS_DIFF_DELAY);
+    editor.job_broker.request_slot(id, &vcs_diff_slot(bid), job);
+    ActionResult::Ok
+}
+
+/// Diff a buffer against the last commit in the background
+pub(crate) fn request_vcs_diff(editor: &mut Editor, id: ClientId, bid: BufferId) -> ActionResult {
+    if !needs_vcs_diff(editor, bid) {
+        return ActionResult::Skipped;
+    }
+    let buf = getf!(editor.buffers().get(bid));
+    let path = getf!(buf.path());
+
+    let job = VcsDiff::new(
+        id,
+        bid,
+        buf.total_changes_made(),
+        path.to_path_buf(),
+        buf.vcs.base.clone(),
+        buf.slice(..),
+    );
+    editor.job_broker.request_slot(id, &vcs_diff_slot(bid), job);
+    ActionResult::Ok
+}
+
+/// Whether the buffer can be diffed, big files and files that are not
+/// tracked are skipped
+fn needs_vcs_diff(editor: &Editor, bid: BufferId) -> bool {
+    let Some(buf) = editor.buffers().get(bid) else {
+        return false;
+    };
+    let untracked = buf.vcs.fetched && buf.vcs.base.is_none();
+    buf.path().is_some() && !buf.is_file_backed() && !untracked
+}
+
+fn vcs_diff_slot(bid: BufferId) -> String {
+    format!("{VCS_DIFF_JOB}-{bid:?}")
+}
+
+#[action("Buffer: Reload version control signs")]
+fn reload_vcs_signs(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let bid = getf!(hook_buffer(editor, id));
+    let buf = getf!(editor.buffers_mut().get_mut(bid));
+    // The file may have been committed since
+    buf.vcs.base = None;
+    buf.vcs.fetched = false;
+    update_vcs_signs.execute(editor, id)
+}
+
+#[action("Bookmark: Toggle bookmark on line")]
+fn toggle_bookmark(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    let slice = buf.slice(..);
+    let cpos = win.cursors().primary().pos();
+    let start = start_of_line(&slice, cpos);
+    let end = end_of_line(&slice, cpos);
+
+    let len = buf.bookmarks.len();
+    let marks = std::mem::take(&mut buf.bookmarks);
+    let kept: Vec<_> = marks
+        .into_iter()
+        .filter(|mark| {
+            let pos = buf.mark_to_pos(mark).pos();
+            pos < start || end < pos
+        })
+        .collect();
+    buf.bookmarks = kept;
+
+    if buf.bookmarks.len() == len {
+        let mark = buf.mark(start);
+        buf.bookmarks.push(mark);
+    }
+
+    ActionResult::Ok
+}
+
+#[action("Bookmark: Jump to next bookmark")]
+fn next_bookmark(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    let slice = buf.slice(..);
+    let end = end_of_line(&slice, win.cursors().primary().pos());
+    let next = buf
+        .bookmarks
+        .iter()
+        .map(|mark| buf.mark_to_pos(mark).pos())
+        .filter(|pos| *pos > end)
+        .min();
+    let pos = getf!(next);
+    win.jump_to_offset(pos, buf);
+    ActionResult::Ok
+}
+
+#[action("Bookmark: Jump to previous bookmark")]
+fn prev_bookmark(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    let slice = buf.slice(..);
+    let start = start_of_line(&slice, win.cursors().primary().pos());
+    let prev = buf
+        .bookmarks
+        .iter()
+        .map(|mark| buf.mark_to_pos(mark).pos())
+        .filter(|pos| *pos < start)
+        .max();
+    let pos = getf!(prev);
+    win.jump_to_offset(pos, buf);
+    ActionResult::Ok
+}
+
+#[action("Bookmark: Clear bookmarks")]
+fn clear_bookmarks(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (_win, buf) = win_buf!(editor, id);
+    buf.bookmarks.clear();
+    ActionResult::Ok
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use sanedit_buffer::Mark;
+use sanedit_buffer::Gravity;
 use sanedit_core::{indent_at_line, BufferRange, Change, Changes, Range};
 use sanedit_server::ClientId;
 
@@ -113,14 +113,9 @@ pub(crate) fn insert_snippet_impl(
     let (win, buf) = win_buf!(editor, id);
     let mut groups = vec![];
     for (start, end) in placeholders {
-        let smark = buf.mark(start);
-        let mut emark: Option<Mark> = None;
-
-        if start != end {
-            emark = Some(buf.mark(end));
-        }
-
-        let group = JumpGroup::new(buf.id, vec![Jump::new(smark, emark)]);
+        // Text typed at the placeholder ends belongs to the placeholder
+        let range = buf.mark_range(start..end, Gravity::Left, Gravity::Right);
+        let group = JumpGroup::new(buf.id, vec![Jump::new_range(range)]);
         groups.push(group);
     }
 
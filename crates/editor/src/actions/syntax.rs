@@ -1,6 +1,10 @@
+use sanedit_core::Changes;
+
 use crate::editor::{
+    buffers::Buffer,
     hooks::Hook,
     syntax::{HORIZON_BOTTOM, HORIZON_TOP_MIN},
+    windows::{PaneId, Window},
     Editor,
 };
 
@@ -20,44 +24,53 @@ pub(crate) fn prevent_flicker(editor: &mut Editor, id: ClientId) -> ActionResult
         .running_hook()
         .and_then(Hook::buffer_id)
         .unwrap_or(bid);
-    let clients = editor.windows().find_clients_with_buf(bid);
+    let buf = getf!(editor.buffers.get(bid));
+    let changes = getf!(buf.last_edit()).changes.clone();
 
+    let clients = editor.windows().find_clients_with_buf(bid);
     for client in clients {
-        let (win, buf) = win_buf!(editor, client);
-        let old = win.view_syntax();
-        let edit = getf!(buf.last_edit());
-
-        // Order is preserved
-        unsafe {
-            old.spans_mut().retain_mut(|hl| {
-                for next in edit.changes.iter() {
-                    let removed = next.range().len() as i128;
-                    let added = next.text().len() as i128;
-                    if next.end() <= hl.start() {
-                        // Before highlight
-                        hl.add_offset(added - removed);
-                    } else if next.start() >= hl.end() {
-                        // Went past highlight
-                        break;
-                    } else if hl.range().includes(next.range()) {
-                        // Inside a higlight assume the highlight spans this edit too
-                        // Extend or shrink instead
-                        hl.extend_by(added as u64);
-                        hl.shrink_by(removed as u64);
-                    } else {
-                        // When edit contains highlight just remove the higlight
-                        return false;
-                    }
-                }
+        let (win, _buf) = win_buf!(editor, client);
+        adjust_highlights(win, &changes);
+    }
 
-                true
-            });
-        }
+    for win in editor.windows.inactive_with_buf_mut(bid) {
+        adjust_highlights(win, &changes);
     }
 
     ActionResult::Ok
 }
 
+fn adjust_highlights(win: &mut Window, changes: &Changes) {
+    let old = win.view_syntax();
+
+    // Order is preserved
+    unsafe {
+        old.spans_mut().retain_mut(|hl| {
+            for next in changes.iter() {
+                let removed = next.range().len() as i128;
+                let added = next.text().len() as i128;
+                if next.end() <= hl.start() {
+                    // Before highlight
+                    hl.add_offset(added - removed);
+                } else if next.start() >= hl.end() {
+                    // Went past highlight
+                    break;
+                } else if hl.range().includes(next.range()) {
+                    // Inside a higlight assume the highlight spans this edit too
+                    // Extend or shrink instead
+                    hl.extend_by(added as u64);
+                    hl.shrink_by(removed as u64);
+                } else {
+                    // When edit contains highlight just remove the higlight
+                    return false;
+                }
+            }
+
+            true
+        });
+    }
+}
+
 #[action("Parse buffer syntax for view")]
 pub(crate) fn reparse_view(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (_win, buf) = win_buf!(editor, id);
@@ -79,37 +92,70 @@ pub(crate) fn reparse_view(editor: &mut Editor, id: ClientId) -> ActionResult {
             continue;
         }
 
-        win.redraw_view(buf);
-        let bid = buf.id;
-        let total = buf.total_changes_made();
-
-        let view = win.view().range();
-        let old = win.view_syntax();
-        let old_range = old.parsed_range();
+        if needs_parse(win, buf) {
+            parse_syntax.execute(editor, client);
+        }
+    }
 
-        let view_start_forward = view.start.saturating_sub(HORIZON_TOP_MIN / 2);
-        let view_end_forward = (view.end + HORIZON_BOTTOM / 4).min(buf.len());
+    // Panes that are not focused showing the buffer
+    let buf = getf!(editor.buffers.get(bid));
+    let has_syntax = buf
+        .language
+        .as_ref()
+        .is_some_and(|lang| editor.syntaxes.contains_key(lang));
+    if !has_syntax {
+        return ActionResult::Ok;
+    }
 
-        if old.buffer_id() != bid
-            || old.total_changes_made() != total
-            || !old_range.contains(&view_start_forward)
-            || !old_range.contains(&view_end_forward)
-        {
-            parse_syntax.execute(editor, client);
+    for (client, pane) in editor.windows.inactive_panes_with_buf(bid) {
+        let buf = getf!(editor.buffers.get(bid));
+        let Some(win) = editor.windows.inactive_mut(client, pane) else {
+            continue;
+        };
+        if win.config.highlight_syntax && needs_parse(win, buf) {
+            request_parse(editor, client, Some(pane));
         }
     }
 
     ActionResult::Ok
 }
 
+/// Whether the parsed syntax of a window does not cover its view anymore
+fn needs_parse(win: &mut Window, buf: &Buffer) -> bool {
+    win.redraw_view(buf);
+    let bid = buf.id;
+    let total = buf.total_changes_made();
+
+    let view = win.view().range();
+    let old = win.view_syntax();
+    let old_range = old.parsed_range();
+
+    let view_start_forward = view.start.saturating_sub(HORIZON_TOP_MIN / 2);
+    let view_end_forward = (view.end + HORIZON_BOTTOM / 4).min(buf.len());
+
+    old.buffer_id() != bid
+        || old.total_changes_made() != total
+        || !old_range.contains(&view_start_forward)
+        || !old_range.contains(&view_end_forward)
+}
+
 #[action("Parse buffer syntax")]
 pub(crate) fn parse_syntax(editor: &mut Editor, id: ClientId) -> ActionResult {
+    request_parse(editor, id, None)
+}
+
+/// Parse the syntax of the focused window of a client, or the window of
+/// one of its other panes
+fn request_parse(editor: &mut Editor, id: ClientId, pane: Option<PaneId>) -> ActionResult {
     const JOB_NAME: &str = "parse-syntax";
 
-    let (win, buf) = win_buf!(editor, id);
+    // Results go to the pane that requested them even if focus moves
+    let pane = getf!(pane.or_else(|| editor.windows.focused_pane(id)));
+    let win = getf!(editor.windows.pane_mut(id, pane));
     if !win.config.highlight_syntax {
         return ActionResult::Skipped;
     }
+    let buf = getf!(editor.buffers.get(win.buffer_id()));
     let bid = buf.id;
     let total_changes_made = buf.total_changes_made();
     let range = win.view().range();
@@ -119,8 +165,8 @@ pub(crate) fn parse_syntax(editor: &mut Editor, id: ClientId) -> ActionResult {
     if let Ok(s) = editor.syntaxes.get(&lang) {
         editor.job_broker.request_slot(
             id,
-            JOB_NAME,
-            SyntaxParser::new(id, bid, total_changes_made, s, ropt, range),
+            &format!("{JOB_NAME}-{pane}"),
+            SyntaxParser::new(id, pane, bid, total_changes_made, s, ropt, range),
         );
     }
 
@@ -1,16 +1,25 @@
-use std::{mem, sync::Arc};
+use std::{
+    hash::{BuildHasher, Hasher, RandomState},
+    mem,
+    sync::Arc,
+    time::Duration,
+};
 
 use sanedit_buffer::utf8::EndOfLine;
-use sanedit_core::{at_start_of_line, is_indent_at_pos, IndentKind, Language};
+use sanedit_core::{
+    at_start_of_line, is_indent_at_pos, movement::prev_grapheme_boundary, Case, Change, Changes,
+    IndentKind, Language, LineOrder, SortKey, SortOptions, Surround,
+};
+use sanedit_messages::key::Key;
 
 use crate::{
     actions::movement::start_of_buffer,
     common::is_yes,
     editor::{
-        buffers::{Buffer, BufferError, BufferId},
+        buffers::{Buffer, BufferError, BufferId, Encoding, COMMON_ENCODINGS},
         hooks::Hook,
         language::Languages,
-        windows::{Focus, NextKeyFunction, Prompt, ViewSyntax, Window},
+        windows::{Focus, Mode, NextKeyFunction, Prompt, ViewSyntax, Window},
         Editor,
     },
 };
@@ -21,13 +30,18 @@ use super::{
     completion,
     cursors::{remove_cursor_selections, swap_selection_dir},
     hooks::run,
-    jobs::MatcherJob,
+    jobs::{Compact, FlushJournal, LineIndexer, MatcherJob},
     movement::{end_of_line, prev_line},
     text_objects::{select_line, select_line_content},
     window::{focus, mode_insert, mode_normal},
     ActionResult,
 };
 
+const FLUSH_JOURNAL_JOB: &str = "flush-journal";
+
+/// Time without edits after which a fragmented buffer is compacted
+const COMPACT_DELAY: Duration = Duration::from_secs(1);
+
 #[action("Buffer: Remove character after cursor")]
 fn remove_grapheme_after_cursor(editor: &mut Editor, id: ClientId) -> ActionResult {
     run(editor, id, Hook::RemovePre);
@@ -44,7 +58,14 @@ fn remove_grapheme_after_cursor(editor: &mut Editor, id: ClientId) -> ActionResu
 fn remove_grapheme_before_cursor(editor: &mut Editor, id: ClientId) -> ActionResult {
     run(editor, id, Hook::RemovePre);
     let (win, buf) = win_buf!(editor, id);
+    // Backspace removes from the text recorded to the insert register
+    let record = win.mode == Mode::Insert && !win.view().is_hex() && !win.cursors.has_selections();
+    let cpos = win.cursors.primary().pos();
+    let removed = String::from(&buf.slice(prev_grapheme_boundary(&buf.slice(..), cpos)..cpos));
     if win.remove_grapheme_before_cursors(buf).is_ok() {
+        if record {
+            editor.registers.pop_insert(&removed);
+        }
         let hook = Hook::BufChanged(buf.id);
         run(editor, id, hook);
     }
@@ -88,10 +109,26 @@ pub(crate) fn insert(editor: &mut Editor, id: ClientId, text: &str) {
                 (on_input)(editor, id, &input)
             }
         }
+        Window if win.view().is_hex() => {
+            let (win, buf) = win_buf!(editor, id);
+            let total = buf.total_changes_made();
+            if win.hex_input(buf, text).is_ok() && total != buf.total_changes_made() {
+                win.view_to_cursor(buf);
+                let hook = Hook::BufChanged(buf.id);
+                run(editor, id, hook);
+            }
+        }
         Completion | Window => {
             run(editor, id, Hook::InsertPre);
             let (win, buf) = win_buf!(editor, id);
             if win.insert_at_cursors(buf, text).is_ok() {
+                if win.mode == Mode::Insert {
+                    editor.registers.push_insert(text);
+                }
+                if buf.config.auto_wrap {
+                    let comment = line_comment(&editor.languages, win, buf);
+                    let _ = win.auto_wrap_cursor_lines(buf, comment);
+                }
                 win.view_to_cursor(buf);
                 let hook = Hook::BufChanged(buf.id);
                 run(editor, id, hook);
@@ -185,9 +222,16 @@ fn save_as(editor: &mut Editor, id: ClientId) -> ActionResult {
 
 #[action("Buffer: Insert newline")]
 fn insert_newline(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    if win.view().is_hex() {
+        return ActionResult::Skipped;
+    }
+
     run(editor, id, Hook::InsertPre);
     let (win, buf) = win_buf!(editor, id);
-    let _ = win.insert_newline(buf);
+    if win.insert_newline(buf).is_ok() && win.mode == Mode::Insert {
+        editor.registers.push_insert(buf.config.eol.as_str());
+    }
     win.view_to_cursor(buf);
 
     let hook = Hook::BufChanged(buf.id);
@@ -199,6 +243,9 @@ fn insert_newline(editor: &mut Editor, id: ClientId) -> ActionResult {
 #[action("Buffer: Insert tab")]
 fn insert_tab(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, buf) = win_buf!(editor, id);
+    if win.view().is_hex() {
+        return ActionResult::Skipped;
+    }
 
     let slice = buf.slice(..);
     let primary = win.cursors.primary().pos();
@@ -244,6 +291,8 @@ fn backtab(editor: &mut Editor, id: ClientId) -> ActionResult {
 
 #[action("Buffer: Remove to line end")]
 fn remove_to_end_of_line(editor: &mut Editor, id: ClientId) -> ActionResult {
+    editor.push_kill_line_after_cursor(id);
+
     let (win, buf) = win_buf!(editor, id);
     if win.remove_line_after_cursor(buf).is_ok() {
         let hook = Hook::BufChanged(buf.id);
@@ -349,6 +398,15 @@ fn get_comment<'a>(
     Some((comment, &langconfig.comment_end))
 }
 
+/// Line comment string of the buffer language if the language uses line
+/// comments, otherwise an empty string
+fn line_comment<'a>(languages: &'a Languages, win: &mut Window, buf: &Buffer) -> &'a str {
+    match get_comment(languages, win, buf, false) {
+        Some((comment, "")) => comment,
+        _ => "",
+    }
+}
+
 #[action("Buffer: Comment lines")]
 fn comment_lines(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, buf) = win_buf!(editor, id);
@@ -411,6 +469,20 @@ fn join_lines(editor: &mut Editor, id: ClientId) -> ActionResult {
     ActionResult::Ok
 }
 
+#[action("Buffer: Reflow paragraph or selected lines")]
+fn reflow(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    let comment = line_comment(&editor.languages, win, buf);
+
+    if win.reflow_cursor_lines(buf, comment).is_ok() {
+        let hook = Hook::BufChanged(buf.id);
+        run(editor, id, hook);
+    }
+
+    mode_normal(editor, id);
+    ActionResult::Ok
+}
+
 #[action("Buffer: Insert literal")]
 fn insert_literal(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, _buf) = win_buf!(editor, id);
@@ -456,6 +528,141 @@ fn change_to_eol(editor: &mut Editor, id: ClientId) -> ActionResult {
     ActionResult::Ok
 }
 
+#[action("Buffer: Index lines")]
+fn index_lines(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let bid = editor
+        .hooks
+        .running_hook()
+        .and_then(Hook::buffer_id)
+        .unwrap_or_else(|| {
+            let (win, _) = win_buf_ref!(editor, id);
+            win.buffer_id()
+        });
+    let buf = getf!(editor.buffers().get(bid));
+    if buf.line_count().is_some() {
+        return ActionResult::Skipped;
+    }
+
+    let slice = buf.slice(..);
+    editor.job_broker.request(LineIndexer::new(id, bid, slice));
+    ActionResult::Ok
+}
+
+#[action("Buffer: Persist undo history")]
+fn persist_undo_history(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let bid = editor
+        .hooks
+        .running_hook()
+        .and_then(Hook::buffer_id)
+        .or_else(|| editor.windows().get(id).map(|win| win.buffer_id()));
+    let bid = getf!(bid);
+    editor.persist_undo_history(bid);
+    ActionResult::Ok
+}
+
+#[action("Buffer: Persist undo history in the background")]
+fn persist_undo_history_in_background(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let bid = editor
+        .hooks
+        .running_hook()
+        .and_then(Hook::buffer_id)
+        .or_else(|| editor.windows().get(id).map(|win| win.buffer_id()));
+    let bid = getf!(bid);
+    editor.persist_undo_history_in_background(id, bid);
+    ActionResult::Ok
+}
+
+/// Flush the recovery journal once its write interval has passed, if a
+/// change was not written to it because of the interval
+#[action("Buffer: Schedule recovery journal flush")]
+fn schedule_journal_flush(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let bid = editor
+        .hooks
+        .running_hook()
+        .and_then(Hook::buffer_id)
+        .or_else(|| editor.windows().bid(id));
+    let bid = getf!(bid);
+    let buf = getf!(editor.buffers().get(bid));
+    let delay = getf!(buf.journal_flush_in());
+    let job = FlushJournal::new(id, bid, delay);
+    editor
+        .job_broker
+        .request_slot(id, &format!("{FLUSH_JOURNAL_JOB}-{bid:?}"), job);
+    ActionResult::Ok
+}
+
+/// Compact the buffer once edits have settled, if it has fragmented enough
+#[action("Buffer: Schedule compaction")]
+fn schedule_compaction(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let bid = editor
+        .hooks
+        .running_hook()
+        .and_then(Hook::buffer_id)
+        .or_else(|| editor.windows().bid(id));
+    let bid = getf!(bid);
+    let buf = getf!(editor.buffers().get(bid));
+    if !buf.needs_compaction() {
+        return ActionResult::Skipped;
+    }
+
+    let job = Compact::new(id, bid, COMPACT_DELAY);
+    editor.job_broker.request_slot(id, &Compact::slot(bid), job);
+    ActionResult::Ok
+}
+
+#[action("Buffer: Recover unsaved changes")]
+fn recover_unsaved_changes(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    if !buf.has_recovery_journal() {
+        return ActionResult::Skipped;
+    }
+
+    let text = match buf.recover_journal() {
+        Ok(Some(text)) => text,
+        Ok(None) => {
+            buf.discard_journal();
+            return ActionResult::Skipped;
+        }
+        Err(e) => {
+            win.warn_msg(&format!("Cannot recover unsaved changes: {e}"));
+            buf.discard_journal();
+            return ActionResult::Failed;
+        }
+    };
+
+    win.prompt = Prompt::builder()
+        .prompt("Found unsaved changes from a previous session. Recover them? (Y/n)")
+        .simple()
+        .on_confirm(move |editor, id, out| {
+            let input = getf!(out.text());
+            let yes = input.is_empty() || is_yes(input);
+            let (win, buf) = win_buf!(editor, id);
+            if !yes {
+                buf.discard_journal();
+                return ActionResult::Ok;
+            }
+
+            // Keep the journal if the changes cannot be applied
+            let change = Change::replace(0..buf.len(), &text);
+            let changes = Changes::from(change);
+            if win.change(buf, &changes).is_err() {
+                return ActionResult::Failed;
+            }
+            // The change replaces the journal with a new one, remove the
+            // recovered one if it was not replaced
+            if buf.has_recovery_journal() {
+                buf.discard_journal();
+            }
+
+            let hook = Hook::BufChanged(buf.id);
+            run(editor, id, hook);
+            ActionResult::Ok
+        })
+        .build();
+    focus(editor, id, Focus::Prompt);
+    ActionResult::Ok
+}
+
 #[action("Buffer: Check if file has been modified")]
 fn check_file_modification(editor: &mut Editor, id: ClientId) -> ActionResult {
     let prompt = editor.config.editor.auto_reload_changed_or_removed_file;
@@ -540,6 +747,7 @@ fn reload_file_from_disk(editor: &mut Editor, id: ClientId) -> ActionResult {
     if !ok {
         return ActionResult::Ok;
     }
+    editor.decompress_buffer(id, bid);
 
     let hook = Hook::BufCreated(bid);
     run(editor, id, hook);
@@ -554,6 +762,12 @@ fn reload_file_from_disk(editor: &mut Editor, id: ClientId) -> ActionResult {
         win.full_reload(buf);
     }
 
+    if let Some(buf) = editor.buffers.get(bid) {
+        for win in editor.windows.inactive_with_buf_mut(bid) {
+            win.full_reload(buf);
+        }
+    }
+
     ActionResult::Ok
 }
 
@@ -581,6 +795,303 @@ fn lowercase(editor: &mut Editor, id: ClientId) -> ActionResult {
     }
 }
 
+#[action("Buffer: Selections to snake_case")]
+fn snake_case(editor: &mut Editor, id: ClientId) -> ActionResult {
+    convert_case(editor, id, Case::Snake)
+}
+
+#[action("Buffer: Selections to camelCase")]
+fn camel_case(editor: &mut Editor, id: ClientId) -> ActionResult {
+    convert_case(editor, id, Case::Camel)
+}
+
+#[action("Buffer: Selections to PascalCase")]
+fn pascal_case(editor: &mut Editor, id: ClientId) -> ActionResult {
+    convert_case(editor, id, Case::Pascal)
+}
+
+#[action("Buffer: Selections to kebab-case")]
+fn kebab_case(editor: &mut Editor, id: ClientId) -> ActionResult {
+    convert_case(editor, id, Case::Kebab)
+}
+
+#[action("Buffer: Selections to SCREAMING_SNAKE_CASE")]
+fn constant_case(editor: &mut Editor, id: ClientId) -> ActionResult {
+    convert_case(editor, id, Case::Constant)
+}
+
+#[action("Buffer: Selections to Title Case")]
+fn title_case(editor: &mut Editor, id: ClientId) -> ActionResult {
+    convert_case(editor, id, Case::Title)
+}
+
+#[action("Buffer: Cycle selections through identifier cases")]
+fn cycle_case(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf_ref!(editor, id);
+    let primary = getf!(win.cursors.primary().selection());
+    let text = String::from(&buf.slice(primary));
+    // Convert all selections to the case following the primary's case
+    let case = Case::detect(&text)
+        .map(|case| case.next())
+        .unwrap_or(Case::Snake);
+    convert_case(editor, id, case)
+}
+
+fn convert_case(editor: &mut Editor, id: ClientId, case: Case) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    if win.convert_case_selections(buf, case).is_ok() {
+        let hook = Hook::BufChanged(buf.id);
+        run(editor, id, hook);
+        ActionResult::Ok
+    } else {
+        ActionResult::Failed
+    }
+}
+
+#[action("Buffer: Sort lines")]
+fn sort_lines(editor: &mut Editor, id: ClientId) -> ActionResult {
+    sort_lines_by(editor, id, SortOptions::default())
+}
+
+#[action("Buffer: Sort lines in reverse")]
+fn sort_lines_reverse(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let opts = SortOptions {
+        reverse: true,
+        ..Default::default()
+    };
+    sort_lines_by(editor, id, opts)
+}
+
+#[action("Buffer: Sort lines numerically")]
+fn sort_lines_numeric(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let opts = SortOptions {
+        order: LineOrder::Numeric,
+        ..Default::default()
+    };
+    sort_lines_by(editor, id, opts)
+}
+
+#[action("Buffer: Sort lines naturally")]
+fn sort_lines_natural(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let opts = SortOptions {
+        order: LineOrder::Natural,
+        ..Default::default()
+    };
+    sort_lines_by(editor, id, opts)
+}
+
+#[action("Buffer: Sort lines case insensitively")]
+fn sort_lines_case_insensitive(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let opts = SortOptions {
+        order: LineOrder::CaseInsensitive,
+        ..Default::default()
+    };
+    sort_lines_by(editor, id, opts)
+}
+
+#[action("Buffer: Sort lines by field")]
+fn sort_lines_by_field(editor: &mut Editor, id: ClientId) -> ActionResult {
+    prompt_sort_key(editor, id, "Sort by field", SortKey::Field)
+}
+
+#[action("Buffer: Sort lines by column")]
+fn sort_lines_by_column(editor: &mut Editor, id: ClientId) -> ActionResult {
+    prompt_sort_key(editor, id, "Sort by column", SortKey::Column)
+}
+
+fn prompt_sort_key(
+    editor: &mut Editor,
+    id: ClientId,
+    msg: &str,
+    key: fn(usize) -> SortKey,
+) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    win.prompt = Prompt::builder()
+        .prompt(msg)
+        .simple()
+        .on_confirm(move |editor, id, out| {
+            let input = getf!(out.text());
+            let n = match input.trim().parse::<usize>() {
+                Ok(n) if n != 0 => n,
+                _ => {
+                    let (win, _buf) = win_buf!(editor, id);
+                    win.warn_msg(&format!("Invalid number '{input}'"));
+                    return ActionResult::Failed;
+                }
+            };
+
+            let opts = SortOptions {
+                key: key(n),
+                ..Default::default()
+            };
+            sort_lines_by(editor, id, opts)
+        })
+        .build();
+    focus(editor, id, Focus::Prompt);
+    ActionResult::Ok
+}
+
+fn sort_lines_by(editor: &mut Editor, id: ClientId, opts: SortOptions) -> ActionResult {
+    edit_lines(editor, id, |mut lines| {
+        sanedit_core::sort_lines(&mut lines, &opts);
+        lines
+    })
+}
+
+#[action("Buffer: Remove duplicate lines")]
+fn unique_lines(editor: &mut Editor, id: ClientId) -> ActionResult {
+    edit_lines(editor, id, sanedit_core::unique_lines)
+}
+
+#[action("Buffer: Reverse lines")]
+fn reverse_lines(editor: &mut Editor, id: ClientId) -> ActionResult {
+    edit_lines(editor, id, |mut lines| {
+        lines.reverse();
+        lines
+    })
+}
+
+#[action("Buffer: Shuffle lines")]
+fn shuffle_lines(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let seed = RandomState::new().build_hasher().finish();
+    edit_lines(editor, id, |mut lines| {
+        sanedit_core::shuffle_lines(&mut lines, seed);
+        lines
+    })
+}
+
+/// Edit selected lines or the whole buffer
+fn edit_lines<F>(editor: &mut Editor, id: ClientId, f: F) -> ActionResult
+where
+    F: FnMut(Vec<String>) -> Vec<String>,
+{
+    let (win, buf) = win_buf!(editor, id);
+    if win.edit_lines(buf, f).is_ok() {
+        let hook = Hook::BufChanged(buf.id);
+        run(editor, id, hook);
+        ActionResult::Ok
+    } else {
+        ActionResult::Failed
+    }
+}
+
+#[action("Buffer: Increment number, date or boolean")]
+fn increment(editor: &mut Editor, id: ClientId) -> ActionResult {
+    increment_by(editor, id, 1)
+}
+
+#[action("Buffer: Decrement number, date or boolean")]
+fn decrement(editor: &mut Editor, id: ClientId) -> ActionResult {
+    increment_by(editor, id, -1)
+}
+
+/// Increment by count typed before the action times sign
+fn increment_by(editor: &mut Editor, id: ClientId, sign: i64) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    let count = win.take_count().unwrap_or(1) as i64;
+    if win.increment_cursors(buf, sign * count).is_ok() {
+        let hook = Hook::BufChanged(buf.id);
+        run(editor, id, hook);
+        ActionResult::Ok
+    } else {
+        ActionResult::Failed
+    }
+}
+
+#[action("Buffer: Surround selections")]
+fn surround_selections(editor: &mut Editor, id: ClientId) -> ActionResult {
+    read_surround(editor, id, |editor, id, surround| {
+        let (win, buf) = win_buf!(editor, id);
+        if win.surround_selections(buf, &surround).is_ok() {
+            let hook = Hook::BufChanged(buf.id);
+            run(editor, id, hook);
+            ActionResult::Ok
+        } else {
+            ActionResult::Failed
+        }
+    });
+    ActionResult::Ok
+}
+
+#[action("Buffer: Change surrounding pair")]
+fn change_surrounding(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    win.next_key_handler = Some(NextKeyFunction(Arc::new(|editor, id, event| {
+        let Key::Char(ch) = *event.key() else {
+            return ActionResult::Failed;
+        };
+
+        read_surround(editor, id, move |editor, id, surround| {
+            let (win, buf) = win_buf!(editor, id);
+            if win.change_surrounding(buf, ch, Some(&surround)).is_ok() {
+                let hook = Hook::BufChanged(buf.id);
+                run(editor, id, hook);
+                ActionResult::Ok
+            } else {
+                ActionResult::Failed
+            }
+        });
+        ActionResult::Ok
+    })));
+    ActionResult::Ok
+}
+
+#[action("Buffer: Delete surrounding pair")]
+fn delete_surrounding(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    win.next_key_handler = Some(NextKeyFunction(Arc::new(|editor, id, event| {
+        let Key::Char(ch) = *event.key() else {
+            return ActionResult::Failed;
+        };
+
+        let (win, buf) = win_buf!(editor, id);
+        if win.change_surrounding(buf, ch, None).is_ok() {
+            let hook = Hook::BufChanged(buf.id);
+            run(editor, id, hook);
+            ActionResult::Ok
+        } else {
+            ActionResult::Failed
+        }
+    })));
+    ActionResult::Ok
+}
+
+/// Read delimiters from the next key and call `f` with them, `t` prompts for
+/// a tag name instead
+fn read_surround<F>(editor: &mut Editor, id: ClientId, f: F)
+where
+    F: Fn(&mut Editor, ClientId, Surround) -> ActionResult + 'static,
+{
+    let f = Arc::new(f);
+    let (win, _buf) = win_buf!(editor, id);
+    win.next_key_handler = Some(NextKeyFunction(Arc::new(move |editor, id, event| {
+        let Key::Char(ch) = *event.key() else {
+            return ActionResult::Failed;
+        };
+
+        if ch != 't' {
+            return f(editor, id, Surround::from_char(ch));
+        }
+
+        let f = f.clone();
+        let (win, _buf) = win_buf!(editor, id);
+        win.prompt = Prompt::builder()
+            .prompt("Tag")
+            .simple()
+            .on_confirm(move |editor, id, out| {
+                let tag = getf!(out.text());
+                if tag.trim().is_empty() {
+                    return ActionResult::Failed;
+                }
+
+                f(editor, id, Surround::Tag(tag.into()))
+            })
+            .build();
+        focus(editor, id, Focus::Prompt);
+        ActionResult::Ok
+    })));
+}
+
 #[action("Buffer: Rotate selections")]
 fn rotate_selections(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, buf) = win_buf!(editor, id);
@@ -734,6 +1245,91 @@ fn set_eol(editor: &mut Editor, id: ClientId) -> ActionResult {
     ActionResult::Ok
 }
 
+#[action("Buffer: Set encoding")]
+fn set_encoding(editor: &mut Editor, id: ClientId) -> ActionResult {
+    prompt_encoding(editor, id, "Encoding", |editor, id, encoding| {
+        let (_win, buf) = win_buf!(editor, id);
+        // Buffer is converted to the new encoding on save
+        if buf.config.encoding != encoding {
+            buf.config.encoding = encoding;
+            buf.set_unsaved();
+        }
+        ActionResult::Ok
+    })
+}
+
+#[action("Buffer: Reopen with encoding")]
+fn reopen_with_encoding(editor: &mut Editor, id: ClientId) -> ActionResult {
+    prompt_encoding(
+        editor,
+        id,
+        "Reopen with encoding",
+        |editor, id, encoding| {
+            let (win, buf) = win_buf!(editor, id);
+            if buf.path().is_none() {
+                win.warn_msg("Buffer has no file to reopen");
+                return ActionResult::Failed;
+            }
+            if buf.is_modified() {
+                win.warn_msg("Save or undo changes before reopening");
+                return ActionResult::Failed;
+            }
+
+            buf.config.encoding = encoding;
+            let result = reload_file_from_disk.execute(editor, id);
+
+            // Reading falls back to UTF-8 if the file is not valid in the encoding
+            let (win, buf) = win_buf!(editor, id);
+            if buf.config.encoding != encoding {
+                win.warn_msg(&format!("Failed to decode file as {encoding}"));
+                return ActionResult::Failed;
+            }
+            result
+        },
+    )
+}
+
+/// Prompt for an encoding and call `on_encoding` with it. Big files are
+/// always read as UTF-8 so they cannot be transcoded.
+fn prompt_encoding(
+    editor: &mut Editor,
+    id: ClientId,
+    prompt: &str,
+    on_encoding: fn(&mut Editor, ClientId, Encoding) -> ActionResult,
+) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    if buf.is_file_backed() {
+        win.warn_msg("Big files are always opened as UTF-8");
+        return ActionResult::Failed;
+    }
+
+    let options: Arc<Vec<&'static str>> = Arc::new(COMMON_ENCODINGS.to_vec());
+    let job = MatcherJob::builder(id)
+        .options(options)
+        .handler(Prompt::matcher_result_handler)
+        .build();
+
+    win.prompt = Prompt::builder()
+        .prompt(prompt)
+        .loads_options()
+        .simple()
+        .on_confirm(move |editor, id, input| {
+            let (win, _buf) = win_buf!(editor, id);
+            let input = getf!(input.text());
+            let Some(encoding) = Encoding::from_label(input) else {
+                win.warn_msg(&format!("Unknown encoding {input}"));
+                return ActionResult::Failed;
+            };
+
+            on_encoding(editor, id, encoding)
+        })
+        .build();
+    focus(editor, id, Focus::Prompt);
+    editor.job_broker.request(job);
+
+    ActionResult::Ok
+}
+
 #[action("Buffer: Fix end of lines")]
 fn fix_eols(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, buf) = win_buf!(editor, id);
@@ -1,14 +1,18 @@
+use std::cmp::{max, min};
+
 use sanedit_buffer::PieceTreeSlice;
 use sanedit_core::{
     find_range,
     movement::{self, next_line_start},
-    paragraph_at_pos, word_at_pos, BufferRange, Cursor, FindRangeOptions, Range, Searcher,
+    paragraph_at_pos, sentence_at_pos, word_at_pos, BufferRange, Cursor, FindRangeOptions, Range,
+    Searcher,
 };
 use sanedit_syntax::PieceTreeSliceSource;
 
 use crate::editor::{
     buffers::Buffer,
     hooks::Hook,
+    syntax::{text_object_at, TEXT_OBJECT_HORIZON},
     windows::{Cursors, Focus, HistoryKind, Prompt, Window, Zone},
     Editor,
 };
@@ -269,7 +273,9 @@ fn select_backtick(editor: &mut Editor, id: ClientId) -> ActionResult {
 
 #[action("Select: Word")]
 fn select_word(editor: &mut Editor, id: ClientId) -> ActionResult {
-    select(editor, id, word_at_pos)
+    let (win, _buf) = win_buf_ref!(editor, id);
+    let subword = win.config.subword;
+    select(editor, id, |slice, pos| word_at_pos(slice, pos, subword))
 }
 
 #[action("Select: Paragraph")]
@@ -277,6 +283,109 @@ fn select_paragraph(editor: &mut Editor, id: ClientId) -> ActionResult {
     select(editor, id, paragraph_at_pos)
 }
 
+/// Select a text object defined by the buffer language syntax
+fn select_syntax_object(
+    editor: &mut Editor,
+    id: ClientId,
+    kind: &str,
+    inside: bool,
+) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    let lang = getf!(buf.language.clone());
+    let syntax = getf!(editor.syntaxes.get(&lang).ok());
+    let slice = buf.slice(..);
+
+    // Parse once around cursors that are close to each other
+    let mut positions: Vec<u64> = win.cursors.cursors().iter().map(Cursor::pos).collect();
+    positions.sort();
+    let mut windows: Vec<BufferRange> = vec![];
+    for pos in positions {
+        let start = pos.saturating_sub(TEXT_OBJECT_HORIZON);
+        let end = min(slice.len(), pos + TEXT_OBJECT_HORIZON);
+        match windows.last_mut() {
+            Some(last) if start <= last.end => last.end = max(last.end, end),
+            _ => windows.push(Range::from(start..end)),
+        }
+    }
+
+    let mut objects = vec![];
+    for window in windows {
+        match syntax.text_objects(&slice, window, kind) {
+            Ok(objs) => objects.extend(objs),
+            Err(e) => {
+                log::error!("Failed to parse text objects: {e}");
+                return ActionResult::Failed;
+            }
+        }
+    }
+
+    select(editor, id, |_, pos| {
+        let obj = text_object_at(&objects, pos)?;
+        let range = if inside { obj.inside } else { obj.range };
+        Some(range)
+    })
+}
+
+#[action("Select: Function")]
+fn select_function(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "function", true)
+}
+
+#[action("Select: Function (incl)")]
+fn select_function_incl(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "function", false)
+}
+
+#[action("Select: Class")]
+fn select_class(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "class", true)
+}
+
+#[action("Select: Class (incl)")]
+fn select_class_incl(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "class", false)
+}
+
+#[action("Select: Argument")]
+fn select_argument(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "argument", true)
+}
+
+#[action("Select: Argument (incl)")]
+fn select_argument_incl(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "argument", false)
+}
+
+#[action("Select: Comment")]
+fn select_comment(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "comment", true)
+}
+
+#[action("Select: Comment (incl)")]
+fn select_comment_incl(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "comment", false)
+}
+
+#[action("Select: String")]
+fn select_string(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "string", true)
+}
+
+#[action("Select: String (incl)")]
+fn select_string_incl(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select_syntax_object(editor, id, "string", false)
+}
+
+#[action("Select: Sentence")]
+fn select_sentence(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select(editor, id, |slice, pos| sentence_at_pos(slice, pos, false))
+}
+
+#[action("Select: Sentence (incl)")]
+fn select_sentence_incl(editor: &mut Editor, id: ClientId) -> ActionResult {
+    select(editor, id, |slice, pos| sentence_at_pos(slice, pos, true))
+}
+
 #[action("Select: Pattern")]
 fn select_pattern(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, _buf) = win_buf!(editor, id);
@@ -1,5 +1,8 @@
-use crate::editor::Editor;
+use std::cmp::min;
 
+use crate::editor::{syntax::TEXT_OBJECT_HORIZON, Editor};
+
+use sanedit_core::{fold_from_range, indent_fold_at, BufferRange, Range};
 use sanedit_server::ClientId;
 
 use super::ActionResult;
@@ -19,3 +22,73 @@ fn scroll_up(editor: &mut Editor, id: ClientId) -> ActionResult {
 
     ActionResult::Ok
 }
+
+/// Fold at the cursor, from the buffer language syntax if it defines folds
+/// or from the indentation
+fn fold_at_cursor(editor: &mut Editor, id: ClientId) -> Option<BufferRange> {
+    let (win, buf) = win_buf_ref!(editor, id);
+    let slice = buf.slice(..);
+    let pos = win.cursors().primary().pos();
+
+    let syntax = buf
+        .language
+        .as_ref()
+        .and_then(|lang| editor.syntaxes.get(lang).ok());
+    if let Some(syntax) = syntax {
+        let start = pos.saturating_sub(TEXT_OBJECT_HORIZON);
+        let end = min(slice.len(), pos + TEXT_OBJECT_HORIZON);
+        match syntax.fold_ranges(&slice, Range::from(start..end)) {
+            Ok(ranges) => {
+                // Innermost fold containing the cursor line
+                let fold = ranges
+                    .into_iter()
+                    .filter_map(|range| fold_from_range(&slice, range))
+                    .filter(|fold| fold.start <= pos && pos <= fold.end)
+                    .min_by_key(|fold| fold.len());
+                if fold.is_some() {
+                    return fold;
+                }
+            }
+            Err(e) => log::error!("Failed to parse folds: {e}"),
+        }
+    }
+
+    indent_fold_at(&slice, pos)
+}
+
+#[action("View: Close fold")]
+fn close_fold(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let range = getf!(fold_at_cursor(editor, id));
+    let (win, buf) = win_buf!(editor, id);
+    win.close_fold(buf, range);
+    ActionResult::Ok
+}
+
+#[action("View: Open fold")]
+fn open_fold(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    let pos = win.cursors().primary().pos();
+    if win.open_fold(buf, pos) {
+        ActionResult::Ok
+    } else {
+        ActionResult::Failed
+    }
+}
+
+#[action("View: Toggle fold")]
+fn toggle_fold(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf_ref!(editor, id);
+    let pos = win.cursors().primary().pos();
+    if win.view().folds().is_closed_at(buf, pos) {
+        open_fold.execute(editor, id)
+    } else {
+        close_fold.execute(editor, id)
+    }
+}
+
+#[action("View: Open all folds")]
+fn open_all_folds(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    win.open_all_folds();
+    ActionResult::Ok
+}
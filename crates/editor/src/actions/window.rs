@@ -4,7 +4,7 @@ use crate::{
     editor::{
         buffers::Buffer,
         hooks::Hook,
-        windows::{games::snake::Snake, Focus, Mode, Zone},
+        windows::{games::snake::Snake, Direction, Focus, Mode, SplitDirection, Zone},
         Editor,
     },
     VERSION,
@@ -94,6 +94,39 @@ fn clear_messages(editor: &mut Editor, id: ClientId) -> ActionResult {
     ActionResult::Ok
 }
 
+#[action("Window: Toggle hex mode")]
+fn toggle_hex_mode(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    win.toggle_hex(buf);
+    ActionResult::Ok
+}
+
+#[action("Window: Toggle hex insert and overwrite")]
+fn toggle_hex_insert(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    if !win.view().is_hex() {
+        return ActionResult::Skipped;
+    }
+    win.hex.insert = !win.hex.insert;
+    win.hex.pending = None;
+    ActionResult::Ok
+}
+
+#[action("Window: Cycle line number modes")]
+fn cycle_line_numbers(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, _buf) = win_buf!(editor, id);
+    win.config.line_numbers = win.config.line_numbers.next();
+    ActionResult::Ok
+}
+
+#[action("Window: Toggle line wrapping")]
+fn toggle_wrap(editor: &mut Editor, id: ClientId) -> ActionResult {
+    let (win, buf) = win_buf!(editor, id);
+    win.config.wrap = !win.config.wrap;
+    win.view_to_cursor(buf);
+    ActionResult::Ok
+}
+
 #[action("Sync windows if a buffer is changed")]
 fn sync_windows(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (_win, buf) = win_buf_ref!(editor, id);
@@ -112,6 +145,12 @@ fn sync_windows(editor: &mut Editor, id: ClientId) -> ActionResult {
         }
     }
 
+    if let Some(buf) = editor.buffers.get(bid) {
+        for win in editor.windows.inactive_with_buf_mut(bid) {
+            win.on_buffer_changed(buf);
+        }
+    }
+
     ActionResult::Ok
 }
 
@@ -197,6 +236,111 @@ fn new_window_vertical(editor: &mut Editor, id: ClientId) -> ActionResult {
     ActionResult::Ok
 }
 
+fn split(editor: &mut Editor, id: ClientId, dir: SplitDirection) -> ActionResult {
+    let (win, _buf) = win_buf_ref!(editor, id);
+    let split = win.split();
+    if !editor.windows.split(id, dir, split) {
+        return ActionResult::Failed;
+    }
+    editor.windows.update_sizes(id, &editor.buffers);
+    ActionResult::Ok
+}
+
+#[action("Window: Split vertically")]
+fn split_vertical(editor: &mut Editor, id: ClientId) -> ActionResult {
+    split(editor, id, SplitDirection::Vertical)
+}
+
+#[action("Window: Split horizontally")]
+fn split_horizontal(editor: &mut Editor, id: ClientId) -> ActionResult {
+    split(editor, id, SplitDirection::Horizontal)
+}
+
+#[action("Window: Close split")]
+fn close_split(editor: &mut Editor, id: ClientId) -> ActionResult {
+    if !editor
+        .windows
+        .layout(id)
+        .is_some_and(|layout| layout.is_split())
+    {
+        let (win, _buf) = win_buf!(editor, id);
+        win.warn_msg("Cannot close the last window");
+        return ActionResult::Failed;
+    }
+
+    let (_win, buf) = win_buf!(editor, id);
+    let old = buf.id;
+    run(editor, id, Hook::BufLeave(old));
+
+    let closed = getf!(editor.windows.close_pane(id));
+    editor.windows.update_sizes(id, &editor.buffers);
+    let (_win, buf) = win_buf!(editor, id);
+    let bid = buf.id;
+    run(editor, id, Hook::BufEnter(bid));
+    editor.remove_unused_buffer(closed.buffer_id());
+    ActionResult::Ok
+}
+
+fn focus_split(editor: &mut Editor, id: ClientId, dir: Direction) -> ActionResult {
+    match editor.windows.neighbour(id, dir) {
+        Some(pane) if editor.focus_pane(id, pane) => ActionResult::Ok,
+        _ => ActionResult::Skipped,
+    }
+}
+
+#[action("Window: Focus split on the left")]
+fn focus_split_left(editor: &mut Editor, id: ClientId) -> ActionResult {
+    focus_split(editor, id, Direction::Left)
+}
+
+#[action("Window: Focus split on the right")]
+fn focus_split_right(editor: &mut Editor, id: ClientId) -> ActionResult {
+    focus_split(editor, id, Direction::Right)
+}
+
+#[action("Window: Focus split above")]
+fn focus_split_up(editor: &mut Editor, id: ClientId) -> ActionResult {
+    focus_split(editor, id, Direction::Up)
+}
+
+#[action("Window: Focus split below")]
+fn focus_split_down(editor: &mut Editor, id: ClientId) -> ActionResult {
+    focus_split(editor, id, Direction::Down)
+}
+
+fn resize_split(
+    editor: &mut Editor,
+    id: ClientId,
+    dir: SplitDirection,
+    amount: isize,
+) -> ActionResult {
+    if !editor.windows.resize_pane(id, dir, amount) {
+        return ActionResult::Skipped;
+    }
+    editor.windows.update_sizes(id, &editor.buffers);
+    ActionResult::Ok
+}
+
+#[action("Window: Increase split width")]
+fn grow_split_width(editor: &mut Editor, id: ClientId) -> ActionResult {
+    resize_split(editor, id, SplitDirection::Vertical, 2)
+}
+
+#[action("Window: Decrease split width")]
+fn shrink_split_width(editor: &mut Editor, id: ClientId) -> ActionResult {
+    resize_split(editor, id, SplitDirection::Vertical, -2)
+}
+
+#[action("Window: Increase split height")]
+fn grow_split_height(editor: &mut Editor, id: ClientId) -> ActionResult {
+    resize_split(editor, id, SplitDirection::Horizontal, 1)
+}
+
+#[action("Window: Decrease split height")]
+fn shrink_split_height(editor: &mut Editor, id: ClientId) -> ActionResult {
+    resize_split(editor, id, SplitDirection::Horizontal, -1)
+}
+
 #[action("Editor: Status")]
 fn status(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, buf) = win_buf_ref!(editor, id);
@@ -414,6 +558,7 @@ fn remove_indent(editor: &mut Editor, id: ClientId) {
 fn on_insert_mode_leave(editor: &mut Editor, id: ClientId) -> ActionResult {
     let (win, _buf) = win_buf!(editor, id);
     win.delete_indent_on_insert_leave = false;
+    editor.registers.finish_insert();
     ActionResult::Ok
 }
 
@@ -1,6 +1,8 @@
 mod completion;
 mod filetree;
+pub(crate) mod line_numbers;
 mod locations;
+mod panes;
 mod popup;
 mod prompt;
 mod search;
@@ -19,16 +21,19 @@ use sanedit_messages::{
 use sanedit_server::{FromEditor, FromEditorSharedMessage};
 
 use crate::editor::{
-    buffers::Buffer,
+    buffers::{Buffer, Buffers},
     filetree::Filetree,
     lsp::Lsp,
-    windows::{Focus, Window},
+    windows::{ClientLayout, Focus, Window},
     Map,
 };
 
 pub(crate) struct EditorContext<'a> {
     pub(crate) win: &'a Window,
     pub(crate) buf: &'a Buffer,
+    pub(crate) buffers: &'a Buffers,
+    /// Panes of the client
+    pub(crate) layout: Option<&'a ClientLayout>,
     pub(crate) theme: &'a Theme,
     pub(crate) working_dir: &'a Path,
     pub(crate) filetree: &'a Filetree,
@@ -86,6 +91,8 @@ pub(crate) struct DrawState {
     pub(crate) window_buffer: Receiver<Arc<FromEditor>>,
     pub(crate) window_buffer_sender: Sender<Arc<FromEditor>>,
     last_window: Option<Hash>,
+    last_panes: Option<Hash>,
+    last_line_numbers: Option<Hash>,
 }
 
 impl DrawState {
@@ -113,6 +120,8 @@ impl DrawState {
             window_buffer: rx,
             window_buffer_sender: tx,
             last_window: None,
+            last_panes: None,
+            last_line_numbers: None,
             last_prompt_selection: None,
         };
 
@@ -122,6 +131,9 @@ impl DrawState {
         };
 
         let mut redraw = vec![];
+        if let Some(numbers) = line_numbers::draw(&mut ctx) {
+            redraw.push(numbers.into());
+        }
         if let Some(window) = window::draw(&mut ctx) {
             redraw.push(window);
         }
@@ -141,7 +153,14 @@ impl DrawState {
             state: self,
         };
 
+        if let Some(panes) = panes::draw(&mut ctx) {
+            redraw.push(panes.into());
+        }
+
         if mem::replace(&mut ctx.state.redraw_window, true) {
+            if let Some(numbers) = line_numbers::draw(&mut ctx) {
+                redraw.push(numbers.into());
+            }
             if let Some(window) = window::draw(&mut ctx) {
                 redraw.push(window);
             }
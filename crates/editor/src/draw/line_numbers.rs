@@ -0,0 +1,109 @@
+use sanedit_messages::redraw::{
+    line_numbers::{LineNumber, LineNumbers},
+    Component, Redraw,
+};
+
+use crate::editor::{
+    buffers::Buffer,
+    windows::{Cell, LineNumbering, Window},
+};
+
+use super::{DrawContext, EditorContext, Hash};
+
+pub(crate) fn draw(ctx: &mut DrawContext) -> Option<Redraw> {
+    let EditorContext { win, buf, .. } = ctx.editor;
+    let numbers = line_numbers(win, buf);
+    let hash = numbers.as_ref().map(Hash::new);
+    if ctx.state.last_line_numbers == hash {
+        return None;
+    }
+    ctx.state.last_line_numbers = hash;
+
+    match numbers {
+        Some(numbers) => Some(numbers.into()),
+        None => Some(Redraw::LineNumbers(Component::Close)),
+    }
+}
+
+/// Line numbers and signs of the view lines, None if the window has no
+/// gutter
+pub(crate) fn line_numbers(win: &Window, buf: &Buffer) -> Option<LineNumbers> {
+    let width = win.gutter_width();
+    if width == 0 {
+        return None;
+    }
+
+    let view = win.view();
+    let cells = view.cells();
+    let signs = (win.sign_column_width() != 0).then(|| {
+        win.view_signs(buf)
+            .into_iter()
+            .map(|sign| sign.map(|sign| sign.kind))
+            .collect()
+    });
+
+    // Line numbers may be off, or finding them would need scanning the whole
+    // file until the line index is built
+    let mode = win.config.line_numbers;
+    if mode == LineNumbering::Off || (buf.line_count().is_none() && buf.is_file_backed()) {
+        return Some(LineNumbers {
+            width,
+            lines: vec![LineNumber::Empty; cells.len()],
+            signs,
+        });
+    }
+
+    let slice = buf.slice(..);
+    let (mut line, _) = slice.line_at(view.start());
+    let (cursor_line, _) = slice.line_at(win.cursors().primary().pos());
+    let number = |line: u64| {
+        let distance = line.abs_diff(cursor_line);
+        match mode {
+            _ if line == cursor_line && mode == LineNumbering::Relative => LineNumber::Current(0),
+            _ if line == cursor_line => LineNumber::Current(line + 1),
+            LineNumbering::Relative | LineNumbering::Hybrid => LineNumber::Line(distance),
+            _ => LineNumber::Line(line + 1),
+        }
+    };
+
+    // First view line may be a continuation of a wrapped line
+    let mut line_start = win.view_starts_line(buf);
+    let mut lines = Vec::with_capacity(cells.len());
+    let folds = view.folds().closed(buf);
+    let mut pos = view.start();
+
+    for (i, row) in cells.iter().enumerate() {
+        if row.iter().all(Cell::is_empty) {
+            lines.push(LineNumber::Empty);
+            continue;
+        }
+
+        if line_start {
+            lines.push(number(line));
+        } else {
+            lines.push(LineNumber::Wrapped);
+        }
+
+        let end = pos + view.line_len_in_buffer(i);
+        let folded = folds
+            .iter()
+            .any(|fold| pos <= fold.hidden.start && fold.hidden.start < end);
+        line_start = row
+            .iter()
+            .any(|cell| cell.char().is_some_and(|ch| ch.is_eol()));
+
+        // Skip over the lines hidden in a fold
+        if folded {
+            (line, _) = slice.line_at(end);
+        } else if line_start {
+            line += 1;
+        }
+        pos = end;
+    }
+
+    Some(LineNumbers {
+        width,
+        lines,
+        signs,
+    })
+}
@@ -0,0 +1,33 @@
+use sanedit_messages::redraw::{
+    panes::{Pane, Panes},
+    Redraw,
+};
+
+use super::{line_numbers::line_numbers, window::draw_inactive, DrawContext, Hash};
+
+pub(crate) fn draw(ctx: &mut DrawContext) -> Option<Redraw> {
+    let layout = ctx.editor.layout?;
+    let mut panes = Panes::default();
+
+    if layout.is_split() {
+        panes.focused = layout.focused_rect();
+        for (rect, win) in layout.inactive() {
+            let Some(buf) = ctx.editor.buffers.get(win.buffer_id()) else {
+                continue;
+            };
+            panes.others.push(Pane {
+                rect,
+                window: draw_inactive(win, buf, ctx.editor.theme),
+                line_numbers: line_numbers(win, buf),
+            });
+        }
+    }
+
+    let hash = Hash::new(&panes);
+    if ctx.state.last_panes.as_ref() == Some(&hash) {
+        return None;
+    }
+    ctx.state.last_panes = Some(hash);
+
+    Some(panes.into())
+}
@@ -1,4 +1,4 @@
-use std::{mem::take, time::Instant};
+use std::{mem::take, time::SystemTime};
 
 use sanedit_messages::redraw::{
     self,
@@ -38,12 +38,12 @@ pub(crate) fn draw(ctx: &mut DrawContext) -> Option<redraw::Redraw> {
 
 fn draw_impl(ctx: &mut DrawContext) -> redraw::snapshots::Snapshots {
     let last_saved_id = ctx.editor.buf.last_saved_snapshot();
-    let now = Instant::now();
+    let now = SystemTime::now();
     let snaps = ctx.editor.buf.snapshots();
     let points: Vec<SnapshotPoint> = snaps
         .iter()
         .map(|node| {
-            let since = now.duration_since(node.timestamp);
+            let since = now.duration_since(node.timestamp).unwrap_or_default();
             let ts = human_readable_duration(since);
             SnapshotPoint {
                 title: ts.to_string(),
@@ -41,6 +41,9 @@ fn draw_statusline(ctx: &mut DrawContext) -> Statusline {
     if buf.read_only {
         left.push_str("(RO) ");
     }
+    if let Some(codec) = buf.codec() {
+        left.push_str(&format!("({}) ", codec.name()));
+    }
 
     let cursor = win.primary_cursor();
     let cpos = cursor.pos();
@@ -71,7 +74,23 @@ fn draw_statusline(ctx: &mut DrawContext) -> Statusline {
         if win.macro_record.is_recording() {
             result.push_str(" Recording macro | ")
         }
-        let language = buf.language.as_ref().map(|lang| lang.as_str()).unwrap_or("no language");
+        let language = buf
+            .language
+            .as_ref()
+            .map(|lang| lang.as_str())
+            .unwrap_or("no language");
+
+        let view = win.view();
+        if !view.is_wrapped() && !view.is_hex() {
+            match view.horizontal_offset() {
+                0 => result.push_str(" No wrap │"),
+                n => result.push_str(&format!(" No wrap +{n} │")),
+            }
+        }
+
+        if win.view().is_hex() {
+            result.push_str(&format!(" 0x{cpos:x} │ {} │", win.hex.statusline()));
+        }
 
         result.push_str(&format!(
             " {}% │ {} │ {} ",
@@ -13,7 +13,7 @@ use sanedit_utils::{either::Either, sorted_vec::SortedVec};
 
 use crate::editor::{
     buffers::Buffer,
-    windows::{Cell, Cursors, Focus, Mode, View},
+    windows::{Cell, Cursors, Focus, Mode, View, Window as EditorWindow},
 };
 
 use super::{DrawContext, EditorContext, Hash};
@@ -74,6 +74,35 @@ pub(crate) fn draw(ctx: &mut DrawContext) -> Option<FromEditorSharedMessage> {
         return calculate_message(ctx, window_buffer).into();
     }
 
+    let diagnostics = if win.config.highlight_diagnostics {
+        buf.language
+            .as_ref()
+            .and_then(|lang| ctx.editor.language_servers.get_mut(lang))
+            .and_then(|lsp| lsp.diagnostics(buf))
+    } else {
+        None
+    };
+    grid.cursor = draw_view(grid, win, buf, theme, diagnostics, true);
+
+    calculate_message(ctx, window_buffer).into()
+}
+
+/// Draw window of a pane that is not focused
+pub(crate) fn draw_inactive(win: &EditorWindow, buf: &Buffer, theme: &Theme) -> Window {
+    let mut grid = Window::default();
+    draw_view(&mut grid, win, buf, theme, None, false);
+    grid
+}
+
+/// Draw the view of a window, returns the primary cursor
+fn draw_view(
+    grid: &mut Window,
+    win: &EditorWindow,
+    buf: &Buffer,
+    theme: &Theme,
+    diagnostics: Option<&[Diagnostic]>,
+    focused: bool,
+) -> Option<redraw::Cursor> {
     let style = theme.get(ThemeField::Default);
     let vstyle = theme.get(ThemeField::Virtual);
     let view = win.view();
@@ -118,16 +147,8 @@ pub(crate) fn draw(ctx: &mut DrawContext) -> Option<FromEditorSharedMessage> {
         }
     }
 
-    let focus_on_win = win.focus() == Focus::Window;
+    let focus_on_win = focused && win.focus() == Focus::Window;
     let cursors = win.cursors();
-    let diagnostics = if win.config.highlight_diagnostics {
-        buf.language
-            .as_ref()
-            .and_then(|lang| ctx.editor.language_servers.get_mut(lang))
-            .and_then(|lsp| lsp.diagnostics(buf))
-    } else {
-        None
-    };
     // if layer does not discard, we can insert
     let can_insert = win.mode == Mode::Insert;
 
@@ -136,20 +157,20 @@ pub(crate) fn draw(ctx: &mut DrawContext) -> Option<FromEditorSharedMessage> {
         draw_diagnostics(grid, diagnostics, view, theme);
     }
     draw_end_of_buffer(grid, view, theme);
-    draw_trailing_whitespace(grid, view, theme, buf);
+    if !view.is_hex() {
+        draw_trailing_whitespace(grid, view, theme, buf);
+    }
     if let Some(hls) = win.search.highlights() {
         draw_search_highlights(grid, &hls.highlights, view, theme);
     }
     draw_secondary_cursors(grid, cursors, focus_on_win, view, theme);
-    grid.cursor = draw_primary_cursor(
+    draw_primary_cursor(
         grid,
         cursors.primary(),
         can_insert && focus_on_win,
         view,
         theme,
-    );
-
-    calculate_message(ctx, window_buffer).into()
+    )
 }
 
 fn draw_syntax(grid: &mut Window, view: &View, theme: &Theme) {
@@ -10,6 +10,8 @@ pub(crate) mod job_broker;
 pub(crate) mod keymap;
 pub(crate) mod language;
 pub(crate) mod lsp;
+pub(crate) mod registers;
+pub(crate) mod signs;
 pub(crate) mod snippets;
 pub(crate) mod syntax;
 pub(crate) mod themes;
@@ -47,6 +49,7 @@ use sanedit_server::ToEditor;
 use tokio::runtime::Runtime;
 use windows::Mode;
 use windows::MouseClick;
+use windows::PaneId;
 use windows::Zone;
 
 use std::cmp::min;
@@ -56,6 +59,7 @@ use std::env;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
 use tokio::io;
@@ -65,20 +69,24 @@ use anyhow::Result;
 use crate::actions;
 use crate::actions::hooks::run;
 use crate::actions::jobs::ClientConnectionTest;
+use crate::actions::jobs::Decompress;
+use crate::actions::jobs::PersistHistory;
 use crate::actions::jobs::DISCONNECT_DURATION;
 use crate::actions::mouse;
 use crate::actions::window::focus_with_mode;
 use crate::actions::window::goto_other_buffer;
+use crate::actions::ActionResult;
 use crate::common::Choice;
 use crate::draw::DrawState;
 use crate::draw::EditorContext;
 use crate::editor::buffers::Buffer;
+use crate::editor::buffers::PendingHistory;
 use crate::editor::config::Config;
 use crate::editor::hooks::Hook;
 use crate::editor::windows::Focus;
-use sanedit_core::copy_cursors_to_lines;
 use sanedit_core::paste_separate_cursor_lines;
 use sanedit_core::ConfigDirectory;
+use sanedit_core::{recovery_dir, undo_dir};
 
 use self::buffers::BufferId;
 use self::buffers::Buffers;
@@ -88,6 +96,8 @@ use self::config::EditorConfig;
 use self::hooks::Hooks;
 use self::job_broker::JobBroker;
 use self::keymap::Keymaps;
+use self::registers::*;
+use self::signs::{SignContext, SignProviders};
 
 use self::filetree::Filetree;
 use self::lsp::Lsp;
@@ -119,6 +129,8 @@ pub(crate) struct Editor {
     pub job_broker: JobBroker,
     pub hooks: Hooks,
     pub clipboard: Box<dyn Clipboard>,
+    pub registers: Registers,
+    pub signs: SignProviders,
     pub histories: Map<HistoryKind, History>,
     pub keymaps: Keymaps,
     pub language_servers: Map<Language, Lsp>,
@@ -167,6 +179,8 @@ impl Editor {
             working_dir,
             histories: Default::default(),
             clipboard: DefaultClipboard::new_default(),
+            registers: Registers::default(),
+            signs: SignProviders::default(),
             language_servers: Map::default(),
             keymaps: Keymaps::from_config(&config),
             config,
@@ -212,12 +226,8 @@ impl Editor {
         log::info!("Quit client: {id:?}");
         self.send_to_client(id, ClientMessage::Bye.into());
 
-        if let Some(win) = self.windows.remove(id) {
-            let old = win.buffer_id();
-            let is_used = self.windows.iter().any(|(_, win)| win.buffer_id() == old);
-            if !is_used {
-                self.buffers.remove(old);
-            }
+        for win in self.windows.remove(id) {
+            self.remove_unused_buffer(win.buffer_id());
         }
 
         self.draw_states.remove(&id);
@@ -227,6 +237,14 @@ impl Editor {
         self.is_running = !self.clients.is_empty();
     }
 
+    /// Remove a buffer that is no longer shown in any window
+    pub fn remove_unused_buffer(&mut self, bid: BufferId) {
+        if !self.windows.is_buffer_shown(bid) && self.buffers.get(bid).is_some() {
+            self.persist_undo_history(bid);
+            self.buffers.remove(bid);
+        }
+    }
+
     pub fn test_client_connections(&mut self) {
         let mut quit = FxHashSet::default();
         let now = Instant::now();
@@ -253,6 +271,11 @@ impl Editor {
     }
 
     pub fn quit(&mut self) {
+        let bids: Vec<BufferId> = self.buffers.iter().map(|(bid, _)| bid).collect();
+        for bid in bids {
+            self.persist_undo_history(bid);
+        }
+
         let client_ids: Vec<ClientId> = self.clients.keys().copied().collect();
         for id in client_ids {
             log::info!("Client {} quit", id.as_usize());
@@ -293,13 +316,42 @@ impl Editor {
         }
     }
 
+    /// Focus a pane of a client, leaving the buffer of the previously
+    /// focused pane and entering the buffer of the new one.
+    ///
+    /// Returns false if there is no such pane
+    pub fn focus_pane(&mut self, id: ClientId, pane: PaneId) -> bool {
+        if self.windows.focused_pane(id) == Some(pane) {
+            return true;
+        }
+        let Some(old) = self.windows.bid(id) else {
+            return false;
+        };
+        let Some(bid) = self
+            .windows
+            .inactive_mut(id, pane)
+            .map(|win| win.buffer_id())
+        else {
+            return false;
+        };
+
+        if old != bid {
+            run(self, id, Hook::BufLeave(old));
+        }
+        self.windows.focus_pane(id, pane);
+        if old != bid {
+            run(self, id, Hook::BufEnter(bid));
+        }
+        true
+    }
+
     /// Create a new buffer from path
     pub fn create_buffer(&mut self, id: ClientId, path: impl AsRef<Path>) -> Result<BufferId> {
         let file = FileDescription::new(path, &self.config)?;
         if let Some(lang) = &file.language {
             self.load_language(lang, false);
         }
-        let config = file
+        let mut config = file
             .language
             .as_ref()
             .and_then(|lang| self.languages.get(lang))
@@ -308,13 +360,115 @@ impl Editor {
                 // If eol and indent are detected automatically they will override using the hook
                 self.config.buffer.clone()
             });
+        if let Some(encoding) = file.encoding() {
+            config.encoding = encoding;
+        }
 
         let bid = self.buffers.create_new(file, config)?;
+        self.decompress_buffer(id, bid);
+        self.restore_undo_history(bid);
+        self.start_recovery_journal(bid);
         run(self, id, Hook::BufCreated(bid));
 
         Ok(bid)
     }
 
+    /// Decompress the file of a big compressed buffer in the background, if
+    /// it is waiting for it
+    pub fn decompress_buffer(&mut self, id: ClientId, bid: BufferId) {
+        let Some(buf) = self.buffers.get(bid) else {
+            return;
+        };
+        if !buf.is_decompressing() {
+            return;
+        }
+        let (Some(path), Some(codec)) = (buf.path(), buf.codec()) else {
+            return;
+        };
+
+        let job = Decompress::new(id, bid, path.to_path_buf(), codec);
+        self.job_broker.request(job);
+    }
+
+    /// Restore persisted undo history for a buffer if the file has not
+    /// changed since
+    fn restore_undo_history(&mut self, bid: BufferId) {
+        if !self.config.editor.persist_undo {
+            return;
+        }
+
+        let Some(dir) = undo_dir() else {
+            return;
+        };
+        let Some(buf) = self.buffers.get_mut(bid) else {
+            return;
+        };
+
+        if let Err(e) = buf.load_history(&dir) {
+            log::warn!("Failed to restore undo history for {}: {e}", buf.name());
+        }
+    }
+
+    /// Start journaling unsaved changes of a buffer
+    fn start_recovery_journal(&mut self, bid: BufferId) {
+        if !self.config.editor.recovery_journal {
+            return;
+        }
+
+        let Some(dir) = recovery_dir() else {
+            return;
+        };
+        let Some(buf) = self.buffers.get_mut(bid) else {
+            return;
+        };
+
+        let interval = Duration::from_millis(self.config.editor.recovery_journal_interval_ms);
+        buf.start_journal(&dir, interval);
+    }
+
+    /// Persist undo history of a buffer
+    pub fn persist_undo_history(&mut self, bid: BufferId) {
+        let Some(history) = self.capture_undo_history(bid) else {
+            return;
+        };
+
+        if let Err(e) = history.write() {
+            log::warn!("Failed to persist undo history: {e}");
+        }
+    }
+
+    /// Persist undo history of a buffer in the background
+    pub fn persist_undo_history_in_background(&mut self, id: ClientId, bid: BufferId) {
+        let Some(history) = self.capture_undo_history(bid) else {
+            return;
+        };
+
+        let name = format!("persist-history-{bid:?}");
+        self.job_broker
+            .request_slot(id, &name, PersistHistory::new(id, history));
+    }
+
+    fn capture_undo_history(&self, bid: BufferId) -> Option<PendingHistory> {
+        if !self.config.editor.persist_undo {
+            return None;
+        }
+
+        let dir = undo_dir()?;
+        let buf = self.buffers.get(bid)?;
+        if buf.path().is_none() || buf.is_file_backed() {
+            return None;
+        }
+
+        let limits = self.config.editor.history_limits();
+        match buf.capture_history(&dir, &limits) {
+            Ok(history) => history,
+            Err(e) => {
+                log::warn!("Failed to persist undo history for {}: {e}", buf.name());
+                None
+            }
+        }
+    }
+
     pub fn remove_buffer(&mut self, id: ClientId, bid: BufferId) -> Result<()> {
         if self.buffers.get(bid).is_none() {
             bail!("No such buffer {bid:?}");
@@ -325,6 +479,16 @@ impl Editor {
             goto_other_buffer(self, client);
         }
 
+        // Switch the buffer of split panes by focusing them for a moment
+        for (client, pane) in self.windows.inactive_panes_with_buf(bid) {
+            let Some(focused) = self.windows.focused_pane(client) else {
+                continue;
+            };
+            self.windows.focus_pane(client, pane);
+            goto_other_buffer(self, client);
+            self.windows.focus_pane(client, focused);
+        }
+
         run(self, id, Hook::BufDeletedPre(bid));
         self.buffers.remove(bid);
         Ok(())
@@ -425,6 +589,8 @@ impl Editor {
         EditorContext {
             win,
             buf,
+            buffers: &self.buffers,
+            layout: self.windows.layout(id),
             theme,
             working_dir: &self.working_dir,
             filetree: &self.filetree,
@@ -491,8 +657,7 @@ impl Editor {
     }
 
     fn handle_resize(&mut self, id: ClientId, size: Size) {
-        let (win, buf) = win_buf!(self, id);
-        win.resize(size, buf);
+        self.windows.resize(id, size, &self.buffers);
     }
 
     fn handle_command(&mut self, id: ClientId, cmd: Command) {
@@ -527,7 +692,7 @@ impl Editor {
         }
     }
 
-    fn handle_mouse_event(&mut self, id: ClientId, event: MouseEvent) {
+    fn handle_mouse_event(&mut self, id: ClientId, mut event: MouseEvent) {
         match event.element {
             Element::Snapshots => {
                 if let MouseEventKind::ButtonDown(MouseButton::Left) = event.kind {
@@ -621,7 +786,23 @@ impl Editor {
                 _ => {}
             },
             Element::Window => {
+                let Some((pane, rect)) = self.windows.pane_at(id, &event.point) else {
+                    return;
+                };
+                if self.windows.focused_pane(id) != Some(pane) {
+                    // Dragging a selection stays in the focused pane
+                    if matches!(event.kind, MouseEventKind::Drag(_)) {
+                        return;
+                    }
+                    self.focus_pane(id, pane);
+                }
+
                 let (win, _buf) = win_buf!(self, id);
+                // Clicks on a sign jump to it, and elsewhere on the gutter go
+                // to the start of the line
+                event.point = event.point - rect.position();
+                let on_sign = event.point.x < win.sign_column_width();
+                event.point.x = event.point.x.saturating_sub(win.gutter_width());
                 if win.focus != Focus::Window {
                     focus_with_mode(self, id, Focus::Window, Mode::Normal);
                 }
@@ -630,7 +811,11 @@ impl Editor {
                     MouseEventKind::ScrollDown => win.scroll_down_n(buf, 3),
                     MouseEventKind::ScrollUp => win.scroll_up_n(buf, 3),
                     MouseEventKind::ButtonDown(MouseButton::Left) => {
-                        mouse::on_button_down_left_click(self, id, event);
+                        let jumped = on_sign
+                            && mouse::goto_sign(self, id, event.point.y) == ActionResult::Ok;
+                        if !jumped {
+                            mouse::on_button_down_left_click(self, id, event);
+                        }
                     }
                     MouseEventKind::Drag(MouseButton::Left) => {
                         mouse::on_drag(self, id, event.point);
@@ -664,7 +849,7 @@ impl Editor {
         }
 
         if let Some(bid) = self.windows.bid(id) {
-            for cid in self.windows.find_clients_with_buf(bid) {
+            for cid in self.windows.find_clients_showing_buf(bid) {
                 self.redraw_client(cid);
                 drawn.push(cid);
             }
@@ -682,21 +867,38 @@ impl Editor {
             .get_mut(&id)
             .expect("Client window is closed");
 
+        let signs = &self.signs;
+        let language_servers = &mut self.language_servers;
+        let mut collect_signs = |buf: &Buffer| {
+            let diagnostics = buf
+                .language
+                .as_ref()
+                .and_then(|lang| language_servers.get_mut(lang))
+                .and_then(|lsp| lsp.diagnostics(buf));
+            signs.collect(&SignContext { buf, diagnostics })
+        };
+
+        self.windows
+            .redraw_inactive(id, &self.buffers, &mut collect_signs);
         let win = self.windows.get_mut(id).expect("No window for {id}");
         let buf = self
             .buffers
             .get(win.buffer_id())
             .expect("No window for {id}");
+        win.set_signs(collect_signs(buf));
+        win.redraw_view(buf);
+
+        let win = self.windows.get(id).expect("No window for {id}");
         let theme = {
             let theme_name = &win.config.theme;
             self.themes.get(theme_name).expect("Theme not present")
         };
 
-        win.redraw_view(buf);
-
         let ctx = EditorContext {
             win,
             buf,
+            buffers: &self.buffers,
+            layout: self.windows.layout(id),
             theme,
             working_dir: &self.working_dir,
             filetree: &self.filetree,
@@ -737,6 +939,30 @@ impl Editor {
             return;
         }
 
+        // Count typed before an action. Digits that are bound to an action
+        // start a count only if one is already being typed, and zero never
+        // starts one.
+        if win.focus == Focus::Window && matches!(win.mode, Mode::Normal | Mode::Select) {
+            let digit = match win.keys() {
+                [key] if !key.alt_pressed() && !key.control_pressed() => match key.key() {
+                    Char(ch) => ch.to_digit(10),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            let counting = win.has_count();
+            let starts_count = |digit: &u32| {
+                *digit != 0 && matches!(self.mapped_action(id), KeymapResult::NotFound)
+            };
+            if let Some(digit) = digit.filter(|d| counting || starts_count(d)) {
+                let win = self.windows.get_mut(id).expect("No window found");
+                win.clear_keys();
+                win.push_count_digit(digit);
+                return;
+            }
+        }
+
         // Handle key bindings
         let events;
         match self.mapped_action(id) {
@@ -745,6 +971,9 @@ impl Editor {
                 // We may have removed the window
                 if let Some(win) = self.windows.get_mut(id) {
                     win.clear_keys();
+                    // Count and register only apply to the next action
+                    win.take_count();
+                    win.take_register();
                 }
                 return;
             }
@@ -757,6 +986,8 @@ impl Editor {
             KeymapResult::NotFound => {
                 let (win, _buf) = win_buf!(self, id);
                 events = win.clear_keys();
+                win.take_count();
+                win.take_register();
 
                 if win.focus == Focus::Window && win.mode != Mode::Insert {
                     return;
@@ -867,20 +1098,101 @@ impl Editor {
         run(self, id, Hook::Reload);
     }
 
+    /// Paste from the register selected for the window or from the clipboard
     pub fn paste_from_clipboard(&mut self, id: ClientId) {
-        let Ok(text) = self.clipboard.paste() else {
+        let (win, _buf) = win_buf!(self, id);
+        let name = win.take_register().unwrap_or(CLIPBOARD_REGISTER);
+        let Some(yank) = self.register_yank(id, name) else {
+            let (win, _buf) = win_buf!(self, id);
+            win.warn_msg(&format!("Register {name} is empty"));
             return;
         };
 
-        let (win, _buf) = win_buf_ref!(self, id);
-        let lines = paste_separate_cursor_lines(text.as_str());
-        let single_with_eol = lines.len() == 1 && lines[0].1 && win.cursors.len() == 1;
-        let multicursor_match = win.cursors.len() == lines.len();
+        self.paste_yank(id, yank);
+    }
 
-        if single_with_eol || !multicursor_match {
-            self.paste_on_line_below(id, lines);
-        } else {
-            self.paste_inline(id, lines);
+    /// Contents of a register
+    pub fn register_yank(&mut self, id: ClientId, name: char) -> Option<Yank> {
+        let (win, buf) = win_buf_ref!(self, id);
+        let yank = match name {
+            CLIPBOARD_REGISTER => {
+                let text = self.clipboard.paste().ok()?;
+                self.registers.clipboard_yank(text)
+            }
+            SEARCH_REGISTER => Yank::from_text(win.search.current.pattern.clone()),
+            INSERT_REGISTER => Yank::from_text(self.registers.last_insert().to_string()),
+            FILE_NAME_REGISTER => {
+                let path = buf.path()?;
+                let path = path.strip_prefix(&self.working_dir).unwrap_or(path);
+                Yank::from_text(path.to_string_lossy().into())
+            }
+            _ => self.registers.get(name)?.clone(),
+        };
+
+        if yank.is_empty() {
+            return None;
+        }
+
+        Some(yank)
+    }
+
+    /// Paste a deletion from the kill ring. Pasting again right after
+    /// replaces the pasted text with an older deletion.
+    pub fn paste_from_kill_ring(&mut self, id: ClientId) {
+        let (win, buf) = win_buf!(self, id);
+        let state = (buf.id, buf.total_changes_made());
+        let n = match self.registers.kill_paste {
+            Some((bid, changes, n)) if (bid, changes) == state => {
+                if win.undo(buf).is_err() {
+                    return;
+                }
+                run(self, id, Hook::BufChanged(state.0));
+                n + 1
+            }
+            _ => 0,
+        };
+
+        let Some(yank) = self.registers.kill(n).cloned() else {
+            let (win, _buf) = win_buf!(self, id);
+            win.warn_msg("Kill ring is empty");
+            return;
+        };
+        self.paste_yank(id, yank);
+
+        let (_win, buf) = win_buf_ref!(self, id);
+        self.registers.kill_paste = Some((buf.id, buf.total_changes_made(), n));
+    }
+
+    /// Paste copied text, per cursor contents are pasted to each cursor if
+    /// the cursor counts match
+    pub fn paste_yank(&mut self, id: ClientId, yank: Yank) {
+        let (win, buf) = win_buf_ref!(self, id);
+        let cursors = win.cursors.len();
+        let lines = |texts: Vec<String>, eol: bool| {
+            texts
+                .into_iter()
+                .map(|text| (text, eol))
+                .collect::<Vec<_>>()
+        };
+
+        match yank.kind {
+            YankKind::Block => self.paste_block(id, &yank.texts),
+            _ if yank.texts.len() > 1 && yank.texts.len() == cursors => {
+                self.paste_inline(id, lines(yank.texts, false))
+            }
+            YankKind::Line => self.paste_on_line_below(id, lines(yank.texts, true)),
+            YankKind::Selection => {
+                let text = yank.text(buf.config.eol);
+                let lines = paste_separate_cursor_lines(text.as_str());
+                let single_with_eol = lines.len() == 1 && lines[0].1 && cursors == 1;
+                let multicursor_match = cursors == lines.len();
+
+                if single_with_eol || !multicursor_match {
+                    self.paste_on_line_below(id, lines);
+                } else {
+                    self.paste_inline(id, lines);
+                }
+            }
         }
     }
 
@@ -904,6 +1216,16 @@ impl Editor {
         }
     }
 
+    fn paste_block(&mut self, id: ClientId, rows: &[String]) {
+        let (win, buf) = win_buf!(self, id);
+        let bid = buf.id;
+
+        if win.paste_block(buf, rows).is_ok() {
+            win.view_to_around_cursor_zone(buf, Zone::Middle);
+            run(self, id, Hook::BufChanged(bid));
+        }
+    }
+
     // Paste to current cursors
     fn paste_inline(&mut self, id: ClientId, lines: Vec<(String, bool)>) {
         let (win, buf) = win_buf!(self, id);
@@ -934,6 +1256,44 @@ impl Editor {
         }
     }
 
+    /// Store copied text to the register selected for the window or to the
+    /// clipboard
+    fn store_yank(&mut self, id: ClientId, yank: Yank) {
+        let (win, buf) = win_buf!(self, id);
+        let eol = buf.config.eol;
+        match win.take_register() {
+            Some(name) if is_named_register(name) => {
+                self.registers.set(name, yank);
+            }
+            Some(name) if name != CLIPBOARD_REGISTER => {
+                win.warn_msg(&format!("Register {name} is read only"));
+            }
+            _ => {
+                let text = yank.text(eol);
+                self.clipboard.copy(&text);
+                self.registers.set_clipboard(text, yank);
+            }
+        }
+    }
+
+    /// Text of each cursor selection or the rows of a block selection
+    fn selections_yank(&self, id: ClientId) -> Yank {
+        let (win, buf) = win_buf_ref!(self, id);
+        if let Some(rows) = win.block_text(buf) {
+            return Yank::new(rows, YankKind::Block);
+        }
+
+        let mut texts = vec![];
+        for cursor in win.cursors.cursors() {
+            if let Some(sel) = cursor.selection() {
+                let text = String::from(&buf.slice(sel));
+                texts.push(text);
+            }
+        }
+
+        Yank::new(texts, YankKind::Selection)
+    }
+
     pub fn copy_line_to_clipboard(&mut self, id: ClientId) {
         let (win, buf) = win_buf!(self, id);
         let mut lines = vec![];
@@ -943,12 +1303,7 @@ impl Editor {
             lines.push(text);
         }
 
-        if lines.len() == 1 {
-            lines[0].push_str(buf.config.eol.as_str());
-        }
-
-        let line = copy_cursors_to_lines(lines, buf.config.eol);
-        self.clipboard.copy(&line);
+        self.store_yank(id, Yank::new(lines, YankKind::Line));
     }
 
     pub fn copy_to_eol_to_clipboard(&mut self, id: ClientId) {
@@ -960,22 +1315,36 @@ impl Editor {
             lines.push(text);
         }
 
-        let line = copy_cursors_to_lines(lines, buf.config.eol);
-        self.clipboard.copy(&line);
+        self.store_yank(id, Yank::new(lines, YankKind::Selection));
     }
 
     pub fn copy_to_clipboard(&mut self, id: ClientId) {
+        let yank = self.selections_yank(id);
+        self.store_yank(id, yank);
+    }
+
+    /// Push cursor selections to the kill ring before they are deleted
+    pub fn push_kill(&mut self, id: ClientId) {
+        let yank = self.selections_yank(id);
+        self.registers.push_kill(yank);
+    }
+
+    /// Push the text removed by `Window::remove_line_after_cursor` to the
+    /// kill ring before it is deleted
+    pub fn push_kill_line_after_cursor(&mut self, id: ClientId) {
         let (win, buf) = win_buf_ref!(self, id);
-        let mut lines = vec![];
-        for cursor in win.cursors.cursors() {
-            if let Some(sel) = cursor.selection() {
-                let text = String::from(&buf.slice(sel));
-                lines.push(text);
-            }
+        if win.cursors.has_selections() {
+            self.push_kill(id);
+            return;
         }
 
-        let line = copy_cursors_to_lines(lines, buf.config.eol);
-        self.clipboard.copy(&line);
+        let texts = win
+            .line_after_cursors(buf)
+            .into_iter()
+            .map(|range| String::from(&buf.slice(range)))
+            .collect();
+        self.registers
+            .push_kill(Yank::new(texts, YankKind::Selection));
     }
 
     pub fn prompt_history_next(&mut self, id: ClientId) {
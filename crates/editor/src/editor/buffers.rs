@@ -5,7 +5,9 @@ use std::path::Path;
 use sanedit_utils::idmap::IdMap;
 
 pub(crate) use self::buffer::{
-    Buffer, BufferConfig, BufferError, BufferId, SavedWindowState, SnapshotId,
+    decompress_to_tmp, detect_codec, is_binary, open_decoder, Buffer, BufferConfig, BufferError,
+    BufferId, Codec, Encoding, HistoryLimits, PendingHistory, SavedWindowState, SnapshotId,
+    COMMON_ENCODINGS, DETECT_SAMPLE_SIZE,
 };
 
 use super::file_description::FileDescription;
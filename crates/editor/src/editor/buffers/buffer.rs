@@ -1,42 +1,58 @@
 mod change;
+mod codec;
 mod config;
+mod encoding;
+mod history;
+mod journal;
 mod snapshots;
 
 use std::{
     borrow::Cow,
     ffi::OsString,
     fs::{self, File},
-    io::{self, Write},
-    ops::RangeBounds,
+    io::{self, Read as _, Write},
+    ops::{Range, RangeBounds},
     os::unix::fs::MetadataExt as _,
     path::{Path, PathBuf},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
 use anyhow::ensure;
 use anyhow::Result;
-use sanedit_buffer::{Mark, MarkResult, PieceTree, PieceTreeSlice};
+use sanedit_buffer::{
+    Compactor, Gravity, Mark, MarkResult, PieceTree, PieceTreeSlice, RangeMark, RangeMarkResult,
+};
 use sanedit_core::Edit;
 use sanedit_core::{tmp_file, Changes, Language};
 use sanedit_utils::key_type;
 use thiserror::Error;
 
-use crate::editor::file_description::FileDescription;
+use crate::editor::{file_description::FileDescription, signs::VcsChanges};
 
-use self::snapshots::Snapshots;
+use self::{journal::Journal, snapshots::Snapshots};
 
 pub(crate) use change::ChangeResult;
+pub(crate) use codec::{detect_codec, open_decoder, Codec};
 pub(crate) use config::BufferConfig;
+pub(crate) use encoding::{
+    is_binary, Encoding, EncodingError, COMMON_ENCODINGS, DETECT_SAMPLE_SIZE,
+};
+pub(crate) use history::{HistoryLimits, PendingHistory};
 pub(crate) use snapshots::{SavedWindowState, SnapshotId};
 
 key_type!(pub(crate) BufferId);
 
+/// Piece trees with fewer pieces are not compacted
+const COMPACT_MIN_PIECES: usize = 1024;
+
 #[derive(Debug)]
 pub(crate) struct Buffer {
     pub(crate) id: BufferId,
     pub(crate) language: Option<Language>,
     pub(crate) config: BufferConfig,
     pub(crate) read_only: bool,
+    /// Whether the buffer contents look like binary data
+    pub(crate) is_binary: bool,
     /// Whether the buffer should be removed when it is no longer shown
     /// Useful for example status buffers that should always be destroyed once left
     pub(crate) remove_on_exit: bool,
@@ -55,6 +71,26 @@ pub(crate) struct Buffer {
 
     /// Path used for saving the file.
     path: Option<PathBuf>,
+
+    /// Recovery journal of unsaved changes
+    journal: Option<Journal>,
+
+    /// Incremental compaction of the piece tree
+    compactor: Compactor,
+    /// Number of pieces after the previous compaction
+    compacted_pieces: usize,
+
+    /// Compression codec of the file, used to compress the buffer when it is
+    /// saved
+    codec: Option<&'static dyn Codec>,
+    /// Whether a big compressed file is still being decompressed in the
+    /// background. The buffer is empty and read only until it is done.
+    decompressing: bool,
+
+    /// Bookmarked positions
+    pub(crate) bookmarks: Vec<Mark>,
+    /// Lines changed since the last commit
+    pub(crate) vcs: VcsChanges,
 }
 
 impl Buffer {
@@ -64,6 +100,7 @@ impl Buffer {
             id: BufferId::default(),
             language: None,
             read_only: false,
+            is_binary: false,
             pt,
             is_modified: false,
             remove_on_exit: false,
@@ -74,25 +111,45 @@ impl Buffer {
             last_saved_snapshot: 0,
             last_saved_modified: None,
             total_changes_made: 0,
+            journal: None,
+            compactor: Compactor::new(true),
+            compacted_pieces: 0,
+            codec: None,
+            decompressing: false,
+            bookmarks: vec![],
+            vcs: VcsChanges::default(),
         }
     }
 
     pub fn from_file(file: FileDescription, options: BufferConfig) -> Result<Buffer> {
-        if file.is_big() {
-            Self::file_backed(file, options)
+        let is_binary = file.is_binary();
+        let codec = file.codec();
+        let mut buf = if file.is_big() {
+            Self::file_backed(file, options)?
         } else {
-            Self::in_memory(file, options)
-        }
+            Self::in_memory(file, options)?
+        };
+        buf.is_binary = is_binary;
+        buf.codec = codec;
+        Ok(buf)
     }
 
-    fn file_backed(file: FileDescription, options: BufferConfig) -> Result<Buffer> {
+    fn file_backed(file: FileDescription, mut options: BufferConfig) -> Result<Buffer> {
         log::debug!("Creating file backed buffer: {file:?}");
         let path = file.path();
-        let pt = PieceTree::from_path(path)?;
+        if !options.encoding.is_utf8() {
+            log::warn!(
+                "Cannot transcode big file {path:?} from {}, opening as is",
+                options.encoding
+            );
+            options.encoding = Encoding::default();
+        }
+        let (pt, decompressing) = read_file_backed(path, file.codec())?;
         let modified = path.metadata()?.modified()?;
         Ok(Buffer {
             id: BufferId::default(),
-            read_only: file.read_only(),
+            read_only: file.read_only() || decompressing,
+            is_binary: false,
             pt,
             language: file.language().cloned(),
             is_modified: false,
@@ -104,6 +161,13 @@ impl Buffer {
             last_saved_snapshot: 0,
             last_saved_modified: Some(modified),
             total_changes_made: 0,
+            journal: None,
+            compactor: Compactor::new(true),
+            compacted_pieces: 0,
+            codec: None,
+            decompressing,
+            bookmarks: vec![],
+            vcs: VcsChanges::default(),
         })
     }
 
@@ -113,11 +177,15 @@ impl Buffer {
             file.path().exists()
         );
         let path = file.path();
+        let mut encoding = options.encoding;
         let mut buf = if !path.exists() {
             Self::new()
         } else {
-            let ffile = fs::File::open(path)?;
-            Self::from_reader(ffile)?
+            let (pt, enc) = read_transcoded(path, encoding, file.codec())?;
+            encoding = enc;
+            let mut buf = Self::new();
+            buf.pt = pt;
+            buf
         };
 
         let modified = {
@@ -136,6 +204,7 @@ impl Buffer {
         buf.path = Some(path.into());
         buf.read_only = file.read_only();
         buf.config = options;
+        buf.config.encoding = encoding;
         Ok(buf)
     }
 
@@ -146,6 +215,7 @@ impl Buffer {
             pt,
             language: None,
             read_only: false,
+            is_binary: false,
             is_modified: false,
             remove_on_exit: false,
             snapshots: Snapshots::new(),
@@ -155,6 +225,13 @@ impl Buffer {
             last_saved_snapshot: 0,
             last_saved_modified: None,
             total_changes_made: 0,
+            journal: None,
+            compactor: Compactor::new(true),
+            compacted_pieces: 0,
+            codec: None,
+            decompressing: false,
+            bookmarks: vec![],
+            vcs: VcsChanges::default(),
         })
     }
 
@@ -196,12 +273,12 @@ impl Buffer {
     fn needs_undo_point(&mut self, change: &Changes) -> bool {
         let last = self.last_edit.as_ref();
         change.allows_undo_point_creation()
-            && (self.is_modified || self.total_changes_made == 0)
+            && (self.is_modified || self.snapshots.current().is_none())
             && change.needs_undo_point(last.as_ref().map(|edit| &edit.changes))
     }
 
     pub fn on_undopoint(&self) -> bool {
-        let on_save = !self.is_modified && self.total_changes_made != 0;
+        let on_save = !self.is_modified && self.snapshots.current().is_some();
         let on_previous_point = self
             .last_edit
             .as_ref()
@@ -250,6 +327,7 @@ impl Buffer {
                     changes: changes.clone(),
                 });
                 self.total_changes_made += 1;
+                self.write_journal();
             }
             Err(e) => {
                 self.pt.restore(rollback);
@@ -311,9 +389,14 @@ impl Buffer {
     }
 
     pub fn set_path<P: AsRef<Path>>(&mut self, path: P) {
-        self.path = Some(path.as_ref().to_owned());
+        let path = path.as_ref();
+        self.path = Some(path.to_owned());
         self.is_modified = true;
         self.last_saved_modified = None;
+
+        if let Some(journal) = self.journal.as_mut() {
+            journal.set_path(path, PieceTree::new().slice(..));
+        }
     }
 
     pub fn path(&self) -> Option<&Path> {
@@ -328,6 +411,17 @@ impl Buffer {
         self.is_modified
     }
 
+    /// Number of lines in the buffer, if the line index has been built
+    pub fn line_count(&self) -> Option<u64> {
+        self.pt.line_count()
+    }
+
+    /// Complete the line index once the buffer lines have been indexed in
+    /// the background
+    pub fn update_line_index(&mut self) -> bool {
+        self.pt.update_line_index()
+    }
+
     /// Save the buffer by copying it to a temporary file and renaming it to the
     /// buffers path
     pub fn save_rename(&mut self) -> Result<Saved> {
@@ -343,7 +437,7 @@ impl Buffer {
         }
 
         let copy_view = self.slice(..);
-        let copy = Self::save_copy(&copy_view)?;
+        let copy = Self::save_copy(&copy_view, self.config.encoding, self.codec)?;
         let saved = self.save_rename_copy(copy_view, &copy);
         let _ = fs::remove_file(&copy);
         saved
@@ -365,12 +459,13 @@ impl Buffer {
         };
 
         // Rename backing file if it is the same as our path
-        if self.pt.is_file_backed() {
-            let backing = self.pt.backing_file().unwrap();
-            if backing == path {
-                let (path, _file) = tmp_file().ok_or(BufferError::CannotCreateTmpFile)?;
-                self.pt.rename_backing_file(path)?;
-            }
+        if self
+            .pt
+            .backing_file()
+            .is_some_and(|backing| backing == path)
+        {
+            let (path, _file) = tmp_file().ok_or(BufferError::CannotCreateTmpFile)?;
+            self.pt.rename_backing_file(path)?;
         }
 
         if let Err(e) = fs::rename(copy, path) {
@@ -386,6 +481,9 @@ impl Buffer {
         self.last_saved_modified = Some(modified);
 
         self.is_modified = false;
+        if let Some(journal) = self.journal.as_mut() {
+            journal.set_saved(copy_view.clone());
+        }
         let snap = self.snapshots.insert(copy_view);
         self.last_saved_snapshot = snap;
         Ok(Saved { snapshot: snap })
@@ -395,7 +493,39 @@ impl Buffer {
         self.last_saved_snapshot
     }
 
-    fn save_copy(buf: &PieceTreeSlice) -> Result<PathBuf> {
+    fn save_copy(
+        buf: &PieceTreeSlice,
+        encoding: Encoding,
+        codec: Option<&dyn Codec>,
+    ) -> Result<PathBuf> {
+        let copy = Self::save_copy_uncompressed(buf, encoding)?;
+        let Some(codec) = codec else {
+            return Ok(copy);
+        };
+
+        let compressed = tmp_file()
+            .ok_or(BufferError::CannotCreateTmpFile.into())
+            .and_then(|(path, mut file)| {
+                if let Err(e) = codec::compress_file(&copy, &mut file, codec) {
+                    let _ = fs::remove_file(&path);
+                    return Err(e.into());
+                }
+                Ok(path)
+            });
+        let _ = fs::remove_file(&copy);
+        compressed
+    }
+
+    fn save_copy_uncompressed(buf: &PieceTreeSlice, encoding: Encoding) -> Result<PathBuf> {
+        if !encoding.is_utf8() {
+            let text = String::from_utf8(Vec::from(buf)).map_err(|_| EncodingError::InvalidUtf8)?;
+            let bytes = encoding.encode(&text)?;
+            let (path, mut file) = tmp_file().ok_or(BufferError::CannotCreateTmpFile)?;
+            file.write_all(&bytes)?;
+            file.flush()?;
+            return Ok(path);
+        }
+
         let (path, mut file) = tmp_file().ok_or(BufferError::CannotCreateTmpFile)?;
 
         let mut chunks = buf.chunks();
@@ -418,6 +548,20 @@ impl Buffer {
         self.pt.mark_to_pos(mark)
     }
 
+    /// Mark a range that grows and shrinks with the edits made to it
+    pub fn mark_range(&self, range: Range<u64>, start: Gravity, end: Gravity) -> RangeMark {
+        self.pt.mark_range(range, start, end)
+    }
+
+    pub fn range_mark_to_range(&self, mark: &RangeMark) -> RangeMarkResult {
+        self.pt.range_mark_to_range(mark)
+    }
+
+    /// Resolve many range marks at once
+    pub fn range_marks_to_ranges(&self, marks: &[RangeMark]) -> Vec<RangeMarkResult> {
+        self.pt.range_marks_to_ranges(marks)
+    }
+
     pub fn set_unsaved(&mut self) {
         self.is_modified = true;
     }
@@ -429,18 +573,60 @@ impl Buffer {
         self.reload_from_disk_impl(&path).is_ok()
     }
 
+    /// Compression codec of the file, if it is compressed
+    pub fn codec(&self) -> Option<&'static dyn Codec> {
+        self.codec
+    }
+
     pub fn is_file_backed(&self) -> bool {
-        self.pt.is_file_backed()
+        self.pt.is_file_backed() || self.decompressing
+    }
+
+    /// Whether the buffer is waiting for its file to be decompressed, see
+    /// [`Buffer::finish_decompress`]
+    pub fn is_decompressing(&self) -> bool {
+        self.decompressing
+    }
+
+    /// Use the decompressed contents of a big compressed file as the backing
+    /// file of the buffer. The file at `tmp` is owned by the buffer
+    /// afterwards.
+    pub fn finish_decompress(&mut self, tmp: &Path) -> Result<()> {
+        ensure!(self.decompressing, BufferError::NotDecompressing);
+
+        // SAFETY: the temporary file is private to this buffer
+        self.pt = unsafe { PieceTree::from_path_mmap(tmp)? };
+        self.decompressing = false;
+        self.read_only = self
+            .path()
+            .and_then(|path| path.metadata().ok())
+            .is_some_and(|metadata| metadata.permissions().readonly());
+        self.snapshots = Snapshots::new();
+        self.last_edit = None;
+        Ok(())
     }
 
-    fn reload_from_disk_impl(&mut self, path: &Path) -> io::Result<()> {
-        let file_backed = self.pt.is_file_backed();
+    fn reload_from_disk_impl(&mut self, path: &Path) -> Result<()> {
+        let file_backed = self.is_file_backed();
+        let bookmarked_lines: Vec<u64> = {
+            let slice = self.pt.slice(..);
+            self.bookmarks
+                .iter()
+                .map(|mark| slice.line_at(self.pt.mark_to_pos(mark).pos()).0)
+                .collect()
+        };
+        let codec = codec::detect_file_codec(path)?;
         self.pt = if file_backed {
-            PieceTree::from_path(path)?
+            let (pt, decompressing) = read_file_backed(path, codec)?;
+            self.decompressing = decompressing;
+            self.read_only |= decompressing;
+            pt
         } else {
-            let file = File::open(path)?;
-            PieceTree::from_reader(file)?
+            let (pt, encoding) = read_transcoded(path, self.config.encoding, codec)?;
+            self.config.encoding = encoding;
+            pt
         };
+        self.codec = codec;
 
         let modified = path.metadata()?.modified()?;
         self.last_saved_modified = Some(modified);
@@ -449,17 +635,149 @@ impl Buffer {
         self.last_edit = None;
         self.last_saved_snapshot = 0;
         self.total_changes_made = 0;
+        // Marks do not point to the new contents, bookmarks stay on the same
+        // lines if they still exist
+        self.vcs.set_lines(vec![]);
+        self.bookmarks = bookmarked_lines
+            .into_iter()
+            .filter_map(|line| self.pt.pos_at_line(line))
+            .map(|pos| self.pt.mark(pos))
+            .collect();
+
+        if let Some(journal) = self.journal.as_mut() {
+            journal.set_saved(self.pt.slice(..));
+        }
 
         Ok(())
     }
 
+    /// Capture the undo history to be persisted to a directory using
+    /// `PendingHistory::write`, so it can be restored when the file is opened
+    /// again. Returns none if there is nothing to persist.
+    pub fn capture_history(
+        &self,
+        dir: &Path,
+        limits: &HistoryLimits,
+    ) -> Result<Option<PendingHistory>> {
+        history::capture(self, dir, limits)
+    }
+
+    /// Restore the undo history persisted using `capture_history`, if the file
+    /// has not changed since. Returns whether the history was restored.
+    pub fn load_history(&mut self, dir: &Path) -> Result<bool> {
+        history::load(self, dir)
+    }
+
+    /// Start journaling unsaved changes to a directory, so they can be
+    /// recovered if the editor dies before the buffer is saved
+    pub fn start_journal(&mut self, dir: &Path, interval: Duration) {
+        let Some(path) = self.path.as_ref() else {
+            return;
+        };
+        if self.is_file_backed() {
+            return;
+        }
+
+        let saved = if self.last_saved_modified.is_some() {
+            self.pt.slice(..)
+        } else {
+            PieceTree::new().slice(..)
+        };
+        self.journal = Some(Journal::new(dir, path, saved, interval));
+    }
+
+    /// Whether a journal written in a previous session exists for this
+    /// buffer
+    pub fn has_recovery_journal(&self) -> bool {
+        self.journal
+            .as_ref()
+            .map(Journal::is_recoverable)
+            .unwrap_or(false)
+    }
+
+    /// Read the unsaved contents from a journal written in a previous
+    /// session. Returns none if there is nothing to recover.
+    pub fn recover_journal(&self) -> Result<Option<Vec<u8>>> {
+        journal::recover(self)
+    }
+
+    /// Remove the recovery journal
+    pub fn discard_journal(&mut self) {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.remove();
+        }
+    }
+
+    fn write_journal(&mut self) {
+        let Some(journal) = self.journal.as_mut() else {
+            return;
+        };
+
+        if !self.is_modified {
+            if journal.is_started() {
+                journal.remove();
+            }
+            return;
+        }
+
+        let Some(path) = self.path.as_ref() else {
+            return;
+        };
+        if let Err(e) = journal.write(&self.pt, path) {
+            log::warn!("Failed to write recovery journal for {path:?}: {e}");
+        }
+    }
+
+    /// Whether the piece tree has fragmented enough since the previous
+    /// compaction to be compacted again
+    pub fn needs_compaction(&self) -> bool {
+        let pieces = self.pt.piece_count();
+        pieces > COMPACT_MIN_PIECES && pieces > 2 * self.compacted_pieces
+    }
+
+    /// Compact at most `budget` pieces of the piece tree, continuing from
+    /// where the previous call ended. Returns true once the whole tree has
+    /// been compacted.
+    pub fn compact(&mut self, budget: usize) -> bool {
+        let done = self.compactor.step(&mut self.pt, budget);
+        if done {
+            self.compacted_pieces = self.pt.piece_count();
+        }
+        done
+    }
+
+    /// Time left until the changes not yet written to the recovery journal
+    /// should be flushed, None if there is nothing to flush
+    pub fn journal_flush_in(&self) -> Option<Duration> {
+        self.journal.as_ref()?.flush_in()
+    }
+
+    /// Write the changes not yet written to the recovery journal
+    pub fn flush_journal(&mut self) {
+        let Some(journal) = self.journal.as_mut() else {
+            return;
+        };
+        if !self.is_modified {
+            return;
+        }
+        let Some(path) = self.path.as_ref() else {
+            return;
+        };
+        if let Err(e) = journal.flush(&self.pt, path) {
+            log::warn!("Failed to write recovery journal for {path:?}: {e}");
+        }
+    }
+
     /// Rename or move the buffer to a different location
     pub fn rename(&mut self, path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        if self.pt.is_file_backed() {
+        // Backing file of a compressed file contains the decompressed
+        // contents, it cannot be used as the file. Mapped private clones have
+        // no backing file and are saved to the new path instead.
+        if self.pt.backing_file().is_some() && self.codec.is_none() {
             self.pt.rename_backing_file(path)?;
         } else {
             self.set_path(path);
@@ -472,6 +790,13 @@ impl Buffer {
 
 impl Drop for Buffer {
     fn drop(&mut self) {
+        // Journal is only needed if the editor does not exit cleanly
+        if let Some(journal) = self.journal.as_mut() {
+            if journal.is_started() {
+                journal.remove();
+            }
+        }
+
         if self.pt.is_file_backed() {
             let path = self.path();
             let bfpath = self.pt.backing_file();
@@ -491,6 +816,63 @@ impl Default for Buffer {
     }
 }
 
+/// Read a file to a piece tree decompressing and transcoding it to UTF-8 if
+/// needed.
+///
+/// Returns the encoding that was used, files that cannot be transcoded
+/// losslessly are read as is.
+fn read_transcoded(
+    path: &Path,
+    encoding: Encoding,
+    codec: Option<&dyn Codec>,
+) -> io::Result<(PieceTree, Encoding)> {
+    let mut reader: Box<dyn io::Read> = match codec {
+        Some(codec) => open_decoder(path, codec)?,
+        None => Box::new(File::open(path)?),
+    };
+    if encoding.is_utf8() {
+        let pt = PieceTree::from_reader(reader)?;
+        return Ok((pt, encoding));
+    }
+
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    match encoding.decode(&bytes) {
+        Some((text, encoding)) => {
+            let pt = PieceTree::from_reader(text.as_bytes())?;
+            Ok((pt, encoding))
+        }
+        None => {
+            log::warn!("Failed to decode {path:?} as {encoding}, opening as is");
+            let pt = PieceTree::from_reader(bytes.as_slice())?;
+            Ok((pt, Encoding::default()))
+        }
+    }
+}
+
+/// Create a file backed piece tree.
+///
+/// Compressed files are decompressed in the background using
+/// [`decompress_to_tmp`], so an empty piece tree is returned for them
+/// instead. Returns whether the file still needs to be decompressed.
+fn read_file_backed(path: &Path, codec: Option<&dyn Codec>) -> Result<(PieceTree, bool)> {
+    match codec {
+        Some(_) => Ok((PieceTree::new(), true)),
+        None => Ok((PieceTree::from_path(path)?, false)),
+    }
+}
+
+/// Decompress a file to a temporary file, which can be used as the backing
+/// file of a buffer using [`Buffer::finish_decompress`]
+pub(crate) fn decompress_to_tmp(path: &Path, codec: &dyn Codec) -> Result<PathBuf> {
+    let (tmp, mut file) = tmp_file().ok_or(BufferError::CannotCreateTmpFile)?;
+    if let Err(e) = codec::decompress_file(path, &mut file, codec) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(tmp)
+}
+
 fn copy_metadata(
     metadata: std::fs::Metadata,
     xattrs: Vec<(OsString, Vec<u8>)>,
@@ -538,6 +920,9 @@ pub(crate) enum BufferError {
 
     #[error("Snapshot does not exist")]
     NoSuchSnapshot,
+
+    #[error("Buffer is not being decompressed")]
+    NotDecompressing,
 }
 
 #[derive(Debug)]
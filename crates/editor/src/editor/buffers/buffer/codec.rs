@@ -0,0 +1,368 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use lzma_rust2::{XzOptions, XzReader, XzWriter};
+use ruzstd::{
+    decoding::StreamingDecoder,
+    encoding::{compress, CompressionLevel},
+};
+
+/// Compression format of a file.
+///
+/// Compressed files are decompressed when loaded and compressed using the
+/// same codec when saved. New codecs are added by implementing this trait
+/// and listing them in [`CODECS`].
+pub(crate) trait Codec: fmt::Debug + Sync {
+    /// Name shown to the user
+    fn name(&self) -> &'static str;
+
+    /// Whether the start of a file looks like it is compressed with this codec
+    fn detect(&self, sample: &[u8]) -> bool;
+
+    /// Decompressed size of a file as recorded in its headers, if the format
+    /// records it. Only the last member or stream of a file is looked at, so
+    /// the size may be smaller than the actual one.
+    fn decompressed_len_hint(&self, file: &mut File) -> io::Result<Option<u64>>;
+
+    /// Wrap a reader of compressed data into a reader of decompressed data
+    fn decoder<'a>(&self, reader: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>>;
+
+    /// Compress all of `source` into `target`
+    fn encode(&self, source: &mut dyn Read, target: &mut dyn Write) -> io::Result<()>;
+}
+
+/// How many bytes are needed to detect any codec
+const MAGIC_SAMPLE_SIZE: usize = 8;
+
+/// Supported codecs
+pub(crate) const CODECS: &[&dyn Codec] = &[&Gzip, &Zstd, &Xz];
+
+/// Detect codec from the start of a file
+pub(crate) fn detect_codec(sample: &[u8]) -> Option<&'static dyn Codec> {
+    CODECS.iter().copied().find(|codec| codec.detect(sample))
+}
+
+/// Detect codec of a file, returns none if the file is not compressed
+pub(crate) fn detect_file_codec(path: &Path) -> io::Result<Option<&'static dyn Codec>> {
+    let mut sample = Vec::with_capacity(MAGIC_SAMPLE_SIZE);
+    File::open(path)?
+        .take(MAGIC_SAMPLE_SIZE as u64)
+        .read_to_end(&mut sample)?;
+    Ok(detect_codec(&sample))
+}
+
+/// Open a decompressing reader to a file
+pub(crate) fn open_decoder(path: &Path, codec: &dyn Codec) -> io::Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(path)?);
+    codec.decoder(Box::new(file))
+}
+
+/// Decompress a file to another file
+pub(crate) fn decompress_file(from: &Path, to: &mut File, codec: &dyn Codec) -> io::Result<()> {
+    let mut reader = open_decoder(from, codec)?;
+    let mut writer = BufWriter::new(to);
+    io::copy(&mut reader, &mut writer)?;
+    writer.flush()
+}
+
+/// Compress a file to another file
+pub(crate) fn compress_file(from: &Path, to: &mut File, codec: &dyn Codec) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(from)?);
+    let mut writer = BufWriter::new(to);
+    codec.encode(&mut reader, &mut writer)?;
+    writer.flush()
+}
+
+#[derive(Debug)]
+pub(crate) struct Gzip;
+
+impl Codec for Gzip {
+    fn name(&self) -> &'static str {
+        "gzip"
+    }
+
+    fn detect(&self, sample: &[u8]) -> bool {
+        sample.starts_with(&[0x1f, 0x8b])
+    }
+
+    fn decompressed_len_hint(&self, file: &mut File) -> io::Result<Option<u64>> {
+        // Trailer ends with the size modulo 2^32
+        let Some(trailer) = read_tail(file, 4)? else {
+            return Ok(None);
+        };
+        let size = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        Ok(Some(size as u64))
+    }
+
+    fn decoder<'a>(&self, reader: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        // Concatenated gzip members are a valid gzip file
+        Ok(Box::new(flate2::read::MultiGzDecoder::new(reader)))
+    }
+
+    fn encode(&self, source: &mut dyn Read, target: &mut dyn Write) -> io::Result<()> {
+        let mut encoder = flate2::write::GzEncoder::new(target, flate2::Compression::default());
+        io::copy(source, &mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Zstd;
+
+impl Codec for Zstd {
+    fn name(&self) -> &'static str {
+        "zstd"
+    }
+
+    fn detect(&self, sample: &[u8]) -> bool {
+        sample.starts_with(&[0x28, 0xb5, 0x2f, 0xfd])
+    }
+
+    fn decompressed_len_hint(&self, file: &mut File) -> io::Result<Option<u64>> {
+        // Magic, frame header descriptor and at most 1 + 4 + 8 bytes of
+        // window descriptor, dictionary id and frame content size
+        let mut header = Vec::with_capacity(18);
+        file.seek(SeekFrom::Start(0))?;
+        file.take(18).read_to_end(&mut header)?;
+        let Some(&descriptor) = header.get(4) else {
+            return Ok(None);
+        };
+
+        let single_segment = descriptor & 0x20 != 0;
+        let window_len = if single_segment { 0 } else { 1 };
+        let dict_id_len = [0, 1, 2, 4][(descriptor & 0x3) as usize];
+        let content_size_len = match descriptor >> 6 {
+            0 if single_segment => 1,
+            0 => return Ok(None),
+            1 => 2,
+            2 => 4,
+            _ => 8,
+        };
+
+        let start = 5 + window_len + dict_id_len;
+        let Some(field) = header.get(start..start + content_size_len) else {
+            return Ok(None);
+        };
+        let mut bytes = [0u8; 8];
+        bytes[..content_size_len].copy_from_slice(field);
+        let mut size = u64::from_le_bytes(bytes);
+        if content_size_len == 2 {
+            size += 256;
+        }
+        Ok(Some(size))
+    }
+
+    fn decoder<'a>(&self, reader: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        let decoder = StreamingDecoder::new(reader).map_err(io::Error::other)?;
+        Ok(Box::new(decoder))
+    }
+
+    fn encode(&self, source: &mut dyn Read, target: &mut dyn Write) -> io::Result<()> {
+        // The compressor panics on IO errors, so catch them before it sees
+        // them and report them afterwards
+        let mut source = Fallible::new(source);
+        let mut target = Fallible::new(target);
+        compress(&mut source, &mut target, CompressionLevel::Fastest);
+        source.result()?;
+        target.result()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Xz;
+
+impl Codec for Xz {
+    fn name(&self) -> &'static str {
+        "xz"
+    }
+
+    fn detect(&self, sample: &[u8]) -> bool {
+        sample.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00])
+    }
+
+    fn decompressed_len_hint(&self, file: &mut File) -> io::Result<Option<u64>> {
+        // Stream footer points to the index, which lists the decompressed
+        // size of each block
+        let Some(footer) = read_tail(file, 12)? else {
+            return Ok(None);
+        };
+        if &footer[10..] != b"YZ" {
+            return Ok(None);
+        }
+        let backward_size = u32::from_le_bytes([footer[4], footer[5], footer[6], footer[7]]);
+        let index_len = (backward_size as i64 + 1) * 4;
+        if file.seek(SeekFrom::End(0))? < index_len as u64 + 12 {
+            return Ok(None);
+        }
+        file.seek(SeekFrom::End(-12 - index_len))?;
+
+        let mut index = BufReader::new(file.take(index_len as u64));
+        let mut indicator = [0u8];
+        index.read_exact(&mut indicator)?;
+        if indicator[0] != 0 {
+            return Ok(None);
+        }
+
+        let records = read_xz_varint(&mut index)?;
+        let mut size: u64 = 0;
+        for _ in 0..records {
+            let _unpadded_size = read_xz_varint(&mut index)?;
+            size = size.saturating_add(read_xz_varint(&mut index)?);
+        }
+        Ok(Some(size))
+    }
+
+    fn decoder<'a>(&self, reader: Box<dyn Read + 'a>) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(XzReader::new(reader, true)))
+    }
+
+    fn encode(&self, source: &mut dyn Read, target: &mut dyn Write) -> io::Result<()> {
+        let mut encoder = XzWriter::new(target, XzOptions::with_preset(6))?;
+        io::copy(source, &mut encoder)?;
+        encoder.finish()?;
+        Ok(())
+    }
+}
+
+/// Read the last `len` bytes of a file, returns none if the file is
+/// shorter
+fn read_tail(file: &mut File, len: usize) -> io::Result<Option<Vec<u8>>> {
+    if file.seek(SeekFrom::End(0))? < len as u64 {
+        return Ok(None);
+    }
+    file.seek(SeekFrom::End(-(len as i64)))?;
+    let mut tail = vec![0; len];
+    file.read_exact(&mut tail)?;
+    Ok(Some(tail))
+}
+
+/// Read a variable length integer used in xz headers
+fn read_xz_varint(reader: &mut impl Read) -> io::Result<u64> {
+    let mut value = 0;
+    for i in 0..9 {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as u64) << (i * 7);
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Invalid xz variable length integer",
+    ))
+}
+
+/// Reader or writer that never fails. The first error is stored and
+/// all operations after it do nothing.
+struct Fallible<T> {
+    inner: T,
+    error: Option<io::Error>,
+}
+
+impl<T> Fallible<T> {
+    fn new(inner: T) -> Fallible<T> {
+        Fallible { inner, error: None }
+    }
+
+    fn result(self) -> io::Result<()> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Read> Read for Fallible<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.error.is_some() {
+            return Ok(0);
+        }
+
+        match self.inner.read(buf) {
+            Ok(n) => Ok(n),
+            Err(e) => {
+                self.error = Some(e);
+                Ok(0)
+            }
+        }
+    }
+}
+
+impl<T: Write> Write for Fallible<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.error.is_none() {
+            if let Err(e) = self.inner.write_all(buf) {
+                self.error = Some(e);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.error.is_none() {
+            if let Err(e) = self.inner.flush() {
+                self.error = Some(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let text = "hello world\n".repeat(1000);
+        for codec in CODECS {
+            let mut compressed = vec![];
+            codec.encode(&mut text.as_bytes(), &mut compressed).unwrap();
+            assert!(compressed.len() < text.len(), "{}", codec.name());
+            assert_eq!(codec.name(), detect_codec(&compressed).unwrap().name());
+
+            let mut decoder = codec.decoder(Box::new(compressed.as_slice())).unwrap();
+            let mut decompressed = String::new();
+            decoder.read_to_string(&mut decompressed).unwrap();
+            assert_eq!(text, decompressed, "{}", codec.name());
+        }
+
+        assert!(detect_codec(text.as_bytes()).is_none());
+    }
+
+    #[test]
+    fn decompressed_len() {
+        let path = std::env::temp_dir().join(format!("sanedit-codec-{}", std::process::id()));
+        let text = "hello world\n".repeat(1000);
+        for codec in CODECS {
+            let mut file = File::create(&path).unwrap();
+            codec.encode(&mut text.as_bytes(), &mut file).unwrap();
+            drop(file);
+
+            // Zstd encoder does not write the size
+            let mut file = File::open(&path).unwrap();
+            let len = codec.decompressed_len_hint(&mut file).unwrap();
+            let expected = (codec.name() != "zstd").then_some(text.len() as u64);
+            assert_eq!(expected, len, "{}", codec.name());
+
+            let tmp = super::super::decompress_to_tmp(&path, *codec).unwrap();
+            assert_eq!(text, std::fs::read_to_string(&tmp).unwrap());
+            let _ = std::fs::remove_file(tmp);
+        }
+
+        // Single segment zstd frame with a 2 byte content size
+        let header = [0x28, 0xb5, 0x2f, 0xfd, 0x60, 0xe0, 0x2d];
+        std::fs::write(&path, header).unwrap();
+        let mut file = File::open(&path).unwrap();
+        let len = Zstd.decompressed_len_hint(&mut file).unwrap();
+        assert_eq!(Some(text.len() as u64), len);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
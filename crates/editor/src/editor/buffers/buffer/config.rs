@@ -4,6 +4,8 @@ use sanedit_buffer::utf8::EndOfLine;
 
 use sanedit_core::IndentKind;
 
+use super::Encoding;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(remote = "EndOfLine")]
 pub(crate) enum EndOfLineDef {
@@ -46,6 +48,20 @@ pub(crate) struct BufferConfig {
 
     /// How many indent characters a single indent should be
     pub(crate) indent_amount: u8,
+
+    /// Default file encoding, overridden if detect_encoding is set
+    /// Any WHATWG encoding label is accepted, for example:
+    /// utf-8, utf-16le, utf-16be, iso-8859-1, windows-1251, shift_jis
+    /// Add a "-bom" suffix to unicode encodings to write a byte order mark,
+    /// for example: utf-16le-bom
+    /// Big files are not transcoded, they are always opened as UTF-8
+    pub(crate) encoding: Encoding,
+
+    /// Maximum display width of a line when reflowing or wrapping text
+    pub(crate) text_width: usize,
+
+    /// Automatically wrap lines longer than `text_width` while typing
+    pub(crate) auto_wrap: bool,
 }
 
 impl Default for BufferConfig {
@@ -55,6 +71,9 @@ impl Default for BufferConfig {
             tabstop: 8,
             indent_kind: IndentKind::Space,
             indent_amount: 4,
+            encoding: Encoding::default(),
+            text_width: 80,
+            auto_wrap: false,
         }
     }
 }
@@ -0,0 +1,383 @@
+use std::{borrow::Cow, fmt};
+
+use encoding_rs::EncoderResult;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+/// How many bytes from the start of a file are used to detect its encoding
+pub(crate) const DETECT_SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Encodings listed when selecting an encoding, any other label known to
+/// encoding_rs can be used too
+pub(crate) const COMMON_ENCODINGS: &[&str] = &[
+    "utf-8",
+    "utf-8-bom",
+    "utf-16le-bom",
+    "utf-16be-bom",
+    "utf-16le",
+    "utf-16be",
+    "iso-8859-1",
+    "iso-8859-15",
+    "windows-1250",
+    "windows-1251",
+    "windows-1252",
+    "koi8-r",
+    "shift_jis",
+    "euc-jp",
+    "euc-kr",
+    "gbk",
+    "gb18030",
+    "big5",
+];
+
+/// Character encoding of a file.
+///
+/// Buffers are always UTF-8 in memory, files in other encodings are transcoded
+/// when they are loaded and saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Encoding {
+    charset: &'static encoding_rs::Encoding,
+    /// Whether the file starts with a byte order mark
+    bom: bool,
+}
+
+impl Encoding {
+    /// Parse an encoding label such as "utf-16le" or "latin1".
+    /// A "-bom" suffix can be used with unicode encodings to write a byte order
+    /// mark.
+    pub fn from_label(label: &str) -> Option<Encoding> {
+        let label = label.trim();
+        let (label, bom) = match label.len().checked_sub(4) {
+            Some(n) if label.is_char_boundary(n) && label[n..].eq_ignore_ascii_case("-bom") => {
+                (&label[..n], true)
+            }
+            _ => (label, false),
+        };
+        let charset = encoding_rs::Encoding::for_label(label.as_bytes())?;
+        // Replacement encoding decodes everything to U+FFFD, it cannot be
+        // used to edit files
+        if charset == encoding_rs::REPLACEMENT {
+            return None;
+        }
+        let encoding = Encoding { charset, bom };
+        if bom && encoding.bom_bytes().is_none() {
+            return None;
+        }
+
+        Some(encoding)
+    }
+
+    /// Detect encoding from the start of a file.
+    ///
+    /// A byte order mark is always trusted. Otherwise UTF-16 is recognized
+    /// from the null bytes in ASCII range characters, valid UTF-8 is UTF-8
+    /// and other text is assumed to be Windows-1252, a superset of ISO-8859-1.
+    /// Binary data is left as is.
+    ///
+    /// `complete` tells whether the sample is the whole file, if not a
+    /// truncated UTF-8 sequence at the end of the sample is allowed.
+    pub fn detect(sample: &[u8], complete: bool) -> Encoding {
+        if let Some((charset, _)) = encoding_rs::Encoding::for_bom(sample) {
+            return Encoding { charset, bom: true };
+        }
+
+        if let Some(charset) = detect_utf16(sample) {
+            return Encoding {
+                charset,
+                bom: false,
+            };
+        }
+
+        match std::str::from_utf8(sample) {
+            Ok(_) => return Encoding::default(),
+            Err(e) if e.error_len().is_none() && !complete => return Encoding::default(),
+            _ => {}
+        }
+
+        if is_binary(sample) {
+            return Encoding::default();
+        }
+
+        Encoding {
+            charset: encoding_rs::WINDOWS_1252,
+            bom: false,
+        }
+    }
+
+    /// Whether the buffer contents can be used as is without transcoding
+    pub fn is_utf8(&self) -> bool {
+        self.charset == encoding_rs::UTF_8 && !self.bom
+    }
+
+    pub fn name(&self) -> String {
+        let mut name = self.charset.name().to_ascii_lowercase();
+        if self.bom {
+            name.push_str("-bom");
+        }
+        name
+    }
+
+    fn bom_bytes(&self) -> Option<&'static [u8]> {
+        let charset = self.charset;
+        if charset == encoding_rs::UTF_8 {
+            Some(b"\xEF\xBB\xBF")
+        } else if charset == encoding_rs::UTF_16LE {
+            Some(b"\xFF\xFE")
+        } else if charset == encoding_rs::UTF_16BE {
+            Some(b"\xFE\xFF")
+        } else {
+            None
+        }
+    }
+
+    /// Decode bytes to UTF-8 so that encoding the result produces the exact
+    /// same bytes. Whether the byte order mark is present is determined from
+    /// the bytes themselves.
+    ///
+    /// Returns the decoded text and the encoding to use when encoding it
+    /// back, or None if the bytes cannot be decoded losslessly.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Option<(Cow<'a, str>, Encoding)> {
+        let mut encoding = *self;
+        let mut content = bytes;
+        encoding.bom = false;
+        if let Some(bom) = self.bom_bytes() {
+            if let Some(rest) = bytes.strip_prefix(bom) {
+                encoding.bom = true;
+                content = rest;
+            }
+        }
+
+        let text = self
+            .charset
+            .decode_without_bom_handling_and_without_replacement(content)?;
+        // Some charsets have multiple byte sequences for the same character
+        let encoded = encoding.encode(&text).ok()?;
+        if encoded != bytes {
+            return None;
+        }
+
+        Some((text, encoding))
+    }
+
+    /// Encode UTF-8 text using this encoding
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, EncodingError> {
+        let mut out = Vec::with_capacity(text.len() + 3);
+        if self.bom {
+            out.extend_from_slice(self.bom_bytes().unwrap_or_default());
+        }
+
+        // encoding_rs only decodes UTF-16
+        if self.charset == encoding_rs::UTF_16LE {
+            out.reserve(text.len() * 2);
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&unit.to_le_bytes());
+            }
+            return Ok(out);
+        }
+
+        if self.charset == encoding_rs::UTF_16BE {
+            out.reserve(text.len() * 2);
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&unit.to_be_bytes());
+            }
+            return Ok(out);
+        }
+
+        let mut encoder = self.charset.new_encoder();
+        let max = encoder
+            .max_buffer_length_from_utf8_without_replacement(text.len())
+            .ok_or(EncodingError::TooLarge)?;
+        out.reserve(max);
+        let (result, _) = encoder.encode_from_utf8_to_vec_without_replacement(text, &mut out, true);
+        match result {
+            EncoderResult::InputEmpty => Ok(out),
+            EncoderResult::Unmappable(ch) => Err(EncodingError::Unmappable(ch, self.name())),
+            EncoderResult::OutputFull => Err(EncodingError::TooLarge),
+        }
+    }
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding {
+            charset: encoding_rs::UTF_8,
+            bom: false,
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Serialize for Encoding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Encoding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let label = String::deserialize(deserializer)?;
+        Encoding::from_label(&label)
+            .ok_or_else(|| de::Error::custom(format!("unknown encoding: {label}")))
+    }
+}
+
+/// Whether the start of a file looks like binary data, that is it has null
+/// bytes and is not UTF-16
+pub(crate) fn is_binary(sample: &[u8]) -> bool {
+    encoding_rs::Encoding::for_bom(sample).is_none()
+        && detect_utf16(sample).is_none()
+        && sample.contains(&0)
+}
+
+/// Detect UTF-16 without a byte order mark, most text contains ASCII
+/// characters which have a null byte either as the high or the low byte.
+fn detect_utf16(sample: &[u8]) -> Option<&'static encoding_rs::Encoding> {
+    let pairs = sample.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+
+    let mut even = 0;
+    let mut odd = 0;
+    for pair in sample.chunks_exact(2) {
+        if pair[0] == 0 {
+            even += 1;
+        }
+        if pair[1] == 0 {
+            odd += 1;
+        }
+    }
+
+    // Over 30% on one side and barely any on the other
+    let many = |n: usize| n * 10 >= pairs * 3;
+    let few = |n: usize| n * 20 <= pairs;
+
+    if many(odd) && few(even) {
+        Some(encoding_rs::UTF_16LE)
+    } else if many(even) && few(odd) {
+        Some(encoding_rs::UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum EncodingError {
+    #[error("Character {0:?} cannot be represented in {1}")]
+    Unmappable(char, String),
+
+    #[error("Buffer contains invalid UTF-8")]
+    InvalidUtf8,
+
+    #[error("Buffer too large to encode")]
+    TooLarge,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn enc(label: &str) -> Encoding {
+        Encoding::from_label(label).unwrap()
+    }
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = vec![];
+        if bom {
+            bytes.extend_from_slice(b"\xFF\xFE");
+        }
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(enc("utf-16le-bom").name(), "utf-16le-bom");
+        assert_eq!(enc("UTF-8").name(), "utf-8");
+        assert_eq!(enc("latin1").name(), "windows-1252");
+        assert!(enc("utf-8").is_utf8());
+        assert!(!enc("utf-8-bom").is_utf8());
+        assert!(Encoding::from_label("latin1-bom").is_none());
+        assert!(Encoding::from_label("iso-2022-kr").is_none());
+        assert!(Encoding::from_label("nonsense").is_none());
+    }
+
+    #[test]
+    fn detect_bom() {
+        let bytes = utf16le("hello", true);
+        assert_eq!(Encoding::detect(&bytes, true), enc("utf-16le-bom"));
+        assert_eq!(
+            Encoding::detect(b"\xFE\xFF\x00a", true),
+            enc("utf-16be-bom")
+        );
+        assert_eq!(Encoding::detect(b"\xEF\xBB\xBFabc", true), enc("utf-8-bom"));
+    }
+
+    #[test]
+    fn detect_heuristic() {
+        let bytes = utf16le("key = value\nöäå\n", false);
+        assert_eq!(Encoding::detect(&bytes, true), enc("utf-16le"));
+
+        let be: Vec<u8> = bytes.chunks(2).flat_map(|p| [p[1], p[0]]).collect();
+        assert_eq!(Encoding::detect(&be, true), enc("utf-16be"));
+
+        assert_eq!(Encoding::detect("päivää\n".as_bytes(), true), enc("utf-8"));
+        assert_eq!(Encoding::detect(b"p\xE4iv\xE4\xE4\n", true), enc("latin1"));
+        // Truncated multibyte sequence
+        assert_eq!(Encoding::detect(b"abc\xC3", false), enc("utf-8"));
+        assert_eq!(Encoding::detect(b"abc\xC3", true), enc("latin1"));
+        // Binary
+        assert_eq!(
+            Encoding::detect(b"\x7FELF\x02\x01\x01\x00\x00\xFF", true),
+            enc("utf-8")
+        );
+        assert_eq!(Encoding::detect(b"", true), enc("utf-8"));
+    }
+
+    #[test]
+    fn binary() {
+        assert!(is_binary(b"\x7FELF\x02\x01\x01\x00\x00\xFF"));
+        assert!(!is_binary(&utf16le("text", false)));
+        assert!(!is_binary(&utf16le("text", true)));
+        assert!(!is_binary(b"p\xE4iv\xE4\xE4\n"));
+    }
+
+    #[test]
+    fn round_trip_single_byte() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let encoding = enc("iso-8859-1");
+        let (text, used) = encoding.decode(&bytes).unwrap();
+        assert_eq!(used, encoding);
+        assert_eq!(used.encode(&text).unwrap(), bytes);
+    }
+
+    #[test]
+    fn round_trip_utf16() {
+        let bytes = utf16le("a🦀\r\nb", true);
+        let (text, used) = enc("utf-16le").decode(&bytes).unwrap();
+        assert_eq!(text, "a🦀\r\nb");
+        assert_eq!(used, enc("utf-16le-bom"));
+        assert_eq!(used.encode(&text).unwrap(), bytes);
+
+        let bytes = utf16le("abc", false);
+        let (text, used) = enc("utf-16le-bom").decode(&bytes).unwrap();
+        assert_eq!(used, enc("utf-16le"));
+        assert_eq!(used.encode(&text).unwrap(), bytes);
+
+        // Unpaired surrogate
+        assert!(enc("utf-16le").decode(b"\x00\xD8a\x00").is_none());
+    }
+
+    #[test]
+    fn unmappable() {
+        let result = enc("latin1").encode("a🦀");
+        assert!(matches!(result, Err(EncodingError::Unmappable('🦀', _))));
+    }
+}
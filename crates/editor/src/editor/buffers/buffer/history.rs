@@ -0,0 +1,499 @@
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+use anyhow::{bail, ensure, Result};
+use sanedit_buffer::{Layout, PieceTree, PieceTreeSlice};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::editor::windows::Cursors;
+
+use super::{
+    snapshots::{SavedWindowState, SnapshotId, SnapshotNode, Snapshots},
+    Buffer, BufferError,
+};
+
+/// Increment when the persisted format changes
+const VERSION: u32 = 1;
+
+/// Extension of the persisted history files
+const EXTENSION: &str = "undo";
+
+/// Used to name temporary files of histories being written
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Limits for persisted undo histories
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HistoryLimits {
+    /// Maximum number of snapshots persisted per file, oldest are pruned
+    pub(crate) max_snapshots: usize,
+    /// Maximum size of a single history, larger ones are not persisted
+    pub(crate) max_size_bytes: u64,
+    /// Maximum number of histories to keep, least recently saved are removed
+    pub(crate) max_files: usize,
+}
+
+/// Undo history of a file, persisted between editor sessions
+#[derive(Debug, Serialize, Deserialize)]
+struct History {
+    version: u32,
+    path: PathBuf,
+    /// Hash of the file contents the history is valid for
+    hash: u64,
+    /// Contents of the original buffer, if it differs from the file contents
+    original: Option<Vec<u8>>,
+    add: Vec<u8>,
+    /// Snapshot that matches the file contents
+    saved: SnapshotId,
+    nodes: Vec<HistoryNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryNode {
+    layout: Vec<u8>,
+    timestamp: SystemTime,
+    previous: Vec<SnapshotId>,
+    next: Vec<SnapshotId>,
+
+    cursors: Cursors,
+    view_offset: u64,
+    /// Position of the change start mark in the snapshot
+    change_start: Option<u64>,
+    last_selection: Option<Cursors>,
+}
+
+impl History {
+    fn size(&self) -> u64 {
+        let original = self.original.as_ref().map(Vec::len).unwrap_or(0);
+        let layouts: usize = self.nodes.iter().map(|node| node.layout.len()).sum();
+        (original + self.add.len() + layouts) as u64
+    }
+
+    /// Remove the oldest snapshots so that at most `max` remain. The saved
+    /// snapshot is always kept. Snapshots whose parents were removed are
+    /// attached to the oldest remaining snapshot.
+    fn prune(&mut self, max: usize) {
+        let max = max.max(1);
+        if self.nodes.len() <= max {
+            return;
+        }
+
+        let cut = self.nodes.len() - max;
+        let mut ids = vec![None; self.nodes.len()];
+        let mut next_id = 0;
+        for (id, new) in ids.iter_mut().enumerate() {
+            if cut <= id || id == self.saved {
+                *new = Some(next_id);
+                next_id += 1;
+            }
+        }
+
+        let old = std::mem::take(&mut self.nodes);
+        for (id, mut node) in old.into_iter().enumerate() {
+            if ids[id].is_none() {
+                continue;
+            }
+
+            node.previous = node.previous.iter().filter_map(|p| ids[*p]).collect();
+            node.next = node.next.iter().filter_map(|n| ids[*n]).collect();
+            self.nodes.push(node);
+        }
+
+        for id in 1..self.nodes.len() {
+            if self.nodes[id].previous.is_empty() {
+                self.nodes[id].previous.push(0);
+                self.nodes[0].next.push(id);
+            }
+        }
+        self.nodes[0].next.sort();
+        self.saved = ids[self.saved].unwrap_or(0);
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum HistoryError {
+    #[error("Undo history is not persisted for file backed buffers")]
+    FileBacked,
+
+    #[error("Undo history is too large to persist")]
+    TooLarge,
+
+    #[error("Invalid undo history")]
+    Invalid,
+}
+
+/// Path of the history file for a file
+fn history_file(dir: &Path, path: &Path) -> PathBuf {
+    let hash = hash_bytes(path.as_os_str().as_encoded_bytes());
+    dir.join(format!("{hash:016x}.{EXTENSION}"))
+}
+
+/// Stable FNV-1a hash, persisted hashes should not change between versions
+pub(super) fn hash_bytes(bytes: &[u8]) -> u64 {
+    hash_chunks(0xcbf29ce484222325, bytes)
+}
+
+fn hash_chunks(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub(super) fn hash_slice(slice: &PieceTreeSlice) -> u64 {
+    let mut hash = 0xcbf29ce484222325;
+    let mut chunks = slice.chunks();
+    let mut chunk = chunks.get();
+    while let Some((_, chk)) = chunk {
+        hash = hash_chunks(hash, chk.as_ref());
+        chunk = chunks.next();
+    }
+    hash
+}
+
+/// Undo history captured from a buffer, to be written to disk. Capturing
+/// only clones the snapshots so the history can be written in the
+/// background.
+#[derive(Debug, Clone)]
+pub(crate) struct PendingHistory {
+    dir: PathBuf,
+    path: PathBuf,
+    limits: HistoryLimits,
+    /// Contents of the whole buffer, used to access its add and original
+    /// buffers
+    pt: PieceTreeSlice,
+    add_len: u64,
+    saved: SnapshotId,
+    nodes: Vec<SnapshotNode>,
+}
+
+/// Capture the undo history of a buffer to be persisted to a directory.
+/// Returns none if there is nothing to persist.
+pub(crate) fn capture(
+    buf: &Buffer,
+    dir: &Path,
+    limits: &HistoryLimits,
+) -> Result<Option<PendingHistory>> {
+    let path = buf.path().ok_or(BufferError::NoSavePath)?;
+    ensure!(!buf.is_file_backed(), HistoryError::FileBacked);
+
+    // Nothing on disk or nothing to undo
+    if buf.last_saved_modified.is_none() || buf.snapshots.nodes().is_empty() {
+        return Ok(None);
+    }
+
+    ensure!(
+        buf.snapshots.get(buf.last_saved_snapshot).is_some(),
+        BufferError::NoSuchSnapshot
+    );
+
+    Ok(Some(PendingHistory {
+        dir: dir.to_path_buf(),
+        path: path.to_path_buf(),
+        limits: *limits,
+        pt: buf.pt.slice(..),
+        add_len: buf.pt.add_buffer_len(),
+        saved: buf.last_saved_snapshot,
+        nodes: buf.snapshots.nodes().to_vec(),
+    }))
+}
+
+impl PendingHistory {
+    /// Write the history to disk.
+    ///
+    /// The history is valid while the file contents match the last saved
+    /// snapshot.
+    pub fn write(&self) -> Result<()> {
+        let saved = &self.nodes[self.saved].snapshot;
+        let hash = hash_slice(saved);
+        let original = if saved.is_original_buffer()? {
+            None
+        } else {
+            Some(self.pt.original_buffer_bytes()?)
+        };
+
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| HistoryNode {
+                layout: node.snapshot.layout().to_bytes(),
+                timestamp: node.timestamp,
+                previous: node.previous.clone(),
+                next: node.next.clone(),
+                cursors: node.data.cursors.clone(),
+                view_offset: node.data.view_offset,
+                change_start: node
+                    .data
+                    .change_start
+                    .map(|mark| node.snapshot.mark_to_pos(&mark).pos()),
+                last_selection: node.data.last_selection.clone(),
+            })
+            .collect();
+
+        let mut history = History {
+            version: VERSION,
+            path: self.path.clone(),
+            hash,
+            original,
+            add: self.pt.add_buffer_bytes(0..self.add_len),
+            saved: self.saved,
+            nodes,
+        };
+        history.prune(self.limits.max_snapshots);
+
+        let file = history_file(&self.dir, &self.path);
+        if history.size() > self.limits.max_size_bytes {
+            // Stale history would not match anymore anyway
+            let _ = fs::remove_file(&file);
+            bail!(HistoryError::TooLarge);
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        let bytes = bincode::serialize(&history)?;
+        // Histories of the same file may be written concurrently
+        let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let tmp = file.with_extension(format!("{n}.tmp"));
+        let written = fs::File::create(&tmp).and_then(|mut f| {
+            f.write_all(&bytes)?;
+            f.flush()
+        });
+        if let Err(e) = written.and_then(|_| fs::rename(&tmp, &file)) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+
+        remove_oldest(&self.dir, self.limits.max_files);
+        Ok(())
+    }
+}
+
+/// Remove the least recently saved histories so that at most `max` remain
+fn remove_oldest(dir: &Path, max: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let path = entry.path();
+            if path.extension()? != EXTENSION {
+                return None;
+            }
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, path))
+        })
+        .collect();
+
+    if files.len() <= max {
+        return;
+    }
+
+    files.sort();
+    let n = files.len() - max;
+    for (_, path) in files.into_iter().take(n) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Restore a persisted undo history of a buffer.
+///
+/// The history is restored only if no changes have been made to the buffer
+/// and the buffer contents match the contents the history was saved with.
+/// Returns whether the history was restored.
+pub(crate) fn load(buf: &mut Buffer, dir: &Path) -> Result<bool> {
+    let path = buf.path().ok_or(BufferError::NoSavePath)?;
+    if buf.is_file_backed()
+        || buf.total_changes_made != 0
+        || buf.is_modified
+        || !buf.snapshots.nodes().is_empty()
+    {
+        return Ok(false);
+    }
+
+    let file = history_file(dir, path);
+    let Ok(bytes) = fs::read(&file) else {
+        return Ok(false);
+    };
+    let history: History = match bincode::deserialize(&bytes) {
+        Ok(history) => history,
+        Err(e) => {
+            log::warn!("Failed to read undo history {file:?}: {e}");
+            let _ = fs::remove_file(&file);
+            return Ok(false);
+        }
+    };
+
+    if history.version != VERSION || history.path != path {
+        return Ok(false);
+    }
+
+    if history.hash != hash_slice(&buf.pt.slice(..)) {
+        // File has changed since, the history cannot be used anymore
+        let _ = fs::remove_file(&file);
+        return Ok(false);
+    }
+
+    let original = match history.original {
+        Some(original) => original,
+        None => buf.pt.original_buffer_bytes()?,
+    };
+    let mut pt = PieceTree::from_reader(original.as_slice())?;
+    pt.extend_add_buffer(&history.add);
+
+    let mut nodes = Vec::with_capacity(history.nodes.len());
+    for (id, hnode) in history.nodes.into_iter().enumerate() {
+        let snapshot = Layout::from_bytes(&hnode.layout)
+            .and_then(|layout| pt.slice_from_layout(&layout))
+            .ok_or(HistoryError::Invalid)?;
+        let change_start = hnode
+            .change_start
+            .filter(|pos| *pos <= snapshot.len())
+            .map(|pos| snapshot.mark(pos));
+
+        nodes.push(SnapshotNode {
+            id,
+            timestamp: hnode.timestamp,
+            previous: hnode.previous,
+            next: hnode.next,
+            data: SavedWindowState {
+                cursors: hnode.cursors,
+                view_offset: hnode.view_offset,
+                change_start,
+                last_selection: hnode.last_selection,
+            },
+            snapshot,
+        });
+    }
+
+    let valid_links = nodes.iter().all(|node| {
+        node.previous
+            .iter()
+            .chain(&node.next)
+            .all(|n| *n < nodes.len())
+    });
+    let saved = nodes.get(history.saved).ok_or(HistoryError::Invalid)?;
+    ensure!(valid_links, HistoryError::Invalid);
+    ensure!(
+        hash_slice(&saved.snapshot) == history.hash,
+        HistoryError::Invalid
+    );
+
+    pt.restore(saved.snapshot.clone());
+    buf.pt = pt;
+    buf.snapshots = Snapshots::from_nodes(nodes, Some(history.saved));
+    buf.last_saved_snapshot = history.saved;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn node(previous: Vec<SnapshotId>, next: Vec<SnapshotId>) -> HistoryNode {
+        HistoryNode {
+            layout: vec![],
+            timestamp: SystemTime::UNIX_EPOCH,
+            previous,
+            next,
+            cursors: Cursors::default(),
+            view_offset: 0,
+            change_start: None,
+            last_selection: None,
+        }
+    }
+
+    fn history(nodes: Vec<HistoryNode>, saved: SnapshotId) -> History {
+        History {
+            version: VERSION,
+            path: PathBuf::from("/test"),
+            hash: 0,
+            original: None,
+            add: vec![],
+            saved,
+            nodes,
+        }
+    }
+
+    #[test]
+    fn prune_keeps_saved() {
+        // 0 - 1 - 2 - 3
+        //      \- 4
+        let mut hist = history(
+            vec![
+                node(vec![], vec![1]),
+                node(vec![0], vec![2, 4]),
+                node(vec![1], vec![3]),
+                node(vec![2], vec![]),
+                node(vec![1], vec![]),
+            ],
+            1,
+        );
+        hist.prune(2);
+
+        // 1 - 3
+        //  \- 4
+        assert_eq!(3, hist.nodes.len());
+        assert_eq!(0, hist.saved);
+        assert_eq!(vec![1, 2], hist.nodes[0].next);
+        assert_eq!(vec![0], hist.nodes[1].previous);
+        assert_eq!(vec![0], hist.nodes[2].previous);
+    }
+
+    #[test]
+    fn prune_under_limit() {
+        let mut hist = history(vec![node(vec![], vec![1]), node(vec![0], vec![])], 0);
+        hist.prune(5);
+        assert_eq!(2, hist.nodes.len());
+        assert_eq!(vec![1], hist.nodes[0].next);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("sanedit-history-{}", std::process::id()));
+        let file = dir.join("file.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&file, "hello").unwrap();
+
+        let limits = HistoryLimits {
+            max_snapshots: 10,
+            max_size_bytes: 1024,
+            max_files: 10,
+        };
+        let mut buf = Buffer::new();
+        buf.pt = PieceTree::from("hello");
+        buf.path = Some(file.clone());
+        buf.last_saved_modified = Some(SystemTime::now());
+        buf.create_undopoint(SavedWindowState::default());
+        buf.pt.insert(5, " world");
+        buf.is_modified = true;
+        buf.create_undopoint(SavedWindowState::default());
+        buf.undo().unwrap();
+        let history = capture(&buf, &dir, &limits).unwrap().unwrap();
+        history.write().unwrap();
+
+        let mut loaded = Buffer::new();
+        loaded.pt = PieceTree::from("hello");
+        loaded.path = Some(file.clone());
+        assert!(load(&mut loaded, &dir).unwrap());
+        assert_eq!(2, loaded.snapshots.nodes().len());
+        assert_eq!("hello", String::from(&loaded.slice(..)));
+
+        loaded.redo().unwrap();
+        assert_eq!("hello world", String::from(&loaded.slice(..)));
+
+        // Different contents on disk
+        let mut changed = Buffer::new();
+        changed.pt = PieceTree::from("hello!");
+        changed.path = Some(file.clone());
+        assert!(!load(&mut changed, &dir).unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,469 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write as _,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use sanedit_buffer::{Layout, PieceTree, PieceTreeSlice};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    history::{hash_bytes, hash_slice},
+    Buffer,
+};
+
+/// Increment when the journal format changes
+const VERSION: u32 = 1;
+
+/// Extension of the journal files
+const EXTENSION: &str = "journal";
+
+/// Journals smaller than this are never rewritten
+const MIN_REWRITE_SIZE: u64 = 1024 * 1024;
+
+/// A journal file is a sequence of length prefixed records. The first record
+/// is always a header, followed by checkpoints. Each checkpoint contains the
+/// add buffer bytes written since the previous checkpoint and the piece
+/// layout of the buffer at that time. A record that was only partially
+/// written when the process died is ignored.
+#[derive(Debug, Serialize, Deserialize)]
+enum Record {
+    Header {
+        version: u32,
+        path: PathBuf,
+        /// Hash of the file contents the journal is valid for
+        hash: u64,
+        /// Contents of the original buffer, if it differs from the file contents
+        original: Option<Vec<u8>>,
+    },
+    Checkpoint {
+        add_start: u64,
+        add: Vec<u8>,
+        layout: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum JournalError {
+    #[error("File has been changed since the journal was written")]
+    FileChanged,
+
+    #[error("Invalid recovery journal")]
+    Invalid,
+}
+
+/// Append only recovery journal of the unsaved changes of a buffer
+#[derive(Debug)]
+pub(crate) struct Journal {
+    dir: PathBuf,
+    file: PathBuf,
+    interval: Duration,
+    /// Contents of the file on disk
+    saved: PieceTreeSlice,
+    /// Whether the journal file has been written to in this session
+    started: bool,
+    /// Whether a journal from a previous session exists
+    recoverable: bool,
+    /// Length of the add buffer written to the journal
+    add_written: u64,
+    size: u64,
+    last_write: Option<Instant>,
+    /// Whether a write was skipped because the previous one was too recent
+    dirty: bool,
+}
+
+impl Journal {
+    pub fn new(dir: &Path, path: &Path, saved: PieceTreeSlice, interval: Duration) -> Journal {
+        let file = journal_file(dir, path);
+        Journal {
+            dir: dir.to_path_buf(),
+            recoverable: file.exists(),
+            file,
+            interval,
+            saved,
+            started: false,
+            add_written: 0,
+            size: 0,
+            last_write: None,
+            dirty: false,
+        }
+    }
+
+    /// Write a checkpoint if enough time has passed since the previous one,
+    /// otherwise mark the journal dirty so it can be flushed later
+    pub fn write(&mut self, pt: &PieceTree, path: &Path) -> Result<()> {
+        if self.started && !self.time_left().is_zero() {
+            self.dirty = true;
+            return Ok(());
+        }
+
+        self.write_checkpoint(pt, path)
+    }
+
+    /// Write the checkpoint skipped by a previous write
+    pub fn flush(&mut self, pt: &PieceTree, path: &Path) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.write_checkpoint(pt, path)
+    }
+
+    /// Time left until a skipped checkpoint can be written, None if there
+    /// is nothing to flush
+    pub fn flush_in(&self) -> Option<Duration> {
+        if !self.dirty {
+            return None;
+        }
+        Some(self.time_left())
+    }
+
+    /// Time left until the next checkpoint can be written
+    fn time_left(&self) -> Duration {
+        self.last_write
+            .map(|last| self.interval.saturating_sub(last.elapsed()))
+            .unwrap_or_default()
+    }
+
+    fn write_checkpoint(&mut self, pt: &PieceTree, path: &Path) -> Result<()> {
+        self.last_write = Some(Instant::now());
+        self.dirty = false;
+
+        let layout = pt.slice(..).layout().to_bytes();
+        let add_len = pt.add_buffer_len();
+        let rewrite = MIN_REWRITE_SIZE < self.size
+            && 2 * (add_len + layout.len() as u64 + self.saved.len()) < self.size;
+        if !self.started || rewrite {
+            return self.start(pt, path, layout);
+        }
+
+        let checkpoint = Record::Checkpoint {
+            add_start: self.add_written,
+            add: pt.add_buffer_bytes(self.add_written..add_len),
+            layout,
+        };
+        self.append(&checkpoint)?;
+        self.add_written = add_len;
+        Ok(())
+    }
+
+    /// Start a new journal file replacing any previous one. The new file is
+    /// written next to the old one and renamed over it, so a crash in
+    /// between leaves the previous journal intact.
+    fn start(&mut self, pt: &PieceTree, path: &Path, layout: Vec<u8>) -> Result<()> {
+        let hash = hash_slice(&self.saved);
+        let original = pt.original_buffer_bytes()?;
+        let original = if original.len() as u64 == self.saved.len() && hash_bytes(&original) == hash
+        {
+            None
+        } else {
+            Some(original)
+        };
+        let header = Record::Header {
+            version: VERSION,
+            path: path.to_path_buf(),
+            hash,
+            original,
+        };
+
+        let checkpoint = Record::Checkpoint {
+            add_start: 0,
+            add: pt.add_buffer_bytes(0..pt.add_buffer_len()),
+            layout,
+        };
+        let mut frames = frame(&header)?;
+        frames.extend(frame(&checkpoint)?);
+
+        fs::create_dir_all(&self.dir)?;
+        let tmp = self.file.with_extension(format!("{EXTENSION}.tmp"));
+        let written = fs::write(&tmp, &frames).and_then(|_| fs::rename(&tmp, &self.file));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+
+        self.size = frames.len() as u64;
+        self.add_written = pt.add_buffer_len();
+        self.recoverable = false;
+        self.started = true;
+        Ok(())
+    }
+
+    fn append(&mut self, record: &Record) -> Result<()> {
+        let frame = frame(record)?;
+        let mut file = OpenOptions::new().append(true).open(&self.file)?;
+        file.write_all(&frame)?;
+        self.size += frame.len() as u64;
+        Ok(())
+    }
+
+    /// Contents on disk changed, the journal is no longer needed
+    pub fn set_saved(&mut self, saved: PieceTreeSlice) {
+        self.saved = saved;
+        self.remove();
+    }
+
+    /// Journal the buffer using a different path
+    pub fn set_path(&mut self, path: &Path, saved: PieceTreeSlice) {
+        self.remove();
+        self.file = journal_file(&self.dir, path);
+        self.saved = saved;
+    }
+
+    /// Remove the journal file
+    pub fn remove(&mut self) {
+        if self.started || self.recoverable {
+            let _ = fs::remove_file(&self.file);
+        }
+        self.started = false;
+        self.recoverable = false;
+        self.add_written = 0;
+        self.size = 0;
+        self.last_write = None;
+        self.dirty = false;
+    }
+
+    pub fn is_recoverable(&self) -> bool {
+        self.recoverable
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started
+    }
+}
+
+/// Serialize a length prefixed record
+fn frame(record: &Record) -> Result<Vec<u8>> {
+    let bytes = bincode::serialize(record)?;
+    let mut frame = Vec::with_capacity(bytes.len() + 4);
+    frame.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&bytes);
+    Ok(frame)
+}
+
+/// Path of the journal file for a file
+fn journal_file(dir: &Path, path: &Path) -> PathBuf {
+    let hash = hash_bytes(path.as_os_str().as_encoded_bytes());
+    dir.join(format!("{hash:016x}.{EXTENSION}"))
+}
+
+/// Contents of a journal file
+#[derive(Debug)]
+struct Contents {
+    path: PathBuf,
+    hash: u64,
+    original: Option<Vec<u8>>,
+    add: Vec<u8>,
+    layout: Option<Layout>,
+}
+
+/// Parse a journal file, stops at the first incomplete or invalid record
+fn parse(mut bytes: &[u8]) -> Option<Contents> {
+    let mut records = std::iter::from_fn(|| {
+        let len = u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?) as usize;
+        let record = bytes.get(4..4 + len)?;
+        bytes = &bytes[4 + len..];
+        bincode::deserialize::<Record>(record).ok()
+    });
+
+    let Some(Record::Header {
+        version: VERSION,
+        path,
+        hash,
+        original,
+    }) = records.next()
+    else {
+        return None;
+    };
+
+    let mut contents = Contents {
+        path,
+        hash,
+        original,
+        add: vec![],
+        layout: None,
+    };
+
+    for record in records {
+        let Record::Checkpoint {
+            add_start,
+            add,
+            layout,
+        } = record
+        else {
+            break;
+        };
+        let Some(layout) = Layout::from_bytes(&layout) else {
+            break;
+        };
+        if add_start != contents.add.len() as u64 {
+            break;
+        }
+
+        contents.add.extend_from_slice(&add);
+        contents.layout = Some(layout);
+    }
+
+    Some(contents)
+}
+
+/// Read the unsaved contents of a buffer from a journal written in a
+/// previous session. Returns none if there is nothing to recover.
+pub(crate) fn recover(buf: &Buffer) -> Result<Option<Vec<u8>>> {
+    let Some(journal) = buf.journal.as_ref().filter(|j| j.recoverable) else {
+        return Ok(None);
+    };
+    let Some(path) = buf.path() else {
+        return Ok(None);
+    };
+    let Ok(bytes) = fs::read(&journal.file) else {
+        return Ok(None);
+    };
+    let contents = parse(&bytes).ok_or(JournalError::Invalid)?;
+    if contents.path != path {
+        return Ok(None);
+    }
+    let Some(layout) = contents.layout else {
+        return Ok(None);
+    };
+
+    let current = buf.pt.slice(..);
+    if contents.hash != hash_slice(&current) {
+        bail!(JournalError::FileChanged);
+    }
+
+    let original = match contents.original {
+        Some(original) => original,
+        None => buf.pt.original_buffer_bytes()?,
+    };
+    let mut pt = PieceTree::from_reader(original.as_slice())?;
+    pt.extend_add_buffer(&contents.add);
+    let slice = pt.slice_from_layout(&layout).ok_or(JournalError::Invalid)?;
+
+    if slice == Vec::from(&current) {
+        return Ok(None);
+    }
+
+    Ok(Some(Vec::from(&slice)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tmp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("sanedit-journal-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn open(dir: &Path, file: &Path, content: &str) -> Buffer {
+        let mut buf = Buffer::new();
+        buf.pt = PieceTree::from(content);
+        buf.path = Some(file.to_path_buf());
+        buf.journal = Some(Journal::new(dir, file, buf.pt.slice(..), Duration::ZERO));
+        buf
+    }
+
+    #[test]
+    fn write_and_recover() {
+        let dir = tmp_dir("recover");
+        let file = dir.join("file.txt");
+
+        let mut buf = open(&dir, &file, "hello");
+        buf.pt.insert(5, " world");
+        buf.is_modified = true;
+        buf.write_journal();
+        buf.pt.remove(0..1);
+        buf.pt.insert(0, "H");
+        buf.write_journal();
+        // Simulate a crash
+        buf.journal = None;
+
+        let buf = open(&dir, &file, "hello");
+        assert!(buf.journal.as_ref().unwrap().is_recoverable());
+        let recovered = recover(&buf).unwrap().unwrap();
+        assert_eq!(b"Hello world", recovered.as_slice());
+
+        // Contents on disk changed
+        let changed = open(&dir, &file, "hello!");
+        assert!(recover(&changed).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn flush_throttled_write() {
+        let dir = tmp_dir("flush");
+        let file = dir.join("file.txt");
+
+        let mut buf = open(&dir, &file, "hello");
+        buf.journal.as_mut().unwrap().interval = Duration::from_secs(3600);
+        buf.pt.insert(5, " world");
+        buf.is_modified = true;
+        buf.write_journal();
+        buf.pt.insert(0, "oh ");
+        buf.write_journal();
+        assert!(buf.journal_flush_in().is_some());
+
+        let bytes = fs::read(journal_file(&dir, &file)).unwrap();
+        let contents = parse(&bytes).unwrap();
+        assert_eq!(b" world", contents.add.as_slice());
+
+        buf.flush_journal();
+        assert!(buf.journal_flush_in().is_none());
+        buf.journal = None;
+
+        let buf = open(&dir, &file, "hello");
+        let recovered = recover(&buf).unwrap().unwrap();
+        assert_eq!(b"oh hello world", recovered.as_slice());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn torn_record() {
+        let dir = tmp_dir("torn");
+        let file = dir.join("file.txt");
+
+        let mut buf = open(&dir, &file, "hello");
+        buf.pt.insert(5, " world");
+        buf.is_modified = true;
+        buf.write_journal();
+        buf.pt.insert(0, "oh ");
+        buf.write_journal();
+        buf.journal = None;
+
+        let jfile = journal_file(&dir, &file);
+        let bytes = fs::read(&jfile).unwrap();
+        fs::write(&jfile, &bytes[..bytes.len() - 3]).unwrap();
+
+        let buf = open(&dir, &file, "hello");
+        let recovered = recover(&buf).unwrap().unwrap();
+        assert_eq!(b"hello world", recovered.as_slice());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn removed_when_unmodified() {
+        let dir = tmp_dir("unmodified");
+        let file = dir.join("file.txt");
+
+        let mut buf = open(&dir, &file, "hello");
+        buf.pt.insert(5, " world");
+        buf.is_modified = true;
+        buf.write_journal();
+        assert!(journal_file(&dir, &file).exists());
+
+        buf.is_modified = false;
+        buf.write_journal();
+        assert!(!journal_file(&dir, &file).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::time::SystemTime;
 
 use sanedit_buffer::{Mark, PieceTreeSlice};
 
@@ -30,6 +30,12 @@ impl Snapshots {
         }
     }
 
+    /// Create snapshots from nodes, the node ids must be their indices
+    pub fn from_nodes(snapshots: Vec<SnapshotNode>, current: Option<SnapshotId>) -> Snapshots {
+        debug_assert!(snapshots.iter().enumerate().all(|(i, node)| node.id == i));
+        Snapshots { current, snapshots }
+    }
+
     pub fn nodes(&self) -> &[SnapshotNode] {
         &self.snapshots
     }
@@ -147,7 +153,7 @@ impl Snapshots {
 pub(crate) struct SnapshotNode {
     pub(crate) id: SnapshotId,
     pub(crate) snapshot: PieceTreeSlice,
-    pub(crate) timestamp: Instant,
+    pub(crate) timestamp: SystemTime,
 
     pub(crate) previous: Vec<SnapshotId>,
     pub(crate) next: Vec<SnapshotId>,
@@ -161,7 +167,7 @@ impl SnapshotNode {
         SnapshotNode {
             id,
             snapshot,
-            timestamp: Instant::now(),
+            timestamp: SystemTime::now(),
             previous: vec![],
             next: vec![],
 
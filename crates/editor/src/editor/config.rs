@@ -269,6 +269,10 @@ pub(crate) struct EditorConfig {
     /// Autodetect indentation from file
     pub detect_indent: bool,
 
+    /// Autodetect encoding from file
+    /// Big files are not transcoded, they are always opened as UTF-8
+    pub detect_encoding: bool,
+
     /// Language glob patterns
     /// By default the language is the extension of the file
     #[serde(skip_serializing)]
@@ -279,6 +283,37 @@ pub(crate) struct EditorConfig {
 
     /// Prompt whether to load when file changes on disk
     pub auto_reload_changed_or_removed_file: bool,
+
+    /// Persist undo history of files, restored when the file is opened
+    /// again unchanged
+    pub persist_undo: bool,
+
+    /// Maximum number of undo points persisted per file, oldest are pruned
+    pub persist_undo_max_snapshots: usize,
+
+    /// Maximum size of a persisted undo history in bytes, larger histories
+    /// are not persisted
+    pub persist_undo_max_size_bytes: u64,
+
+    /// Maximum number of files to keep undo history for
+    pub persist_undo_max_files: usize,
+
+    /// Journal unsaved changes, so they can be recovered if the editor
+    /// exits unexpectedly
+    pub recovery_journal: bool,
+
+    /// Minimum time between recovery journal writes in milliseconds
+    pub recovery_journal_interval_ms: u64,
+}
+
+impl EditorConfig {
+    pub fn history_limits(&self) -> buffers::HistoryLimits {
+        buffers::HistoryLimits {
+            max_snapshots: self.persist_undo_max_snapshots,
+            max_size_bytes: self.persist_undo_max_size_bytes,
+            max_files: self.persist_undo_max_files,
+        }
+    }
 }
 
 pub(crate) struct Keymapping {
@@ -134,9 +134,16 @@ impl Default for EditorConfig {
             git_ignore: true,
             detect_eol: true,
             detect_indent: true,
+            detect_encoding: true,
             language_detect: Self::default_language_map(),
             copy_on_delete: true,
             auto_reload_changed_or_removed_file: false,
+            persist_undo: true,
+            persist_undo_max_snapshots: 1000,
+            persist_undo_max_size_bytes: 10 * 1024 * 1024, // 10MB
+            persist_undo_max_files: 500,
+            recovery_journal: true,
+            recovery_journal_interval_ms: 2000,
         }
     }
 }
@@ -337,6 +344,8 @@ pub(crate) fn normal() -> KeymapLayer {
         "y", copy,
         "Y", copy_to_eol,
         "p", paste,
+        "alt+p", paste_kill_ring,
+        "\"", select_register,
         "i", insert_mode,
         "u", undo,
         "U", redo,
@@ -353,14 +362,25 @@ pub(crate) fn normal() -> KeymapLayer {
         "z z", view_to_cursor_middle,
         "z t", view_to_cursor_top,
         "z b", view_to_cursor_bottom,
+        "z c", close_fold,
+        "z o", open_fold,
+        "z a", toggle_fold,
+        "z R", open_all_folds,
         "H", cursor_to_view_top,
         "M", cursor_to_view_middle,
         "L", cursor_to_view_bottom,
         "g c", toggle_comment_lines,
         "g v", select_last_selection,
         "J", join_lines,
+        "g q", reflow,
+        "alt+a", increment,
+        "alt+x", decrement,
+        "m r", change_surrounding,
+        "m d", delete_surrounding,
+        "m m", toggle_bookmark,
         "ctrl+p", open_file,
         "v", start_selection,
+        "alt+v", start_block_selection,
         "$", end_of_line,
         "0", start_of_line,
         "^", first_char_of_line,
@@ -381,6 +401,8 @@ pub(crate) fn normal() -> KeymapLayer {
         "g g", start_of_buffer,
         "g p", next_paragraph,
         "g P", prev_paragraph,
+        "g )", next_sentence,
+        "g (", prev_sentence,
         "o", newline_below,
         "O", newline_above,
         ">", indent_line,
@@ -414,6 +436,8 @@ pub(crate) fn normal() -> KeymapLayer {
         "g r",     references,
         "g e",     next_diagnostic,
         "g E",     prev_diagnostic,
+        "g m",     next_bookmark,
+        "g M",     prev_bookmark,
         "K",       hover,
 
         "space u", show_snapshots,
@@ -426,6 +450,19 @@ pub(crate) fn normal() -> KeymapLayer {
         "space f", format,
         "space e", show_diagnostics,
         "space d", diagnostics_to_locations,
+        "space p", paste_from_register,
+
+        "space w v", split_vertical,
+        "space w s", split_horizontal,
+        "space w c", close_split,
+        "space w h", focus_split_left,
+        "space w l", focus_split_right,
+        "space w k", focus_split_up,
+        "space w j", focus_split_down,
+        "space w >", grow_split_width,
+        "space w <", shrink_split_width,
+        "space w +", grow_split_height,
+        "space w -", shrink_split_height,
 
         "backspace", goto_prev_buffer,
 
@@ -452,7 +489,19 @@ pub(crate) fn normal() -> KeymapLayer {
         "s '", select_single,
         "s `", select_backtick,
         "s p", select_paragraph,
+        "s e", select_sentence,
+        "s E", select_sentence_incl,
         "s w", select_word,
+        "s f", select_function,
+        "s F", select_function_incl,
+        "s t", select_class,
+        "s T", select_class_incl,
+        "s g", select_argument,
+        "s G", select_argument_incl,
+        "s n", select_comment,
+        "s N", select_comment_incl,
+        "s q", select_string,
+        "s Q", select_string_incl,
 
         "f5", reload_window,
     );
@@ -504,17 +553,25 @@ pub(crate) fn select() -> KeymapLayer {
         "d", remove_cursor_selections,
         "c", change_cursor_selections,
         "y", copy,
+        "alt+v", start_block_selection,
         "esc", normal_mode,
         "§", normal_mode,
         ".", swap_selection_dir,
 
         "g", nop,
         "g c", toggle_comment_lines,
+        "g q", reflow,
+        "alt+a", increment,
+        "alt+x", decrement,
+        "m s", surround_selections,
+        "m r", change_surrounding,
+        "m d", delete_surrounding,
         "I", cursors_to_lines_start,
         "A", cursors_to_lines_end,
         "s", select_pattern,
         "u", lowercase,
         "U", uppercase,
+        "~", cycle_case,
         "r", rotate_selections,
         "R", rotate_selections_backwards,
         "-", cursor_trim_whitespace,
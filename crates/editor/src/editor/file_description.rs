@@ -1,19 +1,29 @@
 use std::{
     fs,
-    io::{self},
+    io::{self, Read as _},
     path::{Path, PathBuf},
 };
 
 use sanedit_core::Language;
 
-use super::config::Config;
+use super::{
+    buffers::{detect_codec, is_binary, open_decoder, Codec, Encoding, DETECT_SAMPLE_SIZE},
+    config::Config,
+};
 
 #[derive(Debug)]
 pub struct FileDescription {
     pub(crate) absolute_path: PathBuf,
+    /// Whether the file is big, for compressed files this is determined
+    /// using the decompressed size if the file records it
     pub(crate) is_big: bool,
     pub(crate) read_only: bool,
     pub(crate) language: Option<Language>,
+    /// Detected encoding, if detection is enabled
+    pub(crate) encoding: Option<Encoding>,
+    pub(crate) is_binary: bool,
+    /// Compression codec, if the file is compressed
+    pub(crate) codec: Option<&'static dyn Codec>,
 }
 
 impl FileDescription {
@@ -26,19 +36,59 @@ impl FileDescription {
             return Self::new_empty(&path, config);
         }
 
-        let file = fs::File::open(&path)?;
+        let mut file = fs::File::open(&path)?;
         let metadata = file.metadata()?;
         let size = metadata.len();
 
-        let is_big = config.editor.big_file_threshold_bytes <= size;
+        let threshold = config.editor.big_file_threshold_bytes;
+        let mut is_big = threshold <= size;
         let read_only = metadata.permissions().readonly();
-        let lang = Language::determine(&path, &config.editor.language_detect);
+
+        let mut sample = Vec::with_capacity(DETECT_SAMPLE_SIZE.min(size as usize));
+        file.by_ref()
+            .take(DETECT_SAMPLE_SIZE as u64)
+            .read_to_end(&mut sample)?;
+        let mut complete = sample.len() as u64 == size;
+
+        // Detect the rest from the decompressed contents
+        let codec = detect_codec(&sample);
+        if let Some(codec) = codec {
+            sample.clear();
+            let decoder = open_decoder(&path, codec)?;
+            decoder
+                .take(DETECT_SAMPLE_SIZE as u64 + 1)
+                .read_to_end(&mut sample)?;
+            complete = sample.len() <= DETECT_SAMPLE_SIZE;
+            sample.truncate(DETECT_SAMPLE_SIZE);
+            // Decompressed size is at least the compressed size in practice,
+            // broken headers are reported when decompressing
+            let hint = codec.decompressed_len_hint(&mut file).ok().flatten();
+            is_big = !complete && (is_big || hint.is_some_and(|len| threshold <= len));
+        }
+
+        // Detect language of compressed files without the compression extension
+        let lang_path = match codec {
+            Some(_) => path.with_extension(""),
+            None => path.clone(),
+        };
+        let lang = Language::determine(&lang_path, &config.editor.language_detect);
+
+        let is_binary = is_binary(&sample);
+        // Big files are not transcoded, so no need to detect
+        let encoding = if config.editor.detect_encoding && !is_big {
+            Some(Encoding::detect(&sample, complete))
+        } else {
+            None
+        };
 
         let file_metadata = FileDescription {
             absolute_path: path,
             is_big,
             read_only,
             language: lang,
+            encoding,
+            is_binary,
+            codec,
         };
 
         Ok(file_metadata)
@@ -51,6 +101,9 @@ impl FileDescription {
             is_big: false,
             read_only: false, // TODO can write here?
             language: lang,
+            encoding: None,
+            is_binary: false,
+            codec: None,
         };
 
         Ok(file_metadata)
@@ -71,4 +124,16 @@ impl FileDescription {
     pub fn is_big(&self) -> bool {
         self.is_big
     }
+
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    pub fn is_binary(&self) -> bool {
+        self.is_binary
+    }
+
+    pub fn codec(&self) -> Option<&'static dyn Codec> {
+        self.codec
+    }
 }
@@ -38,6 +38,10 @@ pub(crate) enum Hook {
     /// After buffer is closed, and will be removed
     BufDeletedPre(BufferId),
 
+    /// After buffer contents are replaced while it stays open, such as when
+    /// a compressed file has been decompressed
+    BufReloaded(BufferId),
+
     /// Before client message is processed
     OnMessagePre,
 
@@ -82,7 +86,8 @@ impl Hook {
             | Hook::BufLeave(id)
             | Hook::BufEnter(id)
             | Hook::BufChanged(id)
-            | Hook::BufDeletedPre(id) => Some(*id),
+            | Hook::BufDeletedPre(id)
+            | Hook::BufReloaded(id) => Some(*id),
             _ => None,
         }
     }
@@ -173,9 +178,18 @@ impl Default for Hooks {
         hooks.register(ModeEnter, window::run_keymap_mode_enter);
         hooks.register(ModeLeave, window::on_insert_mode_leave);
         hooks.register(ModeLeave, window::run_keymap_mode_leave);
+        hooks.register(ModeLeave, cursors::stop_block_selection);
         hooks.register(ModeEnter, window::view_to_cursor);
         hooks.register(OnFocusChanged, snapshots::toggle_preview);
 
+        hooks.register(BufCreated, text::index_lines);
+        hooks.register(BufReloaded, text::index_lines);
+        hooks.register(BufSavedPost, text::persist_undo_history_in_background);
+        hooks.register(BufDeletedPre, text::persist_undo_history);
+        hooks.register(BufEnter, text::recover_unsaved_changes);
+        hooks.register(BufChanged, text::schedule_journal_flush);
+        hooks.register(BufChanged, text::schedule_compaction);
+        hooks.register(BufSavedPost, text::schedule_compaction);
         hooks.register(BufEnter, text::check_file_modification);
         hooks.register(WindowFocus, text::check_file_modification);
 
@@ -183,6 +197,8 @@ impl Default for Hooks {
         hooks.register(BufChanged, completion::send_word);
         hooks.register(BufCreated, indent::detect_indent);
         hooks.register(BufCreated, indent::detect_eol);
+        hooks.register(BufReloaded, indent::detect_indent);
+        hooks.register(BufReloaded, indent::detect_eol);
         hooks.register(CursorMoved, popup::close);
         hooks.register(BufChanged, popup::close);
         hooks.register(ModeEnter, popup::close);
@@ -201,11 +217,16 @@ impl Default for Hooks {
         hooks.register(BufCreated, lsp::open_document);
         hooks.register(BufChanged, lsp::sync_document);
         hooks.register(BufDeletedPre, lsp::close_document);
+        hooks.register(BufReloaded, lsp::reload_document);
         hooks.register(BufSavedPre, lsp::will_save_document);
         hooks.register(BufSavedPost, lsp::did_save_document);
 
         // Buffer
         // hooks.register(BufChanged, text::clear_diagnostics);
+        hooks.register(BufCreated, signs::update_vcs_signs);
+        hooks.register(BufReloaded, signs::update_vcs_signs);
+        hooks.register(BufChanged, signs::schedule_vcs_signs);
+        hooks.register(BufSavedPost, signs::reload_vcs_signs);
 
         hooks
     }
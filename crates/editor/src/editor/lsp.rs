@@ -3,6 +3,7 @@ use std::{
     sync::atomic::{AtomicU32, Ordering},
 };
 
+use sanedit_buffer::{Gravity, RangeMark, RangeMarkResult};
 use sanedit_core::Diagnostic;
 use sanedit_lsp::{
     LSPClientSender, LSPRequestError, Notification, PositionEncoding, Request, RequestKind,
@@ -27,10 +28,65 @@ pub(crate) enum Constraint {
 
 #[derive(Debug)]
 enum DiagnosticList {
-    Resolved(SortedVec<Diagnostic>),
+    Resolved(MarkedDiagnostics),
     Unresolved(Vec<sanedit_lsp::TextDiagnostic>),
 }
 
+/// Diagnostics marked in a buffer, so they follow the edits made to the
+/// buffer until the server sends new ones
+#[derive(Debug)]
+struct MarkedDiagnostics {
+    /// Buffer version the diagnostics were last resolved at
+    version: u32,
+    marks: Vec<RangeMark>,
+    /// Diagnostics in the same order as the marks
+    marked: Vec<Diagnostic>,
+    diagnostics: SortedVec<Diagnostic>,
+}
+
+impl MarkedDiagnostics {
+    fn new(buf: &Buffer, diagnostics: Vec<Diagnostic>) -> MarkedDiagnostics {
+        let marks = diagnostics
+            .iter()
+            .map(|diag| {
+                let range = diag.range();
+                buf.mark_range(range.start..range.end, Gravity::Right, Gravity::Left)
+            })
+            .collect();
+
+        MarkedDiagnostics {
+            version: buf.total_changes_made(),
+            marks,
+            diagnostics: SortedVec::from(diagnostics.clone()),
+            marked: diagnostics,
+        }
+    }
+
+    /// Move the diagnostics to where the marked text is now, diagnostics
+    /// whose text has been deleted are removed
+    fn update(&mut self, buf: &Buffer) {
+        if self.version == buf.total_changes_made() {
+            return;
+        }
+
+        let ranges = buf.range_marks_to_ranges(&self.marks);
+        self.diagnostics = self
+            .marked
+            .iter()
+            .zip(ranges)
+            .filter_map(|(diag, result)| {
+                let RangeMarkResult::Found(range) = result else {
+                    return None;
+                };
+                let mut diag = diag.clone();
+                diag.set_range(range.into());
+                Some(diag)
+            })
+            .collect();
+        self.version = buf.total_changes_made();
+    }
+}
+
 /// A handle to send operations to LSP instance.
 ///
 /// LSP is running in a job slot and communicates back using messages.
@@ -138,8 +194,9 @@ impl Lsp {
         let diagnostics = self.diagnostics.get_mut(path)?;
         if let DiagnosticList::Unresolved(text_diagnostics) = diagnostics {
             let slice = buf.slice(..);
+            let len = slice.len();
             let converted_diags = text_diagnostics
-                .into_iter()
+                .iter()
                 .map(|d| {
                     let start;
                     let end;
@@ -150,15 +207,17 @@ impl Lsp {
                         start = d.range.start.to_offset(&slice, &enc);
                         end = d.range.end.to_offset(&slice, &enc);
                     }
-                    Diagnostic::new(d.severity, (start..end).into(), d.line, &d.description)
+                    let range = start.min(len)..end.min(len);
+                    Diagnostic::new(d.severity, range.into(), d.line, &d.description)
                 })
                 .collect();
 
-            *diagnostics = DiagnosticList::Resolved(converted_diags);
+            *diagnostics = DiagnosticList::Resolved(MarkedDiagnostics::new(buf, converted_diags));
         }
 
         if let DiagnosticList::Resolved(diags) = diagnostics {
-            Some(diags.iter().as_slice())
+            diags.update(buf);
+            Some(diags.diagnostics.iter().as_slice())
         } else {
             None
         }
@@ -0,0 +1,259 @@
+use std::collections::VecDeque;
+
+use sanedit_buffer::utf8::EndOfLine;
+use sanedit_core::copy_cursors_to_lines;
+
+use super::{buffers::BufferId, Map};
+
+/// Maximum number of deletions kept in the kill ring
+const KILL_RING_SIZE: usize = 16;
+
+/// Last search pattern
+pub(crate) const SEARCH_REGISTER: char = '/';
+/// Text inserted during the last insert mode
+pub(crate) const INSERT_REGISTER: char = '.';
+/// Current file name
+pub(crate) const FILE_NAME_REGISTER: char = '%';
+/// System clipboard
+pub(crate) const CLIPBOARD_REGISTER: char = '+';
+
+/// Special registers that are read only
+pub(crate) const SPECIAL_REGISTERS: [char; 4] = [
+    CLIPBOARD_REGISTER,
+    SEARCH_REGISTER,
+    INSERT_REGISTER,
+    FILE_NAME_REGISTER,
+];
+
+/// Whether name can be used as a named register
+pub(crate) fn is_named_register(name: char) -> bool {
+    name.is_ascii_lowercase()
+}
+
+pub(crate) fn is_register(name: char) -> bool {
+    is_named_register(name) || SPECIAL_REGISTERS.contains(&name)
+}
+
+/// How the copied text was selected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum YankKind {
+    /// Selections, pasted at the cursors
+    #[default]
+    Selection,
+    /// Whole lines, pasted on the line below
+    Line,
+    /// Rectangular block, rows are pasted on consecutive lines
+    Block,
+}
+
+/// Copied text, one entry per cursor
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Yank {
+    pub texts: Vec<String>,
+    pub kind: YankKind,
+}
+
+impl Yank {
+    pub fn new(texts: Vec<String>, kind: YankKind) -> Yank {
+        Yank { texts, kind }
+    }
+
+    pub fn from_text(text: String) -> Yank {
+        Yank {
+            texts: vec![text],
+            kind: YankKind::Selection,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.texts.iter().all(String::is_empty)
+    }
+
+    /// Text of all cursors separated by line endings
+    pub fn text(&self, eol: EndOfLine) -> String {
+        let mut text = copy_cursors_to_lines(self.texts.clone(), eol);
+        // Single line ends in a line ending so it is pasted on its own line
+        if self.kind == YankKind::Line && self.texts.len() == 1 {
+            text.push_str(eol.as_str());
+        }
+        text
+    }
+
+    /// Short single line description of the contents
+    pub fn preview(&self) -> String {
+        const MAX_LEN: usize = 60;
+        let text = self.texts.join(" ⏎ ");
+        let mut preview: String = text
+            .chars()
+            .map(|ch| if ch == '\n' || ch == '\r' { '⏎' } else { ch })
+            .take(MAX_LEN)
+            .collect();
+        if text.chars().count() > MAX_LEN {
+            preview.push('…');
+        }
+        preview
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Registers {
+    /// Named registers a-z
+    named: Map<char, Yank>,
+
+    /// Recent deletions, newest first
+    kill_ring: VecDeque<Yank>,
+
+    /// Buffer, its change count after pasting and the kill ring index of the
+    /// last kill ring paste. Used to cycle through older deletions.
+    pub kill_paste: Option<(BufferId, u32, usize)>,
+
+    /// Last yank copied to the system clipboard and the clipboard text it
+    /// produced. Used to restore per cursor contents when pasting it back.
+    clipboard: Option<(String, Yank)>,
+
+    /// Text inserted during the current insert mode
+    insert: String,
+
+    /// Text inserted during the last insert mode
+    last_insert: String,
+}
+
+impl Registers {
+    pub fn get(&self, name: char) -> Option<&Yank> {
+        self.named.get(&name)
+    }
+
+    /// Set a named register, returns false if name is not a named register
+    pub fn set(&mut self, name: char, yank: Yank) -> bool {
+        if !is_named_register(name) {
+            return false;
+        }
+
+        self.named.insert(name, yank);
+        true
+    }
+
+    /// Named registers that have contents sorted by name
+    pub fn named(&self) -> Vec<(char, &Yank)> {
+        let mut named: Vec<(char, &Yank)> = self.named.iter().map(|(n, y)| (*n, y)).collect();
+        named.sort_by_key(|(name, _)| *name);
+        named
+    }
+
+    /// Push a deletion to the kill ring
+    pub fn push_kill(&mut self, yank: Yank) {
+        if yank.is_empty() {
+            return;
+        }
+
+        if self.kill_ring.len() >= KILL_RING_SIZE {
+            self.kill_ring.pop_back();
+        }
+        self.kill_ring.push_front(yank);
+    }
+
+    /// Deletion n steps back in the kill ring, wraps around
+    pub fn kill(&self, n: usize) -> Option<&Yank> {
+        if self.kill_ring.is_empty() {
+            return None;
+        }
+
+        self.kill_ring.get(n % self.kill_ring.len())
+    }
+
+    pub fn kills(&self) -> impl Iterator<Item = &Yank> {
+        self.kill_ring.iter()
+    }
+
+    /// Remember a yank copied to the clipboard as text
+    pub fn set_clipboard(&mut self, text: String, yank: Yank) {
+        self.clipboard = Some((text, yank));
+    }
+
+    /// Yank for clipboard contents. If the text was copied from the editor
+    /// the original per cursor contents are returned.
+    pub fn clipboard_yank(&self, text: String) -> Yank {
+        match &self.clipboard {
+            Some((copied, yank)) if *copied == text => yank.clone(),
+            _ => Yank::from_text(text),
+        }
+    }
+
+    /// Record text inserted in insert mode
+    pub fn push_insert(&mut self, text: &str) {
+        self.insert.push_str(text);
+    }
+
+    /// Text before the cursor was removed in insert mode, remove it from
+    /// the recorded text too
+    pub fn pop_insert(&mut self, text: &str) {
+        if let Some(rest) = self.insert.strip_suffix(text) {
+            self.insert.truncate(rest.len());
+        }
+    }
+
+    /// Insert mode ended, keep the inserted text if anything was inserted
+    pub fn finish_insert(&mut self) {
+        if !self.insert.is_empty() {
+            self.last_insert = std::mem::take(&mut self.insert);
+        }
+    }
+
+    pub fn last_insert(&self) -> &str {
+        &self.last_insert
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn yank(text: &str) -> Yank {
+        Yank::from_text(text.into())
+    }
+
+    #[test]
+    fn kill_ring() {
+        let mut regs = Registers::default();
+        assert_eq!(None, regs.kill(0));
+
+        for i in 0..KILL_RING_SIZE + 2 {
+            regs.push_kill(yank(&i.to_string()));
+        }
+        regs.push_kill(yank(""));
+
+        assert_eq!(KILL_RING_SIZE, regs.kills().count());
+        assert_eq!(Some(&yank("17")), regs.kill(0));
+        assert_eq!(Some(&yank("16")), regs.kill(1));
+        // Wraps around to the newest
+        assert_eq!(Some(&yank("17")), regs.kill(KILL_RING_SIZE));
+    }
+
+    #[test]
+    fn clipboard() {
+        let mut regs = Registers::default();
+        let copied = Yank::new(vec!["a".into(), "b".into()], YankKind::Selection);
+        let text = copied.text(EndOfLine::Lf);
+        regs.set_clipboard(text.clone(), copied.clone());
+
+        assert_eq!(copied, regs.clipboard_yank(text));
+        assert_eq!(yank("other"), regs.clipboard_yank("other".into()));
+        assert!(!regs.set('+', copied));
+    }
+
+    #[test]
+    fn insert_with_backspace() {
+        let mut regs = Registers::default();
+        regs.push_insert("hello");
+        regs.pop_insert("o");
+        regs.pop_insert("l");
+        regs.push_insert("p");
+        regs.finish_insert();
+        assert_eq!("help", regs.last_insert());
+
+        // Removing text inserted before does not change the register
+        regs.pop_insert("x");
+        regs.finish_insert();
+        assert_eq!("help", regs.last_insert());
+    }
+}
@@ -0,0 +1,169 @@
+use std::{cell::RefCell, fmt};
+
+use sanedit_buffer::{Mark, PieceTreeSlice};
+use sanedit_core::{Diagnostic, Severity};
+pub(crate) use sanedit_messages::redraw::line_numbers::SignKind;
+
+use super::buffers::Buffer;
+
+/// A marker on the line containing `pos`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Sign {
+    pub pos: u64,
+    pub kind: SignKind,
+}
+
+impl Sign {
+    /// Only the most important sign of a line is shown
+    pub fn priority(&self) -> u8 {
+        match self.kind {
+            SignKind::Hint => 0,
+            SignKind::Info => 1,
+            SignKind::Deleted => 2,
+            SignKind::Added => 3,
+            SignKind::Modified => 4,
+            SignKind::Bookmark => 5,
+            SignKind::Warn => 6,
+            SignKind::Error => 7,
+        }
+    }
+}
+
+/// Lines of a buffer changed since the last commit
+#[derive(Debug, Default)]
+pub(crate) struct VcsChanges {
+    /// File contents in the last commit, None if not fetched yet or the file
+    /// is not tracked
+    pub base: Option<PieceTreeSlice>,
+    /// Whether base has been fetched
+    pub fetched: bool,
+    /// Start of each changed line
+    lines: Vec<(Mark, SignKind)>,
+    /// Signs of the lines resolved at a buffer version, resolving marks is
+    /// too slow to do on every redraw
+    resolved: RefCell<Option<(u32, Vec<Sign>)>>,
+}
+
+impl VcsChanges {
+    pub fn set_lines(&mut self, lines: Vec<(Mark, SignKind)>) {
+        self.lines = lines;
+        self.resolved = RefCell::new(None);
+    }
+
+    /// Signs of the changed lines at the current buffer version
+    fn signs(&self, buf: &Buffer) -> Vec<Sign> {
+        let version = buf.total_changes_made();
+        let mut resolved = self.resolved.borrow_mut();
+        match resolved.as_ref() {
+            Some((at, signs)) if *at == version => signs.clone(),
+            _ => {
+                let signs: Vec<Sign> = self
+                    .lines
+                    .iter()
+                    .map(|(mark, kind)| Sign {
+                        pos: buf.mark_to_pos(mark).pos(),
+                        kind: *kind,
+                    })
+                    .collect();
+                *resolved = Some((version, signs.clone()));
+                signs
+            }
+        }
+    }
+}
+
+/// What providers can use to create signs
+pub(crate) struct SignContext<'a> {
+    pub buf: &'a Buffer,
+    pub diagnostics: Option<&'a [Diagnostic]>,
+}
+
+/// Source of signs shown in the sign column
+pub(crate) trait SignProvider: fmt::Debug {
+    /// Push signs of the buffer into `signs`, in any order
+    fn signs(&self, ctx: &SignContext, signs: &mut Vec<Sign>);
+}
+
+#[derive(Debug)]
+struct DiagnosticSigns;
+
+impl SignProvider for DiagnosticSigns {
+    fn signs(&self, ctx: &SignContext, signs: &mut Vec<Sign>) {
+        let Some(diagnostics) = ctx.diagnostics else {
+            return;
+        };
+
+        signs.extend(diagnostics.iter().map(|diag| Sign {
+            pos: diag.range().start,
+            kind: match diag.severity() {
+                Severity::Hint => SignKind::Hint,
+                Severity::Info => SignKind::Info,
+                Severity::Warn => SignKind::Warn,
+                Severity::Error => SignKind::Error,
+            },
+        }));
+    }
+}
+
+#[derive(Debug)]
+struct VcsSigns;
+
+impl SignProvider for VcsSigns {
+    fn signs(&self, ctx: &SignContext, signs: &mut Vec<Sign>) {
+        let buf = ctx.buf;
+        signs.extend(buf.vcs.signs(buf));
+    }
+}
+
+#[derive(Debug)]
+struct BookmarkSigns;
+
+impl SignProvider for BookmarkSigns {
+    fn signs(&self, ctx: &SignContext, signs: &mut Vec<Sign>) {
+        let buf = ctx.buf;
+        signs.extend(buf.bookmarks.iter().map(|mark| Sign {
+            pos: buf.mark_to_pos(mark).pos(),
+            kind: SignKind::Bookmark,
+        }));
+    }
+}
+
+/// Registered sign providers
+#[derive(Debug)]
+pub(crate) struct SignProviders {
+    providers: Vec<Box<dyn SignProvider>>,
+}
+
+impl Default for SignProviders {
+    fn default() -> Self {
+        let mut providers = SignProviders { providers: vec![] };
+        providers.register(DiagnosticSigns);
+        providers.register(VcsSigns);
+        providers.register(BookmarkSigns);
+        providers
+    }
+}
+
+impl SignProviders {
+    pub fn register<P: SignProvider + 'static>(&mut self, provider: P) {
+        self.providers.push(Box::new(provider));
+    }
+
+    /// Signs from all providers sorted by position
+    pub fn collect(&self, ctx: &SignContext) -> Vec<Sign> {
+        let mut signs = vec![];
+        for provider in &self.providers {
+            provider.signs(ctx, &mut signs);
+        }
+
+        let len = ctx.buf.len();
+        for sign in &mut signs {
+            // Signs past the end are shown on the last line
+            if sign.pos >= len {
+                sign.pos = len.saturating_sub(1);
+            }
+        }
+        signs.sort_by_key(|sign| sign.pos);
+        signs
+    }
+}
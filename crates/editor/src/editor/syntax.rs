@@ -1,5 +1,5 @@
 use std::{
-    cmp::{min, Ordering},
+    cmp::{max, min, Ordering},
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
@@ -159,9 +159,14 @@ impl LanguageLoader for SyntaxLoader {
 
 const COMPLETION_ANNOTATION: &str = "completion";
 const HIGHLIGHT_ANNOTATION: &str = "highlight";
+const TEXT_OBJECT_ANNOTATION: &str = "textobject";
+const TEXT_OBJECT_INSIDE_ANNOTATION: &str = "textobject-inside";
+const FOLD_ANNOTATION: &str = "fold";
 pub const HORIZON_TOP: u64 = 1024 * 8;
 pub const HORIZON_TOP_MIN: u64 = 2048;
 pub const HORIZON_BOTTOM: u64 = 1024 * 16;
+/// How far around cursors to parse when searching for text objects
+pub const TEXT_OBJECT_HORIZON: u64 = 1024 * 64;
 
 #[derive(Debug, Clone)]
 pub struct Syntax {
@@ -263,6 +268,126 @@ impl Syntax {
         })
     }
 
+    /// Parse a range of the buffer and return text objects of a kind found
+    /// in it. Text objects are rules annotated with `@textobject(kind)`, and
+    /// their insides are rules annotated with `@textobject-inside(kind)`.
+    pub fn text_objects(
+        &self,
+        pt: &PieceTreeSlice,
+        range: BufferRange,
+        kind: &str,
+    ) -> anyhow::Result<Vec<TextObject>> {
+        let start = range.start;
+        let slice = pt.slice(range);
+        let source = PieceTreeSliceSource::new(&slice)?;
+        let captures: Captures = self.parser.parse(source)?;
+        let mut objects = Self::to_text_objects(start, &self.parser, captures.captures, kind);
+
+        let mut stack = captures.injections;
+        while let Some((lang, captures)) = stack.pop() {
+            stack.extend(captures.injections);
+            let loader = self.parser.loader.as_ref().unwrap();
+            let parser = loader.get(&lang).unwrap();
+            objects.extend(Self::to_text_objects(
+                start,
+                &parser,
+                captures.captures,
+                kind,
+            ));
+        }
+
+        Ok(objects)
+    }
+
+    /// Parse a range of the buffer and return the ranges of rules annotated
+    /// with `@fold` found in it
+    pub fn fold_ranges(
+        &self,
+        pt: &PieceTreeSlice,
+        range: BufferRange,
+    ) -> anyhow::Result<Vec<BufferRange>> {
+        let start = range.start;
+        let slice = pt.slice(range);
+        let source = PieceTreeSliceSource::new(&slice)?;
+        let captures: Captures = self.parser.parse(source)?;
+        let mut folds = Self::to_fold_ranges(start, &self.parser, captures.captures);
+
+        let mut stack = captures.injections;
+        while let Some((lang, captures)) = stack.pop() {
+            stack.extend(captures.injections);
+            let loader = self.parser.loader.as_ref().unwrap();
+            let parser = loader.get(&lang).unwrap();
+            folds.extend(Self::to_fold_ranges(start, &parser, captures.captures));
+        }
+
+        Ok(folds)
+    }
+
+    fn to_fold_ranges(start: u64, parser: &Parser, captures: Vec<Capture>) -> Vec<BufferRange> {
+        captures
+            .iter()
+            .filter(|cap| {
+                parser
+                    .annotations_for(cap.id())
+                    .iter()
+                    .any(|ann| matches!(ann, Annotation::Other(name, _) if name == FOLD_ANNOTATION))
+            })
+            .map(|cap| {
+                let mut range: BufferRange = cap.range().into();
+                range.forward(start);
+                range
+            })
+            .collect()
+    }
+
+    fn to_text_objects(
+        start: u64,
+        parser: &Parser,
+        captures: Vec<Capture>,
+        kind: &str,
+    ) -> Vec<TextObject> {
+        let has_annotation = |cap: &Capture, annotation: &str| {
+            parser
+                .annotations_for(cap.id())
+                .iter()
+                .any(|ann| match ann {
+                    Annotation::Other(name, spec) => {
+                        name == annotation && spec.as_deref() == Some(kind)
+                    }
+                    _ => false,
+                })
+        };
+        let to_range = |cap: &Capture| -> BufferRange {
+            let mut range: BufferRange = cap.range().into();
+            range.forward(start);
+            range
+        };
+
+        let insides: Vec<BufferRange> = captures
+            .iter()
+            .filter(|cap| has_annotation(cap, TEXT_OBJECT_INSIDE_ANNOTATION))
+            .map(to_range)
+            .collect();
+
+        captures
+            .iter()
+            .filter(|cap| has_annotation(cap, TEXT_OBJECT_ANNOTATION))
+            .map(|cap| {
+                let range = to_range(cap);
+                // Inside spans all the insides within the object, or the
+                // whole object if it has none
+                let mut inside: Option<BufferRange> = None;
+                for ins in insides.iter().filter(|ins| range.includes(*ins)) {
+                    let merged = inside.get_or_insert(*ins);
+                    merged.start = min(merged.start, ins.start);
+                    merged.end = max(merged.end, ins.end);
+                }
+                let inside = inside.unwrap_or(range);
+                TextObject { range, inside }
+            })
+            .collect()
+    }
+
     pub fn to_spans(start: u64, parser: &Parser, captures: Vec<Capture>) -> SortedVec<Span> {
         captures
             .into_iter()
@@ -330,6 +455,24 @@ fn style_from_color(text: &str) -> Option<Style> {
     })
 }
 
+/// Text object defined by the syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextObject {
+    /// The whole object
+    pub range: BufferRange,
+    /// Contents of the object, for example a function body without the
+    /// braces
+    pub inside: BufferRange,
+}
+
+/// Find the smallest text object containing a position
+pub fn text_object_at(objects: &[TextObject], pos: u64) -> Option<&TextObject> {
+    objects
+        .iter()
+        .filter(|obj| obj.range.contains(&pos))
+        .min_by_key(|obj| obj.range.len())
+}
+
 #[derive(Debug, Default)]
 pub struct SyntaxResult {
     pub buffer_range: BufferRange,
@@ -424,3 +567,250 @@ impl Span {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use sanedit_buffer::PieceTree;
+
+    use super::*;
+
+    const RUST: &str = r#"impl Foo {
+    /// Documentation
+    pub fn bar(&self, a: HashMap<u8, u8>, b: u32) -> u32 {
+        let s = "hello";
+        call(a, [1, 2], b)
+    }
+}
+"#;
+
+    fn text_object(pt: &PieceTree, needle: &str, kind: &str) -> Option<(String, String)> {
+        let peg = include_str!("../../../../runtime/language/rust/syntax.peg");
+        text_object_in(peg, pt, needle, kind)
+    }
+
+    fn text_object_in(
+        peg: &str,
+        pt: &PieceTree,
+        needle: &str,
+        kind: &str,
+    ) -> Option<(String, String)> {
+        let syntax = Syntax {
+            parser: Arc::new(Parser::new(peg.as_bytes()).unwrap()),
+        };
+        let slice = pt.slice(..);
+        let objects = syntax
+            .text_objects(&slice, Range::from(0..pt.len()), kind)
+            .unwrap();
+        let pos = String::from(&slice).find(needle).unwrap() as u64;
+        let obj = text_object_at(&objects, pos)?;
+        let range = String::from(&pt.slice(obj.range));
+        let inside = String::from(&pt.slice(obj.inside));
+        Some((range, inside))
+    }
+
+    #[test]
+    fn text_objects() {
+        let pt = PieceTree::from(RUST);
+
+        let (range, inside) = text_object(&pt, "let", "function").unwrap();
+        assert!(range.starts_with("pub fn bar"));
+        assert!(range.ends_with(")\n    }"));
+        assert_eq!(inside, range[range.find('{').unwrap() + 1..range.len() - 1]);
+
+        let (range, _) = text_object(&pt, "let", "class").unwrap();
+        assert_eq!(range, RUST.trim_end());
+
+        let (range, inside) = text_object(&pt, "HashMap", "argument").unwrap();
+        assert_eq!(range, "a: HashMap<u8, u8>, ");
+        assert_eq!(inside, "a: HashMap<u8, u8>");
+
+        let (range, inside) = text_object(&pt, "2]", "argument").unwrap();
+        assert_eq!(range, "[1, 2], ");
+        assert_eq!(inside, "[1, 2]");
+
+        let (range, inside) = text_object(&pt, "hello", "string").unwrap();
+        assert_eq!(range, "\"hello\"");
+        assert_eq!(inside, "hello");
+
+        let (range, inside) = text_object(&pt, "Documentation", "comment").unwrap();
+        assert_eq!(range, "/// Documentation");
+        assert_eq!(inside, "Documentation");
+
+        assert!(text_object(&pt, "Foo", "function").is_none());
+    }
+
+    #[test]
+    fn text_objects_in_languages() {
+        let c = include_str!("../../../../runtime/language/c/syntax.peg");
+        let cpp = include_str!("../../../../runtime/language/cpp/syntax.peg");
+        let glsl = include_str!("../../../../runtime/language/glsl/syntax.peg");
+        let go = include_str!("../../../../runtime/language/go/syntax.peg");
+        let java = include_str!("../../../../runtime/language/java/syntax.peg");
+        let js = include_str!("../../../../runtime/language/javascript/syntax.peg");
+        let lua = include_str!("../../../../runtime/language/lua/syntax.peg");
+        let python = include_str!("../../../../runtime/language/python/syntax.peg");
+        let sh = include_str!("../../../../runtime/language/shellscript/syntax.peg");
+
+        let c_text = "struct point {\n    int x;\n};\n\nstatic int *add(int a, int b)\n{\n    if (a) {\n        return f(a, b[1]);\n    }\n}\n";
+        let cpp_text = "template <typename T>\nclass Foo : public Bar<T> {\npublic:\n    int get(int a) const override {\n        auto f = [&](int x) { return x; };\n        return f(a);\n    }\n};\n";
+        let glsl_text = "struct Light {\n    vec3 pos;\n};\nvoid main() {\n    gl_FragColor = vec4(1.0, 0.0, 0.0, 1.0);\n}\n";
+        let go_text = "type Point struct {\n\tX int\n}\n\nfunc (p *Point) Add(a int, b []int) (int, error) {\n\tfn := func(x int) int {\n\t\treturn x\n\t}\n\treturn fn(a), nil\n}\n";
+        let java_text = "public class Foo extends Bar {\n    @Override\n    public List<String> run(int a, String[] b) throws IOException {\n        if (a > 0) {\n            list.forEach(x -> {\n                print(x);\n            });\n        }\n        return null;\n    }\n}\n";
+        let js_text = "export class Foo extends Bar {\n  async run(a, b = {}) {\n    const f = (x) => {\n      return x;\n    };\n    return f(a, [1, 2]);\n  }\n}\n\nfunction add(a, b) {\n  return a + b;\n}\n";
+        let lua_text = "local function add(a, b)\n  if a then\n    return a\n  elseif b then\n    return b\n  end\n  for i = 1, 2 do\n    print(i)\n  end\n  return f(a, { 1, 2 })\nend\n";
+        let python_text = "class Foo(Bar):\n    @property\n    def run(self, a, b=[1, 2]):\n        def inner(x):\n            return x\n\n        return inner(a)\n\n    x = 1\n\n\ndef add(a, b):\n    return a + b\n";
+        let sh_text = "function greet() {\n    if [ -n \"$1\" ]; then\n        echo \"${1}\"\n    fi\n}\n\nadd() {\n    echo $(( $1 + $2 ))\n}\n";
+
+        // Language, text, needle, kind, expected range and inside
+        let cases = [
+            (c, c_text, "return", "function", "static int *add(int a, int b)\n{\n    if (a) {\n        return f(a, b[1]);\n    }\n}", "\n    if (a) {\n        return f(a, b[1]);\n    }\n"),
+            (c, c_text, "int x", "class", "struct point {\n    int x;\n}", "\n    int x;\n"),
+            (c, c_text, "int b", "argument", "int b", "int b"),
+            (c, c_text, "b[1]", "argument", "b[1]", "b[1]"),
+            (cpp, cpp_text, "return f", "function", "int get(int a) const override {\n        auto f = [&](int x) { return x; };\n        return f(a);\n    }", "\n        auto f = [&](int x) { return x; };\n        return f(a);\n    "),
+            (cpp, cpp_text, "return x", "function", "[&](int x) { return x; }", " return x; "),
+            (cpp, cpp_text, "public:", "class", &cpp_text[..cpp_text.len() - 2], "\npublic:\n    int get(int a) const override {\n        auto f = [&](int x) { return x; };\n        return f(a);\n    }\n"),
+            (glsl, glsl_text, "gl_", "function", "void main() {\n    gl_FragColor = vec4(1.0, 0.0, 0.0, 1.0);\n}", "\n    gl_FragColor = vec4(1.0, 0.0, 0.0, 1.0);\n"),
+            (glsl, glsl_text, "pos", "class", "struct Light {\n    vec3 pos;\n}", "\n    vec3 pos;\n"),
+            (glsl, glsl_text, "0.0, 1", "argument", "0.0, ", "0.0"),
+            (go, go_text, "nil", "function", "func (p *Point) Add(a int, b []int) (int, error) {\n\tfn := func(x int) int {\n\t\treturn x\n\t}\n\treturn fn(a), nil\n}", "\n\tfn := func(x int) int {\n\t\treturn x\n\t}\n\treturn fn(a), nil\n"),
+            (go, go_text, "return x", "function", "func(x int) int {\n\t\treturn x\n\t}", "\n\t\treturn x\n\t"),
+            (go, go_text, "X int", "class", "type Point struct {\n\tX int\n}", "\n\tX int\n"),
+            (go, go_text, "b []", "argument", "b []int", "b []int"),
+            (java, java_text, "null", "function", "@Override\n    public List<String> run(int a, String[] b) throws IOException {\n        if (a > 0) {\n            list.forEach(x -> {\n                print(x);\n            });\n        }\n        return null;\n    }", "\n        if (a > 0) {\n            list.forEach(x -> {\n                print(x);\n            });\n        }\n        return null;\n    "),
+            (java, java_text, "print", "function", "x -> {\n                print(x);\n            }", "\n                print(x);\n            "),
+            (java, java_text, "@Override", "class", java_text.trim_end(), &java_text[java_text.find('{').unwrap() + 1..java_text.len() - 2]),
+            (java, java_text, "String[]", "argument", "String[] b", "String[] b"),
+            (js, js_text, "const", "function", "async run(a, b = {}) {\n    const f = (x) => {\n      return x;\n    };\n    return f(a, [1, 2]);\n  }", "\n    const f = (x) => {\n      return x;\n    };\n    return f(a, [1, 2]);\n  "),
+            (js, js_text, "return x", "function", "(x) => {\n      return x;\n    }", "\n      return x;\n    "),
+            (js, js_text, "a + b", "function", "function add(a, b) {\n  return a + b;\n}", "\n  return a + b;\n"),
+            (js, js_text, "async", "class", "export class Foo extends Bar {\n  async run(a, b = {}) {\n    const f = (x) => {\n      return x;\n    };\n    return f(a, [1, 2]);\n  }\n}", "\n  async run(a, b = {}) {\n    const f = (x) => {\n      return x;\n    };\n    return f(a, [1, 2]);\n  }\n"),
+            (js, js_text, "2]", "argument", "[1, 2]", "[1, 2]"),
+            (lua, lua_text, "print", "function", lua_text.trim_end(), &lua_text["local function add(a, b)".len()..lua_text.len() - 4]),
+            (lua, lua_text, "2 }", "argument", "{ 1, 2 }", "{ 1, 2 }"),
+            (python, python_text, "x = 1", "function", "", ""),
+            (python, python_text, "inner(a)", "function", "    def run(self, a, b=[1, 2]):\n        def inner(x):\n            return x\n\n        return inner(a)", "\n        def inner(x):\n            return x\n\n        return inner(a)"),
+            (python, python_text, "return x", "function", "        def inner(x):\n            return x", "\n            return x"),
+            (python, python_text, "x = 1", "class", "class Foo(Bar):\n    @property\n    def run(self, a, b=[1, 2]):\n        def inner(x):\n            return x\n\n        return inner(a)\n\n    x = 1", "\n    @property\n    def run(self, a, b=[1, 2]):\n        def inner(x):\n            return x\n\n        return inner(a)\n\n    x = 1"),
+            (python, python_text, "a + b", "function", "def add(a, b):\n    return a + b", "\n    return a + b"),
+            (python, python_text, "b=", "argument", "b=[1, 2]", "b=[1, 2]"),
+            (sh, sh_text, "echo \"", "function", "function greet() {\n    if [ -n \"$1\" ]; then\n        echo \"${1}\"\n    fi\n}", "\n    if [ -n \"$1\" ]; then\n        echo \"${1}\"\n    fi\n"),
+            (sh, sh_text, "$2", "function", "add() {\n    echo $(( $1 + $2 ))\n}", "\n    echo $(( $1 + $2 ))\n"),
+        ];
+
+        for (peg, text, needle, kind, range, inside) in cases {
+            let pt = PieceTree::from(text);
+            let found = text_object_in(peg, &pt, needle, kind);
+            let expected = (!range.is_empty()).then(|| (range.to_string(), inside.to_string()));
+            assert_eq!(expected, found, "{kind} at {needle:?} in {text:?}");
+        }
+    }
+
+    fn highlights(peg: &str, text: &str) -> String {
+        let syntax = Syntax {
+            parser: Arc::new(Parser::new(peg.as_bytes()).unwrap()),
+        };
+        let pt = PieceTree::from(text);
+        let result = syntax
+            .parse(&pt.slice(..), Range::from(0..pt.len()), KillSwitch::new())
+            .unwrap();
+        result
+            .highlights
+            .iter()
+            .filter(|span| span.highlight())
+            .map(|span| {
+                let content = &text[span.start() as usize..span.end() as usize];
+                format!(
+                    "{}..{} {} {content:?}\n",
+                    span.start(),
+                    span.end(),
+                    span.name()
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn highlights_in_languages() {
+        // Spans recorded before text objects were added to the grammars, text
+        // object rules should not change highlighting. Glsl now highlights
+        // separators right after names.
+        let cases = [
+            (
+                include_str!("../../../../runtime/language/c/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.c"),
+                include_str!("../../testdata/highlights/sample.c.spans"),
+            ),
+            (
+                include_str!("../../../../runtime/language/cpp/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.cpp"),
+                include_str!("../../testdata/highlights/sample.cpp.spans"),
+            ),
+            (
+                include_str!("../../../../runtime/language/glsl/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.glsl"),
+                include_str!("../../testdata/highlights/sample.glsl.spans"),
+            ),
+            (
+                include_str!("../../../../runtime/language/go/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.go"),
+                include_str!("../../testdata/highlights/sample.go.spans"),
+            ),
+            (
+                include_str!("../../../../runtime/language/java/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.java"),
+                include_str!("../../testdata/highlights/sample.java.spans"),
+            ),
+            (
+                include_str!("../../../../runtime/language/javascript/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.js"),
+                include_str!("../../testdata/highlights/sample.js.spans"),
+            ),
+            (
+                include_str!("../../../../runtime/language/lua/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.lua"),
+                include_str!("../../testdata/highlights/sample.lua.spans"),
+            ),
+            (
+                include_str!("../../../../runtime/language/python/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.py"),
+                include_str!("../../testdata/highlights/sample.py.spans"),
+            ),
+            (
+                include_str!("../../../../runtime/language/rust/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.rs"),
+                include_str!("../../testdata/highlights/sample.rs.spans"),
+            ),
+            (
+                include_str!("../../../../runtime/language/shellscript/syntax.peg"),
+                include_str!("../../testdata/highlights/sample.sh"),
+                include_str!("../../testdata/highlights/sample.sh.spans"),
+            ),
+        ];
+
+        for (peg, text, expected) in cases {
+            assert_eq!(expected, highlights(peg, text), "in {text:?}");
+        }
+    }
+
+    #[test]
+    fn fold_ranges() {
+        let peg = include_str!("../../../../runtime/language/rust/syntax.peg");
+        let syntax = Syntax {
+            parser: Arc::new(Parser::new(peg.as_bytes()).unwrap()),
+        };
+        let pt = PieceTree::from(RUST);
+        let slice = pt.slice(..);
+        let folds: Vec<String> = syntax
+            .fold_ranges(&slice, Range::from(0..pt.len()))
+            .unwrap()
+            .into_iter()
+            .map(|range| String::from(&slice.slice(range)))
+            .collect();
+        assert!(folds.iter().any(|fold| fold == RUST.trim_end()));
+        assert!(folds
+            .iter()
+            .any(|fold| fold.starts_with("pub fn bar") && fold.ends_with(")\n    }")));
+        assert!(folds.iter().any(|fold| fold == "/// Documentation"));
+    }
+}
@@ -1,12 +1,64 @@
+mod layout;
 mod window;
 
-use super::{buffers::BufferId, Map};
+use super::{
+    buffers::{Buffer, BufferId, Buffers},
+    signs::Sign,
+    Map,
+};
+pub(crate) use layout::*;
+use sanedit_messages::redraw::{panes::PaneRect, Point, Size};
 use sanedit_server::ClientId;
 pub(crate) use window::*;
 
+/// Panes of a client
+#[derive(Debug, Default)]
+pub(crate) struct ClientLayout {
+    layout: Layout,
+    focused: PaneId,
+    /// Windows of the panes that are not focused
+    inactive: Map<PaneId, Window>,
+    /// Size of the whole window area
+    size: Size,
+}
+
+impl ClientLayout {
+    fn new(size: Size) -> ClientLayout {
+        ClientLayout {
+            size,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.layout.is_split()
+    }
+
+    pub fn focused_rect(&self) -> PaneRect {
+        self.rects()
+            .into_iter()
+            .find(|(id, _)| *id == self.focused)
+            .map(|(_, rect)| rect)
+            .unwrap_or_default()
+    }
+
+    /// Windows that are not focused and their rectangles
+    pub fn inactive(&self) -> impl Iterator<Item = (PaneRect, &Window)> {
+        self.rects()
+            .into_iter()
+            .filter_map(|(id, rect)| Some((rect, self.inactive.get(&id)?)))
+    }
+
+    fn rects(&self) -> Vec<(PaneId, PaneRect)> {
+        self.layout.rects(self.size)
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct Windows {
+    /// Focused window of each client
     windows: Map<ClientId, Window>,
+    layouts: Map<ClientId, ClientLayout>,
 }
 
 impl Windows {
@@ -20,6 +72,8 @@ impl Windows {
     ) -> &mut Window {
         let win = Window::new(buf, width, height, options);
         self.windows.insert(id, win);
+        self.layouts
+            .insert(id, ClientLayout::new(Size { width, height }));
         self.get_mut(id).unwrap()
     }
 
@@ -31,12 +85,13 @@ impl Windows {
         self.windows.get_mut(&id)
     }
 
-    pub fn remove(&mut self, id: ClientId) -> Option<Window> {
-        self.windows.remove(&id)
-    }
-
-    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, ClientId, Window> {
-        self.windows.iter()
+    /// Remove all windows of a client
+    pub fn remove(&mut self, id: ClientId) -> Vec<Window> {
+        let mut windows: Vec<Window> = self.windows.remove(&id).into_iter().collect();
+        if let Some(layout) = self.layouts.remove(&id) {
+            windows.extend(layout.inactive.into_values());
+        }
+        windows
     }
 
     pub fn find_clients_with_buf(&self, bid: BufferId) -> Vec<ClientId> {
@@ -52,4 +107,195 @@ impl Windows {
         let bid = win.buffer_id();
         Some(bid)
     }
+
+    pub fn layout(&self, id: ClientId) -> Option<&ClientLayout> {
+        self.layouts.get(&id)
+    }
+
+    /// Whether any window, focused or not, shows the buffer
+    pub fn is_buffer_shown(&self, bid: BufferId) -> bool {
+        !self.find_clients_showing_buf(bid).is_empty()
+    }
+
+    /// Clients that show the buffer in any of their windows
+    pub fn find_clients_showing_buf(&self, bid: BufferId) -> Vec<ClientId> {
+        let mut clients = self.find_clients_with_buf(bid);
+        for (cid, layout) in &self.layouts {
+            let shown = layout.inactive.values().any(|win| win.buffer_id() == bid);
+            if shown && !clients.contains(cid) {
+                clients.push(*cid);
+            }
+        }
+        clients
+    }
+
+    /// Windows of panes that are not focused showing the buffer
+    pub fn inactive_with_buf_mut(&mut self, bid: BufferId) -> impl Iterator<Item = &mut Window> {
+        self.layouts
+            .values_mut()
+            .flat_map(|layout| layout.inactive.values_mut())
+            .filter(move |win| win.buffer_id() == bid)
+    }
+
+    /// Window of a pane, focused or not
+    pub fn pane_mut(&mut self, id: ClientId, pane: PaneId) -> Option<&mut Window> {
+        if self.focused_pane(id)? == pane {
+            self.windows.get_mut(&id)
+        } else {
+            self.inactive_mut(id, pane)
+        }
+    }
+
+    /// Window of a pane that is not focused
+    pub fn inactive_mut(&mut self, id: ClientId, pane: PaneId) -> Option<&mut Window> {
+        self.layouts.get_mut(&id)?.inactive.get_mut(&pane)
+    }
+
+    /// Panes that are not focused showing the buffer
+    pub fn inactive_panes_with_buf(&self, bid: BufferId) -> Vec<(ClientId, PaneId)> {
+        let mut panes = vec![];
+        for (cid, layout) in &self.layouts {
+            for (pane, win) in &layout.inactive {
+                if win.buffer_id() == bid {
+                    panes.push((*cid, *pane));
+                }
+            }
+        }
+        panes
+    }
+
+    /// Split the focused pane and focus the new pane showing `win`
+    pub fn split(&mut self, id: ClientId, dir: SplitDirection, win: Window) -> bool {
+        let Some(layout) = self.layouts.get_mut(&id) else {
+            return false;
+        };
+        let Some(pane) = layout.layout.split(layout.focused, dir) else {
+            return false;
+        };
+
+        if let Some(old) = self.windows.insert(id, win) {
+            layout.inactive.insert(layout.focused, old);
+        }
+        layout.focused = pane;
+        true
+    }
+
+    /// Focus a pane, returns false if there is no such pane
+    pub fn focus_pane(&mut self, id: ClientId, pane: PaneId) -> bool {
+        let Some(layout) = self.layouts.get_mut(&id) else {
+            return false;
+        };
+        if layout.focused == pane {
+            return true;
+        }
+        let Some(win) = layout.inactive.remove(&pane) else {
+            return false;
+        };
+
+        if let Some(old) = self.windows.insert(id, win) {
+            layout.inactive.insert(layout.focused, old);
+        }
+        layout.focused = pane;
+        true
+    }
+
+    /// Pane next to the focused one in a direction
+    pub fn neighbour(&self, id: ClientId, dir: Direction) -> Option<PaneId> {
+        let layout = self.layouts.get(&id)?;
+        layout.layout.neighbour(layout.focused, dir, layout.size)
+    }
+
+    /// Focused pane of a client
+    pub fn focused_pane(&self, id: ClientId) -> Option<PaneId> {
+        self.layouts.get(&id).map(|layout| layout.focused)
+    }
+
+    /// Pane at a point in the window area, None if the point is on a border
+    pub fn pane_at(&self, id: ClientId, point: &Point) -> Option<(PaneId, PaneRect)> {
+        let layout = self.layouts.get(&id)?;
+        layout
+            .rects()
+            .into_iter()
+            .find(|(_, rect)| rect.contains(point))
+    }
+
+    /// Close the focused pane, the last pane cannot be closed.
+    ///
+    /// Returns the window of the closed pane.
+    pub fn close_pane(&mut self, id: ClientId) -> Option<Window> {
+        let layout = self.layouts.get_mut(&id)?;
+        let pane = layout.layout.remove(layout.focused)?;
+        let win = layout.inactive.remove(&pane)?;
+        layout.focused = pane;
+        self.windows.insert(id, win)
+    }
+
+    /// Grow or shrink the focused pane
+    pub fn resize_pane(&mut self, id: ClientId, dir: SplitDirection, amount: isize) -> bool {
+        let Some(layout) = self.layouts.get_mut(&id) else {
+            return false;
+        };
+        layout
+            .layout
+            .resize(layout.focused, dir, amount, layout.size)
+    }
+
+    /// Set the size of a client's window area and resize all of its windows
+    /// to fit their panes
+    pub fn resize(&mut self, id: ClientId, size: Size, buffers: &Buffers) {
+        let Some(layout) = self.layouts.get_mut(&id) else {
+            return;
+        };
+        layout.size = size;
+        self.update_sizes(id, buffers);
+    }
+
+    /// Resize windows of a client to fit their panes
+    pub fn update_sizes(&mut self, id: ClientId, buffers: &Buffers) {
+        let Some(layout) = self.layouts.get_mut(&id) else {
+            return;
+        };
+
+        for (pane, rect) in layout.rects() {
+            let win = if pane == layout.focused {
+                self.windows.get_mut(&id)
+            } else {
+                layout.inactive.get_mut(&pane)
+            };
+            let Some(win) = win else {
+                continue;
+            };
+            let Some(buf) = buffers.get(win.buffer_id()) else {
+                continue;
+            };
+
+            // Keep atleast one cell to draw to
+            let size = Size {
+                width: rect.width.max(1),
+                height: rect.height.max(1),
+            };
+            if win.size() != size {
+                win.resize(size, buf);
+            }
+        }
+    }
+
+    /// Redraw views of the panes that are not focused
+    pub fn redraw_inactive(
+        &mut self,
+        id: ClientId,
+        buffers: &Buffers,
+        signs: &mut impl FnMut(&Buffer) -> Vec<Sign>,
+    ) {
+        let Some(layout) = self.layouts.get_mut(&id) else {
+            return;
+        };
+
+        for win in layout.inactive.values_mut() {
+            if let Some(buf) = buffers.get(win.buffer_id()) {
+                win.set_signs(signs(buf));
+                win.redraw_view(buf);
+            }
+        }
+    }
 }
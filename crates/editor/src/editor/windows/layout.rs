@@ -0,0 +1,363 @@
+use std::cmp::Reverse;
+
+use sanedit_messages::redraw::{panes::PaneRect, Size};
+
+pub(crate) type PaneId = usize;
+
+/// How a pane is split in two
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SplitDirection {
+    /// Panes side by side
+    Vertical,
+    /// Panes on top of each other
+    Horizontal,
+}
+
+/// Direction to move focus to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Pane(PaneId),
+    Split {
+        dir: SplitDirection,
+        /// Share of the space given to the first node
+        ratio: f64,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    fn contains(&self, pane: PaneId) -> bool {
+        match self {
+            Node::Pane(id) => *id == pane,
+            Node::Split { first, second, .. } => first.contains(pane) || second.contains(pane),
+        }
+    }
+
+    fn first_pane(&self) -> PaneId {
+        match self {
+            Node::Pane(id) => *id,
+            Node::Split { first, .. } => first.first_pane(),
+        }
+    }
+
+    fn rects(&self, rect: PaneRect, rects: &mut Vec<(PaneId, PaneRect)>) {
+        match self {
+            Node::Pane(id) => rects.push((*id, rect)),
+            Node::Split {
+                dir,
+                ratio,
+                first,
+                second,
+            } => {
+                let (frect, srect) = split_rect(rect, *dir, *ratio);
+                first.rects(frect, rects);
+                second.rects(srect, rects);
+            }
+        }
+    }
+}
+
+/// Split rect in two leaving a one cell border between the parts
+fn split_rect(rect: PaneRect, dir: SplitDirection, ratio: f64) -> (PaneRect, PaneRect) {
+    let len = match dir {
+        SplitDirection::Vertical => rect.width,
+        SplitDirection::Horizontal => rect.height,
+    };
+    let avail = len.saturating_sub(1);
+    let mut flen = (avail as f64 * ratio).round() as usize;
+    if avail >= 2 {
+        flen = flen.clamp(1, avail - 1);
+    }
+    let slen = avail.saturating_sub(flen);
+
+    let mut first = rect;
+    let mut second = rect;
+    match dir {
+        SplitDirection::Vertical => {
+            first.width = flen;
+            second.x = rect.x + flen + 1;
+            second.width = slen;
+        }
+        SplitDirection::Horizontal => {
+            first.height = flen;
+            second.y = rect.y + flen + 1;
+            second.height = slen;
+        }
+    }
+
+    (first, second)
+}
+
+/// Tree of panes splitting a client's window area
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Layout {
+    root: Node,
+    next_id: PaneId,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            root: Node::Pane(0),
+            next_id: 1,
+        }
+    }
+}
+
+impl Layout {
+    pub fn is_split(&self) -> bool {
+        matches!(self.root, Node::Split { .. })
+    }
+
+    /// Split a pane in two. The new pane is placed right or below the split
+    /// pane.
+    pub fn split(&mut self, pane: PaneId, dir: SplitDirection) -> Option<PaneId> {
+        let id = self.next_id;
+        let node = Self::find_mut(&mut self.root, pane)?;
+        *node = Node::Split {
+            dir,
+            ratio: 0.5,
+            first: Box::new(Node::Pane(pane)),
+            second: Box::new(Node::Pane(id)),
+        };
+        self.next_id += 1;
+        Some(id)
+    }
+
+    fn find_mut(node: &mut Node, pane: PaneId) -> Option<&mut Node> {
+        match node {
+            Node::Pane(id) if *id == pane => Some(node),
+            Node::Pane(_) => None,
+            Node::Split { first, second, .. } => {
+                if first.contains(pane) {
+                    Self::find_mut(first, pane)
+                } else {
+                    Self::find_mut(second, pane)
+                }
+            }
+        }
+    }
+
+    /// Remove a pane, its sibling takes its space. The last pane cannot be
+    /// removed.
+    ///
+    /// Returns a pane from the sibling that took the space.
+    pub fn remove(&mut self, pane: PaneId) -> Option<PaneId> {
+        Self::remove_from(&mut self.root, pane)
+    }
+
+    fn remove_from(node: &mut Node, pane: PaneId) -> Option<PaneId> {
+        let Node::Split { first, second, .. } = node else {
+            return None;
+        };
+
+        let sibling = match (first.as_ref(), second.as_ref()) {
+            (Node::Pane(id), _) if *id == pane => second.as_ref().clone(),
+            (_, Node::Pane(id)) if *id == pane => first.as_ref().clone(),
+            _ if first.contains(pane) => return Self::remove_from(first, pane),
+            _ => return Self::remove_from(second, pane),
+        };
+
+        let focus = sibling.first_pane();
+        *node = sibling;
+        Some(focus)
+    }
+
+    /// Rectangles of all panes in an area, panes are separated by one cell
+    /// borders
+    pub fn rects(&self, size: Size) -> Vec<(PaneId, PaneRect)> {
+        let rect = PaneRect {
+            x: 0,
+            y: 0,
+            width: size.width,
+            height: size.height,
+        };
+        let mut rects = vec![];
+        self.root.rects(rect, &mut rects);
+        rects
+    }
+
+    /// Nearest pane next to a pane in a direction
+    pub fn neighbour(&self, pane: PaneId, dir: Direction, size: Size) -> Option<PaneId> {
+        let rects = self.rects(size);
+        let (_, rect) = rects.iter().find(|(id, _)| *id == pane)?;
+        let overlap = |a: usize, alen: usize, b: usize, blen: usize| {
+            (a + alen).min(b + blen).saturating_sub(a.max(b))
+        };
+
+        rects
+            .iter()
+            .filter_map(|(id, other)| {
+                let (adjacent, shared) = match dir {
+                    Direction::Left => (
+                        other.x + other.width + 1 == rect.x,
+                        overlap(rect.y, rect.height, other.y, other.height),
+                    ),
+                    Direction::Right => (
+                        rect.x + rect.width + 1 == other.x,
+                        overlap(rect.y, rect.height, other.y, other.height),
+                    ),
+                    Direction::Up => (
+                        other.y + other.height + 1 == rect.y,
+                        overlap(rect.x, rect.width, other.x, other.width),
+                    ),
+                    Direction::Down => (
+                        rect.y + rect.height + 1 == other.y,
+                        overlap(rect.x, rect.width, other.x, other.width),
+                    ),
+                };
+                (adjacent && shared > 0).then_some((*id, shared))
+            })
+            // Prefer the most shared edge, then the top or leftmost pane
+            .min_by_key(|(_, shared)| Reverse(*shared))
+            .map(|(id, _)| id)
+    }
+
+    /// Grow a pane by amount cells, or shrink if amount is negative, by
+    /// moving the closest border in the split direction.
+    pub fn resize(&mut self, pane: PaneId, dir: SplitDirection, amount: isize, size: Size) -> bool {
+        let rect = PaneRect {
+            x: 0,
+            y: 0,
+            width: size.width,
+            height: size.height,
+        };
+        Self::resize_in(&mut self.root, rect, pane, dir, amount)
+    }
+
+    fn resize_in(
+        node: &mut Node,
+        rect: PaneRect,
+        pane: PaneId,
+        rdir: SplitDirection,
+        amount: isize,
+    ) -> bool {
+        let Node::Split {
+            dir,
+            ratio,
+            first,
+            second,
+        } = node
+        else {
+            return false;
+        };
+
+        // Resize the innermost split first
+        let (frect, srect) = split_rect(rect, *dir, *ratio);
+        let in_first = first.contains(pane);
+        let resized = if in_first {
+            Self::resize_in(first, frect, pane, rdir, amount)
+        } else {
+            Self::resize_in(second, srect, pane, rdir, amount)
+        };
+        if resized || *dir != rdir {
+            return resized;
+        }
+
+        let (len, flen) = match dir {
+            SplitDirection::Vertical => (rect.width, frect.width),
+            SplitDirection::Horizontal => (rect.height, frect.height),
+        };
+        let avail = len.saturating_sub(1);
+        if avail < 2 {
+            return false;
+        }
+
+        let amount = if in_first { amount } else { -amount };
+        let nlen = (flen as isize + amount).clamp(1, avail as isize - 1);
+        if nlen as usize == flen {
+            return false;
+        }
+        *ratio = nlen as f64 / avail as f64;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SIZE: Size = Size {
+        width: 81,
+        height: 21,
+    };
+
+    fn rect(x: usize, y: usize, width: usize, height: usize) -> PaneRect {
+        PaneRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn split_and_remove() {
+        let mut layout = Layout::default();
+        assert_eq!(vec![(0, rect(0, 0, 81, 21))], layout.rects(SIZE));
+
+        let right = layout.split(0, SplitDirection::Vertical).unwrap();
+        let below = layout.split(right, SplitDirection::Horizontal).unwrap();
+        assert_eq!(
+            vec![
+                (0, rect(0, 0, 40, 21)),
+                (right, rect(41, 0, 40, 10)),
+                (below, rect(41, 11, 40, 10)),
+            ],
+            layout.rects(SIZE)
+        );
+
+        assert_eq!(Some(below), layout.remove(right));
+        assert_eq!(
+            vec![(0, rect(0, 0, 40, 21)), (below, rect(41, 0, 40, 21))],
+            layout.rects(SIZE)
+        );
+        assert_eq!(Some(below), layout.remove(0));
+        assert_eq!(None, layout.remove(below));
+        assert!(!layout.is_split());
+    }
+
+    #[test]
+    fn neighbours() {
+        let mut layout = Layout::default();
+        let right = layout.split(0, SplitDirection::Vertical).unwrap();
+        let below = layout.split(right, SplitDirection::Horizontal).unwrap();
+
+        assert_eq!(Some(right), layout.neighbour(0, Direction::Right, SIZE));
+        assert_eq!(Some(0), layout.neighbour(below, Direction::Left, SIZE));
+        assert_eq!(Some(right), layout.neighbour(below, Direction::Up, SIZE));
+        assert_eq!(None, layout.neighbour(0, Direction::Up, SIZE));
+    }
+
+    #[test]
+    fn resize() {
+        let mut layout = Layout::default();
+        let right = layout.split(0, SplitDirection::Vertical).unwrap();
+        let below = layout.split(right, SplitDirection::Horizontal).unwrap();
+
+        // Horizontal border of the right column moves
+        assert!(layout.resize(below, SplitDirection::Horizontal, 2, SIZE));
+        assert_eq!(
+            vec![
+                (0, rect(0, 0, 40, 21)),
+                (right, rect(41, 0, 40, 8)),
+                (below, rect(41, 9, 40, 12)),
+            ],
+            layout.rects(SIZE)
+        );
+
+        // Vertical border is found from the outer split
+        assert!(layout.resize(below, SplitDirection::Vertical, 5, SIZE));
+        assert_eq!(rect(0, 0, 35, 21), layout.rects(SIZE)[0].1);
+        assert!(!layout.resize(0, SplitDirection::Horizontal, 1, SIZE));
+    }
+}
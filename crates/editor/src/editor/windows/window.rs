@@ -5,6 +5,7 @@ mod cursors;
 mod filetree;
 mod focus;
 pub(crate) mod games;
+mod hex;
 mod jumps;
 mod locations;
 mod macro_record;
@@ -29,15 +30,19 @@ use anyhow::{bail, Result};
 use games::Game;
 pub(crate) use mouse::{Mouse, MouseClick};
 use rustc_hash::FxHashSet as Set;
-use sanedit_buffer::{utf8::next_eol, Mark, MarkResult};
+use sanedit_buffer::{
+    utf8::{next_eol, prev_eol},
+    Mark, MarkResult,
+};
 use sanedit_core::{
-    grapheme_category, indent_at_line,
+    find_surrounding, grapheme_category, increment, indent_at_line,
     movement::{
         end_of_line, find_prev_whitespace, next_grapheme_boundary, next_line_end, next_line_start,
         prev_grapheme_boundary, start_of_line,
     },
-    selection_first_chars_of_lines, selection_line_ends, selection_line_starts, width_at_pos,
-    BufferRange, Change, Changes, Cursor, DisplayOptions, GraphemeCategory, Locations, Range,
+    pos_at_width, reflow, reflow_range_at, selection_first_chars_of_lines, selection_line_ends,
+    selection_line_starts, width_at_pos, wrap_line, BufferRange, Case, Change, Changes, Cursor,
+    DisplayOptions, GraphemeCategory, Locations, Range, ReflowOptions, Surround,
 };
 use sanedit_messages::{
     key::KeyEvent,
@@ -57,6 +62,7 @@ use crate::{
     editor::{
         buffers::{Buffer, BufferId, SavedWindowState, SnapshotId},
         keymap::LayerKey,
+        signs::Sign,
         Editor, Map,
     },
 };
@@ -66,8 +72,8 @@ pub(crate) use cursors::Cursors;
 pub(crate) use locations::LocationsView;
 
 pub(crate) use self::{
-    completion::*, config::*, focus::*, jumps::*, macro_record::*, mode::*, prompt::*, search::*,
-    snapshot::*, view::*,
+    completion::*, config::*, focus::*, hex::*, jumps::*, macro_record::*, mode::*, prompt::*,
+    search::*, snapshot::*, view::*,
 };
 
 #[derive(Debug)]
@@ -76,7 +82,17 @@ pub(crate) struct Window {
     visited_buffers: Map<BufferId, SavedWindowState>,
     message: Option<StatusMessage>,
     view: View,
+    /// Size of the whole window, the view is smaller if a gutter is shown
+    size: Size,
+    /// Width of the gutter, sign column and line numbers
+    gutter: usize,
+    /// Signs of the buffer sorted by position
+    signs: Vec<Sign>,
     keys: Vec<KeyEvent>,
+    /// Count typed before an action
+    count: Option<u32>,
+    /// Register selected for the next copy or paste
+    register: Option<char>,
     popup: Option<Popup>,
 
     pub last_selection: Option<Cursors>,
@@ -105,6 +121,7 @@ pub(crate) struct Window {
     /// Delete indent when insert mode is left. Auto indenting changes should set this
     pub delete_indent_on_insert_leave: bool,
     pub game: Option<Box<dyn Game>>,
+    pub hex: HexEdit,
 }
 
 impl Window {
@@ -112,10 +129,15 @@ impl Window {
         Window {
             bid,
             keys: vec![],
+            count: None,
+            register: None,
             last_buffer: None,
             visited_buffers: Map::default(),
             last_selection: None,
             view: View::new(width, height),
+            size: Size { width, height },
+            gutter: 0,
+            signs: vec![],
             message: None,
             completion: Completion::default(),
             cursors: Cursors::default(),
@@ -137,6 +159,7 @@ impl Window {
             game: None,
             macro_record: Default::default(),
             macro_replay: Default::default(),
+            hex: HexEdit::default(),
         }
     }
 
@@ -166,6 +189,32 @@ impl Window {
         mem::take(&mut self.keys)
     }
 
+    /// Add a digit to the count typed before an action
+    pub fn push_count_digit(&mut self, digit: u32) {
+        let count = self.count.unwrap_or(0);
+        self.count = Some(count.saturating_mul(10).saturating_add(digit));
+    }
+
+    /// Take the count typed before an action
+    pub fn take_count(&mut self) -> Option<u32> {
+        self.count.take()
+    }
+
+    /// Whether a count is being typed
+    pub fn has_count(&self) -> bool {
+        self.count.is_some()
+    }
+
+    /// Select a register for the next copy or paste
+    pub fn select_register(&mut self, name: char) {
+        self.register = Some(name);
+    }
+
+    /// Take the register selected for the next copy or paste
+    pub fn take_register(&mut self) -> Option<char> {
+        self.register.take()
+    }
+
     pub fn clear_popup(&mut self) {
         self.popup = None;
     }
@@ -199,6 +248,7 @@ impl Window {
 
     pub fn full_reload(&mut self, buf: &Buffer) {
         self.view.invalidate();
+        self.view.invalidate_columns();
         self.view.align_view_to_line(buf);
         self.ensure_cursor_on_grapheme_boundary(buf);
         self.reload();
@@ -237,6 +287,7 @@ impl Window {
         self.reset();
         self.cursor_jumps.goto_start();
         self.view.options.tabstop = buf.config.tabstop;
+        self.view.set_hex(buf.is_binary);
         if let Some(data) = self.visited_buffers.get(&self.bid).cloned() {
             self.restore(&data, buf);
         }
@@ -362,6 +413,8 @@ impl Window {
         );
         let cursor = self.primary_cursor().pos();
 
+        self.view.set_wrap(self.config.wrap);
+        self.view.hscroll_to(cursor, buf);
         self.view.redraw(buf);
 
         if !self.view.is_visible(cursor) {
@@ -372,12 +425,9 @@ impl Window {
     /// Move primary cursor to line and the view
     pub fn goto_line(&mut self, line: u64, buf: &Buffer) {
         let slice = buf.slice(..);
-        let mut lines = slice.lines();
-        for _ in 1..max(line, 1) {
-            lines.next();
-        }
-
-        let offset = lines.next().map(|line| line.start()).unwrap_or(buf.len());
+        let offset = slice
+            .pos_at_line(line.saturating_sub(1))
+            .unwrap_or(buf.len());
         self.jump_to_offset(offset, buf);
     }
 
@@ -415,6 +465,11 @@ impl Window {
         // Ensure cursor in buf range
         self.cursors.contain_to(0..buf.len());
 
+        // Cursors are on bytes in hex mode
+        if self.view.is_hex() {
+            return;
+        }
+
         // Ensure cursor in buf grapheme boundary
         let mut cursors = self.cursors.cursors_mut();
         let primary = cursors.primary();
@@ -527,6 +582,7 @@ impl Window {
         self.bid = new;
 
         self.view.syntax = ViewSyntax::default();
+        self.view.folds = Folds::default();
         self.search.reset_highlighting();
     }
 
@@ -535,6 +591,25 @@ impl Window {
         self.goto_offset(offset, buf);
     }
 
+    /// Create a new window showing the same buffer position as this one
+    pub fn split(&self) -> Window {
+        let mut win = Window::new(
+            self.bid,
+            self.size.width,
+            self.size.height,
+            self.config.clone(),
+        );
+        win.last_buffer = self.last_buffer;
+        win.visited_buffers = self.visited_buffers.clone();
+        win.cursors = self.cursors.clone();
+        win.view.options = self.view.options.clone();
+        win.view.set_hex(self.view.is_hex());
+        win.view.set_offset(self.view.start());
+        win.signs = self.signs.clone();
+        win.view.folds = self.view.folds.clone();
+        win
+    }
+
     pub fn view(&self) -> &View {
         &self.view
     }
@@ -550,11 +625,144 @@ impl Window {
             buf.id,
             self.bid
         );
-        self.view.resize(size);
+        self.size = size;
+        self.gutter = self.calculate_gutter(buf);
+        self.view.resize(self.text_size());
         self.view_to_cursor(buf);
         self.game = None;
     }
 
+    /// Size of the whole window including the gutter
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    pub fn gutter_width(&self) -> usize {
+        self.gutter
+    }
+
+    fn text_size(&self) -> Size {
+        Size {
+            width: self.size.width.saturating_sub(self.gutter).max(1),
+            height: self.size.height,
+        }
+    }
+
+    /// Width of the gutter: the sign column and line numbers including one
+    /// cell of padding
+    fn calculate_gutter(&self, buf: &Buffer) -> usize {
+        // Keep room for atleast this many digits, so the gutter does not
+        // resize while editing small files
+        const MIN_DIGITS: usize = 3;
+
+        if self.view.is_hex() {
+            return 0;
+        }
+
+        let largest = match self.config.line_numbers {
+            LineNumbering::Off => None,
+            LineNumbering::Relative => Some(self.size.height as u64),
+            // File backed buffers may still be indexing their lines, the
+            // buffer length is an upper bound for the line count until then
+            LineNumbering::Absolute | LineNumbering::Hybrid => {
+                Some(buf.line_count().unwrap_or(buf.len() + 1))
+            }
+        };
+        let numbers = largest.map_or(0, |largest| {
+            let digits = largest.checked_ilog10().unwrap_or(0) as usize + 1;
+            digits.max(MIN_DIGITS) + 1
+        });
+        let width = numbers + self.sign_column();
+
+        // Leave the window for text if it is too narrow
+        if width * 2 > self.size.width {
+            0
+        } else {
+            width
+        }
+    }
+
+    /// Width of the sign column in the gutter, 0 if it is not shown
+    pub fn sign_column_width(&self) -> usize {
+        if self.gutter == 0 {
+            0
+        } else {
+            self.sign_column()
+        }
+    }
+
+    /// Width the sign column would take, a sign and a cell of padding
+    fn sign_column(&self) -> usize {
+        let shown = match self.config.sign_column {
+            SignColumn::Never => false,
+            SignColumn::Auto => !self.signs.is_empty(),
+            SignColumn::Always => true,
+        };
+        if shown {
+            2
+        } else {
+            0
+        }
+    }
+
+    /// Set signs of the buffer, the gutter is resized on the next redraw
+    pub fn set_signs(&mut self, signs: Vec<Sign>) {
+        self.signs = signs;
+    }
+
+    /// Whether the first view line starts a line, or is a continuation of a
+    /// wrapped line
+    pub fn view_starts_line(&self, buf: &Buffer) -> bool {
+        let before = buf.slice(..self.view.start());
+        let mut graphemes = before.graphemes_at(before.len());
+        graphemes.prev().is_none_or(|prev| prev.is_eol())
+    }
+
+    /// Most important sign of each view line. Signs are shown on the first
+    /// view line of a wrapped line.
+    pub fn view_signs(&self, buf: &Buffer) -> Vec<Option<Sign>> {
+        let cells = self.view.cells();
+        let mut view_signs = vec![None; cells.len()];
+        let mut pos = self.view.start();
+        let start = self.signs.partition_point(|sign| sign.pos < pos);
+        let mut signs = self.signs[start..].iter().peekable();
+        let mut line_start = self.view_starts_line(buf);
+        let mut line_row = None;
+
+        for (row, cells) in cells.iter().enumerate() {
+            if line_start {
+                line_row = Some(row);
+            }
+
+            let end = pos + self.view.line_len_in_buffer(row);
+            while let Some(sign) = signs.next_if(|sign| sign.pos < end) {
+                let Some(slot) = line_row.map(|row| &mut view_signs[row]) else {
+                    continue;
+                };
+                if slot.is_none_or(|old: Sign| old.priority() < sign.priority()) {
+                    *slot = Some(*sign);
+                }
+            }
+
+            pos = end;
+            line_start = cells
+                .iter()
+                .any(|cell| cell.char().is_some_and(|ch| ch.is_eol()));
+        }
+
+        view_signs
+    }
+
+    /// Resize the view if the gutter width has changed
+    fn update_gutter(&mut self, buf: &Buffer) {
+        let gutter = self.calculate_gutter(buf);
+        if gutter != self.gutter {
+            self.gutter = gutter;
+            self.view.resize(self.text_size());
+            self.view_to_cursor(buf);
+        }
+    }
+
     pub fn message(&self) -> Option<&StatusMessage> {
         self.message.as_ref()
     }
@@ -568,9 +776,63 @@ impl Window {
         );
 
         self.ensure_cursor_on_grapheme_boundary(buf);
+        self.open_folds_at_cursors(buf);
+        self.update_gutter(buf);
+        self.view.set_wrap(self.config.wrap);
+        self.view.hscroll_to(self.primary_cursor().pos(), buf);
         self.view.redraw(buf);
     }
 
+    /// Open closed folds hiding cursors, so jumps and searches into a fold
+    /// reveal it
+    fn open_folds_at_cursors(&mut self, buf: &Buffer) {
+        if self.view.folds.is_empty() {
+            return;
+        }
+
+        let positions: Vec<u64> = self.cursors.iter().map(Cursor::pos).collect();
+        if self.view.folds.open_hiding(buf, &positions) {
+            self.view.invalidate();
+            self.view_to_cursor(buf);
+        }
+    }
+
+    /// Close a fold covering `range`, cursors that would be hidden are moved
+    /// to the fold header
+    pub fn close_fold(&mut self, buf: &Buffer, range: BufferRange) {
+        let slice = buf.slice(..);
+        let hidden_start = end_of_line(&slice, range.start);
+        let hides_cursor = self
+            .cursors
+            .iter()
+            .any(|cursor| hidden_start < cursor.pos() && cursor.pos() < range.end);
+        if hides_cursor {
+            let mut cursors = self.cursors.cursors_mut();
+            cursors.remove_except_primary();
+            cursors.replace_primary(Cursor::new(range.start));
+        }
+
+        self.view.folds.close(buf, range);
+        self.view.invalidate();
+    }
+
+    /// Open the innermost closed fold containing `pos`
+    pub fn open_fold(&mut self, buf: &Buffer, pos: u64) -> bool {
+        let opened = self.view.folds.open_at(buf, pos);
+        if opened {
+            self.view.invalidate();
+        }
+        opened
+    }
+
+    pub fn open_all_folds(&mut self) -> bool {
+        let opened = self.view.folds.open_all();
+        if opened {
+            self.view.invalidate();
+        }
+        opened
+    }
+
     pub fn open_snapshot_preview(&mut self, buf: &mut Buffer) {
         if self.snapshot_view.original_buffer.is_some() {
             return;
@@ -629,6 +891,8 @@ impl Window {
 
     pub fn change(&mut self, buf: &mut Buffer, changes: &Changes) -> Result<()> {
         self.delete_indent_on_insert_leave = false;
+        // Block rows are edited as separate cursors
+        self.cursors.stop_block();
         self.last_edit_jump = None;
         self.cursor_jumps.goto_start();
 
@@ -730,19 +994,78 @@ impl Window {
         Ok(())
     }
 
+    /// Toggle hex mode, where buffer bytes are shown and edited in hex
+    pub fn toggle_hex(&mut self, buf: &Buffer) {
+        let hex = !self.view.is_hex();
+        self.view.set_hex(hex);
+        self.hex = HexEdit::default();
+        self.ensure_cursor_on_grapheme_boundary(buf);
+        self.view.invalidate();
+        self.view.ensure_view_on_grapheme_boundary(buf);
+        self.view_to_cursor(buf);
+    }
+
+    /// Handle text typed in hex mode, two hex digits make up a byte which
+    /// is written to each cursor. Other characters are ignored.
+    pub fn hex_input(&mut self, buf: &mut Buffer, text: &str) -> Result<()> {
+        for ch in text.chars() {
+            let Some(digit) = ch.to_digit(16) else {
+                continue;
+            };
+            let digit = digit as u8;
+            let pos = self.cursors.primary().pos();
+
+            match self.hex.pending.take() {
+                Some((ppos, high)) if ppos == pos => {
+                    let byte = (high << 4) | digit;
+                    self.hex_write_byte(buf, byte)?;
+                }
+                _ => self.hex.pending = Some((pos, digit)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn hex_write_byte(&mut self, buf: &mut Buffer, byte: u8) -> Result<()> {
+        let len = buf.len();
+        let insert = self.hex.insert;
+        let changes: Vec<Change> = self
+            .cursors
+            .iter()
+            .map(|cursor| {
+                if let Some(sel) = cursor.selection() {
+                    Change::replace(sel, &[byte])
+                } else if insert || cursor.pos() >= len {
+                    Change::insert(cursor.pos(), &[byte])
+                } else {
+                    let pos = cursor.pos();
+                    Change::replace(pos..pos + 1, &[byte])
+                }
+            })
+            .collect();
+        let changes: Changes = changes.into();
+        self.change(buf, &changes)
+    }
+
     pub fn remove_grapheme_after_cursors(&mut self, buf: &mut Buffer) -> Result<()> {
         if self.remove_cursor_selections(buf)? {
             return Ok(());
         }
 
         let slice = buf.slice(..);
+        let hex = self.view.is_hex();
         let ranges: Vec<BufferRange> = self
             .cursors
             .cursors()
             .iter()
             .map(Cursor::pos)
             .map(|pos| {
-                let next = next_grapheme_boundary(&slice, pos);
+                let next = if hex {
+                    min(pos + 1, slice.len())
+                } else {
+                    next_grapheme_boundary(&slice, pos)
+                };
                 Range::from(pos..next)
             })
             .collect();
@@ -907,12 +1230,17 @@ impl Window {
             return Ok(());
         }
 
+        let hex = self.view.is_hex();
         let ranges: Vec<BufferRange> = {
             let mut ranges = vec![];
 
             for cursor in self.cursors.cursors() {
                 let cpos = cursor.pos();
-                let pos = prev_grapheme_boundary(&buf.slice(..), cpos);
+                let pos = if hex {
+                    cpos.saturating_sub(1)
+                } else {
+                    prev_grapheme_boundary(&buf.slice(..), cpos)
+                };
                 ranges.push(Range::from(pos..cpos));
             }
 
@@ -1184,6 +1512,7 @@ impl Window {
     }
 
     pub fn stop_selection(&mut self) {
+        self.cursors.stop_block();
         if self.cursors.has_selections() {
             self.last_selection = Some(self.cursors.clone());
             let mut cursors = self.cursors.cursors_mut();
@@ -1286,9 +1615,16 @@ impl Window {
             return Ok(());
         }
 
+        let ranges = self.line_after_cursors(buf);
+        self.remove(buf, &ranges)?;
+        Ok(())
+    }
+
+    /// Ranges from each cursor to the next line end, removed by
+    /// `remove_line_after_cursor` when nothing is selected
+    pub fn line_after_cursors(&self, buf: &Buffer) -> Vec<BufferRange> {
         let slice = buf.slice(..);
-        let ranges: Vec<BufferRange> = self
-            .cursors
+        self.cursors
             .cursors()
             .iter()
             .map(Cursor::pos)
@@ -1296,10 +1632,7 @@ impl Window {
                 let npos = next_line_end(&slice, pos);
                 Range::from(pos..npos)
             })
-            .collect();
-
-        self.remove(buf, &ranges)?;
-        Ok(())
+            .collect()
     }
 
     pub fn strip_trailing_whitespace(&mut self, buf: &mut Buffer) -> Result<()> {
@@ -1585,6 +1918,337 @@ impl Window {
         Ok(())
     }
 
+    fn reflow_options<'a>(buf: &'a Buffer, comment: &'a str) -> ReflowOptions<'a> {
+        ReflowOptions {
+            width: buf.config.text_width,
+            tabstop: buf.config.tabstop,
+            comment,
+            eol: buf.config.eol.as_ref(),
+        }
+    }
+
+    /// Rewrap the selected lines or the paragraph at each cursor to the
+    /// buffer text width
+    pub fn reflow_cursor_lines(&mut self, buf: &mut Buffer, comment: &str) -> Result<()> {
+        let slice = buf.slice(..);
+        let mut ranges: Vec<BufferRange> = self
+            .cursors
+            .iter()
+            .filter_map(|cursor| match cursor.selection() {
+                Some(sel) => {
+                    let start = start_of_line(&slice, sel.start);
+                    let end = if start_of_line(&slice, sel.end) == sel.end {
+                        sel.end
+                    } else {
+                        next_line_start(&slice, sel.end)
+                    };
+                    Some(Range::from(start..end))
+                }
+                None => reflow_range_at(&slice, cursor.pos(), comment),
+            })
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        ranges.dedup_by(|next, prev| {
+            let overlaps = next.start < prev.end;
+            if overlaps {
+                prev.end = max(prev.end, next.end);
+            }
+            overlaps
+        });
+
+        let opts = Self::reflow_options(buf, comment);
+        let mut changes = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            let text = String::from(&slice.slice(range));
+            let reflowed = reflow(&text, &opts);
+            if reflowed != text {
+                changes.push(Change::replace(range, reflowed.as_bytes()));
+            }
+        }
+
+        if changes.is_empty() {
+            bail!("No changes");
+        }
+
+        self.stop_selection();
+        let changes = Changes::from(changes);
+        self.change(buf, &changes)
+    }
+
+    /// Wrap the cursor lines that are longer than the buffer text width at
+    /// the last whitespace that fits, used while typing
+    pub fn auto_wrap_cursor_lines(&mut self, buf: &mut Buffer, comment: &str) -> Result<()> {
+        let slice = buf.slice(..);
+        let opts = Self::reflow_options(buf, comment);
+        let mut starts = Set::default();
+        let mut changes = vec![];
+
+        for cursor in self.cursors.iter() {
+            let pos = cursor.pos();
+            let start = start_of_line(&slice, pos);
+            if cursor.selection().is_some() || !starts.insert(start) {
+                continue;
+            }
+
+            let line = String::from(&slice.slice(start..pos));
+            if let Some((range, replacement)) = wrap_line(&line, &opts) {
+                let range = Range::from(start + range.start as u64..start + range.end as u64);
+                changes.push(Change::replace(range, replacement.as_bytes()));
+            }
+        }
+
+        if changes.is_empty() {
+            bail!("No changes");
+        }
+
+        let changes = Changes::from(changes);
+        self.change(buf, &changes)
+    }
+
+    /// Increment the number, date or boolean at each cursor by amount. All
+    /// the changes are applied at once.
+    pub fn increment_cursors(&mut self, buf: &mut Buffer, amount: i64) -> Result<()> {
+        let slice = buf.slice(..);
+        let mut changes: Vec<Change> = vec![];
+        for cursor in self.cursors.iter() {
+            let Some((range, text)) = increment(&slice, cursor.pos(), amount) else {
+                continue;
+            };
+
+            // Multiple cursors on the same item
+            if changes.iter().any(|change| change.range() == range) {
+                continue;
+            }
+
+            changes.push(Change::replace(range, text.as_bytes()));
+        }
+
+        if changes.is_empty() {
+            bail!("Nothing to increment");
+        }
+
+        let changes = Changes::from(changes);
+        self.change(buf, &changes)
+    }
+
+    /// Surround each selection with delimiters
+    pub fn surround_selections(&mut self, buf: &mut Buffer, surround: &Surround) -> Result<()> {
+        let open = surround.open();
+        let close = surround.close();
+        let mut inserts: Vec<(u64, String)> = vec![];
+        let mut push = |pos: u64, text: &str| match inserts.last_mut() {
+            // Adjacent selections insert to the same position
+            Some((last, ltext)) if *last == pos => ltext.push_str(text),
+            _ => inserts.push((pos, text.to_string())),
+        };
+
+        for cursor in self.cursors.iter() {
+            if let Some(range) = cursor.selection() {
+                push(range.start, &open);
+                push(range.end, &close);
+            }
+        }
+
+        if inserts.is_empty() {
+            bail!("No selections to surround");
+        }
+
+        let changes: Vec<Change> = inserts
+            .iter()
+            .map(|(pos, text)| Change::insert(*pos, text.as_bytes()))
+            .collect();
+        let changes = Changes::from(changes);
+        self.change(buf, &changes)
+    }
+
+    /// Replace the delimiter pair surrounding each cursor, pair is selected
+    /// using `ch` as in [`find_surrounding`]. If `surround` is none the pair
+    /// is removed.
+    pub fn change_surrounding(
+        &mut self,
+        buf: &mut Buffer,
+        ch: char,
+        surround: Option<&Surround>,
+    ) -> Result<()> {
+        let slice = buf.slice(..);
+        let mut pairs: Vec<(BufferRange, BufferRange)> = self
+            .cursors
+            .iter()
+            .filter_map(|cursor| find_surrounding(&slice, cursor.pos(), ch))
+            .collect();
+        pairs.sort();
+        pairs.dedup();
+
+        if pairs.is_empty() {
+            bail!("No surrounding pair found");
+        }
+
+        let open = surround.map(Surround::open).unwrap_or_default();
+        let close = surround.map(Surround::close).unwrap_or_default();
+        let mut changes: Vec<Change> = vec![];
+        for (orange, crange) in pairs {
+            changes.push(Change::replace(orange, open.as_bytes()));
+            changes.push(Change::replace(crange, close.as_bytes()));
+        }
+
+        let changes = Changes::from(changes);
+        let overlapping = changes
+            .iter()
+            .zip(changes.iter().skip(1))
+            .any(|(a, b)| a.range().end > b.range().start);
+        if overlapping {
+            bail!("Surrounding pairs overlap");
+        }
+
+        self.change(buf, &changes)
+    }
+
+    /// Start a block selection from the primary cursor, an existing
+    /// selection anchor is used as the block anchor
+    pub fn start_block_selection(&mut self, buf: &Buffer) {
+        let primary = self.cursors.primary();
+        let anchor = primary.anchor().unwrap_or(primary.pos());
+        self.cursors.start_block(anchor);
+        self.update_block_selection(buf);
+    }
+
+    /// Ranges of a block selection on each line it spans. Lines shorter than
+    /// the block have an empty range at the line end.
+    ///
+    /// Returns the ranges and the index of the moving corners row.
+    fn block_ranges(&self, buf: &Buffer) -> Option<(Vec<BufferRange>, usize)> {
+        let anchor = self.cursors.block_anchor()?;
+        let slice = buf.slice(..);
+        let opts = &self.view.options;
+        let head = self.cursors.primary().pos();
+        let acol = width_at_pos(&slice, anchor, opts);
+        let hcol = width_at_pos(&slice, head, opts);
+        let left = acol.min(hcol);
+        let right = acol.max(hcol);
+        let head_sol = start_of_line(&slice, head);
+        let last_sol = start_of_line(&slice, anchor.max(head));
+
+        let mut ranges = vec![];
+        let mut head_row = 0;
+        let mut lines = slice.lines_at(anchor.min(head));
+        while let Some(line) = lines.next() {
+            let sol = line.start();
+            if sol > last_sol {
+                break;
+            }
+
+            if sol == head_sol {
+                head_row = ranges.len();
+            }
+            let start = pos_at_width(&slice, sol, left, opts);
+            let end = pos_at_width(&slice, sol, right, opts);
+            ranges.push(Range::from(start..end));
+        }
+
+        Some((ranges, head_row))
+    }
+
+    /// Rebuild block selection rows after the moving corner has moved
+    pub fn update_block_selection(&mut self, buf: &Buffer) {
+        let Some(anchor) = self.cursors.block_anchor() else {
+            return;
+        };
+        let Some((ranges, head_row)) = self.block_ranges(buf) else {
+            return;
+        };
+
+        let slice = buf.slice(..);
+        let opts = &self.view.options;
+        let primary = self.cursors.primary();
+        let column = primary.column();
+        let acol = width_at_pos(&slice, anchor, opts);
+        let hcol = width_at_pos(&slice, primary.pos(), opts);
+        let left = acol.min(hcol);
+
+        let mut rows = vec![];
+        let mut primary = 0;
+        for (i, range) in ranges.into_iter().enumerate() {
+            let is_head = i == head_row;
+            // Skip lines that end before the block
+            let short = end_of_line(&slice, range.start) == range.start
+                && width_at_pos(&slice, range.start, opts) < left;
+            if !is_head && short {
+                continue;
+            }
+
+            let mut cursor = if range.is_empty() {
+                Cursor::new(range.start)
+            } else {
+                Cursor::new_select(range)
+            };
+            if hcol < acol {
+                cursor.swap_selection_dir();
+            }
+
+            if is_head {
+                if let Some(col) = column {
+                    cursor.set_column(col);
+                }
+                primary = rows.len();
+            }
+            rows.push(cursor);
+        }
+
+        self.cursors.set_block_rows(rows, primary);
+    }
+
+    /// Text of each row in a block selection
+    pub fn block_text(&self, buf: &Buffer) -> Option<Vec<String>> {
+        let (ranges, _) = self.block_ranges(buf)?;
+        let text = ranges
+            .into_iter()
+            .map(|range| String::from(&buf.slice(range)))
+            .collect();
+        Some(text)
+    }
+
+    /// Paste rows as a block starting at the primary cursors column. Short
+    /// lines are padded with spaces and lines are added to the end of the
+    /// buffer if needed.
+    pub fn paste_block(&mut self, buf: &mut Buffer, rows: &[String]) -> Result<()> {
+        let slice = buf.slice(..);
+        let opts = &self.view.options;
+        let pos = self.cursors.primary().pos();
+        let col = width_at_pos(&slice, pos, opts);
+        let eol = buf.config.eol;
+        let mut inserts: Vec<(u64, String)> = vec![];
+        let mut push = |at: u64, text: String| match inserts.last_mut() {
+            Some((last, ltext)) if *last == at => ltext.push_str(&text),
+            _ => inserts.push((at, text)),
+        };
+
+        let mut lines = slice.lines_at(pos);
+        for row in rows {
+            match lines.next() {
+                Some(line) => {
+                    let at = pos_at_width(&slice, line.start(), col, opts);
+                    let pad = col.saturating_sub(width_at_pos(&slice, at, opts));
+                    push(at, format!("{}{row}", " ".repeat(pad)));
+                }
+                None => {
+                    let text = format!("{}{}{row}", eol.as_str(), " ".repeat(col));
+                    push(slice.len(), text);
+                }
+            }
+        }
+
+        if inserts.is_empty() {
+            bail!("Nothing to paste");
+        }
+
+        let changes: Vec<Change> = inserts
+            .iter()
+            .map(|(at, text)| Change::insert(*at, text.as_bytes()))
+            .collect();
+        let changes = Changes::from(changes);
+        self.change(buf, &changes)
+    }
+
     pub fn cursor_trim_whitespace(&mut self, buf: &Buffer) -> bool {
         let changed = self.cursors.trim_whitespace(buf);
         if changed {
@@ -1699,6 +2363,96 @@ impl Window {
         Ok(())
     }
 
+    /// Lines spanned by each selection or the whole buffer if there are no
+    /// selections. Ranges do not include the last line ending.
+    fn selected_line_spans(&self, buf: &Buffer) -> Vec<BufferRange> {
+        let slice = buf.slice(..);
+        let mut spans: Vec<BufferRange> = vec![];
+
+        for cursor in self.cursors.iter() {
+            let Some(sel) = cursor.selection() else {
+                continue;
+            };
+            // Selection ending at a line start does not include that line
+            let last = if sel.end > sel.start {
+                sel.end - 1
+            } else {
+                sel.end
+            };
+            let start = start_of_line(&slice, sel.start);
+            let end = end_of_line(&slice, last);
+
+            match spans.last_mut() {
+                Some(prev) if prev.end >= start => prev.end = max(prev.end, end),
+                _ => spans.push(Range::from(start..end)),
+            }
+        }
+
+        if spans.is_empty() {
+            let mut end = slice.len();
+            let mut bytes = slice.bytes_at(end);
+            if let Some(eol) = prev_eol(&mut bytes) {
+                if eol.range.end == end {
+                    end = eol.range.start;
+                }
+            }
+            spans.push(Range::from(0..end));
+        }
+
+        spans
+    }
+
+    /// Edit the selected lines or the whole buffer if there are no
+    /// selections. Lines are split on the buffer line ending and passed
+    /// without it.
+    pub fn edit_lines<F>(&mut self, buf: &mut Buffer, mut f: F) -> Result<()>
+    where
+        F: FnMut(Vec<String>) -> Vec<String>,
+    {
+        let eol = buf.config.eol.as_str();
+        let mut changes = vec![];
+
+        for span in self.selected_line_spans(buf) {
+            let text = String::from(&buf.slice(span));
+            let lines: Vec<String> = text.split(eol).map(String::from).collect();
+            let edited = f(lines).join(eol);
+            if edited != text {
+                changes.push(Change::replace(span, edited.as_bytes()));
+            }
+        }
+
+        if changes.is_empty() {
+            bail!("No changes");
+        }
+
+        let changes = Changes::from(changes);
+        self.change(buf, &changes)?;
+        Ok(())
+    }
+
+    /// Convert identifiers in selections to a case
+    pub fn convert_case_selections(&mut self, buf: &mut Buffer, case: Case) -> Result<()> {
+        let mut changes = vec![];
+
+        for cursor in self.cursors.cursors() {
+            if let Some(range) = cursor.selection() {
+                let text = String::from(&buf.slice(range));
+                let converted = case.convert(&text);
+                if converted != text {
+                    changes.push(Change::replace(range, converted.as_bytes()));
+                }
+            }
+        }
+
+        if changes.is_empty() {
+            bail!("No changes");
+        }
+
+        let changes = Changes::from(changes);
+        self.change(buf, &changes)?;
+        Ok(())
+    }
+
     pub fn rotate_selections(&mut self, buf: &mut Buffer, reverse: bool) -> Result<()> {
         let mut changes = vec![];
 
@@ -2,6 +2,39 @@ use serde::{Deserialize, Serialize};
 
 use crate::editor::themes::DEFAULT_THEME;
 
+/// How lines are numbered in the gutter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) enum LineNumbering {
+    #[default]
+    Off,
+    Absolute,
+    /// Distance to the cursor line
+    Relative,
+    /// Relative numbers, but the cursor line shows its absolute number
+    Hybrid,
+}
+
+impl LineNumbering {
+    pub fn next(&self) -> LineNumbering {
+        match self {
+            LineNumbering::Off => LineNumbering::Absolute,
+            LineNumbering::Absolute => LineNumbering::Relative,
+            LineNumbering::Relative => LineNumbering::Hybrid,
+            LineNumbering::Hybrid => LineNumbering::Off,
+        }
+    }
+}
+
+/// When the sign column is shown in the gutter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) enum SignColumn {
+    Never,
+    /// Only if the buffer has signs
+    #[default]
+    Auto,
+    Always,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, DocComment)]
 #[serde(default)]
 pub(crate) struct WindowConfig {
@@ -27,6 +60,30 @@ pub(crate) struct WindowConfig {
 
     /// Automatically insert pairs on enter, works only with autoindent
     pub autopair: bool,
+
+    /// Stop word movements and word selection at camelCase and snake_case
+    /// humps
+    pub subword: bool,
+
+    /// Line numbers shown on the left side of the window
+    /// Available options:
+    /// Off: no line numbers
+    /// Absolute: line numbers starting from 1
+    /// Relative: distance to the cursor line
+    /// Hybrid: relative, but the cursor line shows its absolute line number
+    pub line_numbers: LineNumbering,
+
+    /// Sign column showing diagnostics, version control changes and
+    /// bookmarks on the left side of the line numbers
+    /// Available options:
+    /// Never: sign column is never shown
+    /// Auto: shown if the buffer has signs
+    /// Always: sign column is always shown
+    pub sign_column: SignColumn,
+
+    /// Wrap lines that do not fit the window, otherwise the lines are cut
+    /// and the view scrolls sideways to show the cursor
+    pub wrap: bool,
 }
 
 impl Default for WindowConfig {
@@ -40,6 +97,10 @@ impl Default for WindowConfig {
             highlight_diagnostics: true,
             autoindent: true,
             autopair: true,
+            subword: false,
+            line_numbers: LineNumbering::Off,
+            sign_column: SignColumn::Auto,
+            wrap: true,
         }
     }
 }
@@ -2,6 +2,7 @@ use std::{cmp::min, ops::RangeBounds};
 
 use sanedit_buffer::Mark;
 use sanedit_core::{BufferRange, Cursor};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     common::text::{trim_whitespace, trim_whitespace_back},
@@ -79,11 +80,15 @@ impl<'a> Drop for CursorsGuard<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Cursors {
     /// Non overlapping, sorted set of cursors.
     cursors: Vec<Cursor>,
     primary: usize,
+    /// Anchor of a rectangular block selection. Cursors select one row of
+    /// the block each and the primary cursor is the moving corner.
+    #[serde(skip)]
+    block: Option<u64>,
 }
 
 impl Cursors {
@@ -91,6 +96,7 @@ impl Cursors {
         Cursors {
             cursors: vec![cursor],
             primary: 0,
+            block: None,
         }
     }
 
@@ -236,6 +242,45 @@ impl Cursors {
         self.cursors.iter().any(|c| c.selection().is_some())
     }
 
+    pub fn block_anchor(&self) -> Option<u64> {
+        self.block
+    }
+
+    pub fn is_block(&self) -> bool {
+        self.block.is_some()
+    }
+
+    /// Start a block selection from anchor, the primary cursor is used as
+    /// the other corner
+    pub fn start_block(&mut self, anchor: u64) {
+        self.block = Some(anchor);
+    }
+
+    /// Stop block selection, the block rows are kept as separate cursors
+    pub fn stop_block(&mut self) {
+        self.block = None;
+    }
+
+    /// Keep only the moving corner of a block selection so it can be moved
+    /// as a single cursor
+    pub fn collapse_block(&mut self) {
+        if self.block.is_none() {
+            return;
+        }
+
+        let mut cursors = self.cursors_mut();
+        cursors.remove_except_primary();
+        cursors.primary().stop_selection();
+    }
+
+    /// Replace block rows with new ones
+    pub fn set_block_rows(&mut self, rows: Vec<Cursor>, primary: usize) {
+        debug_assert!(primary < rows.len(), "Primary block row out of bounds");
+        self.cursors = rows;
+        self.primary = primary;
+        self.sort_and_merge_overlapping();
+    }
+
     pub fn mark_first(&self, buf: &Buffer) -> Mark {
         let pos = self
             .cursors()
@@ -252,6 +297,7 @@ impl Default for Cursors {
         Cursors {
             cursors: vec![Cursor::default()],
             primary: 0,
+            block: None,
         }
     }
 }
@@ -264,6 +310,7 @@ impl From<Vec<Cursor>> for Cursors {
         Cursors {
             cursors: value,
             primary: last,
+            block: None,
         }
     }
 }
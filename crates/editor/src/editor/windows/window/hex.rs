@@ -0,0 +1,20 @@
+/// Editing state of hex mode, bytes are edited by typing two hex digits
+#[derive(Debug, Default)]
+pub(crate) struct HexEdit {
+    /// Insert typed bytes instead of overwriting them
+    pub(crate) insert: bool,
+
+    /// First digit of a byte being typed and the cursor position it was typed at
+    pub(crate) pending: Option<(u64, u8)>,
+}
+
+impl HexEdit {
+    pub fn statusline(&self) -> &str {
+        match (self.insert, self.pending.is_some()) {
+            (false, false) => "HEX OVR",
+            (true, false) => "HEX INS",
+            (false, true) => "HEX OVR _",
+            (true, true) => "HEX INS _",
+        }
+    }
+}
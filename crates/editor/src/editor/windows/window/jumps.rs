@@ -1,4 +1,4 @@
-use sanedit_buffer::Mark;
+use sanedit_buffer::{Mark, RangeMark, RangeMarkResult};
 use sanedit_core::Cursor;
 use sanedit_utils::ring::{Ref, RingBuffer, RingItemReference as _};
 
@@ -10,21 +10,30 @@ use super::Cursors;
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Jump {
     start: Mark,
-    /// If jump selects a portion of the text end is set
-    end: Option<Mark>,
+    /// If jump selects a portion of the text, the range is set and it
+    /// follows the edits made to the text
+    range: Option<RangeMark>,
 }
 
 impl Jump {
-    pub fn new(start: Mark, end: Option<Mark>) -> Jump {
-        Jump { start, end }
+    pub fn new(start: Mark, range: Option<RangeMark>) -> Jump {
+        Jump { start, range }
+    }
+
+    /// Jump that selects a range of text
+    pub fn new_range(range: RangeMark) -> Jump {
+        Jump {
+            start: *range.start(),
+            range: Some(range),
+        }
     }
 
     pub fn start(&self) -> &Mark {
         &self.start
     }
 
-    pub fn end(&self) -> Option<&Mark> {
-        self.end.as_ref()
+    pub fn range(&self) -> Option<&RangeMark> {
+        self.range.as_ref()
     }
 }
 
@@ -55,13 +64,12 @@ impl JumpGroup {
             let mut all = cursors.cursors_mut();
 
             for (i, jump) in self.jumps().iter().enumerate() {
-                let start = buf.mark_to_pos(jump.start()).pos();
-                let end = jump.end().map(|mark| buf.mark_to_pos(mark));
-
-                let cursor = if let Some(end) = end {
-                    Cursor::new_select(start..end.pos())
-                } else {
-                    Cursor::new(start)
+                let cursor = match jump.range().map(|mark| buf.range_mark_to_range(mark)) {
+                    Some(RangeMarkResult::Found(range)) if !range.is_empty() => {
+                        Cursor::new_select(range)
+                    }
+                    Some(result) => Cursor::new(result.range().start),
+                    None => Cursor::new(buf.mark_to_pos(jump.start()).pos()),
                 };
 
                 let first = i == 0;
@@ -1,7 +1,13 @@
-use sanedit_core::IndentKind;
+use sanedit_core::{indent_fold_at, IndentKind};
 use sanedit_messages::redraw::Point;
 use sanedit_utils::either::Either;
 
+use crate::draw::line_numbers::line_numbers;
+use crate::editor::{
+    buffers::BufferConfig, config::Config, file_description::FileDescription, signs::SignKind,
+};
+use sanedit_messages::redraw::line_numbers::LineNumber;
+
 use super::*;
 
 fn view_lines(win: &mut Window, buf: &Buffer) -> Vec<String> {
@@ -21,6 +27,14 @@ fn view_lines(win: &mut Window, buf: &Buffer) -> Vec<String> {
     win.view().cells().iter().map(to_str).collect()
 }
 
+/// View lines without the trailing empty cells
+fn view_lines_trimmed(win: &mut Window, buf: &Buffer) -> Vec<String> {
+    view_lines(win, buf)
+        .into_iter()
+        .map(|line| line.trim_end().to_string())
+        .collect()
+}
+
 fn with_buf(content: &str) -> (Window, Buffer) {
     let mut buf = Buffer::new();
     let changes = Changes::multi_insert(&[0], content.as_bytes());
@@ -345,3 +359,291 @@ fn pos_at_point_wrap() {
     assert_eq!(Some(5), win.view().pos_at_point(Point { x: 0, y: 1 }));
     assert_eq!(Some(5), win.view().pos_at_point(Point { x: 1, y: 1 }));
 }
+
+#[test]
+fn hex_edit() {
+    let (mut win, mut buf) = with_buf("abc");
+    win.toggle_hex(&buf);
+
+    // Overwrite, digits of a byte are typed separately
+    let _ = win.hex_input(&mut buf, "4");
+    assert_eq!(String::from(&buf.slice(..)), "abc");
+    let _ = win.hex_input(&mut buf, "z");
+    assert_eq!(String::from(&buf.slice(..)), "abc");
+    let _ = win.hex_input(&mut buf, "1");
+    assert_eq!(String::from(&buf.slice(..)), "Abc");
+    assert_eq!(win.cursors.primary().pos(), 1);
+
+    // Insert
+    win.hex.insert = true;
+    let _ = win.hex_input(&mut buf, "0a");
+    assert_eq!(String::from(&buf.slice(..)), "A\nbc");
+    assert_eq!(win.cursors.primary().pos(), 2);
+
+    // Overwriting at the end appends
+    win.hex.insert = false;
+    win.cursors.cursors_mut().primary().goto(buf.len());
+    let _ = win.hex_input(&mut buf, "21");
+    assert_eq!(String::from(&buf.slice(..)), "A\nbc!");
+
+    // Removing works on bytes
+    let _ = win.remove_grapheme_before_cursors(&mut buf);
+    assert_eq!(String::from(&buf.slice(..)), "A\nbc");
+}
+
+#[test]
+fn block_selection() {
+    let (mut win, buf) = with_buf("abcdef\nab\nabcdef\n");
+    win.cursors.cursors_mut().primary().goto(1);
+    win.start_block_selection(&buf);
+
+    win.cursors.collapse_block();
+    win.cursors.cursors_mut().primary().goto(14);
+    win.update_block_selection(&buf);
+
+    let selections: Vec<BufferRange> = (&win.cursors).into();
+    assert_eq!(
+        vec![Range::from(1..4), Range::from(8..9), Range::from(11..14)],
+        selections
+    );
+    assert_eq!(14, win.cursors.primary().pos());
+    assert_eq!(
+        Some(vec!["bcd".to_string(), "b".into(), "bcd".into()]),
+        win.block_text(&buf)
+    );
+
+    // Lines ending before the block are skipped
+    win.stop_selection();
+    win.cursors.cursors_mut().remove_except_primary();
+    win.cursors.cursors_mut().primary().goto(3);
+    win.start_block_selection(&buf);
+    win.cursors.collapse_block();
+    win.cursors.cursors_mut().primary().goto(15);
+    win.update_block_selection(&buf);
+
+    let selections: Vec<BufferRange> = (&win.cursors).into();
+    assert_eq!(vec![Range::from(3..5), Range::from(13..15)], selections);
+    assert_eq!(
+        Some(vec!["de".to_string(), "".into(), "de".into()]),
+        win.block_text(&buf)
+    );
+}
+
+#[test]
+fn paste_block() {
+    let (mut win, mut buf) = with_buf("abc\nd\n");
+    win.cursors.cursors_mut().primary().goto(1);
+    let rows = vec!["X".to_string(), "Y".into(), "Z".into(), "W".into()];
+    let _ = win.paste_block(&mut buf, &rows);
+    assert_eq!("aXbc\ndY\n Z\n W", String::from(&buf.slice(..)));
+}
+
+#[test]
+fn edit_lines() {
+    let (mut win, mut buf) = with_buf("c\nb\na\n");
+    let sort = |mut lines: Vec<String>| {
+        lines.sort();
+        lines
+    };
+
+    // Whole buffer without selections
+    win.edit_lines(&mut buf, sort).unwrap();
+    assert_eq!("a\nb\nc\n", String::from(&buf.slice(..)));
+    assert!(win.edit_lines(&mut buf, sort).is_err());
+
+    // Selection covers the lines it touches
+    win.edit_lines(&mut buf, |mut lines| {
+        lines.reverse();
+        lines
+    })
+    .unwrap();
+    win.cursors.cursors_mut().primary().select(2..5);
+    win.edit_lines(&mut buf, sort).unwrap();
+    assert_eq!("c\na\nb\n", String::from(&buf.slice(..)));
+
+    // Blank lines are kept, only the final line ending is left out
+    let (mut win, mut buf) = with_buf("b\na\n\n");
+    win.edit_lines(&mut buf, sort).unwrap();
+    assert_eq!("\na\nb\n", String::from(&buf.slice(..)));
+}
+
+#[test]
+fn line_number_gutter() {
+    let text = format!("a\n{}\nc", "b".repeat(20));
+    let (mut win, buf) = with_buf_size(&text, 20, 5);
+    assert_eq!(0, win.gutter_width());
+    assert_eq!(None, line_numbers(&win, &buf));
+
+    win.config.line_numbers = LineNumbering::Absolute;
+    win.redraw_view(&buf);
+    // Minimum of 3 digits and padding
+    assert_eq!(4, win.gutter_width());
+    assert_eq!(16, win.view().width());
+    assert_eq!(20, win.size().width);
+
+    // Long line wraps after 16 characters next to the gutter
+    use LineNumber::*;
+    win.cursors.cursors_mut().replace_primary(Cursor::new(23));
+    let numbers = |win: &mut Window, mode| {
+        win.config.line_numbers = mode;
+        win.redraw_view(&buf);
+        line_numbers(win, &buf).unwrap().lines
+    };
+    assert_eq!(
+        vec![Line(1), Line(2), Wrapped, Current(3), Empty],
+        numbers(&mut win, LineNumbering::Absolute)
+    );
+    assert_eq!(
+        vec![Line(2), Line(1), Wrapped, Current(0), Empty],
+        numbers(&mut win, LineNumbering::Relative)
+    );
+    assert_eq!(
+        vec![Line(2), Line(1), Wrapped, Current(3), Empty],
+        numbers(&mut win, LineNumbering::Hybrid)
+    );
+
+    // Narrow windows leave the space for text
+    win.resize(
+        Size {
+            width: 7,
+            height: 5,
+        },
+        &buf,
+    );
+    assert_eq!(0, win.gutter_width());
+    assert_eq!(7, win.view().width());
+}
+
+#[test]
+fn line_number_gutter_without_line_index() {
+    let path = std::env::temp_dir().join(format!("sanedit-line-numbers-{}", std::process::id()));
+    std::fs::write(&path, "a\nb\nc").unwrap();
+    let mut config = Config::default();
+    config.editor.big_file_threshold_bytes = 0;
+    let file = FileDescription::new(&path, &config).unwrap();
+    let buf = Buffer::from_file(file, BufferConfig::default()).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert!(buf.is_file_backed());
+    assert_eq!(None, buf.line_count());
+
+    // Numbers are not known until the lines are indexed
+    let mut win = Window::new(buf.id, 20, 3, WindowConfig::default());
+    win.config.line_numbers = LineNumbering::Absolute;
+    win.redraw_view(&buf);
+    let numbers = line_numbers(&win, &buf).unwrap();
+    assert_eq!(4, numbers.width);
+    assert_eq!(vec![LineNumber::Empty; 3], numbers.lines);
+}
+
+#[test]
+fn sign_column() {
+    let (mut win, buf) = with_buf_size("a\nbbbbbbbbbbbbbbbbbbbbbbbb\nc", 20, 5);
+    let sign = |pos, kind| Sign { pos, kind };
+    win.set_signs(vec![
+        sign(0, SignKind::Hint),
+        sign(0, SignKind::Error),
+        sign(2, SignKind::Bookmark),
+        sign(4, SignKind::Added),
+    ]);
+    win.redraw_view(&buf);
+    assert_eq!(2, win.sign_column_width());
+    assert_eq!(18, win.view().width());
+
+    // Most important sign is shown on the first line of a wrapped line
+    let kinds: Vec<Option<SignKind>> = win
+        .view_signs(&buf)
+        .into_iter()
+        .map(|sign| sign.map(|sign| sign.kind))
+        .collect();
+    assert_eq!(
+        vec![
+            Some(SignKind::Error),
+            Some(SignKind::Bookmark),
+            None,
+            None,
+            None
+        ],
+        kinds
+    );
+
+    win.set_signs(vec![]);
+    win.redraw_view(&buf);
+    assert_eq!(0, win.gutter_width());
+}
+
+#[test]
+fn fold() {
+    let (mut win, mut buf) = with_buf_size("fn a() {\n    x();\n    y();\n}\nz", 30, 5);
+
+    // Cursors hidden by the fold move to the header
+    win.cursors.cursors_mut().replace_primary(Cursor::new(12));
+    let range = indent_fold_at(&buf.slice(..), 0).unwrap();
+    win.close_fold(&buf, range);
+    assert_eq!(0, win.cursors.primary().pos());
+    assert_eq!(
+        vec!["fn a() { ⋯ 2 lines", "}", "z", "", ""],
+        view_lines_trimmed(&mut win, &buf)
+    );
+
+    // Folds follow edits
+    let _ = buf.apply_changes(&Changes::multi_insert(&[0], b"// c\n"));
+    win.invalidate();
+    assert_eq!(
+        vec!["// c", "fn a() { ⋯ 2 lines", "}", "z", ""],
+        view_lines_trimmed(&mut win, &buf)
+    );
+
+    // Hidden lines are counted from the edited contents
+    let _ = buf.apply_changes(&Changes::multi_insert(&[14], b"    w();\n"));
+    win.invalidate();
+    assert_eq!(
+        vec!["// c", "fn a() { ⋯ 3 lines", "}", "z", ""],
+        view_lines_trimmed(&mut win, &buf)
+    );
+
+    // Moving a cursor inside opens the fold
+    win.cursors.cursors_mut().replace_primary(Cursor::new(20));
+    assert_eq!(
+        vec!["// c", "fn a() {", "    w();", "    x();", "    y();"],
+        view_lines_trimmed(&mut win, &buf)
+    );
+}
+
+#[test]
+fn no_wrap() {
+    let (mut win, mut buf) = with_buf_size("0123456789abcdef\nxy\nlast", 8, 4);
+    win.config.wrap = false;
+    assert_eq!(
+        vec!["0123456»", "xy", "last", ""],
+        view_lines_trimmed(&mut win, &buf)
+    );
+    assert_eq!(buf.len(), win.view().end());
+
+    // Cursor past the right edge scrolls sideways
+    win.cursors.cursors_mut().replace_primary(Cursor::new(12));
+    assert_eq!(
+        vec!["«9abcde»", "«", "«", ""],
+        view_lines_trimmed(&mut win, &buf)
+    );
+    assert_eq!(8, win.view().horizontal_offset());
+    assert_cursor_at(&win, Point { x: 4, y: 0 });
+    assert_eq!(buf.len(), win.view().end());
+
+    // Columns are found again after the line changes
+    win.cursors.cursors_mut().replace_primary(Cursor::new(0));
+    win.insert_at_cursors(&mut buf, "界").unwrap();
+    win.cursors.cursors_mut().replace_primary(Cursor::new(15));
+    assert_eq!(
+        vec!["«789abc»", "«", "«", ""],
+        view_lines_trimmed(&mut win, &buf)
+    );
+    assert_eq!(8, win.view().horizontal_offset());
+    assert_cursor_at(&win, Point { x: 6, y: 0 });
+
+    win.cursors.cursors_mut().replace_primary(Cursor::new(3));
+    assert_eq!(
+        vec!["界01234»", "xy", "last", ""],
+        view_lines_trimmed(&mut win, &buf)
+    );
+    assert_eq!(0, win.view().horizontal_offset());
+}
@@ -1,6 +1,11 @@
+mod folds;
+mod hex;
+
 use std::collections::VecDeque;
+use std::iter::Peekable;
 
-use sanedit_core::movement::prev_line_start;
+use sanedit_buffer::{utf8::next_eol, PieceTreeSlice};
+use sanedit_core::movement::{prev_line_start, start_of_line};
 use sanedit_core::{BufferRange, Char, Chars, DisplayOptions, Range, Replacement};
 use sanedit_messages::redraw::{Point, Size};
 use sanedit_utils::sorted_vec::SortedVec;
@@ -8,6 +13,10 @@ use unicode_width::UnicodeWidthChar;
 
 use crate::editor::buffers::{Buffer, BufferId};
 use crate::editor::syntax::{Span, SyntaxResult};
+use crate::editor::Map;
+
+pub(crate) use folds::{ClosedFold, Folds};
+pub(crate) use hex::HexLayout;
 
 #[derive(Debug, Clone)]
 pub(crate) enum Zone {
@@ -117,6 +126,46 @@ impl ViewSyntax {
     }
 }
 
+/// Columns found on lines that are not wrapped, so lines are not walked from
+/// the start on every redraw. Valid until the buffer contents or tab stop
+/// change.
+#[derive(Debug, Default)]
+struct ColumnCache {
+    key: Option<(BufferId, u32, u8)>,
+    /// Line start, position and column of the primary cursor
+    cursor: Option<(u64, u64, usize)>,
+    /// Position and column of the first drawn grapheme on the drawn lines,
+    /// by line start
+    lines: Map<u64, (u64, usize)>,
+}
+
+impl ColumnCache {
+    fn validate(&mut self, buf: &Buffer, options: &DisplayOptions) {
+        let key = Some((buf.id, buf.total_changes_made(), options.tabstop));
+        if self.key != key {
+            *self = ColumnCache {
+                key,
+                ..ColumnCache::default()
+            };
+        }
+    }
+
+    /// Known position and column on the line starting at `line_start`
+    /// closest before `pos`
+    fn before(&self, line_start: u64, pos: u64) -> Option<(u64, usize)> {
+        let cursor = self
+            .cursor
+            .filter(|(start, _, _)| *start == line_start)
+            .map(|(_, pos, col)| (pos, col));
+        let line = self.lines.get(&line_start).copied();
+        cursor
+            .into_iter()
+            .chain(line)
+            .filter(|(known, _)| *known <= pos)
+            .max_by_key(|(known, _)| *known)
+    }
+}
+
 /// View of the current window, used to draw the actual content sent to client
 /// as well as implement movements which operate on visual information.
 #[derive(Debug)]
@@ -129,8 +178,17 @@ pub(crate) struct View {
     /// Display options which were used to draw this view
     pub options: DisplayOptions,
     needs_redraw: bool,
+    /// Show buffer bytes in hex instead of text
+    hex: bool,
+    /// Wrap lines that do not fit the view, otherwise the lines are cut and
+    /// drawn starting from the horizontal offset
+    wrap: bool,
+    /// Horizontal offset in columns, used if lines are not wrapped
+    hscroll: usize,
+    columns: ColumnCache,
 
     pub(super) syntax: ViewSyntax,
+    pub(super) folds: Folds,
 }
 
 impl View {
@@ -140,7 +198,12 @@ impl View {
             cells: Self::make_default_cells(width, height),
             options: DisplayOptions::new(width, height),
             needs_redraw: true,
+            hex: false,
+            wrap: true,
+            hscroll: 0,
+            columns: ColumnCache::default(),
             syntax: ViewSyntax::default(),
+            folds: Folds::default(),
         }
     }
 
@@ -148,6 +211,12 @@ impl View {
         self.needs_redraw = true;
     }
 
+    /// Forget the columns found on lines, needed if the buffer contents
+    /// are replaced without changing it
+    pub fn invalidate_columns(&mut self) {
+        self.columns = ColumnCache::default();
+    }
+
     pub fn cells(&self) -> &VecDeque<Vec<Cell>> {
         &self.cells
     }
@@ -187,14 +256,33 @@ impl View {
     }
 
     fn draw_cells(&mut self, buf: &Buffer) {
-        let slice = buf.slice(self.range.start..);
+        let start = self.range.start;
+        let slice = buf.slice(start..);
+        let fold_char = self.options.replacements.get(&Replacement::Fold).copied();
+        let mut folds = self
+            .folds
+            .closed(buf)
+            .into_iter()
+            .filter(|fold| fold.hidden.start >= start)
+            .peekable();
         let mut pos = 0;
         let mut line = 0;
         let mut col = 0;
+        // Column on the buffer line and the bytes hidden on the left side of
+        // it, used if lines are not wrapped
+        let mut lcol = 0;
+        let mut skipped = 0;
         let mut is_eol = false;
         let mut graphemes = slice.graphemes_at(pos);
-
-        let wrap = {
+        // Start of the current row and whether a fold was hidden on the left
+        // side of it, used to skip to the first drawn grapheme
+        let mut row_start = pos;
+        let mut row_folded = false;
+        self.columns.validate(buf, &self.options);
+        let known_columns = std::mem::take(&mut self.columns.lines);
+        let mut drawn_columns = Map::default();
+
+        let wrap = self.wrap && {
             let slice = buf.slice(..self.range.start);
             let mut graphemes = slice.graphemes_at(slice.len());
             let prev = graphemes.prev();
@@ -214,13 +302,100 @@ impl View {
             }
         }
 
-        while let Some(grapheme) = graphemes.next() {
-            let chars = Chars::new(&grapheme, col, &self.options);
+        loop {
+            // Skip to a known column on the row, unless folds are hidden
+            // before it
+            let margin = usize::from(self.hscroll != 0);
+            if !self.wrap && margin != 0 && pos == row_start {
+                let known = known_columns.get(&(start + pos)).copied();
+                if let Some((known, column)) = known.filter(|(known, column)| {
+                    *column <= self.hscroll + margin
+                        && folds.peek().is_none_or(|fold| fold.hidden.start >= *known)
+                }) {
+                    skipped += known - start - pos;
+                    pos = known - start;
+                    lcol = column;
+                    graphemes = slice.graphemes_at(pos);
+                }
+            }
+
+            // Closed folds are drawn as a placeholder in place of the hidden
+            // lines
+            let fold = folds.next_if(|fold| fold.hidden.start == start + pos);
+            row_folded |= fold.is_some();
+            let column = if self.wrap { col } else { lcol };
+            let (chars, len) = match fold {
+                Some(fold) => {
+                    let unit = if fold.lines == 1 { "line" } else { "lines" };
+                    let text = match fold_char {
+                        Some(ch) => format!(" {ch} {} {unit} ", fold.lines),
+                        None => format!(" {} {unit} ", fold.lines),
+                    };
+                    let len = fold.hidden.len();
+                    graphemes = slice.graphemes_at(pos + len);
+                    (Chars::placeholder(&text, len), len)
+                }
+                None => match graphemes.next() {
+                    Some(grapheme) => {
+                        (Chars::new(&grapheme, column, &self.options), grapheme.len())
+                    }
+                    None => break,
+                },
+            };
             let ch_width: usize = chars.width();
             is_eol = chars.is_eol();
 
-            // If we cannot fit this character, go to next line
-            if col + ch_width > self.width() {
+            if !self.wrap {
+                lcol += ch_width;
+                // Leave room for the mark showing hidden text on the left
+                if !is_eol && column < self.hscroll + margin {
+                    skipped += len;
+                    pos += len;
+                    continue;
+                }
+
+                if margin != 0 && skipped != 0 && !row_folded {
+                    drawn_columns.insert(start + row_start, (start + pos, column));
+                }
+
+                if skipped != 0 {
+                    let rep = self.options.replacements.get(&Replacement::Precedes);
+                    let vch = Char::new_hidden(rep.copied().unwrap_or(' '), skipped, false);
+                    col += self.draw_ch(line, col, vch);
+                    skipped = 0;
+                }
+
+                if column >= self.hscroll + margin {
+                    col = col.max(column - self.hscroll);
+                }
+
+                // Always leave room for the eol or the mark showing hidden
+                // text on the right
+                if !is_eol && col + ch_width >= self.width() {
+                    if col >= self.width() {
+                        break;
+                    }
+
+                    let (end, eol) = Self::hidden_line_end(&slice, start, pos + len, &mut folds);
+                    let rep = self.options.replacements.get(&Replacement::Extends);
+                    let vch = Char::new_hidden(rep.copied().unwrap_or(' '), end - pos, eol);
+                    col += self.draw_ch(line, col, vch);
+                    pos = end;
+                    graphemes = slice.graphemes_at(pos);
+                    is_eol = eol;
+
+                    if !is_eol || line + 1 >= self.height() {
+                        break;
+                    }
+                    line += 1;
+                    col = 0;
+                    lcol = 0;
+                    row_start = pos;
+                    row_folded = false;
+                    continue;
+                }
+            } else if col + ch_width > self.width() {
+                // If we cannot fit this character, go to next line
                 if line + 1 >= self.height() {
                     break;
                 }
@@ -250,7 +425,7 @@ impl View {
                 }
             }
 
-            pos += grapheme.len();
+            pos += len;
 
             // Goto next line if eol
             if is_eol {
@@ -259,8 +434,19 @@ impl View {
                 }
                 line += 1;
                 col = 0;
+                lcol = 0;
+                row_start = pos;
+                row_folded = false;
             }
         }
+        self.columns.lines = drawn_columns;
+
+        // Last line may be hidden on the left side
+        if skipped != 0 {
+            let rep = self.options.replacements.get(&Replacement::Precedes);
+            let vch = Char::new_hidden(rep.copied().unwrap_or(' '), skipped, false);
+            col += self.draw_ch(line, col, vch);
+        }
 
         // Add in EOF if we have space
         if pos == slice.len() {
@@ -274,6 +460,28 @@ impl View {
         self.range = Range::from(self.range.start..self.range.start + pos);
     }
 
+    /// End of the line containing `pos` including the eol, and wether the
+    /// line had an eol. Closed folds on the line are skipped over.
+    fn hidden_line_end(
+        slice: &PieceTreeSlice,
+        start: u64,
+        mut pos: u64,
+        folds: &mut Peekable<impl Iterator<Item = ClosedFold>>,
+    ) -> (u64, bool) {
+        loop {
+            let mut bytes = slice.bytes_at(pos);
+            let (end, eol) = match next_eol(&mut bytes) {
+                Some(m) => (m.range.end, true),
+                None => (slice.len(), false),
+            };
+
+            match folds.next_if(|fold| fold.hidden.start < start + end) {
+                Some(fold) => pos = fold.hidden.end - start,
+                None => return (end, eol),
+            }
+        }
+    }
+
     pub fn redraw(&mut self, buf: &Buffer) {
         if self.needs_redraw {
             self.draw(buf);
@@ -285,6 +493,15 @@ impl View {
             self.set_offset(buf.len());
         }
 
+        // Hex views are aligned to rows instead
+        if let Some(layout) = self.hex_layout(buf) {
+            let start = layout.row_start(self.start());
+            if start != self.start() {
+                self.set_offset(start);
+            }
+            return;
+        }
+
         // Ensure view in buf grapheme boundary
         let ppos = self.start();
         let slice = buf.slice(..);
@@ -308,8 +525,29 @@ impl View {
             self.scroll_up_n(buf, (self.height() / 2) as u64);
         }
 
+        // Start from the header line if the view starts inside a closed fold
+        if !self.hex {
+            let hidden = self.folds.closed(buf).into_iter().find(|fold| {
+                fold.hidden.start < self.range.start && self.range.start <= fold.hidden.end
+            });
+            if let Some(fold) = hidden {
+                let slice = buf.slice(..);
+                self.range.start = start_of_line(&slice, fold.hidden.start);
+            }
+        }
+
+        // Rows start at line starts if lines are not wrapped
+        if !self.wrap && !self.hex {
+            let slice = buf.slice(..);
+            self.range.start = start_of_line(&slice, self.range.start);
+        }
+
         self.clear();
-        self.draw_cells(buf);
+        if self.hex {
+            self.draw_hex_cells(buf);
+        } else {
+            self.draw_cells(buf);
+        }
     }
 
     pub fn line_len_in_buffer(&self, line: usize) -> u64 {
@@ -346,7 +584,14 @@ impl View {
     pub fn align_start(&mut self, mut width: usize, buf: &Buffer) {
         self.redraw(buf);
 
-        if self.range.start == 0 {
+        if self.range.start == 0 || self.hex {
+            return;
+        }
+
+        if !self.wrap {
+            let slice = buf.slice(..);
+            self.range.start = start_of_line(&slice, self.range.start);
+            self.needs_redraw = true;
             return;
         }
 
@@ -424,6 +669,25 @@ impl View {
 
         n = n.clamp(1, self.height().saturating_sub(1) as u64);
 
+        if self.hex {
+            self.hex_scroll_up_n(buf, n);
+            return;
+        }
+
+        if !self.wrap {
+            let slice = buf.slice(..);
+            let mut pos = start_of_line(&slice, self.range.start);
+            for _ in 0..n {
+                if pos == 0 {
+                    break;
+                }
+                pos = prev_line_start(&slice, pos);
+            }
+            self.range.start = pos;
+            self.needs_redraw = true;
+            return;
+        }
+
         // Go up until we find newlines,
         // but stop at a maximum if there are no lines.
         let mut pos = self.range.start;
@@ -676,6 +940,13 @@ impl View {
     pub fn view_to(&mut self, pos: u64, buf: &Buffer) {
         self.redraw(buf);
 
+        if self.hex {
+            if !self.is_visible(pos) {
+                self.hex_view_to(pos, buf);
+            }
+            return;
+        }
+
         // Scroll to position if its nearby
         let max = ((self.height() / 2) * self.width()) as u64;
         let offset = self.offset_from(pos);
@@ -719,6 +990,62 @@ impl View {
     pub fn syntax(&self) -> &ViewSyntax {
         &self.syntax
     }
+
+    pub fn folds(&self) -> &Folds {
+        &self.folds
+    }
+
+    pub fn is_wrapped(&self) -> bool {
+        self.wrap
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        if self.wrap != wrap {
+            self.wrap = wrap;
+            self.hscroll = 0;
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn horizontal_offset(&self) -> usize {
+        self.hscroll
+    }
+
+    /// Scroll sideways so that the column of `pos` is shown, lines that are
+    /// wrapped are always shown fully
+    pub fn hscroll_to(&mut self, pos: u64, buf: &Buffer) {
+        if self.wrap || self.hex {
+            return;
+        }
+
+        // Continue from a known column on the line if possible
+        self.columns.validate(buf, &self.options);
+        let slice = buf.slice(..);
+        let line_start = start_of_line(&slice, pos);
+        let (from, mut column) = self
+            .columns
+            .before(line_start, pos)
+            .unwrap_or((line_start, 0));
+        let mut graphemes = slice.graphemes_at(from);
+        while let Some(grapheme) = graphemes.next() {
+            if grapheme.start() >= pos {
+                break;
+            }
+            column += Chars::new(&grapheme, column, &self.options).width();
+        }
+        self.columns.cursor = Some((line_start, pos, column));
+
+        // Columns under the marks showing hidden text are not visible
+        let margin = usize::from(self.hscroll != 0);
+        let visible = self.hscroll + margin <= column && column + 1 < self.hscroll + self.width();
+        if !visible {
+            let hscroll = column.saturating_sub(self.width() / 2);
+            if hscroll != self.hscroll {
+                self.hscroll = hscroll;
+                self.needs_redraw = true;
+            }
+        }
+    }
 }
 
 impl Default for View {
@@ -0,0 +1,157 @@
+use sanedit_buffer::{Gravity, PieceTreeSlice, RangeMark, RangeMarkResult};
+use sanedit_core::{movement::end_of_line, BufferRange, Range};
+
+use crate::editor::buffers::Buffer;
+
+/// A closed fold
+#[derive(Debug, Clone)]
+struct Fold {
+    /// From the start of the header line to the end of the last line
+    mark: RangeMark,
+}
+
+/// A resolved closed fold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ClosedFold {
+    /// Hidden part of the fold, from the end of the header line to the end
+    /// of the last line
+    pub hidden: BufferRange,
+    pub lines: u64,
+}
+
+/// Closed folds of a view. Folds are marked so they follow the edits made to
+/// the buffer, and are ignored once their contents are deleted.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Folds {
+    folds: Vec<Fold>,
+}
+
+impl Folds {
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    /// Close a fold covering `range`
+    pub fn close(&mut self, buf: &Buffer, range: BufferRange) {
+        self.open_matching(buf, |found| found == range);
+        let mark = buf.mark_range(range.start..range.end, Gravity::Right, Gravity::Right);
+        self.folds.push(Fold { mark });
+    }
+
+    /// Open the innermost closed fold containing `pos`, returns wether a fold
+    /// was opened
+    pub fn open_at(&mut self, buf: &Buffer, pos: u64) -> bool {
+        let ranges = self.resolve(buf);
+        let innermost = ranges
+            .iter()
+            .filter(|(_, range)| range.start <= pos && pos <= range.end)
+            .min_by_key(|(_, range)| range.len());
+        let Some((i, _)) = innermost else {
+            return false;
+        };
+        self.folds.remove(*i);
+        true
+    }
+
+    /// Open all folds whose hidden part contains any of `positions`, returns
+    /// wether any were opened
+    pub fn open_hiding(&mut self, buf: &Buffer, positions: &[u64]) -> bool {
+        let slice = buf.slice(..);
+        self.open_matching(buf, |range| {
+            let hidden_start = end_of_line(&slice, range.start);
+            positions
+                .iter()
+                .any(|pos| hidden_start < *pos && *pos < range.end)
+        })
+    }
+
+    /// Open all folds, returns wether any were opened
+    pub fn open_all(&mut self) -> bool {
+        let opened = !self.folds.is_empty();
+        self.folds.clear();
+        opened
+    }
+
+    /// Wether a closed fold contains `pos`
+    pub fn is_closed_at(&self, buf: &Buffer, pos: u64) -> bool {
+        self.resolve(buf)
+            .iter()
+            .any(|(_, range)| range.start <= pos && pos <= range.end)
+    }
+
+    fn open_matching<F: FnMut(BufferRange) -> bool>(&mut self, buf: &Buffer, mut f: F) -> bool {
+        let ranges = self.resolve(buf);
+        let len = self.folds.len();
+        let mut i = 0;
+        self.folds.retain(|_| {
+            let remove = ranges.iter().any(|(index, range)| *index == i && f(*range));
+            i += 1;
+            !remove
+        });
+        len != self.folds.len()
+    }
+
+    /// Resolve folds to their current ranges, deleted folds are skipped
+    fn resolve(&self, buf: &Buffer) -> Vec<(usize, BufferRange)> {
+        let marks: Vec<RangeMark> = self.folds.iter().map(|fold| fold.mark).collect();
+        buf.range_marks_to_ranges(&marks)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, result)| match result {
+                RangeMarkResult::Found(range) => Some((i, Range::from(range))),
+                RangeMarkResult::Deleted(_) => None,
+            })
+            .collect()
+    }
+
+    /// Closed folds that are not inside other closed folds, sorted by
+    /// position
+    pub fn closed(&self, buf: &Buffer) -> Vec<ClosedFold> {
+        if self.folds.is_empty() {
+            return vec![];
+        }
+
+        let slice = buf.slice(..);
+        let mut ranges = self.resolve(buf);
+        ranges.sort_by(|(_, a), (_, b)| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+        let mut closed: Vec<ClosedFold> = vec![];
+        for (_, range) in ranges {
+            let hidden = Range::from(end_of_line(&slice, range.start)..range.end);
+            if hidden.is_empty() {
+                continue;
+            }
+
+            // Nested in the previous fold
+            if let Some(prev) = closed.last() {
+                if hidden.start <= prev.hidden.end {
+                    continue;
+                }
+            }
+
+            closed.push(ClosedFold {
+                hidden,
+                lines: hidden_lines(&slice, range),
+            });
+        }
+
+        closed
+    }
+}
+
+/// Number of lines after the header line of a fold covering `range`
+fn hidden_lines(slice: &PieceTreeSlice, range: BufferRange) -> u64 {
+    if slice.line_count().is_some() {
+        let (first, _) = slice.line_at(range.start);
+        let (last, _) = slice.line_at(range.end);
+        return last - first;
+    }
+
+    let fold = slice.slice(range);
+    let mut lines = fold.lines();
+    let mut count: u64 = 0;
+    while lines.next().is_some() {
+        count += 1;
+    }
+    count.saturating_sub(1)
+}
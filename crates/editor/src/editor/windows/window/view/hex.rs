@@ -0,0 +1,236 @@
+use sanedit_core::{Char, Chars};
+
+use crate::editor::buffers::Buffer;
+
+use super::{Cell, View};
+
+/// Bytes per row candidates, the largest that fits the view is used
+const ROW_SIZES: [u64; 6] = [32, 16, 8, 4, 2, 1];
+
+/// Byte group size, groups are separated by an extra space
+const GROUP: u64 = 8;
+
+/// Layout of a hex view row.
+///
+/// Rows are drawn as offset, hex and ASCII columns:
+/// 00000000: 48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a  Hello, world!.
+///
+/// Only the hex column has a length in buffer, so positions can be mapped to
+/// cells the same way as in text views.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct HexLayout {
+    pub(crate) offset_width: usize,
+    pub(crate) bytes_per_row: u64,
+}
+
+impl HexLayout {
+    pub fn new(width: usize, len: u64) -> HexLayout {
+        // Enough hex digits to display all offsets
+        let digits = (u64::BITS - len.leading_zeros()).div_ceil(4) as usize;
+        let offset_width = digits.max(8);
+        let bytes_per_row = ROW_SIZES
+            .into_iter()
+            .find(|n| Self::row_width(offset_width, *n) <= width)
+            .unwrap_or(1);
+
+        HexLayout {
+            offset_width,
+            bytes_per_row,
+        }
+    }
+
+    fn row_width(offset_width: usize, n: u64) -> usize {
+        let n = n as usize;
+        let groups = n.div_ceil(GROUP as usize);
+        offset_width + 2 + n * 3 + groups - 1 + 1 + n
+    }
+
+    /// Start of the row pos is on
+    pub fn row_start(&self, pos: u64) -> u64 {
+        pos - pos % self.bytes_per_row
+    }
+
+    fn hex_column(&self, i: u64) -> usize {
+        self.offset_width + 2 + (i * 3 + i / GROUP) as usize
+    }
+
+    fn ascii_column(&self, i: u64) -> usize {
+        let n = self.bytes_per_row as usize;
+        let groups = n.div_ceil(GROUP as usize);
+        self.offset_width + 2 + n * 3 + groups - 1 + 1 + i as usize
+    }
+}
+
+impl View {
+    pub fn is_hex(&self) -> bool {
+        self.hex
+    }
+
+    pub fn set_hex(&mut self, hex: bool) {
+        if self.hex != hex {
+            self.hex = hex;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Hex layout of this view, or none if not in hex mode
+    pub fn hex_layout(&self, buf: &Buffer) -> Option<HexLayout> {
+        if self.hex {
+            Some(HexLayout::new(self.width(), buf.len()))
+        } else {
+            None
+        }
+    }
+
+    fn draw_str(&mut self, line: usize, mut col: usize, string: &str) {
+        for ch in string.chars() {
+            if col >= self.width() {
+                break;
+            }
+            self.cells[line][col] = Char::new_virtual(ch).into();
+            col += 1;
+        }
+    }
+
+    pub(super) fn draw_hex_cells(&mut self, buf: &Buffer) {
+        let layout = HexLayout::new(self.width(), buf.len());
+        let bpr = layout.bytes_per_row;
+        let start = layout.row_start(self.range.start.min(buf.len()));
+        let end = buf
+            .len()
+            .min(start.saturating_add(bpr * self.height() as u64));
+        let slice = buf.slice(start..end);
+        let mut bytes = slice.bytes();
+        let mut pos = start;
+
+        'rows: for line in 0..self.height() {
+            let offset = format!("{:0w$x}:", pos, w = layout.offset_width);
+            self.draw_str(line, 0, &offset);
+
+            for i in 0..bpr {
+                let col = layout.hex_column(i);
+                if pos == buf.len() {
+                    if col < self.width() {
+                        self.cells[line][col] = Cell::Eof;
+                    }
+                    break 'rows;
+                }
+
+                // Cannot fit the byte on the row
+                if col + 2 > self.width() {
+                    break 'rows;
+                }
+
+                let Some(byte) = bytes.next() else {
+                    break 'rows;
+                };
+
+                if let Chars::Single { ch } = Chars::hex(byte) {
+                    self.draw_ch(line, col, ch);
+                }
+
+                let col = layout.ascii_column(i);
+                if col < self.width() {
+                    let ch = if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    };
+                    self.cells[line][col] = Char::new_virtual(ch).into();
+                }
+                pos += 1;
+            }
+        }
+
+        self.range = (start..pos).into();
+    }
+
+    /// Scroll up n rows
+    pub(super) fn hex_scroll_up_n(&mut self, buf: &Buffer, n: u64) {
+        let layout = HexLayout::new(self.width(), buf.len());
+        let start = layout.row_start(self.range.start.min(buf.len()));
+        self.range.start = start.saturating_sub(n * layout.bytes_per_row);
+        self.needs_redraw = true;
+    }
+
+    /// Align view so that the row containing pos is shown, scrolls if pos is
+    /// nearby otherwise pos is placed in the middle of the view
+    pub(super) fn hex_view_to(&mut self, pos: u64, buf: &Buffer) {
+        let layout = HexLayout::new(self.width(), buf.len());
+        let bpr = layout.bytes_per_row;
+        let height = self.height() as u64;
+        let half = (height / 2) * bpr;
+        let row = layout.row_start(pos);
+        let start = self.start();
+
+        self.range.start = if row < start && start - row <= half {
+            row
+        } else if row >= start && row - start < height * bpr + half {
+            row.saturating_sub(height.saturating_sub(1) * bpr)
+        } else {
+            row.saturating_sub(half)
+        };
+        self.draw(buf);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sanedit_utils::either::Either;
+
+    use super::*;
+
+    #[test]
+    fn layout() {
+        let layout = HexLayout::new(80, 100);
+        assert_eq!(layout.offset_width, 8);
+        assert_eq!(layout.bytes_per_row, 16);
+        assert!(HexLayout::row_width(8, 16) <= 80);
+        assert!(HexLayout::row_width(8, 32) > 80);
+
+        let layout = HexLayout::new(80, 1 << 40);
+        assert_eq!(layout.offset_width, 11);
+
+        let layout = HexLayout::new(10, 100);
+        assert_eq!(layout.bytes_per_row, 1);
+    }
+
+    #[test]
+    fn draw_rows() {
+        let buf = Buffer::from_reader(std::io::Cursor::new(b"Hello\x00world\n")).unwrap();
+        let mut view = View::new(45, 3);
+        view.set_hex(true);
+        view.redraw(&buf);
+
+        let rows: Vec<String> = view
+            .cells()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| match cell {
+                        Cell::Char { ch } => match ch.display() {
+                            Either::Left(s) => s.to_string(),
+                            Either::Right(c) => c.to_string(),
+                        },
+                        Cell::Eof => "$".into(),
+                        Cell::Fill => String::new(),
+                        Cell::Empty => " ".into(),
+                    })
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+
+        assert_eq!(rows[0], "00000000: 48 65 6c 6c 6f 00 77 6f  Hello.wo");
+        assert_eq!(rows[1], "00000008: 72 6c 64 0a $            rld.");
+        assert_eq!(rows[2], "");
+        assert_eq!(view.range(), (0..12).into());
+
+        // Positions map to hex cells
+        let point = view.point_at_pos(9).unwrap();
+        assert_eq!((point.x, point.y), (13, 1));
+        assert_eq!(view.pos_at_point(point), Some(9));
+        assert_eq!(view.point_at_pos(12).map(|p| (p.x, p.y)), Some((22, 1)));
+    }
+}
@@ -0,0 +1,27 @@
+#include <stdio.h>
+#define MAX_ITEMS 16
+
+typedef struct NODE {
+    int value;
+    struct NODE *next;
+} NODE;
+
+enum COLOR { RED, GREEN = 2 };
+
+static NODE *find_node(NODE *head, int value)
+{
+    for (NODE *it = head; it != NULL; it = it->next) {
+        if (it->value == value) {
+            return it;
+        }
+    }
+    return NULL;
+}
+
+int MAX_of(int a, int b[MAX_ITEMS], const char *name)
+{
+    /* Comment */
+    int max = b[0] > a ? b[0] : a;
+    printf("%s: %d\n", name, max);
+    return call(a, b[1], (int)'c');
+}
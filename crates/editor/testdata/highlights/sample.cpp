@@ -0,0 +1,32 @@
+#include <vector>
+
+namespace app {
+
+template <typename T>
+class Stack : public Base<T> {
+public:
+    explicit Stack(size_t capacity) : items_(capacity) {}
+
+    T pop() const override {
+        auto last = [&](int i) -> T { return items_[i]; };
+        return last(items_.size() - 1);
+    }
+
+    static MAX_TYPE limit(int a, std::vector<int> b) noexcept {
+        return MAX_SIZE + b[a];
+    }
+
+private:
+    std::vector<T> items_;
+};
+
+struct POINT {
+    int x, y;
+};
+
+} // namespace app
+
+int main(int argc, char **argv) {
+    app::Stack<int> stack(10);
+    return stack.pop() > 0 ? 0 : 1;
+}
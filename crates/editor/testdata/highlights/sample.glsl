@@ -0,0 +1,19 @@
+#version 330 core
+
+uniform vec3 lightPos;
+in vec2 uv;
+
+struct Light {
+    vec3 pos;
+    float intensity;
+};
+
+float attenuate(Light light, vec3 position[2]) {
+    float d = length(light.pos - position[0]);
+    return light.intensity / (d * d);
+}
+
+void main() {
+    Light l = Light(lightPos, 1.0);
+    gl_FragColor = vec4(attenuate(l, vec3[2](vec3(0.0), vec3(1.0))), 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,34 @@
+package main
+
+import (
+	"fmt"
+	"strings"
+)
+
+type Point struct {
+	X, Y int
+}
+
+type Shape interface {
+	Area() float64
+}
+
+func (p *Point) Add(a int, b []int) (int, error) {
+	fn := func(x int) int {
+		return x * 2
+	}
+	return fn(a) + b[0], nil
+}
+
+func Map[T any, U any](items []T, f func(T) U) []U {
+	out := make([]U, 0, len(items))
+	for _, item := range items {
+		out = append(out, f(item))
+	}
+	return out
+}
+
+func main() {
+	p := &Point{X: 1, Y: 2}
+	fmt.Println(strings.ToUpper("sum"), p.X)
+}
@@ -0,0 +1,28 @@
+package app;
+
+import java.util.List;
+
+@SuppressWarnings("unchecked")
+public class Foo<T> extends Bar implements Runnable {
+    private static final int MAX_SIZE = 10;
+
+    @Override
+    public List<String> run(int a, String[] b) throws IOException {
+        if (a > MAX_SIZE) {
+            list.forEach(x -> {
+                System.out.println(x);
+            });
+        }
+        return null;
+    }
+
+    protected int[] values(int[][] grid) {
+        return grid[0];
+    }
+
+    interface Visitor {
+        void visit(Node node);
+    }
+
+    enum Color { RED, GREEN }
+}
@@ -0,0 +1,32 @@
+import { readFile } from "fs";
+
+const MAX_SIZE = 10;
+
+export class Foo extends Bar {
+  static #count = 0;
+
+  async run(a, b = {}) {
+    const f = (x) => {
+      return x * MAX_SIZE;
+    };
+    return f(a, [1, 2]);
+  }
+
+  *items() {
+    yield* this.list;
+  }
+}
+
+function* generate(n) {
+  for (let i = 0; i < n; i++) yield i;
+}
+
+async function load(path: string): Promise<Buffer> {
+  const data = await readFile(path, { encoding: "utf8" });
+  return JSON.parse(data);
+}
+
+export default function App({ name }) {
+  const onClick = async (e) => console.log(`clicked ${name}`, e);
+  return <div className="app" onClick={onClick}>{name}</div>;
+}
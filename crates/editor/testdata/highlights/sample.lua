@@ -0,0 +1,25 @@
+local M = {}
+
+local MAX_SIZE = 10
+
+local function add(a, b)
+  if a then
+    return a
+  elseif b then
+    return b
+  end
+  for i = 1, 2 do
+    print(i)
+  end
+  return f(a, { 1, 2 })
+end
+
+function M.run(self, ...)
+  local t = { x = 1, y = "two", [3] = true }
+  repeat
+    t.x = t.x + 1
+  until t.x > MAX_SIZE
+  return setmetatable(t, { __index = function(_, k) return k end })
+end
+
+return M
@@ -0,0 +1,30 @@
+import os
+from typing import List
+
+MAX_SIZE = 10
+
+
+class HTTPError(Exception):
+    """An error"""
+
+    @property
+    def run(self, a, b=[1, 2]):
+        def inner(x):
+            return x
+
+        return inner(a)
+
+    x = 1
+
+
+@decorator(MAX_SIZE)
+def URL(path: str, *args, **kwargs) -> List[str]:
+    value = f"{path}/{os.sep}"
+    return [value, None, True]
+
+
+class Empty: pass
+
+
+def add(a, b):
+    return a + b
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+/// A cache of computed values
+pub struct Cache<F: Fn(u32) -> u32> {
+    func: F,
+    values: HashMap<u32, u32>,
+}
+
+impl<F> Cache<F>
+where
+    F: Fn(u32) -> u32,
+{
+    pub const LIMIT: usize = 64;
+
+    pub fn new(func: F) -> Cache<F> {
+        Cache {
+            func,
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn value(&mut self, arg: u32) -> u32 {
+        let func = &self.func;
+        *self.values.entry(arg).or_insert_with(|| func(arg))
+    }
+}
+
+fn apply(f: Box<dyn Fn(&str) -> String>, items: &[&str]) -> Vec<String> {
+    items.iter().map(|item| f(item)).collect()
+}
+
+macro_rules! square {
+    ($x:expr) => {
+        $x * $x
+    };
+}
@@ -0,0 +1,20 @@
+#!/bin/sh
+
+MAX_SIZE=10
+
+function greet() {
+    if [ -n "$1" ]; then
+        echo "${1}"
+    fi
+}
+
+add() {
+    echo $(( $1 + $2 ))
+}
+
+for file in *.txt; do
+    case "$file" in
+        a*) greet "$file" ;;
+        *) add 1 2 ;;
+    esac
+done
@@ -2,7 +2,6 @@ mod cell;
 mod color;
 mod cursor;
 mod cursor_shape;
-// mod line_numbers;
 mod point;
 mod popup;
 mod size;
@@ -13,6 +12,8 @@ mod theme;
 pub mod choice;
 pub mod completion;
 pub mod items;
+pub mod line_numbers;
+pub mod panes;
 pub mod prompt;
 pub mod snapshots;
 pub mod statusline;
@@ -36,8 +37,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     redraw::{
-        completion::CompletionUpdate, items::ItemsUpdate, prompt::PromptUpdate,
-        snapshots::SnapshotsUpdate, window::WindowUpdate,
+        completion::CompletionUpdate, items::ItemsUpdate, line_numbers::LineNumbers, panes::Panes,
+        prompt::PromptUpdate, snapshots::SnapshotsUpdate, window::WindowUpdate,
     },
     ClientMessage,
 };
@@ -60,6 +61,8 @@ pub enum Kind {
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Hash)]
 pub enum Redraw {
     Window(WindowUpdate),
+    LineNumbers(Component<LineNumbers>),
+    Panes(Panes),
     Statusline(statusline::Statusline),
     Prompt(PromptUpdate),
     Completion(CompletionUpdate),
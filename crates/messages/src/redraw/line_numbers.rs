@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Component, Redraw};
+
+/// Line number of a single view line
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum LineNumber {
+    /// Absolute or relative number of a line
+    Line(u64),
+    /// Number of the line the primary cursor is on
+    Current(u64),
+    /// Continuation of a wrapped line
+    Wrapped,
+    /// No line here or the line number is not known yet
+    Empty,
+}
+
+/// Marker shown in the sign column
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum SignKind {
+    Hint,
+    Info,
+    Warn,
+    Error,
+    /// Line added since the last commit
+    Added,
+    /// Line modified since the last commit
+    Modified,
+    /// Lines deleted since the last commit
+    Deleted,
+    Bookmark,
+}
+
+/// Gutter on the left side of a window showing signs and line numbers
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default, Hash)]
+pub struct LineNumbers {
+    /// Width of the gutter, including the sign column and one cell of
+    /// padding on the right
+    pub width: usize,
+    /// Line number for each view line
+    pub lines: Vec<LineNumber>,
+    /// Sign for each view line, None if the sign column is not shown
+    pub signs: Option<Vec<Option<SignKind>>>,
+}
+
+impl From<LineNumbers> for Redraw {
+    fn from(numbers: LineNumbers) -> Self {
+        Redraw::LineNumbers(Component::Update(numbers))
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use super::{line_numbers::LineNumbers, window::Window, Point, Redraw, Size};
+
+/// Area of a window pane relative to the window area
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default, Hash)]
+pub struct PaneRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl PaneRect {
+    pub fn position(&self) -> Point {
+        Point {
+            x: self.x,
+            y: self.y,
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        Size {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        self.x <= point.x
+            && point.x < self.x + self.width
+            && self.y <= point.y
+            && point.y < self.y + self.height
+    }
+}
+
+/// A window that is not focused
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Hash)]
+pub struct Pane {
+    pub rect: PaneRect,
+    pub window: Window,
+    /// Line numbers placed on the left side of the rectangle
+    pub line_numbers: Option<LineNumbers>,
+}
+
+/// Windows split inside the window area. The focused window is sent
+/// separately as a window update and placed on the focused rectangle.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default, Hash)]
+pub struct Panes {
+    pub focused: PaneRect,
+    /// Other windows and their rectangles
+    pub others: Vec<Pane>,
+}
+
+impl Panes {
+    pub fn is_split(&self) -> bool {
+        !self.others.is_empty()
+    }
+}
+
+impl From<Panes> for Redraw {
+    fn from(panes: Panes) -> Self {
+        Redraw::Panes(panes)
+    }
+}
@@ -131,6 +131,10 @@ impl Default for ThemeNode {
 #[derive(Clone, Copy, Debug)]
 pub enum ThemeField {
     Default,
+    Border,
+    LineNumber,
+    LineNumberCurrent,
+    Bookmark,
     Statusline,
     StatuslineNoFocus,
     Selection,
@@ -229,6 +233,10 @@ impl AsRef<str> for ThemeField {
 
         match self {
             Default => "window",
+            Border => "window.border",
+            LineNumber => "window.line_number",
+            LineNumberCurrent => "window.line_number_current",
+            Bookmark => "window.bookmark",
             Statusline => "window.statusline",
             StatuslineNoFocus => "window.statusline_no_focus",
             Selection => "cursor.selection",
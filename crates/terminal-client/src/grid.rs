@@ -2,6 +2,8 @@ mod border;
 mod completion;
 mod drawable;
 mod items;
+mod line_numbers;
+mod panes;
 mod popup;
 mod prompt;
 mod rect;
@@ -18,11 +20,13 @@ use sanedit_messages::{
     redraw::{
         completion::CompletionUpdate,
         items::ItemsUpdate,
+        line_numbers::LineNumbers,
+        panes::Panes,
         prompt::PromptUpdate,
         snapshots::SnapshotsUpdate,
         statusline::Statusline,
         window::{Window, WindowUpdate},
-        Cell, Cursor, Popup, PopupComponent, Redraw, Size, StatusMessage, Theme,
+        Cell, Component, Cursor, Popup, PopupComponent, Redraw, Size, StatusMessage, Theme,
     },
     Message,
 };
@@ -63,6 +67,8 @@ impl<T> From<T> for Placed<T> {
 pub(crate) struct Grid {
     size: Size,
     window: Placed<Window>,
+    panes: Panes,
+    line_numbers: Option<LineNumbers>,
     statusline: Placed<Statusline>,
     prompt: Option<Placed<CustomPrompt>>,
     msg: Option<Placed<StatusMessage>>,
@@ -83,6 +89,8 @@ impl Grid {
         let mut me = Grid {
             size: Size { width, height },
             window: Placed::default(),
+            panes: Panes::default(),
+            line_numbers: None,
             statusline: Placed::default(),
             prompt: None,
             msg: None,
@@ -117,6 +125,17 @@ impl Grid {
                 WindowUpdate::Full(win) => self.window.item = win,
                 WindowUpdate::Cursor(cursor) => self.window.item.cursor = cursor,
             },
+            LineNumbers(update) => {
+                match update {
+                    Component::Update(numbers) => self.line_numbers = Some(numbers),
+                    Component::Close => self.line_numbers = None,
+                }
+                self.refresh_overlays();
+            }
+            Panes(panes) => {
+                self.panes = panes;
+                self.refresh_overlays();
+            }
             Statusline(statusline) => self.statusline.item = statusline,
             Prompt(update) => match update {
                 PromptUpdate::Full(prompt) => match self.prompt {
@@ -268,7 +287,7 @@ impl Grid {
 
     fn refresh_overlays(&mut self) {
         let screen = self.screen();
-        let win = self.window();
+        let win = self.focused_window();
 
         if let Some(compl) = &mut self.completion {
             let new = compl.item.rect(win);
@@ -328,6 +347,37 @@ impl Grid {
         self.window.rect
     }
 
+    /// Rectangle of the focused pane when the window area is split
+    fn focused_pane(&self) -> Rect {
+        let area = self.window.rect;
+        if !self.panes.is_split() {
+            return area;
+        }
+
+        let focused = self.panes.focused;
+        Rect::new(
+            area.x + focused.x,
+            area.y + focused.y,
+            focused.width.min(area.width.saturating_sub(focused.x)),
+            focused.height.min(area.height.saturating_sub(focused.y)),
+        )
+    }
+
+    /// Rectangles of the focused window text and its line number gutter
+    fn focused_window_and_gutter(&self) -> (Rect, Option<Rect>) {
+        let mut rect = self.focused_pane();
+        let gutter = self
+            .line_numbers
+            .as_ref()
+            .map(|numbers| rect.split_off(Split::left_size(numbers.width)));
+        (rect, gutter)
+    }
+
+    /// Rectangle of the focused window text
+    fn focused_window(&self) -> Rect {
+        self.focused_window_and_gutter().0
+    }
+
     pub fn snapshots(&mut self) -> Option<&mut Placed<CustomSnapshots>> {
         self.snapshots.as_mut()
     }
@@ -382,9 +432,30 @@ impl Grid {
         self.clear();
 
         let t = &self.theme;
+        if self.panes.is_split() {
+            Self::draw_drawable(
+                &self.panes,
+                &self.window.rect,
+                t,
+                self.client_in_focus,
+                &mut self.cursor,
+                &mut self.drawn,
+            );
+        }
+        let (window, gutter) = self.focused_window_and_gutter();
+        if let (Some(numbers), Some(gutter)) = (&self.line_numbers, gutter) {
+            Self::draw_drawable(
+                numbers,
+                &gutter,
+                t,
+                self.client_in_focus,
+                &mut self.cursor,
+                &mut self.drawn,
+            );
+        }
         Self::draw_drawable(
             &self.window.item,
-            &self.window.rect,
+            &window,
             t,
             self.client_in_focus,
             &mut self.cursor,
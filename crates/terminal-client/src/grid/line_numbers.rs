@@ -0,0 +1,72 @@
+use sanedit_messages::redraw::{
+    line_numbers::{LineNumber, LineNumbers, SignKind},
+    Style, ThemeField,
+};
+
+use crate::ui::UIContext;
+
+use super::drawable::{DrawCursor, Drawable, Subgrid};
+
+/// Marker shown for continuation lines of a wrapped line
+const WRAPPED: &str = "↳";
+
+/// Width of the sign column, a sign and one cell of padding
+const SIGN_COLUMN_WIDTH: usize = 2;
+
+fn sign(kind: SignKind) -> (&'static str, ThemeField) {
+    match kind {
+        SignKind::Hint => ("H", ThemeField::Hint),
+        SignKind::Info => ("I", ThemeField::Info),
+        SignKind::Warn => ("W", ThemeField::Warn),
+        SignKind::Error => ("E", ThemeField::Error),
+        SignKind::Added => ("+", ThemeField::Added),
+        SignKind::Modified => ("~", ThemeField::Modified),
+        SignKind::Deleted => ("_", ThemeField::Deleted),
+        SignKind::Bookmark => ("»", ThemeField::Bookmark),
+    }
+}
+
+impl Drawable for LineNumbers {
+    fn draw(&self, ctx: &UIContext, mut grid: Subgrid) {
+        let style = ctx.style(ThemeField::LineNumber);
+        let current = ctx.style(ThemeField::LineNumberCurrent);
+        grid.clear_all(style);
+
+        let mut x = 0;
+        if let Some(signs) = &self.signs {
+            x = SIGN_COLUMN_WIDTH;
+            for (y, kind) in signs.iter().enumerate().take(grid.height()) {
+                let Some(kind) = kind else {
+                    continue;
+                };
+                // Only the sign color is used so the gutter background stays
+                // the same
+                let (text, field) = sign(*kind);
+                let sign_style = Style {
+                    fg: ctx.style(field).fg.or(style.fg),
+                    ..style
+                };
+                grid.put_string(y, 0, text, sign_style);
+            }
+        }
+
+        // Last cell is padding
+        let digits = grid.width().saturating_sub(x + 1);
+        if digits == 0 {
+            return;
+        }
+        for (y, line) in self.lines.iter().enumerate().take(grid.height()) {
+            let (text, style) = match line {
+                LineNumber::Line(n) => (format!("{n:>digits$}"), style),
+                LineNumber::Current(n) => (format!("{n:>digits$}"), current),
+                LineNumber::Wrapped => (format!("{WRAPPED:>digits$}"), style),
+                LineNumber::Empty => continue,
+            };
+            grid.put_string(y, x, &text, style);
+        }
+    }
+
+    fn cursor(&self, _ctx: &UIContext) -> DrawCursor {
+        DrawCursor::Ignore
+    }
+}
@@ -0,0 +1,99 @@
+use sanedit_messages::redraw::{
+    panes::{PaneRect, Panes},
+    Cell, ThemeField,
+};
+
+use crate::ui::UIContext;
+
+use super::{
+    drawable::{DrawCursor, Drawable, Subgrid},
+    Rect, Split,
+};
+
+/// Border glyph for a cell based on which of its neighbours are also borders
+fn border_glyph(up: bool, down: bool, left: bool, right: bool) -> &'static str {
+    match (up || down, left || right) {
+        (true, true) => match (up, down, left, right) {
+            (true, true, true, true) => "┼",
+            (true, true, false, true) => "├",
+            (true, true, true, false) => "┤",
+            (false, true, true, true) => "┬",
+            (true, false, true, true) => "┴",
+            _ => "┼",
+        },
+        (false, true) => "─",
+        _ => "│",
+    }
+}
+
+/// Draws the windows that are not focused and borders between all of the
+/// panes. The focused window is drawn separately on top.
+impl Drawable for Panes {
+    fn draw(&self, ctx: &UIContext, mut grid: Subgrid) {
+        let width = grid.width();
+        let height = grid.height();
+        let mut covered = vec![vec![false; width]; height];
+        let rects = std::iter::once(&self.focused).chain(self.others.iter().map(|pane| &pane.rect));
+        for rect in rects {
+            for row in covered.iter_mut().skip(rect.y).take(rect.height) {
+                for cell in row.iter_mut().skip(rect.x).take(rect.width) {
+                    *cell = true;
+                }
+            }
+        }
+
+        let is_border = |y: Option<usize>, x: Option<usize>| match (y, x) {
+            (Some(y), Some(x)) => y < height && x < width && !covered[y][x],
+            _ => false,
+        };
+        let style = ctx.style(ThemeField::Border);
+        for (y, row) in covered.iter().enumerate() {
+            for (x, is_covered) in row.iter().enumerate() {
+                if *is_covered {
+                    continue;
+                }
+
+                let glyph = border_glyph(
+                    is_border(y.checked_sub(1), Some(x)),
+                    is_border(Some(y + 1), Some(x)),
+                    is_border(Some(y), x.checked_sub(1)),
+                    is_border(Some(y), Some(x + 1)),
+                );
+                grid.replace(
+                    y,
+                    x,
+                    Cell {
+                        text: glyph.into(),
+                        style,
+                    },
+                );
+            }
+        }
+
+        for pane in &self.others {
+            let PaneRect { x, y, .. } = pane.rect;
+            let width = pane.rect.width.min(width.saturating_sub(x));
+            let height = pane.rect.height.min(height.saturating_sub(y));
+            let mut rect = Rect::new(grid.rect.x + x, grid.rect.y + y, width, height);
+
+            if let Some(numbers) = &pane.line_numbers {
+                let gutter = rect.split_off(Split::left_size(numbers.width));
+                let subgrid = Subgrid {
+                    cells: &mut *grid.cells,
+                    rect: &gutter,
+                };
+                numbers.draw(ctx, subgrid);
+            }
+
+            let subgrid = Subgrid {
+                cells: &mut *grid.cells,
+                rect: &rect,
+            };
+            pane.window.draw(ctx, subgrid);
+        }
+    }
+
+    fn cursor(&self, _ctx: &UIContext) -> DrawCursor {
+        DrawCursor::Ignore
+    }
+}
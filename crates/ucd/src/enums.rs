@@ -19,6 +19,53 @@ pub enum GraphemeBreak {
     Any,
 }
 
+/// Sentence break property values, in the same order as
+/// SENTENCE_BREAK_ENUM
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SentenceBreak {
+    ATerm = 0,
+    CR,
+    Close,
+    Extend,
+    Format,
+    LF,
+    Lower,
+    Numeric,
+    OLetter,
+    SContinue,
+    STerm,
+    Sep,
+    Sp,
+    Upper,
+    Any,
+}
+
+/// Word break property values, in the same order as WORD_BREAK_ENUM
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[repr(u8)]
+pub enum WordBreak {
+    ALetter = 0,
+    CR,
+    DoubleQuote,
+    Extend,
+    ExtendNumLet,
+    Format,
+    HebrewLetter,
+    Katakana,
+    LF,
+    MidLetter,
+    MidNum,
+    MidNumLet,
+    Newline,
+    Numeric,
+    RegionalIndicator,
+    SingleQuote,
+    WSegSpace,
+    ZWJ,
+    Any,
+}
+
 #[derive(Debug)]
 pub enum Property {
     ExtendedPictographic,
@@ -11,6 +11,8 @@ use std::cmp::Ordering;
 
 pub use enums::GraphemeBreak;
 pub use enums::Property;
+pub use enums::SentenceBreak;
+pub use enums::WordBreak;
 
 pub fn grapheme_break(ch: char) -> GraphemeBreak {
     // Optimization for ascii
@@ -43,6 +45,31 @@ pub fn grapheme_break(ch: char) -> GraphemeBreak {
     }
 }
 
+pub fn sentence_break(ch: char) -> SentenceBreak {
+    table_search(ch, sentence_break::SENTENCE_BREAK)
+        .map(|pos| {
+            // SAFETY: index is from SENTENCE_BREAK_ENUM and SentenceBreak is
+            // just a rust enum version of it with repr(u8)
+            unsafe { std::mem::transmute(pos) }
+        })
+        .unwrap_or(SentenceBreak::Any)
+}
+
+pub fn word_break(ch: char) -> WordBreak {
+    // Optimization for ascii letters
+    if ch.is_ascii_alphabetic() {
+        return WordBreak::ALetter;
+    }
+
+    table_search(ch, word_break::WORD_BREAK)
+        .map(|pos| {
+            // SAFETY: index is from WORD_BREAK_ENUM and WordBreak is just a
+            // rust enum version of it with repr(u8)
+            unsafe { std::mem::transmute(pos) }
+        })
+        .unwrap_or(WordBreak::Any)
+}
+
 fn table_contains(ch: char, table: &'static [(u32, u32)]) -> bool {
     let ch = ch as u32;
     table
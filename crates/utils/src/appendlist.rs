@@ -131,6 +131,11 @@ impl<T> Appendlist<T> {
         Appendlist { list }
     }
 
+    /// Whether both refer to the same list
+    pub fn ptr_eq(&self, other: &Appendlist<T>) -> bool {
+        Arc::ptr_eq(&self.list, &other.list)
+    }
+
     pub fn split() -> (Reader<T>, Writer<T>) {
         let list = Self::new();
         let writer = Writer { list: list.clone() };
@@ -213,6 +218,21 @@ impl<T> Appendlist<T> {
         self.list.len.load(Ordering::Acquire)
     }
 
+    /// Whether the range is stored in a single block, only these ranges can
+    /// be sliced
+    pub fn is_contiguous(&self, range: Range<usize>) -> bool {
+        let start = BucketLocation::of(range.start);
+        let end = BucketLocation::of(range.end);
+        start.bucket == end.bucket || (start.bucket + 1 == end.bucket && end.pos == 0)
+    }
+
+    /// End of the block containing the position, ranges from the position up
+    /// to it can be sliced
+    pub fn block_end(&self, pos: usize) -> usize {
+        let loc = BucketLocation::of(pos);
+        pos - loc.pos + loc.bucket_len
+    }
+
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -353,6 +373,19 @@ impl<T> Reader<T> {
     pub fn is_empty(&self) -> bool {
         self.list.is_empty()
     }
+
+    pub fn is_contiguous(&self, range: Range<usize>) -> bool {
+        self.list.is_contiguous(range)
+    }
+
+    pub fn block_end(&self, pos: usize) -> usize {
+        self.list.block_end(pos)
+    }
+
+    /// Whether both read the same list
+    pub fn ptr_eq(&self, other: &Reader<T>) -> bool {
+        self.list.ptr_eq(&other.list)
+    }
 }
 
 #[cfg(test)]
@@ -390,4 +423,14 @@ mod test {
         let items = list.slice(3..6);
         assert_eq!(items, &[4, 5, 6])
     }
+
+    #[test]
+    fn contiguous() {
+        let list: Appendlist<u8> = Appendlist::new();
+        assert!(list.is_contiguous(0..BUCKET_START_POS));
+        assert!(!list.is_contiguous(BUCKET_START_POS - 1..BUCKET_START_POS + 1));
+        assert!(list.is_contiguous(BUCKET_START_POS..BUCKET_START_POS + 1));
+        assert_eq!(BUCKET_START_POS, list.block_end(0));
+        assert_eq!(BUCKET_START_POS * 3, list.block_end(BUCKET_START_POS));
+    }
 }
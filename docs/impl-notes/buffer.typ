@@ -31,10 +31,16 @@ valid UTF-8. The bytes are decoded to UTF-8 when needed using
 #link("https://bjoern.hoehrmann.de/utf-8/decoder/dfa/")[DFA method by Bjoern
 Hoehrmann].
 
-Line counts could also be cached to nodes (like left subtree byte
-length) to provide a fast line search, but this implementation opts of that so
-we do not need to read the whole file on load. This means opening large files
-should be as fast as opening small ones.
+Line counts are cached to nodes (like left subtree byte length) to provide a
+fast line search. To keep opening large files as fast as opening small ones, the
+line counts of the original buffer are not known on load. The original buffer
+is indexed in the background in blocks, after which the missing counts are
+filled in. Until then line searches fall back to scanning the buffer.
+
+Each piece stores the line count of the bytes it references and an adjustment
+caused by the bytes preceding it in the tree. The adjustment accounts for line
+endings split between pieces, for example a CRLF where CR is at the end of one
+piece and LF at the start of the next one.
 
 == Piece tree is good at
 
@@ -147,6 +147,7 @@ Supported annotations are:
   [\@highlight(anything)], [Highlight text matched by this rule, and use the <anything> provided as a name],
   [\@completion],          [Use the text matched by this rule as a completion item],
   [\@static-completion],   [Use the strings defined in this rule directly as completion items],
+  [\@fold],                [Text matched by this rule spanning multiple lines can be folded],
   [\@injection-language],  [Matches the dynamic name of the language to be injected],
   [\@inject],              [Injects previously matched \@injection-language to this portion of text],
   [\@inject(javascript)],  [Injects static language name to a portion of text],